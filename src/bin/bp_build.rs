@@ -1,42 +1,195 @@
+use anyhow::Context;
 use jvm_function_invoker_buildpack::{
-    builder::{Builder, RUNTIME_JAR_FILE_NAME},
-    util::logger::Logger,
+    builder::{function_bundle_digest, Builder, LAUNCHER_FILE_NAME, RUNTIME_JAR_FILE_NAME},
+    exit_code,
+    failure::FailureSummary,
+    launch::{self, LaunchPaths},
+    metrics::Metrics,
+    platform::Family,
+    util::logger::{JsonLogger, LogFormat, Logger, LoggerConfig, StandardLogger},
 };
 use libcnb::{
     build::{cnb_runtime_build, GenericBuildContext},
-    data,
     platform::Platform,
 };
+use std::{env, panic, path::PathBuf, process, thread};
 
 fn main() -> anyhow::Result<()> {
-    cnb_runtime_build(build);
+    cnb_runtime_build(|ctx| build(ctx).map_err(exit));
 
     Ok(())
 }
 
+/// Exits with the code for `err` instead of the generic failure code.
+fn exit(err: anyhow::Error) -> anyhow::Error {
+    eprintln!("Error: {:?}", err);
+    process::exit(exit_code::for_error(&err))
+}
+
 fn build(ctx: GenericBuildContext) -> anyhow::Result<()> {
-    let heroku_debug = ctx.platform.env().var("HEROKU_BUILDPACK_DEBUG").is_ok();
-    let logger = Logger::new(heroku_debug);
-    let builder = Builder::new(&ctx, &logger)?;
+    // CI systems set NO_COLOR and friends on the build process rather than the platform.
+    let logger_config = LoggerConfig::from_env(|key| {
+        ctx.platform
+            .env()
+            .var(key)
+            .or_else(|_| std::env::var(key))
+            .ok()
+    });
+
+    match logger_config.format {
+        LogFormat::Human => build_with_logger(&ctx, &StandardLogger::from_config(logger_config)),
+        LogFormat::Json => build_with_logger(&ctx, &JsonLogger::from_config(logger_config)),
+    }
+}
+
+fn build_with_logger(ctx: &GenericBuildContext, logger: &impl Logger) -> anyhow::Result<()> {
+    // libcnb checked the arguments, the first one is the layers directory.
+    let layers_dir = env::args_os()
+        .nth(1)
+        .map(PathBuf::from)
+        .ok_or_else(|| anyhow::anyhow!("Missing layers directory argument"))?;
+    let builder = Builder::new(ctx, layers_dir, logger).map_err(|err| {
+        let _ = FailureSummary::new(&err, None).log(logger);
+        err
+    })?;
+    let metrics = Metrics::new();
+    let result = contribute(ctx, logger, &builder, &metrics);
+    // Especially useful when the build failed, so it is dumped either way.
+    builder.diagnostics().log(logger)?;
+    builder.record_telemetry(&metrics, &result)?;
+    if let Err(err) = &result {
+        FailureSummary::new(err, metrics.last_phase().as_deref()).log(logger)?;
+    }
+
+    result
+}
 
-    let opt_layer = builder.contribute_opt_layer()?;
-    let runtime_layer = builder.contribute_runtime_layer()?;
+fn contribute<L: Logger>(
+    ctx: &GenericBuildContext,
+    logger: &L,
+    builder: &Builder<L>,
+    metrics: &Metrics,
+) -> anyhow::Result<()> {
+    let config = builder.config();
+
+    let multiple_functions =
+        ctx.platform.env().var("SF_FUNCTIONS_MULTIPLE").is_ok() || config.multiple;
+    if builder.is_dry_run() {
+        return builder.print_plan(multiple_functions);
+    }
+
+    metrics.measure("Update check", || builder.check_for_update())?;
+    let opt_layer = metrics.measure("Launcher", || builder.contribute_opt_layer())?;
+    metrics.measure("CA certificates", || builder.contribute_truststore_layer())?;
+    metrics.measure("Observability", || builder.contribute_observability_layer())?;
+    metrics.measure("OpenTelemetry", || builder.contribute_opentelemetry_layer())?;
+    metrics.measure("JDK", || builder.check_java_version())?;
+    metrics.measure("Service bindings", || builder.check_bindings())?;
+
+    // Digesting the compiled app doesn't depend on the runtime, so it overlaps the download. The
+    // logger isn't shared across threads, only the plain inputs of the digest are.
+    let (runtime_layer, bundle_digest) = metrics.measure("Function runtime", || {
+        let project_dir = builder.project_dir();
+        let runtime_checksum = builder.runtime().checksum().to_string();
+        let runtime_checksum = runtime_checksum.as_str();
+        thread::scope(|scope| {
+            let bundle_digest = scope.spawn(move || {
+                function_bundle_digest(project_dir, runtime_checksum, multiple_functions)
+            });
+            let runtime_layer = builder.contribute_runtime_layer();
+            let bundle_digest = bundle_digest
+                .join()
+                .unwrap_or_else(|payload| panic::resume_unwind(payload));
+
+            (runtime_layer, bundle_digest)
+        })
+    });
+    let runtime_layer = runtime_layer?;
+    let bundle_digest = bundle_digest?;
     let runtime_jar_path = runtime_layer.as_path().join(RUNTIME_JAR_FILE_NAME);
-    let function_bundle_layer = builder.contribute_function_bundle_layer(&runtime_jar_path)?;
-
-    let mut launch = data::launch::Launch::new();
-    let cmd = format!(
-        "{}/run.sh {} {}",
-        opt_layer.as_path().display(),
-        runtime_jar_path.display(),
-        function_bundle_layer.as_path().display(),
-    );
-    launch.processes.push(data::launch::Process::new(
-        "web",
-        cmd,
-        &[] as &[String],
-        false,
-    )?);
+    metrics.measure("Runtime negotiation", || {
+        builder.negotiate_runtime(&runtime_jar_path)
+    })?;
+    metrics.measure("Protocol", || builder.check_protocol(&runtime_jar_path))?;
+    metrics.measure("Extensions", || {
+        builder.contribute_extensions_layer(&runtime_jar_path)
+    })?;
+    let function_bundle_layer = metrics.measure("Function detection", || {
+        builder.contribute_function_bundle_layer(
+            &runtime_jar_path,
+            multiple_functions,
+            bundle_digest.clone(),
+        )
+    })?;
+    metrics.measure("Function verification", || {
+        builder.verify_functions(
+            &runtime_jar_path,
+            &function_bundle_layer,
+            multiple_functions,
+        )
+    })?;
+    metrics.measure("Class data sharing", || {
+        builder.contribute_cds_layer(
+            &runtime_jar_path,
+            &function_bundle_layer,
+            &bundle_digest,
+            multiple_functions,
+        )
+    })?;
+    metrics.measure("Native image", || {
+        builder.contribute_native_image_layer(
+            &runtime_jar_path,
+            &function_bundle_layer,
+            &bundle_digest,
+            multiple_functions,
+        )
+    })?;
+    metrics.measure("Build report", || {
+        builder.contribute_report_layer(&function_bundle_layer, &bundle_digest, multiple_functions)
+    })?;
+
+    metrics.measure("Launch configuration", || -> anyhow::Result<()> {
+        let family = Family::current();
+        let launcher_path = opt_layer
+            .as_path()
+            .join(family.executable_name(LAUNCHER_FILE_NAME));
+        let mut launch = launch::launch(
+            &LaunchPaths {
+                launcher: &launcher_path,
+                runtime_jar: &runtime_jar_path,
+                function_bundle_layer: function_bundle_layer.as_path(),
+            },
+            family,
+            multiple_functions,
+            &config.processes,
+            &config.launch_options,
+        )?;
+        launch.bom.extend(builder.bom(&function_bundle_layer)?);
+        launch.labels.extend(builder.image_labels(
+            &function_bundle_layer,
+            &bundle_digest,
+            multiple_functions,
+        )?);
+        for (process_type, command_line) in launch::process_commands(&launch)? {
+            builder
+                .diagnostics()
+                .record("Processes", process_type, command_line);
+        }
+        for label in &launch.labels {
+            builder
+                .diagnostics()
+                .record("Labels", &label.key, &label.value);
+        }
+
+        ctx.write_launch(launch)
+            .context("Could not write launch.toml")
+    })?;
+
+    logger.header("Build summary")?;
+    for line in metrics.summary() {
+        logger.info(line)?;
+    }
+    builder.contribute_metrics_layer(metrics)?;
 
     Ok(())
 }
@@ -1,25 +1,78 @@
+use jvm_function_invoker_buildpack::{
+    config,
+    data::buildpack_toml,
+    detect,
+    util::logger::{JsonLogger, LogFormat, Logger, LoggerConfig, StandardLogger},
+};
 use libcnb::{
-    data::build_plan::{BuildPlan, Require},
+    data::build_plan::{BuildPlan, Provide, Require},
     detect::{cnb_runtime_detect, DetectOutcome, GenericDetectContext},
+    platform::Platform,
 };
+use std::env;
 
 fn main() {
     cnb_runtime_detect(detect)
 }
 
 fn detect(ctx: GenericDetectContext) -> anyhow::Result<DetectOutcome> {
+    let logger_config =
+        LoggerConfig::from_env(|key| ctx.platform.env().var(key).or_else(|_| env::var(key)).ok());
+
+    // Log processors expect one line per event, the human format lists every check.
+    match logger_config.format {
+        LogFormat::Human => detect_with_logger(
+            &ctx,
+            &StandardLogger::from_config(logger_config),
+            detect::skip_reasons,
+        ),
+        LogFormat::Json => detect_with_logger(
+            &ctx,
+            &JsonLogger::from_config(logger_config),
+            detect::failed_summary,
+        ),
+    }
+}
+
+fn detect_with_logger(
+    ctx: &GenericDetectContext,
+    logger: &impl Logger,
+    failure_message: fn(&[String]) -> String,
+) -> anyhow::Result<DetectOutcome> {
     let mut buildplan = BuildPlan::new();
 
-    // We check for a function.toml/project.toml to be able to distinguish between regular JVM applications and a function.
-    // Just from the application alone, they're indistinguishable by design.
-    let outcome = if ctx.app_dir().join("function.toml").exists()
-        || ctx.app_dir().join("project.toml").exists()
-    {
+    let project_path = ctx.platform.env().var(config::PROJECT_PATH_ENV).ok();
+    let mut failures = match config::project_dir(ctx.app_dir(), project_path) {
+        Ok(project_dir) => {
+            logger.debug(format!(
+                "Checking {} for a Java function",
+                project_dir.display()
+            ))?;
+            detect::check(project_dir)
+        }
+        Err(err) => vec![err.to_string()],
+    };
+    let metadata = buildpack_toml::Metadata::from_buildpack_dir(ctx.buildpack_dir())?;
+    // The detect context of libcnb 0.1 has no stack, the lifecycle exports it to every buildpack.
+    if let Ok(stack_id) = env::var("CNB_STACK_ID") {
+        failures.extend(metadata.unsupported_stack(&stack_id));
+    }
+    let outcome = if failures.is_empty() {
+        // The JDK and compiled application are contributed by the JVM buildpacks that must run
+        // before this one. Requiring what we provide keeps the plan valid on its own.
+        buildplan
+            .provides
+            .push(Provide::new("jvm-function-runtime"));
         buildplan.requires.push(Require::new("jdk"));
         buildplan.requires.push(Require::new("jvm-application"));
+        buildplan
+            .requires
+            .push(Require::new("jvm-function-runtime"));
 
         DetectOutcome::Pass(buildplan)
     } else {
+        logger.info(failure_message(&failures))?;
+
         DetectOutcome::Fail
     };
 
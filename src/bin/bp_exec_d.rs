@@ -0,0 +1,22 @@
+use jvm_function_invoker_buildpack::{exec_d, platform};
+use std::{env, fs, io::Write, path::Path};
+
+// Runs at container start, see the exec.d section of the CNB buildpack spec.
+fn main() -> anyhow::Result<()> {
+    let derived_env = exec_d::derived_env(
+        |key| env::var(key).ok(),
+        exec_d::memory_limit_bytes("/sys/fs/cgroup"),
+    );
+
+    if let Some(tmpdir) = derived_env
+        .get("TMPDIR")
+        .cloned()
+        .or_else(|| env::var("TMPDIR").ok())
+    {
+        fs::create_dir_all(Path::new(&tmpdir))?;
+    }
+
+    platform::exec_d_output()?.write_all(toml::to_string(&derived_env)?.as_bytes())?;
+
+    Ok(())
+}
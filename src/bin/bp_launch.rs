@@ -0,0 +1,98 @@
+use jvm_function_invoker_buildpack::{bindings, launcher, platform};
+use std::{env, ffi::OsString, path::PathBuf, process::Command, time::Duration};
+
+fn main() -> anyhow::Result<()> {
+    if env::args_os().nth(1).as_deref() == Some(launcher::HEALTH_PROCESS_TYPE.as_ref()) {
+        let (options, _) =
+            launcher::LaunchOptions::parse(&env::args_os().skip(2).collect::<Vec<_>>())?;
+        let (port, path) = launcher::health_check_target(|key| env::var(key).ok(), options.port);
+        return launcher::health_check(&port, &path, Duration::from_secs(5));
+    }
+
+    let mut args = env::args_os().skip(1).peekable();
+    let mut flag = |flag: &str| args.peek().map(OsString::as_os_str) == Some(flag.as_ref());
+    let debug = flag(launcher::DEBUG_FLAG);
+    let test = flag(launcher::TEST_FLAG);
+    if debug || test {
+        args.next();
+    }
+
+    let runtime_jar_path = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Missing runtime jar path argument"))?;
+    let function_bundle_dir = args
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Missing function bundle directory argument"))?;
+
+    // Remote debugging and `local-invoke` need the JVM, so only serving uses the native executable.
+    let native_binary = env::var_os(launcher::NATIVE_BINARY_ENV)
+        .map(PathBuf::from)
+        .filter(|path| path.is_file())
+        .filter(|_| !debug && !test && launcher::debug_port(|key| env::var(key).ok()).is_none());
+
+    let (program, program_args) = if test {
+        let args = launcher::local_invoke_args(&runtime_jar_path, &function_bundle_dir, |key| {
+            env::var(key).ok()
+        });
+        (PathBuf::from("java"), args)
+    } else if let Some(native_binary) = native_binary {
+        let (options, serve_args) = launcher::LaunchOptions::parse(&args.collect::<Vec<_>>())?;
+        let args = launcher::native_args(&function_bundle_dir, &options, &serve_args, |key| {
+            env::var(key).ok()
+        })?;
+        (native_binary, args)
+    } else {
+        let (options, serve_args) = launcher::LaunchOptions::parse(&args.collect::<Vec<_>>())?;
+        let args = launcher::java_args(
+            &runtime_jar_path,
+            &function_bundle_dir,
+            &options,
+            &serve_args,
+            |key| match key {
+                "SF_FUNCTIONS_DEBUG" if debug => Some(String::from("1")),
+                _ => env::var(key).ok(),
+            },
+            java_version,
+        )?;
+        (PathBuf::from("java"), args)
+    };
+
+    // Correlates logs of a running function with the runtime build it was built with.
+    if let Ok(runtime_version) = env::var(launcher::RUNTIME_VERSION_ENV) {
+        eprintln!("Starting Java function runtime {}", runtime_version);
+    }
+
+    let binding_root = bindings::launch_root(|key| env::var(key).ok());
+    let required_bindings =
+        bindings::parse_required(&env::var(bindings::REQUIRED_BINDINGS_ENV).unwrap_or_default());
+    if !required_bindings.is_empty() {
+        let available = match &binding_root {
+            Some(root) => bindings::read_all(root)?,
+            None => Vec::new(),
+        };
+        let unmet: Vec<String> = required_bindings
+            .iter()
+            .filter_map(|required| required.unmet(&available))
+            .collect();
+        if !unmet.is_empty() {
+            anyhow::bail!(
+                "Missing service bindings required by project.toml: {}",
+                unmet.join("; ")
+            );
+        }
+    }
+
+    let mut command = Command::new(program);
+    command.args(program_args);
+    // Platforms implementing the older CNB bindings extension only set CNB_BINDINGS, the runtime
+    // looks for bindings where the current spec puts them.
+    if let Some(root) = binding_root {
+        command.env(bindings::SERVICE_BINDING_ROOT_ENV, root);
+    }
+    Err(platform::exec(command).into())
+}
+
+fn java_version() -> Option<String> {
+    let output = Command::new("java").arg("-version").output().ok()?;
+    launcher::parse_java_version(&String::from_utf8_lossy(&output.stderr))
+}
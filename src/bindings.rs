@@ -0,0 +1,268 @@
+use serde::Deserialize;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the bindings at build and launch, see the Service Binding Specification for
+/// Kubernetes, which the CNB platform spec follows.
+pub const SERVICE_BINDING_ROOT_ENV: &str = "SERVICE_BINDING_ROOT";
+/// Directory of the bindings on platforms that implement the older CNB bindings extension.
+pub const CNB_BINDINGS_ENV: &str = "CNB_BINDINGS";
+/// Launch environment variable with the bindings `project.toml` requires, see [`format_required`].
+pub const REQUIRED_BINDINGS_ENV: &str = "SF_FUNCTION_REQUIRED_BINDINGS";
+/// Entries that describe a binding rather than hold one of its secrets.
+const RESERVED_ENTRIES: &[&str] = &["type", "provider"];
+
+/// A service binding, e.g. the credentials of a Salesforce org. Only the names of its entries are
+/// read, the secrets stay on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub r#type: String,
+    pub provider: Option<String>,
+    pub keys: BTreeSet<String>,
+}
+
+impl Binding {
+    /// Reads the binding in `dir`, `None` if it has no `type` entry and so isn't a binding.
+    pub fn from_dir(dir: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let dir = dir.as_ref();
+        let read_entry = |name| match fs::read_to_string(dir.join(name)) {
+            Ok(value) => Ok(Some(value.trim().to_string())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        };
+        let r#type = match read_entry("type")? {
+            Some(r#type) => r#type,
+            None => return Ok(None),
+        };
+
+        let mut keys = BTreeSet::new();
+        for entry in fs::read_dir(dir)? {
+            let key = entry?.file_name().to_string_lossy().into_owned();
+            // Kubernetes projects secrets through hidden `..data` directories.
+            if !key.starts_with('.') && !RESERVED_ENTRIES.contains(&key.as_str()) {
+                keys.insert(key);
+            }
+        }
+
+        Ok(Some(Binding {
+            name: dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            r#type,
+            provider: read_entry("provider")?,
+            keys,
+        }))
+    }
+}
+
+/// Every binding in `root`, sorted by name. A missing root has no bindings.
+pub fn read_all(root: impl AsRef<Path>) -> io::Result<Vec<Binding>> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut bindings = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let is_hidden = path
+            .file_name()
+            .map_or(true, |name| name.to_string_lossy().starts_with('.'));
+        if path.is_dir() && !is_hidden {
+            bindings.extend(Binding::from_dir(&path)?);
+        }
+    }
+    bindings.sort_by(|a, b| a.name.cmp(&b.name));
+
+    Ok(bindings)
+}
+
+/// Where the platform mounted the bindings of a running image, if anywhere.
+pub fn launch_root(env: impl Fn(&str) -> Option<String>) -> Option<PathBuf> {
+    env(SERVICE_BINDING_ROOT_ENV)
+        .or_else(|| env(CNB_BINDINGS_ENV))
+        .filter(|root| !root.is_empty())
+        .map(PathBuf::from)
+}
+
+/// A binding the function needs, `[[com.salesforce.functions.bindings]]` in `project.toml`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequiredBinding {
+    pub r#type: String,
+    /// Entries the binding must have, e.g. `instance-url`.
+    #[serde(default)]
+    pub keys: Vec<String>,
+}
+
+impl RequiredBinding {
+    /// Why none of `bindings` satisfies this requirement, `None` if one does.
+    pub fn unmet(&self, bindings: &[Binding]) -> Option<String> {
+        let candidates: Vec<&Binding> = bindings
+            .iter()
+            .filter(|binding| binding.r#type == self.r#type)
+            .collect();
+        if candidates.is_empty() {
+            return Some(format!("no binding of type {}", self.r#type));
+        }
+        if candidates
+            .iter()
+            .any(|binding| self.keys.iter().all(|key| binding.keys.contains(key)))
+        {
+            return None;
+        }
+
+        let binding = candidates[0];
+        let missing: Vec<&str> = self
+            .keys
+            .iter()
+            .filter(|key| !binding.keys.contains(*key))
+            .map(String::as_str)
+            .collect();
+        Some(format!(
+            "binding {} of type {} lacks {}",
+            binding.name,
+            self.r#type,
+            missing.join(", ")
+        ))
+    }
+}
+
+/// Renders `required` for [`REQUIRED_BINDINGS_ENV`], e.g. `salesforce-org:instance-url,token`.
+pub fn format_required(required: &[RequiredBinding]) -> String {
+    required
+        .iter()
+        .map(|binding| {
+            if binding.keys.is_empty() {
+                binding.r#type.clone()
+            } else {
+                format!("{}:{}", binding.r#type, binding.keys.join(","))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Reads back what [`format_required`] rendered.
+pub fn parse_required(value: &str) -> Vec<RequiredBinding> {
+    value
+        .split(';')
+        .filter(|binding| !binding.trim().is_empty())
+        .map(|binding| {
+            let (r#type, keys) = binding.split_once(':').unwrap_or((binding, ""));
+            RequiredBinding {
+                r#type: r#type.trim().to_string(),
+                keys: keys
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|key| !key.is_empty())
+                    .map(String::from)
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_binding(root: &Path, name: &str, entries: &[(&str, &str)]) -> io::Result<()> {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir)?;
+        for (key, value) in entries {
+            fs::write(dir.join(key), value)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_all_reads_typed_bindings() -> io::Result<()> {
+        let root = tempfile::tempdir()?;
+        write_binding(
+            root.path(),
+            "org",
+            &[
+                ("type", "salesforce-org\n"),
+                ("provider", "salesforce"),
+                ("instance-url", "https://example.my.salesforce.com"),
+                ("access-token", "secret"),
+            ],
+        )?;
+        write_binding(root.path(), "untyped", &[("password", "secret")])?;
+        write_binding(root.path(), "..data", &[("type", "hidden")])?;
+
+        assert_eq!(
+            read_all(root.path())?,
+            vec![Binding {
+                name: String::from("org"),
+                r#type: String::from("salesforce-org"),
+                provider: Some(String::from("salesforce")),
+                keys: vec![String::from("access-token"), String::from("instance-url")]
+                    .into_iter()
+                    .collect(),
+            }]
+        );
+        assert!(read_all(root.path().join("missing"))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unmet_explains_missing_binding_or_keys() {
+        let bindings = vec![Binding {
+            name: String::from("org"),
+            r#type: String::from("salesforce-org"),
+            provider: None,
+            keys: vec![String::from("instance-url")].into_iter().collect(),
+        }];
+        let required = |r#type: &str, keys: &[&str]| RequiredBinding {
+            r#type: r#type.to_string(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+        };
+
+        assert_eq!(
+            required("salesforce-org", &["instance-url"]).unmet(&bindings),
+            None
+        );
+        assert_eq!(
+            required("salesforce-org", &["instance-url", "access-token"]).unmet(&bindings),
+            Some(String::from(
+                "binding org of type salesforce-org lacks access-token"
+            ))
+        );
+        assert_eq!(
+            required("postgresql", &[]).unmet(&bindings),
+            Some(String::from("no binding of type postgresql"))
+        );
+    }
+
+    #[test]
+    fn parse_required_round_trips_format() {
+        let required = vec![
+            RequiredBinding {
+                r#type: String::from("salesforce-org"),
+                keys: vec![String::from("instance-url"), String::from("access-token")],
+            },
+            RequiredBinding {
+                r#type: String::from("postgresql"),
+                keys: Vec::new(),
+            },
+        ];
+        let formatted = format_required(&required);
+
+        assert_eq!(
+            formatted,
+            "salesforce-org:instance-url,access-token;postgresql"
+        );
+        assert_eq!(parse_required(&formatted), required);
+        assert!(parse_required("").is_empty());
+    }
+}
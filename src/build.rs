@@ -0,0 +1,5 @@
+pub mod bundler;
+pub mod dependency_audit;
+pub mod native;
+pub mod runtime;
+pub mod validate;
@@ -0,0 +1,164 @@
+use crate::util::process::{CommandRunner, OutputLine};
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+/// Number of stderr lines kept for error messages.
+pub const STDERR_TAIL_LINES: usize = 20;
+
+/// A `java -jar runtime.jar bundle` run, without the environment of the build.
+pub struct BundlerInvocation<'a> {
+    pub runtime_jar: &'a Path,
+    /// The project directory, or the single jar of a Spring Boot style app.
+    pub target: &'a Path,
+    pub output_dir: &'a Path,
+    pub java_options: &'a [String],
+    /// Writes one bundle per function into a subdirectory named after the function.
+    pub multiple_functions: bool,
+    /// Only rebundles these jars, `None` for a full bundle.
+    pub changed_jars: Option<&'a [PathBuf]>,
+}
+
+/// How the bundler exited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BundlerRun {
+    /// `None` if the bundler was killed by a signal.
+    pub exit_code: Option<i32>,
+    /// The last [`STDERR_TAIL_LINES`] lines the bundler wrote to stderr.
+    pub stderr_tail: Vec<String>,
+}
+
+impl BundlerInvocation<'_> {
+    /// Arguments of `java`, after any that must precede the configured options.
+    pub fn args(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = self.java_options.iter().map(OsString::from).collect();
+        args.push(OsString::from("-jar"));
+        args.push(self.runtime_jar.into());
+        args.push(OsString::from("bundle"));
+        args.push(self.target.into());
+        args.push(self.output_dir.into());
+        if self.multiple_functions {
+            args.push(OsString::from("--multiple"));
+        }
+        if let Some(changed_jars) = self.changed_jars {
+            args.push(OsString::from("--incremental"));
+            for jar in changed_jars {
+                args.push(OsString::from("--changed"));
+                args.push(jar.into());
+            }
+        }
+
+        args
+    }
+
+    /// Runs the bundler with `command`, a `java` command with the build environment configured.
+    /// Every line of output is passed to `on_line`.
+    pub fn run(
+        &self,
+        runner: &dyn CommandRunner,
+        mut command: Command,
+        timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> io::Result<BundlerRun> {
+        command.args(self.args());
+        let mut stderr_tail = VecDeque::with_capacity(STDERR_TAIL_LINES);
+        let exit_status = runner.run(&mut command, Some(timeout), &mut |line| match line {
+            OutputLine::Stdout(line) => on_line(&line),
+            OutputLine::Stderr(line) => {
+                on_line(&line);
+                if stderr_tail.len() == STDERR_TAIL_LINES {
+                    stderr_tail.pop_front();
+                }
+                stderr_tail.push_back(line);
+            }
+        })?;
+
+        Ok(BundlerRun {
+            exit_code: exit_status.code(),
+            stderr_tail: Vec::from(stderr_tail),
+        })
+    }
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::util::process::ScriptedRunner;
+
+    fn run(invocation: &BundlerInvocation, runner: &ScriptedRunner) -> io::Result<BundlerRun> {
+        invocation.run(
+            runner,
+            Command::new("java"),
+            Duration::from_secs(60),
+            &mut |_| {},
+        )
+    }
+
+    fn invocation<'a>(
+        java_options: &'a [String],
+        changed_jars: Option<&'a [PathBuf]>,
+    ) -> BundlerInvocation<'a> {
+        BundlerInvocation {
+            runtime_jar: Path::new("/layers/runtime/runtime.jar"),
+            target: Path::new("/workspace"),
+            output_dir: Path::new("/layers/function-bundle"),
+            java_options,
+            multiple_functions: true,
+            changed_jars,
+        }
+    }
+
+    #[test]
+    fn run_passes_bundle_arguments() -> io::Result<()> {
+        let java_options = vec![String::from("-Xmx512m")];
+        let changed_jars = vec![PathBuf::from("/workspace/target/function.jar")];
+        let runner = ScriptedRunner::new(0, Vec::new());
+
+        run(&invocation(&java_options, Some(&changed_jars)), &runner)?;
+
+        assert_eq!(
+            runner.args.into_inner(),
+            vec![
+                "-Xmx512m",
+                "-jar",
+                "/layers/runtime/runtime.jar",
+                "bundle",
+                "/workspace",
+                "/layers/function-bundle",
+                "--multiple",
+                "--incremental",
+                "--changed",
+                "/workspace/target/function.jar",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_reports_exit_code_and_stderr_tail() -> io::Result<()> {
+        for code in 0..=6 {
+            let mut lines = vec![OutputLine::Stdout(String::from("Found 1 function"))];
+            lines.extend(
+                (0..25).map(|line| OutputLine::Stderr(format!("error {} of exit {}", line, code))),
+            );
+            let runner = ScriptedRunner::new(code, lines);
+
+            let bundler_run = run(&invocation(&[], None), &runner)?;
+
+            assert_eq!(bundler_run.exit_code, Some(code));
+            assert_eq!(bundler_run.stderr_tail.len(), STDERR_TAIL_LINES);
+            assert_eq!(
+                bundler_run.stderr_tail.last().map(String::as_str),
+                Some(format!("error 24 of exit {}", code).as_str())
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::{
+    data::buildpack_toml::VulnerableVersion, detect::SDK_ARTIFACT_ID, util::logger::Logger,
+};
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+
+const GRADLE_LOCKFILE: &str = "gradle.lockfile";
+
+/// A resolved dependency of the function, as far as a jar name or lockfile entry tells.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Dependency {
+    pub artifact: String,
+    pub version: String,
+}
+
+impl Dependency {
+    /// Splits a Maven style jar name like `sf-fx-sdk-java-1.0.2.jar` at the first `-` followed
+    /// by a digit. `None` for jars without a version.
+    pub fn from_jar_name(file_name: &str) -> Option<Self> {
+        let stem = file_name.strip_suffix(".jar")?;
+        let split = stem.char_indices().find(|&(index, c)| {
+            c == '-'
+                && stem[index + 1..]
+                    .chars()
+                    .next()
+                    .map_or(false, |c| c.is_ascii_digit())
+        })?;
+
+        Some(Dependency {
+            artifact: stem[..split.0].to_string(),
+            version: stem[split.0 + 1..].to_string(),
+        })
+    }
+
+    /// Maven snapshots, either `1.0-SNAPSHOT` or resolved to a timestamp like
+    /// `1.0-20240101.120000-1`.
+    pub fn is_snapshot(&self) -> bool {
+        if self.version.ends_with("SNAPSHOT") {
+            return true;
+        }
+
+        let mut parts = self.version.rsplitn(3, '-');
+        let (build, timestamp) = (parts.next(), parts.next());
+        let digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+        matches!(
+            (build, timestamp.and_then(|timestamp| timestamp.split_once('.'))),
+            (Some(build), Some((date, time)))
+                if digits(build) && digits(date) && date.len() == 8 && digits(time) && time.len() == 6
+        )
+    }
+}
+
+impl Display for Dependency {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.artifact, self.version)
+    }
+}
+
+/// Runtime dependencies locked in the `gradle.lockfile` of `project_dir`, empty without one.
+pub fn gradle_locked_dependencies(project_dir: &Path) -> io::Result<Vec<Dependency>> {
+    let contents = match fs::read_to_string(project_dir.join(GRADLE_LOCKFILE)) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    // Entries look like `group:artifact:version=compileClasspath,runtimeClasspath`.
+    Ok(contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let (coordinates, configurations) = line.split_once('=')?;
+            if !configurations
+                .split(',')
+                .any(|name| name == "runtimeClasspath")
+            {
+                return None;
+            }
+            let mut coordinates = coordinates.split(':').skip(1);
+            Some(Dependency {
+                artifact: coordinates.next()?.to_string(),
+                version: coordinates.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Dependencies that shouldn't ship in a function image.
+#[derive(Debug, Default, PartialEq)]
+pub struct Findings {
+    pub snapshots: Vec<Dependency>,
+    /// Function SDK versions listed as vulnerable, with the advisory if there is one.
+    pub vulnerable_sdks: Vec<(Dependency, Option<String>)>,
+}
+
+impl Findings {
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty() && self.vulnerable_sdks.is_empty()
+    }
+
+    /// One warning for all snapshots, which make rebuilds of the same commit differ, and one per
+    /// vulnerable SDK version.
+    pub fn log(&self, logger: &impl Logger) -> anyhow::Result<()> {
+        if !self.snapshots.is_empty() {
+            logger.warning(
+                "Snapshot dependencies",
+                format!(
+                    "The function depends on snapshot versions, which can change between builds \
+                     of the same code:\n{}\n\n\
+                     Depend on released versions for reproducible builds.",
+                    self.snapshots
+                        .iter()
+                        .map(|dependency| format!("  - {}", dependency))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            )?;
+        }
+        for (dependency, advisory) in &self.vulnerable_sdks {
+            logger.warning(
+                "Vulnerable function SDK",
+                format!(
+                    "The function depends on {}, which has a known vulnerability.{}\n\
+                     Upgrade com.salesforce.functions:{} to a newer version.",
+                    dependency,
+                    advisory
+                        .as_ref()
+                        .map(|advisory| format!(" See {}", advisory))
+                        .unwrap_or_default(),
+                    SDK_ARTIFACT_ID
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Checks `dependencies`, which may contain duplicates, against `vulnerable` SDK versions.
+pub fn audit(
+    dependencies: impl IntoIterator<Item = Dependency>,
+    vulnerable: &[VulnerableVersion],
+) -> Findings {
+    let dependencies: BTreeSet<Dependency> = dependencies.into_iter().collect();
+    let mut findings = Findings::default();
+
+    for dependency in dependencies {
+        if dependency.is_snapshot() {
+            findings.snapshots.push(dependency.clone());
+        }
+        if dependency.artifact == SDK_ARTIFACT_ID {
+            if let Some(entry) = vulnerable
+                .iter()
+                .find(|entry| entry.version == dependency.version)
+            {
+                findings
+                    .vulnerable_sdks
+                    .push((dependency, entry.advisory.clone()));
+            }
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support,
+        util::logger::{Entry, Level, MemoryLogger},
+    };
+
+    fn dependency(artifact: &str, version: &str) -> Dependency {
+        Dependency {
+            artifact: artifact.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn from_jar_name_splits_artifact_and_version() {
+        assert_eq!(
+            Dependency::from_jar_name("sf-fx-sdk-java-1.0.2.jar"),
+            Some(dependency("sf-fx-sdk-java", "1.0.2"))
+        );
+        assert_eq!(
+            Dependency::from_jar_name("commons-lang3-3.12.0-SNAPSHOT.jar"),
+            Some(dependency("commons-lang3", "3.12.0-SNAPSHOT"))
+        );
+        assert_eq!(Dependency::from_jar_name("function.jar"), None);
+        assert_eq!(Dependency::from_jar_name("README-1.txt"), None);
+    }
+
+    #[test]
+    fn is_snapshot_recognizes_timestamped_snapshots() {
+        assert!(dependency("lib", "1.0-SNAPSHOT").is_snapshot());
+        assert!(dependency("lib", "1.0-20240101.120000-1").is_snapshot());
+        assert!(!dependency("lib", "1.0.0").is_snapshot());
+        assert!(!dependency("lib", "2.0-beta-1").is_snapshot());
+    }
+
+    #[test]
+    fn gradle_locked_dependencies_reads_runtime_classpath() -> anyhow::Result<()> {
+        let app_dir = test_support::app_dir(&[(
+            GRADLE_LOCKFILE,
+            "# This is a Gradle generated file for dependency locking.\n\
+             com.salesforce.functions:sf-fx-sdk-java:1.0.0=compileClasspath,runtimeClasspath\n\
+             junit:junit:4.13-SNAPSHOT=testRuntimeClasspath\n\
+             empty=annotationProcessor\n",
+        )])?;
+
+        assert_eq!(
+            gradle_locked_dependencies(app_dir.path())?,
+            vec![dependency("sf-fx-sdk-java", "1.0.0")]
+        );
+        assert!(gradle_locked_dependencies(tempfile::tempdir()?.path())?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn audit_reports_snapshots_and_vulnerable_sdks_once() {
+        let vulnerable = vec![VulnerableVersion {
+            version: String::from("1.0.0"),
+            advisory: Some(String::from("https://example.com/advisory")),
+        }];
+        let findings = audit(
+            vec![
+                dependency("sf-fx-sdk-java", "1.0.0"),
+                dependency("sf-fx-sdk-java", "1.0.0"),
+                dependency("lib", "2.0-SNAPSHOT"),
+                dependency("other", "1.0.0"),
+            ],
+            &vulnerable,
+        );
+
+        assert_eq!(findings.snapshots, vec![dependency("lib", "2.0-SNAPSHOT")]);
+        assert_eq!(
+            findings.vulnerable_sdks,
+            vec![(
+                dependency("sf-fx-sdk-java", "1.0.0"),
+                Some(String::from("https://example.com/advisory"))
+            )]
+        );
+        assert!(audit(vec![dependency("sf-fx-sdk-java", "1.1.0")], &vulnerable).is_empty());
+    }
+
+    #[test]
+    fn log_warns_about_every_finding() -> anyhow::Result<()> {
+        let findings = Findings {
+            snapshots: vec![
+                dependency("lib", "2.0-SNAPSHOT"),
+                dependency("other", "1.0-20240101.120000-1"),
+            ],
+            vulnerable_sdks: vec![(
+                dependency("sf-fx-sdk-java", "1.0.0"),
+                Some(String::from("https://example.com/advisory")),
+            )],
+        };
+        let logger = MemoryLogger::new(false);
+        findings.log(&logger)?;
+
+        assert_eq!(
+            logger.entries(),
+            vec![
+                Entry {
+                    level: Level::Warning,
+                    header: Some(String::from("Snapshot dependencies")),
+                    message: String::from(
+                        "The function depends on snapshot versions, which can change between \
+                         builds of the same code:\n  \
+                         - lib 2.0-SNAPSHOT\n  \
+                         - other 1.0-20240101.120000-1\n\n\
+                         Depend on released versions for reproducible builds."
+                    ),
+                },
+                Entry {
+                    level: Level::Warning,
+                    header: Some(String::from("Vulnerable function SDK")),
+                    message: String::from(
+                        "The function depends on sf-fx-sdk-java 1.0.0, which has a known \
+                         vulnerability. See https://example.com/advisory\n\
+                         Upgrade com.salesforce.functions:sf-fx-sdk-java to a newer version."
+                    ),
+                },
+            ]
+        );
+
+        let logger = MemoryLogger::new(false);
+        Findings::default().log(&logger)?;
+        assert!(logger.entries().is_empty());
+
+        Ok(())
+    }
+}
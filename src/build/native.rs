@@ -0,0 +1,171 @@
+use crate::{
+    platform::Family,
+    util::process::{CommandRunner, OutputLine},
+};
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+    time::Duration,
+};
+
+/// Build environment variable that opts into compiling the function into a native executable.
+pub const NATIVE_ENV: &str = "SF_FUNCTIONS_NATIVE";
+pub const NATIVE_LAYER_NAME: &str = "native-image";
+pub const BINARY_FILE_NAME: &str = "function";
+
+const CLASSPATH_DIR_NAME: &str = "classpath";
+
+/// Whether `SF_FUNCTIONS_NATIVE` asks for a native executable.
+pub fn enabled(value: Option<&str>) -> bool {
+    matches!(value, Some("1") | Some("true"))
+}
+
+/// Version of the GraalVM `native-image` tool on the `PATH`, `None` if the JDK of the build
+/// doesn't have one.
+pub fn graalvm_version(runner: &dyn CommandRunner, timeout: Duration) -> Option<String> {
+    let (exit_status, stdout) = runner
+        .stdout(Command::new("native-image").arg("--version"), Some(timeout))
+        .ok()?;
+    if !exit_status.success() {
+        return None;
+    }
+
+    stdout
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(String::from)
+}
+
+/// A `native-image` run that compiles the runtime together with a function bundle, so the
+/// executable serves the function like `java -jar runtime.jar` does.
+pub struct NativeImageInvocation<'a> {
+    pub runtime_jar: &'a Path,
+    pub runtime_main_class: &'a str,
+    pub bundle_dir: &'a Path,
+    /// Path of the executable, without the extension of the platform.
+    pub output: &'a Path,
+}
+
+impl NativeImageInvocation<'_> {
+    /// The runtime jar followed by the class directory and jars of the bundle, in name order so
+    /// the image doesn't change with directory listing order.
+    pub fn classpath(&self) -> io::Result<OsString> {
+        let classpath_dir = self.bundle_dir.join(CLASSPATH_DIR_NAME);
+        let mut jars: Vec<PathBuf> = fs::read_dir(&classpath_dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<io::Result<Vec<_>>>()?
+            .into_iter()
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |extension| extension == "jar")
+            })
+            .collect();
+        jars.sort();
+
+        let mut classpath = self.runtime_jar.as_os_str().to_os_string();
+        for entry in std::iter::once(classpath_dir).chain(jars) {
+            classpath.push(Family::current().classpath_separator());
+            classpath.push(entry);
+        }
+
+        Ok(classpath)
+    }
+
+    pub fn args(&self) -> io::Result<Vec<OsString>> {
+        Ok(vec![
+            OsString::from("--no-fallback"),
+            OsString::from("-cp"),
+            self.classpath()?,
+            OsString::from(self.runtime_main_class),
+            OsString::from("-o"),
+            self.output.into(),
+        ])
+    }
+
+    /// Runs `native-image`, passing every line of output to `on_line`.
+    pub fn run(
+        &self,
+        runner: &dyn CommandRunner,
+        timeout: Duration,
+        on_line: &mut dyn FnMut(&str),
+    ) -> io::Result<ExitStatus> {
+        let mut command = Command::new("native-image");
+        command.args(self.args()?);
+        runner.run(&mut command, Some(timeout), &mut |line| match line {
+            OutputLine::Stdout(line) | OutputLine::Stderr(line) => on_line(&line),
+        })
+    }
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::util::process::ScriptedRunner;
+
+    #[test]
+    fn enabled_accepts_flag_values() {
+        assert!(enabled(Some("1")));
+        assert!(enabled(Some("true")));
+        assert!(!enabled(Some("0")));
+        assert!(!enabled(None));
+    }
+
+    #[test]
+    fn graalvm_version_reads_first_line() {
+        let runner = ScriptedRunner::new(
+            0,
+            vec![
+                OutputLine::Stdout(String::from("native-image 21.0.2 2024-01-16")),
+                OutputLine::Stdout(String::from("GraalVM Runtime Environment")),
+            ],
+        );
+        assert_eq!(
+            graalvm_version(&runner, Duration::from_secs(5)).as_deref(),
+            Some("native-image 21.0.2 2024-01-16")
+        );
+        assert_eq!(*runner.args.borrow(), vec![OsString::from("--version")]);
+
+        let runner = ScriptedRunner::new(1, Vec::new());
+        assert_eq!(graalvm_version(&runner, Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn run_compiles_runtime_with_bundle_classpath() -> io::Result<()> {
+        let bundle_dir = tempfile::tempdir()?;
+        let classpath_dir = bundle_dir.path().join(CLASSPATH_DIR_NAME);
+        fs::create_dir(&classpath_dir)?;
+        for name in &["lib.jar", "function.jar", "README.txt"] {
+            fs::write(classpath_dir.join(name), "")?;
+        }
+
+        let runner = ScriptedRunner::new(0, Vec::new());
+        let exit_status = NativeImageInvocation {
+            runtime_jar: Path::new("/layers/runtime/runtime.jar"),
+            runtime_main_class: "com.example.Runtime",
+            bundle_dir: bundle_dir.path(),
+            output: Path::new("/layers/native-image/function"),
+        }
+        .run(&runner, Duration::from_secs(60), &mut |_| {})?;
+
+        assert!(exit_status.success());
+        assert_eq!(
+            *runner.args.borrow(),
+            vec![
+                OsString::from("--no-fallback"),
+                OsString::from("-cp"),
+                OsString::from(format!(
+                    "/layers/runtime/runtime.jar:{0}:{0}/function.jar:{0}/lib.jar",
+                    classpath_dir.display()
+                )),
+                OsString::from("com.example.Runtime"),
+                OsString::from("-o"),
+                OsString::from("/layers/native-image/function"),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    data::{runtime_descriptor::Descriptor, Runtime},
+    util::{
+        archive::{self, Compression},
+        checksum::{Algorithm, Checksum},
+        process::CommandRunner,
+    },
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+pub const RUNTIME_JAR_FILE_NAME: &str = "runtime.jar";
+const ARCHIVE_FILE_STEM: &str = "runtime";
+/// Files of a runtime archive besides the jar, e.g. native libraries.
+const RESOURCES_DIR_NAME: &str = "runtime";
+
+/// Where the runtime artifact from `source`, a URL or local path, is installed in the layer.
+/// Archives keep their compression in the file name, so they can be unpacked afterwards.
+pub fn artifact_path(layer_dir: &Path, source: &str) -> (PathBuf, Option<Compression>) {
+    match Compression::from_file_name(source) {
+        Some(compression) => (
+            layer_dir.join(format!("{}.{}", ARCHIVE_FILE_STEM, compression.extension())),
+            Some(compression),
+        ),
+        None => (layer_dir.join(RUNTIME_JAR_FILE_NAME), None),
+    }
+}
+
+/// Unpacks a runtime archive into the layer and moves the jar inside to the runtime jar path.
+/// Returns where the jar was in the archive.
+pub fn unpack(
+    archive_path: &Path,
+    compression: Compression,
+    layer_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let resources_dir = layer_dir.join(RESOURCES_DIR_NAME);
+    archive::unpack(archive_path, compression, &resources_dir)?;
+    let jar_path = archive::find_jar(&resources_dir)?;
+    fs::rename(&jar_path, layer_dir.join(RUNTIME_JAR_FILE_NAME))?;
+    fs::remove_file(archive_path)?;
+
+    Ok(jar_path
+        .strip_prefix(&resources_dir)
+        .map(Path::to_path_buf)
+        .unwrap_or(jar_path))
+}
+
+/// Top-level entries of the runtime layer, see [`crate::layer::LayerContributor::expected_entries`].
+pub fn expected_entries() -> Vec<String> {
+    vec![
+        format!("{}.*", ARCHIVE_FILE_STEM),
+        String::from(RUNTIME_JAR_FILE_NAME),
+        String::from(RESOURCES_DIR_NAME),
+    ]
+}
+
+/// The sha256 of an installed runtime artifact, from buildpack metadata where it declares one.
+pub fn installed_sha256(runtime: &Runtime, artifact_path: &Path) -> io::Result<String> {
+    match runtime.known_sha256() {
+        Some(sha256) => Ok(sha256.to_string()),
+        None => Checksum::of_file(Algorithm::Sha256, artifact_path).map(|checksum| checksum.hex),
+    }
+}
+
+/// What `java -jar runtime.jar describe` says about the runtime.
+#[derive(Debug)]
+pub enum Description {
+    Described(Descriptor),
+    /// The runtime predates `describe`.
+    Unsupported,
+    Unreadable(anyhow::Error),
+}
+
+pub fn describe(
+    runner: &dyn CommandRunner,
+    runtime_jar: &Path,
+    timeout: Duration,
+) -> io::Result<Description> {
+    let (exit_status, stdout) = runner.stdout(
+        Command::new("java")
+            .arg("-jar")
+            .arg(runtime_jar)
+            .arg("describe"),
+        Some(timeout),
+    )?;
+
+    // Older runtimes reject the unknown command with a usage message.
+    if !exit_status.success() {
+        return Ok(Description::Unsupported);
+    }
+    Ok(match Descriptor::from_json(&stdout) {
+        Ok(descriptor) => Description::Described(descriptor),
+        Err(err) => Description::Unreadable(err),
+    })
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::util::{
+        self,
+        process::{OutputLine, ScriptedRunner},
+    };
+
+    #[test]
+    fn artifact_path_keeps_archive_compression() {
+        let layer_dir = Path::new("/layers/sf-fx-runtime-java");
+
+        assert_eq!(
+            artifact_path(layer_dir, "https://foo.com/runtime-1.0.0.jar"),
+            (layer_dir.join("runtime.jar"), None)
+        );
+        assert_eq!(
+            artifact_path(layer_dir, "/tmp/runtime-1.0.0.tgz"),
+            (layer_dir.join("runtime.tar.gz"), Some(Compression::Gzip))
+        );
+    }
+
+    #[test]
+    fn installed_sha256_prefers_declared_sha256() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("runtime.jar");
+        fs::write(&path, "runtime")?;
+
+        let declared = Runtime {
+            sha256: String::from("ABCDEF"),
+            ..Runtime::default()
+        };
+        assert_eq!(installed_sha256(&declared, &path)?, "ABCDEF");
+        assert_eq!(
+            installed_sha256(&Runtime::default(), &path)?,
+            util::sha256(b"runtime")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn describe_tolerates_legacy_and_malformed_output() -> io::Result<()> {
+        let run = |exit_code, stdout: &[&str]| {
+            let lines = stdout
+                .iter()
+                .map(|line| OutputLine::Stdout(line.to_string()))
+                .chain(Some(OutputLine::Stderr(String::from("WARNING: JVM"))))
+                .collect();
+            describe(
+                &ScriptedRunner::new(exit_code, lines),
+                Path::new("runtime.jar"),
+                Duration::from_secs(30),
+            )
+        };
+
+        assert!(matches!(
+            run(0, &[r#"{ "schema_version": 1 }"#])?,
+            Description::Described(descriptor) if descriptor.schema_version == 1
+        ));
+        assert!(matches!(
+            run(2, &["Unknown command: describe"])?,
+            Description::Unsupported
+        ));
+        assert!(matches!(
+            run(0, &["Usage: runtime.jar [bundle|serve]"])?,
+            Description::Unreadable(_)
+        ));
+
+        Ok(())
+    }
+}
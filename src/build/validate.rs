@@ -0,0 +1,185 @@
+use crate::{data::function_bundle, util::jar};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+const CLASSPATH_DIR_NAME: &str = "classpath";
+
+/// Problems with the function bundle in `bundle_dir` that would otherwise only surface at launch.
+/// The bundle must not refer to `build_dirs`, which don't exist in the image.
+pub fn validate_bundle(bundle_dir: &Path, build_dirs: &[PathBuf]) -> io::Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let toml_path = bundle_dir.join(function_bundle::FILE_NAME);
+    if !toml_path.is_file() {
+        problems.push(format!("{} is missing", function_bundle::FILE_NAME));
+        return Ok(problems);
+    }
+
+    match function_bundle::Toml::from_bundle_dir(bundle_dir) {
+        Ok(toml) => {
+            if let Some(problem) = missing_class(bundle_dir, &toml.function.class)? {
+                problems.push(problem);
+            }
+        }
+        Err(err) => problems.push(format!("{:#}", err)),
+    }
+
+    let contents = fs::read_to_string(&toml_path)?;
+    for dir in build_dirs {
+        if contents.contains(&*dir.to_string_lossy()) {
+            problems.push(format!(
+                "{} refers to {}, which doesn't exist at launch",
+                function_bundle::FILE_NAME,
+                dir.display()
+            ));
+        }
+    }
+    for (link, target) in symlinks(bundle_dir)? {
+        if build_dirs.iter().any(|dir| target.starts_with(dir)) {
+            problems.push(format!(
+                "{} links to {}, which doesn't exist at launch",
+                link.strip_prefix(bundle_dir).unwrap_or(&link).display(),
+                target.display()
+            ));
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Checks that the class file of `class` is in the bundle classpath, either as a file or in a jar.
+fn missing_class(bundle_dir: &Path, class: &str) -> io::Result<Option<String>> {
+    let classpath_dir = bundle_dir.join(CLASSPATH_DIR_NAME);
+    if !classpath_dir.is_dir() {
+        return Ok(Some(format!(
+            "The bundle has no {} directory",
+            CLASSPATH_DIR_NAME
+        )));
+    }
+
+    let class_file = format!("{}.class", class.replace('.', "/"));
+    if classpath_dir.join(&class_file).is_file() {
+        return Ok(None);
+    }
+    for entry in fs::read_dir(&classpath_dir)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "jar")
+        {
+            match jar::entry_names(&path) {
+                Ok(names) if names.contains(&class_file) => return Ok(None),
+                Ok(_) => {}
+                // Broken links are reported separately.
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    return Ok(Some(format!(
+                        "{} is not a valid jar",
+                        path.strip_prefix(bundle_dir).unwrap_or(&path).display()
+                    )))
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    Ok(Some(format!(
+        "The function class {} is not on the bundle classpath",
+        class
+    )))
+}
+
+/// Symbolic links below `dir` with their absolute targets.
+fn symlinks(dir: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let mut links = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_symlink() {
+                let target = fs::read_link(entry.path())?;
+                links.push((entry.path(), dir.join(target)));
+            } else if file_type.is_dir() {
+                dirs.push(entry.path());
+            }
+        }
+    }
+
+    Ok(links)
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    const FUNCTION_BUNDLE_TOML: &str = r#"
+[function]
+class = "com.example.MyFunction"
+payload_class = "java.lang.String"
+payload_media_type = "application/json"
+return_class = "java.lang.String"
+return_media_type = "application/json"
+"#;
+
+    #[test]
+    fn validate_bundle_accepts_complete_bundle() -> io::Result<()> {
+        let bundle_dir =
+            test_support::app_dir(&[(function_bundle::FILE_NAME, FUNCTION_BUNDLE_TOML)])?;
+        fs::create_dir(bundle_dir.path().join("classpath"))?;
+        fs::write(
+            bundle_dir.path().join("classpath").join("function.jar"),
+            jar::stored_jar("com/example/MyFunction.class", ""),
+        )?;
+
+        assert!(validate_bundle(
+            bundle_dir.path(),
+            &[PathBuf::from("/layers/dependency-cache")]
+        )?
+        .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_bundle_reports_precise_problems() -> io::Result<()> {
+        let cache_dir = tempfile::tempdir()?;
+        let bundle_dir = test_support::app_dir(&[
+            (
+                function_bundle::FILE_NAME,
+                &format!(
+                    "{}\n[function.annotations]\nsource = \"{}/repository\"\n",
+                    FUNCTION_BUNDLE_TOML,
+                    cache_dir.path().display()
+                ),
+            ),
+            ("classpath/com/example/Other.class", ""),
+        ])?;
+        std::os::unix::fs::symlink(
+            cache_dir.path().join("dependency-1.0.jar"),
+            bundle_dir
+                .path()
+                .join("classpath")
+                .join("dependency-1.0.jar"),
+        )?;
+
+        let problems = validate_bundle(bundle_dir.path(), &[cache_dir.path().to_path_buf()])?;
+
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        assert_eq!(
+            problems[0],
+            "The function class com.example.MyFunction is not on the bundle classpath"
+        );
+        assert!(problems[1].starts_with("function-bundle.toml refers to "));
+        assert!(problems[2].starts_with("classpath/dependency-1.0.jar links to "));
+
+        assert_eq!(
+            validate_bundle(cache_dir.path(), &[])?,
+            vec!["function-bundle.toml is missing"]
+        );
+
+        Ok(())
+    }
+}
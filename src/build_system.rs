@@ -0,0 +1,240 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Jars Gradle builds next to the application jar that don't contain the application itself.
+const SECONDARY_JAR_SUFFIXES: &[&str] = &["-plain.jar", "-sources.jar", "-javadoc.jar"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BuildSystem {
+    Maven,
+    Gradle,
+}
+
+impl BuildSystem {
+    /// Maven wins when both build files exist, matching the order of the JVM buildpacks.
+    pub fn detect(app_dir: impl AsRef<Path>) -> Option<Self> {
+        let app_dir = app_dir.as_ref();
+
+        if app_dir.join("pom.xml").exists() {
+            Some(BuildSystem::Maven)
+        } else if ["build.gradle", "build.gradle.kts"]
+            .iter()
+            .any(|file| app_dir.join(file).exists())
+        {
+            Some(BuildSystem::Gradle)
+        } else {
+            None
+        }
+    }
+}
+
+/// The application jar in `build/libs`, `None` if Gradle didn't build one yet.
+pub fn gradle_jar(app_dir: impl AsRef<Path>) -> anyhow::Result<Option<PathBuf>> {
+    let libs_dir = app_dir.as_ref().join("build").join("libs");
+    if !libs_dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut jars = Vec::new();
+    for entry in fs::read_dir(&libs_dir)? {
+        let path = entry?.path();
+        let file_name = path
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let is_application_jar = file_name.ends_with(".jar")
+            && !SECONDARY_JAR_SUFFIXES
+                .iter()
+                .any(|suffix| file_name.ends_with(suffix));
+        if path.is_file() && is_application_jar {
+            jars.push(path);
+        }
+    }
+    jars.sort();
+
+    match jars.len() {
+        0 | 1 => Ok(jars.pop()),
+        _ => Err(anyhow::anyhow!(
+            "Found multiple jars in {}: {}. Configure the Gradle build to produce a single \
+             application jar.",
+            libs_dir.display(),
+            jars.iter()
+                .filter_map(|jar| jar.file_name())
+                .map(|file_name| file_name.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Jars directly in a `target` directory of the app or one of its modules, as built by Maven,
+/// relative to the app and sorted. Hidden directories are skipped.
+pub fn maven_module_jars(app_dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let app_dir = app_dir.as_ref();
+    let mut jars = Vec::new();
+    let mut dirs = vec![app_dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let is_target = dir.file_name().map_or(false, |name| name == "target");
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let is_hidden = entry.file_name().to_string_lossy().starts_with('.');
+            if entry.file_type()?.is_dir() {
+                // Nothing below target is a module, e.g. target/classes.
+                if !is_target && !is_hidden {
+                    dirs.push(path);
+                }
+            } else if is_target
+                && path
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+            {
+                jars.push(path.strip_prefix(app_dir).unwrap_or(&path).to_path_buf());
+            }
+        }
+    }
+    jars.sort();
+
+    Ok(jars)
+}
+
+/// Builds the application jar without running tests, preferring the Gradle wrapper of the app.
+pub fn gradle_command(app_dir: impl AsRef<Path>) -> Command {
+    let app_dir = app_dir.as_ref();
+    let wrapper = app_dir.join("gradlew");
+
+    let mut command = if wrapper.is_file() {
+        Command::new(wrapper)
+    } else {
+        Command::new("gradle")
+    };
+    command
+        .current_dir(app_dir)
+        .args(&["assemble", "--no-daemon"]);
+
+    command
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn detect_prefers_maven() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        assert_eq!(BuildSystem::detect(app_dir.path()), None);
+
+        fs::write(app_dir.path().join("build.gradle.kts"), "")?;
+        assert_eq!(
+            BuildSystem::detect(app_dir.path()),
+            Some(BuildSystem::Gradle)
+        );
+
+        fs::write(app_dir.path().join("pom.xml"), "")?;
+        assert_eq!(
+            BuildSystem::detect(app_dir.path()),
+            Some(BuildSystem::Maven)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn gradle_jar_skips_secondary_jars() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        assert_eq!(gradle_jar(app_dir.path())?, None);
+
+        let libs_dir = app_dir.path().join("build").join("libs");
+        fs::create_dir_all(&libs_dir)?;
+        for name in &[
+            "function-1.0-plain.jar",
+            "function-1.0-sources.jar",
+            "notes.txt",
+        ] {
+            fs::write(libs_dir.join(name), "")?;
+        }
+        assert_eq!(gradle_jar(app_dir.path())?, None);
+
+        fs::write(libs_dir.join("function-1.0.jar"), "")?;
+        assert_eq!(
+            gradle_jar(app_dir.path())?,
+            Some(libs_dir.join("function-1.0.jar"))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn gradle_jar_rejects_ambiguous_jars() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let libs_dir = app_dir.path().join("build").join("libs");
+        fs::create_dir_all(&libs_dir)?;
+        fs::write(libs_dir.join("a.jar"), "")?;
+        fs::write(libs_dir.join("b.jar"), "")?;
+
+        let err = gradle_jar(app_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("a.jar, b.jar"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn maven_module_jars_finds_jars_of_all_modules() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        for dir in &[
+            "target/classes",
+            "api/target",
+            "function/target",
+            ".git/target",
+        ] {
+            fs::create_dir_all(app_dir.path().join(dir))?;
+        }
+        for jar in &[
+            "target/app.jar",
+            "target/classes/resource.jar",
+            "api/target/api-1.0.jar",
+            "function/target/function-1.0.jar",
+            ".git/target/ignored.jar",
+        ] {
+            fs::write(app_dir.path().join(jar), "")?;
+        }
+
+        assert_eq!(
+            maven_module_jars(app_dir.path())?,
+            vec![
+                PathBuf::from("api/target/api-1.0.jar"),
+                PathBuf::from("function/target/function-1.0.jar"),
+                PathBuf::from("target/app.jar"),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn gradle_command_prefers_wrapper() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        assert_eq!(
+            gradle_command(app_dir.path()).get_program(),
+            OsStr::new("gradle")
+        );
+
+        fs::write(app_dir.path().join("gradlew"), "")?;
+        let command = gradle_command(app_dir.path());
+        assert_eq!(
+            command.get_program(),
+            app_dir.path().join("gradlew").as_os_str()
+        );
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec![OsStr::new("assemble"), OsStr::new("--no-daemon")]
+        );
+
+        Ok(())
+    }
+}
@@ -1,188 +1,715 @@
-use crate::util::{self, logger::Logger};
-use libcnb::{build::GenericBuildContext, layer::Layer};
-use std::{convert::TryFrom, fs, path::Path, process::Command};
+use crate::{
+    bindings,
+    build::{self, runtime::Description},
+    config::{self, FunctionConfig},
+    data::{buildpack_toml, function_bundle, runtime, runtime_descriptor::Descriptor, Runtime},
+    diagnostics::Diagnostics,
+    error::Error,
+    exit_code, labels,
+    launcher::{self, LaunchOptions, ServeMode},
+    layer::{self, BuildLayers, LayerContributor, LayerStore},
+    metrics::Metrics,
+    sbom,
+    settings::Settings,
+    smoke,
+    telemetry::{BuildEvent, CacheStats, Sink},
+    update_check::{self, LatestRelease},
+    util::{
+        logger::Logger,
+        process::{CommandRunner, OutputLine, SystemRunner},
+        proxy::ProxyConfig,
+        retry::RetryPolicy,
+    },
+};
+use libcnb::{
+    build::GenericBuildContext,
+    data::{bom, launch::Label},
+    layer::Layer,
+    platform::Platform,
+};
+use std::{
+    cell::RefCell,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
 
-pub const RUNTIME_JAR_FILE_NAME: &str = "runtime.jar";
+pub use crate::{
+    build::runtime::RUNTIME_JAR_FILE_NAME,
+    layers::{function_bundle::function_bundle_digest, opt::LAUNCHER_FILE_NAME},
+};
+pub const EXEC_D_FILE_NAME: &str = "exec-d";
+/// Optional executable in the app that runs after bundling, e.g. to prune resources.
+pub const POST_BUNDLE_HOOK_PATH: &str = ".sf-functions/post-bundle";
 
-pub struct Builder<'a, 'b> {
-    logger: &'b Logger,
-    ctx: &'a GenericBuildContext,
+/// The update check is informational, so it must not hold up builds on a slow network.
+const UPDATE_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Telemetry is best effort, so it must not hold up builds on a slow network.
+const TELEMETRY_TIMEOUT: Duration = Duration::from_secs(5);
+pub(crate) const SMOKE_TEST_TIMEOUT: Duration = Duration::from_secs(60);
+pub(crate) const DESCRIBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Builder<'a, 'b, L: Logger> {
+    pub(crate) logger: &'b L,
+    pub(crate) ctx: &'a GenericBuildContext,
+    pub(crate) layers: BuildLayers,
+    /// The runtime is already resolved to the target architecture and the version pinned by the
+    /// user, if any.
+    pub(crate) buildpack_toml_metadata: buildpack_toml::Metadata,
+    pub(crate) config: FunctionConfig,
+    /// The app directory, or its subdirectory with the function in monorepos.
+    pub(crate) project_dir: PathBuf,
+    pub(crate) diagnostics: Diagnostics,
+    /// Capabilities of the downloaded runtime, see [`Builder::negotiate_runtime`].
+    pub(crate) runtime_descriptor: RefCell<Descriptor>,
+    cache_stats: CacheStats,
+    pub(crate) command_runner: Box<dyn CommandRunner>,
 }
 
-impl<'a, 'b> Builder<'a, 'b> {
-    pub fn new(ctx: &'a GenericBuildContext, logger: &'b Logger) -> anyhow::Result<Self> {
-        Ok(Builder { ctx, logger })
+impl<'a, 'b, L: Logger> Builder<'a, 'b, L> {
+    pub fn new(
+        ctx: &'a GenericBuildContext,
+        layers_dir: impl Into<PathBuf>,
+        logger: &'b L,
+    ) -> anyhow::Result<Self> {
+        let layers = BuildLayers::new(ctx, layers_dir);
+        let mut buildpack_toml_metadata =
+            buildpack_toml::Metadata::from_buildpack_dir(&ctx.buildpack_dir)?;
+        let project_dir = config::project_dir(
+            &ctx.app_dir,
+            ctx.platform.env().var(config::PROJECT_PATH_ENV).ok(),
+        )?;
+        let mut config = FunctionConfig::from_app_dir(&project_dir)?;
+
+        let runtime_version = ctx
+            .platform
+            .env()
+            .var("SF_FUNCTIONS_RUNTIME_VERSION")
+            .ok()
+            .or_else(|| config.runtime_version.clone());
+        let arch = target_arch(ctx);
+        if let Some(reason) = buildpack_toml_metadata.unsupported_stack(&ctx.stack_id.to_string()) {
+            anyhow::bail!(reason);
+        }
+        buildpack_toml_metadata.runtime = buildpack_toml_metadata
+            .runtime
+            .for_arch(&arch)
+            .for_stack(&ctx.stack_id.to_string())
+            .resolve(runtime_version.as_deref())
+            .map_err(|err| report(logger, err))?;
+
+        // HTTP is the default of every runtime, so only gRPC is passed on. Runtimes that predate
+        // `--mode` keep working unless gRPC is requested.
+        if let Ok(protocol) = ctx.platform.env().var(launcher::PROTOCOL_ENV) {
+            config.launch_options.mode = match protocol
+                .parse()
+                .map_err(|err| anyhow::anyhow!("Invalid {}: {}", launcher::PROTOCOL_ENV, err))?
+            {
+                ServeMode::Http => None,
+                ServeMode::Grpc => Some(ServeMode::Grpc),
+            };
+        }
+
+        let diagnostics = Diagnostics::new();
+        let runtime = &buildpack_toml_metadata.runtime;
+        diagnostics.record(
+            "Runtime",
+            "version",
+            runtime_version.as_deref().unwrap_or("(default)"),
+        );
+        diagnostics.record("Runtime", "arch", &arch);
+        diagnostics.record("Runtime", "stack", &ctx.stack_id);
+        diagnostics.record(
+            "Runtime",
+            "urls",
+            runtime.urls().collect::<Vec<_>>().join(", "),
+        );
+        diagnostics.record("Runtime", "checksum", runtime.checksum());
+        if let Some(path) = &runtime.path {
+            diagnostics.record("Runtime", "path", path.display());
+        }
+        if let Some(name) = &config.name {
+            diagnostics.record("Function", "name", name);
+        }
+        if let Some(api_version) = &config.api_version {
+            diagnostics.record("Function", "Salesforce API version", api_version);
+        }
+        diagnostics.record(
+            "Launch",
+            "protocol",
+            config.launch_options.mode.unwrap_or(ServeMode::Http),
+        );
+        diagnostics.record("Paths", "app", ctx.app_dir.display());
+        diagnostics.record("Paths", "project", project_dir.display());
+        diagnostics.record("Paths", "buildpack", ctx.buildpack_dir.display());
+        diagnostics.record("Paths", "layers", layers.layers_dir().display());
+        diagnostics.record_env(|key| ctx.platform.env().var(key).ok());
+
+        Ok(Builder {
+            ctx,
+            layers,
+            logger,
+            buildpack_toml_metadata,
+            config,
+            project_dir,
+            diagnostics,
+            runtime_descriptor: RefCell::new(Descriptor::legacy()),
+            cache_stats: CacheStats::default(),
+            command_runner: Box::new(SystemRunner),
+        })
     }
 
-    pub fn contribute_opt_layer(&self) -> anyhow::Result<Layer> {
-        let mut layer = self.ctx.layer("opt")?;
-        let mut content_metadata = layer.mut_content_metadata();
-        content_metadata.launch = true;
-        content_metadata.build = true;
-        content_metadata.cache = false;
-        layer.write_content_metadata()?;
+    /// Settings resolved so far, dumped in debug mode once the build ends.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
 
-        let contents = include_str!("../opt/run.sh");
-        let run_sh_path = layer.as_path().join("run.sh");
-        fs::write(&run_sh_path, contents)?;
-        #[cfg(target_family = "unix")]
-        set_executable(&run_sh_path)?;
+    pub(crate) fn contribute_layer(
+        &self,
+        contributor: &impl LayerContributor,
+    ) -> anyhow::Result<Layer> {
+        self.contribute_layer_with_logger(self.logger, contributor)
+    }
+
+    pub(crate) fn contribute_layer_with_logger(
+        &self,
+        logger: &impl Logger,
+        contributor: &impl LayerContributor,
+    ) -> anyhow::Result<Layer> {
+        let (layer, decision) = layer::contribute_layer(&self.layers, logger, contributor)?;
+        self.cache_stats.record(contributor.types(), &decision);
+        self.diagnostics.record(
+            "Layers",
+            contributor.name(),
+            format!("{} ({})", layer.as_path().display(), decision),
+        );
 
         Ok(layer)
     }
 
-    pub fn contribute_runtime_layer(&self) -> anyhow::Result<Layer> {
-        self.logger.header("Installing Java function runtime")?;
+    /// Logs what would happen to a layer, see [`Builder::print_plan`].
+    pub(crate) fn plan_layer(&self, contributor: &impl LayerContributor) -> anyhow::Result<()> {
+        let decision = layer::plan(&self.layers, contributor)?;
+        self.logger
+            .info(format!("{}: would be {}", contributor.name(), decision))
+    }
+
+    pub fn config(&self) -> &FunctionConfig {
+        &self.config
+    }
 
-        let mut runtime_layer = self.ctx.layer("sf-fx-runtime-java")?;
-        let buildpack_toml: libcnb::data::buildpack::BuildpackToml = toml::from_str(
-            &fs::read_to_string(self.ctx.buildpack_dir.join("buildpack.toml"))?,
-        )?;
-        let buildpack_toml_metadata =
-            crate::data::buildpack_toml::Metadata::try_from(&buildpack_toml.metadata)?;
-        let runtime_layer_metadata =
-            crate::data::Runtime::from_runtime_layer(&runtime_layer.content_metadata().metadata);
-        let runtime_jar_path = runtime_layer.as_path().join(RUNTIME_JAR_FILE_NAME);
-
-        if buildpack_toml_metadata.runtime.sha256 == runtime_layer_metadata.sha256
-            && runtime_jar_path.exists()
+    /// Replaces the runner of the `java` invocations, e.g. to build without a JDK in tests.
+    pub fn with_command_runner(mut self, command_runner: impl CommandRunner + 'static) -> Self {
+        self.command_runner = Box::new(command_runner);
+        self
+    }
+
+    /// Where the function project is, see [`config::project_dir`].
+    pub fn project_dir(&self) -> &Path {
+        &self.project_dir
+    }
+
+    /// Settings from the platform environment, see [`Settings`].
+    pub(crate) fn settings(&self) -> Settings<'_, impl Fn(&str) -> Option<String> + '_> {
+        Settings::new(
+            move |key| self.ctx.platform.env().var(key).ok(),
+            &self.buildpack_toml_metadata,
+        )
+    }
+
+    /// Whether `SF_FUNCTIONS_DRY_RUN` asks to only print the plan of the build.
+    pub fn is_dry_run(&self) -> bool {
+        self.settings().is_dry_run()
+    }
+
+    pub fn runtime(&self) -> &Runtime {
+        &self.buildpack_toml_metadata.runtime
+    }
+
+    /// Compares the buildpack version with the latest release from `SF_FUNCTIONS_UPDATE_CHECK_URL`
+    /// or `[metadata.update_check]`, and warns if this version is known to be incompatible.
+    /// Skipped with `SF_FUNCTIONS_SKIP_UPDATE_CHECK`, e.g. in offline builds. Failures are only
+    /// logged in debug mode.
+    pub fn check_for_update(&self) -> anyhow::Result<()> {
+        let env = |key: &str| self.ctx.platform.env().var(key).ok();
+        if env("SF_FUNCTIONS_SKIP_UPDATE_CHECK").is_some() {
+            return Ok(());
+        }
+        let url = match env("SF_FUNCTIONS_UPDATE_CHECK_URL")
+            .or_else(|| self.buildpack_toml_metadata.update_check.url.clone())
         {
-            self.logger
-                .info("Installed Java function runtime from cache")?;
-        } else {
-            self.logger.debug("Creating function runtime layer")?;
-            let mut content_metadata = runtime_layer.mut_content_metadata();
-            content_metadata.launch = true;
-            content_metadata.build = false;
-            content_metadata.cache = true;
-
-            content_metadata.metadata.insert(
-                String::from("runtime_jar_url"),
-                toml::Value::String(buildpack_toml_metadata.runtime.url.clone()),
+            Some(url) => url,
+            None => return Ok(()),
+        };
+
+        let release = match self.fetch_latest_release(&url) {
+            Ok(release) => release,
+            Err(err) => {
+                return self
+                    .logger
+                    .debug(format!("Could not check {} for updates: {}", url, err))
+            }
+        };
+
+        let current = &self.buildpack_toml_metadata.buildpack_version;
+        match release.status(current) {
+            update_check::Status::UpToDate => Ok(()),
+            update_check::Status::Outdated { latest } => self.logger.info(format!(
+                "A newer version of this buildpack is available: {} (using {})",
+                latest, current
+            )),
+            update_check::Status::Incompatible { latest, message } => self.logger.warning(
+                "Outdated buildpack",
+                format!(
+                    "This builder uses version {} of the buildpack, which has known \
+                     incompatibilities:\n{}\n\n\
+                     Please update the builder to version {} or newer.",
+                    current,
+                    message.trim(),
+                    latest
+                ),
+            ),
+        }
+    }
+
+    fn fetch_latest_release(&self, url: &str) -> anyhow::Result<LatestRelease> {
+        let contents = self
+            .http_client()?
+            .get(url)
+            .timeout(UPDATE_CHECK_TIMEOUT)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)
+            .and_then(reqwest::blocking::Response::text)?;
+
+        LatestRelease::from_toml(&contents)
+    }
+
+    /// Protocol the function is served with, from `SF_FUNCTIONS_PROTOCOL` or `project.toml`.
+    pub(crate) fn protocol(&self) -> ServeMode {
+        self.config.launch_options.mode.unwrap_or(ServeMode::Http)
+    }
+
+    /// Asks the runtime for its capabilities with `describe`, and checks the bundle schema and
+    /// serve flags against them. Runtimes without the command keep the previous assumptions.
+    pub fn negotiate_runtime(&self, runtime_jar_path: &Path) -> anyhow::Result<()> {
+        let descriptor = match self.describe_runtime(runtime_jar_path)? {
+            Some(descriptor) => descriptor,
+            None => {
+                self.diagnostics
+                    .record("Runtime", "descriptor", "(none, legacy runtime)");
+                return self
+                    .logger
+                    .debug("The function runtime has no describe command, assuming defaults");
+            }
+        };
+        self.diagnostics.record(
+            "Runtime",
+            "descriptor",
+            format!("schema version {}", descriptor.schema_version),
+        );
+        self.diagnostics.record(
+            "Runtime",
+            "bundle schema version",
+            descriptor.bundle.schema_version,
+        );
+
+        if descriptor.bundle.schema_version > function_bundle::SUPPORTED_SCHEMA_VERSION {
+            anyhow::bail!(
+                "The function runtime writes function bundle schema version {}, this buildpack \
+                 supports up to version {}. Please update the buildpack or pin an older function \
+                 runtime.",
+                descriptor.bundle.schema_version,
+                function_bundle::SUPPORTED_SCHEMA_VERSION
             );
-            content_metadata.metadata.insert(
-                String::from("runtime_jar_sha256"),
-                toml::Value::String(buildpack_toml_metadata.runtime.sha256.clone()),
+        }
+        let launch_args = self.config.launch_options.args();
+        let unsupported = descriptor.unsupported_serve_flags(&launch_args);
+        if !unsupported.is_empty() {
+            anyhow::bail!(
+                "The function runtime doesn't support {} for serving functions. Remove the \
+                 option from project.toml or pin a newer runtime version.",
+                unsupported.join(", ")
             );
-            runtime_layer.write_content_metadata()?;
+        }
 
-            self.logger
-                .debug("Function runtime layer successfully created")?;
+        *self.runtime_descriptor.borrow_mut() = descriptor;
 
-            self.logger.info("Starting download of function runtime")?;
-            util::download(&buildpack_toml_metadata.runtime.url, &runtime_jar_path).map_err(|_| {
-              self.logger.error("Download of function runtime failed", format!(r#"
-We couldn't download the function runtime at {}.
+        Ok(())
+    }
 
-This is usually caused by intermittent network issues. Please try again and contact us should the error persist.
-"#, buildpack_toml_metadata.runtime.url)).unwrap_err()
+    /// Runs `describe`, `None` if the runtime predates it or its descriptor can't be read.
+    fn describe_runtime(&self, runtime_jar_path: &Path) -> anyhow::Result<Option<Descriptor>> {
+        let description = build::runtime::describe(
+            self.command_runner.as_ref(),
+            runtime_jar_path,
+            DESCRIBE_TIMEOUT,
+        )
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => report(self.logger, Error::JdkNotFound),
+            _ => report(self.logger, Error::Io(err)),
         })?;
-            self.logger.info("Function runtime download successful")?;
 
-            if buildpack_toml_metadata.runtime.sha256 != util::sha256(&fs::read(&runtime_jar_path)?)
-            {
-                self.logger.error(
-                    "Function runtime integrity check failed",
-                    r#"
-We could not verify the integrity of the downloaded function runtime.
-Please try again and contact us should the error persist.
-        "#,
+        match description {
+            Description::Described(descriptor) => Ok(Some(descriptor)),
+            Description::Unsupported => Ok(None),
+            Description::Unreadable(err) => {
+                self.logger.warning(
+                    "Unreadable runtime descriptor",
+                    format!(
+                        "The function runtime described itself in a way this buildpack doesn't \
+                         understand, assuming defaults: {}",
+                        err
+                    ),
                 )?;
+                Ok(None)
             }
+        }
+    }
 
-            self.logger
-                .info("Function runtime installation successful")?;
+    /// Checks that the runtime serves the requested protocol, as its `describe` output or
+    /// `[metadata.runtime]` lists, falling back to asking `serve --help`.
+    pub fn check_protocol(&self, runtime_jar_path: &Path) -> anyhow::Result<()> {
+        let protocol = self.protocol();
+        let described = self.runtime_descriptor.borrow().serve.protocols.clone();
+        let supported = match described
+            .as_ref()
+            .or_else(|| self.runtime().protocols.as_ref())
+        {
+            Some(protocols) => protocols.contains(&protocol),
+            None if protocol == ServeMode::Http => true,
+            None => {
+                let mut help = String::new();
+                self.command_runner
+                    .run(
+                        Command::new("java")
+                            .arg("-jar")
+                            .arg(runtime_jar_path)
+                            .args(&["serve", "--help"]),
+                        Some(DESCRIBE_TIMEOUT),
+                        &mut |line| match line {
+                            OutputLine::Stdout(line) | OutputLine::Stderr(line) => {
+                                help.push_str(&line);
+                                help.push('\n');
+                            }
+                        },
+                    )
+                    .map_err(|err| report(self.logger, Error::Io(err)))?;
+                protocol.supported_by_help(&help)
+            }
+        };
+
+        if !supported {
+            anyhow::bail!(
+                "The function runtime doesn't support the {} protocol. Set {} to http or pin a \
+                 newer runtime version.",
+                protocol,
+                launcher::PROTOCOL_ENV
+            );
         }
+        self.logger
+            .debug(format!("The function runtime serves {}", protocol))
+    }
+
+    /// Checks the service bindings of the build against the ones `project.toml` requires. A
+    /// binding that is missing entirely may still be provided at launch, where the launcher
+    /// checks again, while one that lacks required keys fails the build.
+    pub fn check_bindings(&self) -> anyhow::Result<()> {
+        let bindings = match self.bindings_root() {
+            Some(root) => bindings::read_all(root)?,
+            None => Vec::new(),
+        };
+        for binding in &bindings {
+            self.diagnostics.record(
+                "Bindings",
+                &binding.name,
+                match &binding.provider {
+                    Some(provider) => format!("{} ({})", binding.r#type, provider),
+                    None => binding.r#type.clone(),
+                },
+            );
+        }
+
+        for required in &self.config.required_bindings {
+            let reason = match required.unmet(&bindings) {
+                Some(reason) => reason,
+                None => continue,
+            };
+            if bindings
+                .iter()
+                .any(|binding| binding.r#type == required.r#type)
+            {
+                return Err(report(self.logger, Error::ServiceBinding { reason }));
+            }
+            self.logger.warning(
+                "Missing service binding",
+                format!(
+                    "project.toml requires a binding of type {}, but the build has {}. The \
+                     function won't start unless the platform provides one at launch.",
+                    required.r#type, reason
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// `SERVICE_BINDING_ROOT`, or the `bindings` directory of the platform directory the
+    /// lifecycle passes as second argument.
+    fn bindings_root(&self) -> Option<PathBuf> {
+        self.ambient_env(bindings::SERVICE_BINDING_ROOT_ENV)
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::args_os()
+                    .nth(2)
+                    .map(|platform_dir| PathBuf::from(platform_dir).join("bindings"))
+            })
+    }
+
+    /// From buildpack metadata, as local runtime jars don't carry a version of their own.
+    pub(crate) fn runtime_version(&self) -> String {
+        self.buildpack_toml_metadata
+            .runtime
+            .version()
+            .unwrap_or_else(|| String::from("unknown"))
+    }
 
-        Ok(runtime_layer)
+    /// The client for all network access of the build, configured from buildpack metadata and the
+    /// proxy and certificate settings of the build environment.
+    pub(crate) fn http_client(&self) -> anyhow::Result<reqwest::blocking::Client> {
+        let env = |key: &str| self.ctx.platform.env().var(key).ok();
+        let proxy = ProxyConfig::from_env(env).merge(&self.buildpack_toml_metadata.proxy);
+
+        let http_config = self.buildpack_toml_metadata.http.clone().merge_env(env)?;
+        self.diagnostics.record(
+            "Download",
+            "timeouts",
+            format!(
+                "connect {}s, total {}s",
+                http_config.connect_timeout_secs, http_config.timeout_secs
+            ),
+        );
+        if let Some(ca_file) = &http_config.ca_file {
+            self.diagnostics
+                .record("Download", "CA certificates", ca_file.display());
+        }
+
+        http_config.client(&proxy)
+    }
+
+    /// Variables of the build platform, falling back to the environment of the build process,
+    /// e.g. for credentials the builder provides.
+    pub(crate) fn ambient_env(&self, key: &str) -> Option<String> {
+        self.ctx
+            .platform
+            .env()
+            .var(key)
+            .or_else(|_| std::env::var(key))
+            .ok()
+    }
+
+    /// The retry policy from buildpack metadata, tunable with `SF_FUNCTIONS_DOWNLOAD_RETRIES`.
+    pub(crate) fn download_retry_policy(&self) -> anyhow::Result<RetryPolicy> {
+        let retry_policy = self
+            .buildpack_toml_metadata
+            .retry
+            .clone()
+            .merge_env(|key| self.ctx.platform.env().var(key).ok())?;
+        self.diagnostics
+            .record("Download", "attempts", retry_policy.attempts);
+
+        Ok(retry_policy)
+    }
+
+    /// Version of the JDK on the `PATH`, `None` if `java -version` doesn't print one.
+    pub(crate) fn java_version(&self) -> anyhow::Result<Option<String>> {
+        let output = Command::new("java")
+            .arg("-version")
+            .output()
+            .map_err(|err| match err.kind() {
+                io::ErrorKind::NotFound => report(self.logger, Error::JdkNotFound),
+                _ => report(self.logger, Error::Io(err)),
+            })?;
+        // `java -version` writes to stderr.
+        Ok(launcher::parse_java_version(&String::from_utf8_lossy(
+            &output.stderr,
+        )))
+    }
+
+    /// Fails when the JDK of the build is outside the range the runtime declares in
+    /// `min_java` and `max_java`.
+    pub fn check_java_version(&self) -> anyhow::Result<()> {
+        let version = match self.java_version()? {
+            Some(version) => version,
+            None => {
+                return self
+                    .logger
+                    .debug("Could not determine the JDK version, skipping the check")
+            }
+        };
+        self.diagnostics.record("Runtime", "java version", &version);
+
+        let runtime = self.runtime();
+        match launcher::java_major_version(&version) {
+            Some(major) if !runtime.supports_java(major) => Err(report(
+                self.logger,
+                Error::UnsupportedJdk {
+                    version,
+                    min: runtime.min_java,
+                    max: runtime.max_java,
+                },
+            )),
+            _ => self.logger.info(format!("Using JDK {}", version)),
+        }
     }
 
-    pub fn contribute_function_bundle_layer(
+    /// Starts the runtime against each function bundle and invokes it once with an empty payload
+    /// when `SF_FUNCTIONS_VERIFY` is set, so functions that can't even load fail the build.
+    pub fn verify_functions(
         &self,
         runtime_jar_path: impl AsRef<Path>,
-    ) -> anyhow::Result<Layer> {
-        self.logger.header("Detecting function")?;
-
-        let mut function_bundle_layer = self.ctx.layer("function-bundle")?;
-        let mut content_metadata = function_bundle_layer.mut_content_metadata();
-        content_metadata.launch = true;
-        content_metadata.build = false;
-        content_metadata.cache = false;
-        function_bundle_layer.write_content_metadata()?;
-
-        let exit_status = Command::new("java")
-            .arg("-jar")
-            .arg(runtime_jar_path.as_ref())
-            .arg("bundle")
-            .arg(&self.ctx.app_dir)
-            .spawn()?
-            .wait()?;
-
-        if let Some(code) = exit_status.code() {
-            match code {
-                0 => {
-                    self.logger.info("Detection successful")?;
-                    Ok(())
-                }
-                1 => self.logger.error(
-                    "No functions found",
-                    r#"
-Your project does not seem to contain any Java functions.
-The output above might contain information about issues with your function.
-"#,
-                ),
-                2 => self.logger.error(
-                    "Multiple functions found",
-                    r#"
-Your project contains multiple Java functions.
-Currently, only projects that contain exactly one (1) function are supported.
-"#,
-                ),
-                3..=6 => self.logger.error(
-                    "Detection failed",
-                    format!(
-                        r#"Function detection failed with internal error "{}""#,
-                        code
-                    ),
-                ),
-                _ => self.logger.error(
-                    "Detection failed",
-                    format!(
-                        r#"
-Function detection failed with unexpected error code {}.
-The output above might contain hints what caused this error to happen.
-"#,
-                        code
-                    ),
-                ),
-            }?;
+        function_bundle_layer: &Layer,
+        multiple_functions: bool,
+    ) -> anyhow::Result<()> {
+        let verify = self.ctx.platform.env().var("SF_FUNCTIONS_VERIFY");
+        if !matches!(verify.as_deref(), Ok("1") | Ok("true")) {
+            return Ok(());
         }
 
-        let function_bundle_toml: crate::data::function_bundle::Toml = toml::from_slice(
-            &fs::read(&function_bundle_layer.as_path().join("function-bundle.toml"))?,
-        )?;
+        self.logger.header("Verifying function")?;
+        let bundle_dirs = if multiple_functions {
+            function_bundle::bundle_dirs(function_bundle_layer.as_path())?
+        } else {
+            vec![function_bundle_layer.as_path().to_path_buf()]
+        };
+
+        let extensions_env = self.extensions_env(runtime_jar_path.as_ref())?;
+        for bundle_dir in bundle_dirs {
+            let port = smoke::free_port()?.to_string();
+            let mut command = Command::new("java");
+            command.args(launcher::java_args(
+                runtime_jar_path.as_ref(),
+                &bundle_dir,
+                &LaunchOptions {
+                    port: None,
+                    ..self.config.launch_options.clone()
+                },
+                &[],
+                |key| match key {
+                    "PORT" => Some(port.clone()),
+                    _ => extensions_env.get(key).cloned(),
+                },
+                || None,
+            )?);
+
+            let status = smoke::invoke(&mut command, port.parse()?, SMOKE_TEST_TIMEOUT)
+                .map_err(|err| report(self.logger, err))?;
+            self.logger.info(format!(
+                "Function in {} loaded, invocation returned HTTP status {}",
+                bundle_dir.display(),
+                status
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Records the build with the telemetry sink if `SF_FUNCTIONS_TELEMETRY` opts in. Telemetry
+    /// never fails a build, failures are only logged in debug mode.
+    pub fn record_telemetry(
+        &self,
+        metrics: &Metrics,
+        result: &anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        let sink = match Sink::from_env(|key| self.ctx.platform.env().var(key).ok()) {
+            Some(sink) if !self.is_dry_run() => sink,
+            _ => return Ok(()),
+        };
+
+        let event = BuildEvent::new(
+            self.buildpack_toml_metadata.buildpack_version.clone(),
+            self.runtime_version(),
+            target_arch(self.ctx),
+            result
+                .as_ref()
+                .err()
+                .map(|err| exit_code::category(exit_code::for_error(err))),
+            metrics.phases(),
+            self.cache_stats.summary(),
+        );
+        let recorded = match &sink {
+            Sink::Endpoint(url) => self.post_telemetry(url, &event),
+            Sink::File(path) => event.append_to(path),
+            Sink::Log => event
+                .to_json()
+                .and_then(|json| self.logger.info(format!("Telemetry: {}", json))),
+        };
+
+        match recorded {
+            Ok(()) => self.logger.debug("Recorded build telemetry"),
+            Err(err) => self
+                .logger
+                .debug(format!("Could not record build telemetry: {}", err)),
+        }
+    }
+
+    fn post_telemetry(&self, url: &str, event: &BuildEvent) -> anyhow::Result<()> {
+        self.http_client()?
+            .post(url)
+            .timeout(TELEMETRY_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(event.to_json()?)
+            .send()
+            .and_then(reqwest::blocking::Response::error_for_status)?;
 
-        self.logger.header(format!(
-            "Detected function: {}",
-            function_bundle_toml.function.class
-        ))?;
-        self.logger.info(format!(
-            "Payload type: {}",
-            function_bundle_toml.function.payload_class
-        ))?;
-        self.logger.info(format!(
-            "Return type: {}",
-            function_bundle_toml.function.return_class
-        ))?;
-
-        Ok(function_bundle_layer)
+        Ok(())
+    }
+
+    /// Image labels for the function bundle, see [`labels::image_labels`].
+    pub fn image_labels(
+        &self,
+        function_bundle_layer: &Layer,
+        bundle_digest: &str,
+        multiple_functions: bool,
+    ) -> anyhow::Result<Vec<Label>> {
+        labels::image_labels(
+            function_bundle_layer.as_path(),
+            multiple_functions,
+            self.buildpack_toml_metadata.runtime.version().as_deref(),
+            bundle_digest,
+        )
+    }
+
+    /// Bill of materials entries for `launch.toml`, covering the runtime and the function bundle.
+    pub fn bom(&self, function_bundle_layer: &Layer) -> anyhow::Result<Vec<bom::Entry>> {
+        let function_class = function_bundle::bundle_dirs(function_bundle_layer.as_path())?
+            .into_iter()
+            .chain(std::iter::once(
+                function_bundle_layer.as_path().to_path_buf(),
+            ))
+            .filter_map(|bundle_dir| function_bundle::Toml::from_bundle_dir(bundle_dir).ok())
+            .map(|toml| toml.function.class)
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        Ok(vec![
+            sbom::runtime_entry(&self.buildpack_toml_metadata.runtime),
+            sbom::function_bundle_entry(function_class, function_bundle_layer.as_path())?,
+        ])
     }
 }
 
-#[cfg(target_family = "unix")]
-fn set_executable(path: impl AsRef<Path>) -> anyhow::Result<()> {
-    use std::os::unix::fs::OpenOptionsExt;
-    fs::OpenOptions::new().mode(0o775).open(path)?;
+/// Architecture the image is built for, the host's unless the platform targets another.
+fn target_arch(ctx: &GenericBuildContext) -> String {
+    ctx.platform
+        .env()
+        .var("CNB_TARGET_ARCH")
+        .unwrap_or_else(|_| String::from(runtime::host_arch()))
+}
 
-    Ok(())
+/// Logs the user facing explanation of `err` and keeps it matchable for callers.
+pub(crate) fn report(logger: &impl Logger, err: Error) -> anyhow::Error {
+    // The error itself is returned either way, failing to print it must not mask it.
+    let _ = logger.error(err.header(), err.help());
+    err.into()
 }
@@ -0,0 +1,511 @@
+use crate::{
+    bindings::RequiredBinding,
+    data::{
+        function_bundle,
+        project_toml::{self, ProjectToml},
+    },
+    launcher::LaunchOptions,
+};
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+/// Subdirectory of the app with the function, for monorepos. Wins over `project-path` in the
+/// `project.toml` of the app.
+pub const PROJECT_PATH_ENV: &str = "SF_FUNCTIONS_PROJECT_PATH";
+
+/// Configuration of the function, resolved once from the `project.toml` of the app. Detect,
+/// build and launch use it instead of reading the project descriptor themselves.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FunctionConfig {
+    pub is_function: bool,
+    /// `[_.metadata.function] name`, falling back to the Salesforce and project ids.
+    pub name: Option<String>,
+    /// Salesforce org API version the function is written against, e.g. `53.0`.
+    pub api_version: Option<String>,
+    pub max_heap_size: Option<String>,
+    pub thread_stack_size: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub multiple: bool,
+    pub runtime_version: Option<String>,
+    pub processes: Vec<project_toml::Process>,
+    pub launch_options: LaunchOptions,
+    /// Jars appended to the classpath of the function runtime.
+    pub extensions: Vec<project_toml::Extension>,
+    /// Service bindings the function can't run without.
+    pub required_bindings: Vec<RequiredBinding>,
+}
+
+impl FunctionConfig {
+    /// A missing `project.toml` yields the defaults.
+    pub fn from_app_dir(app_dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        FunctionConfig::from_project_toml(ProjectToml::from_app_dir(app_dir)?)
+    }
+
+    pub fn from_project_toml(project_toml: ProjectToml) -> anyhow::Result<Self> {
+        let is_function = project_toml.is_function();
+        let ProjectToml { project, com } = project_toml;
+        let function = project.metadata.function;
+        let salesforce = com.salesforce;
+
+        if let Some(api_version) = &function.salesforce_api_version {
+            if !function_bundle::is_api_version(api_version) {
+                anyhow::bail!(
+                    "Invalid salesforce-api-version {:?} in project.toml, expected a version \
+                     like \"53.0\"",
+                    api_version
+                );
+            }
+        }
+        for (key, size) in &[
+            ("max-heap-size", &function.memory.max_heap_size),
+            ("thread-stack-size", &function.memory.thread_stack_size),
+        ] {
+            if let Some(size) = size {
+                if !is_memory_size(size) {
+                    anyhow::bail!(
+                        "Invalid {} {:?} in project.toml, expected a size like \"512m\"",
+                        key,
+                        size
+                    );
+                }
+            }
+        }
+        if let Some(key) = function
+            .env
+            .keys()
+            .find(|key| key.is_empty() || key.contains('='))
+        {
+            anyhow::bail!(
+                "Invalid environment variable name {:?} in project.toml",
+                key
+            );
+        }
+
+        let mut file_names = Vec::new();
+        for extension in &salesforce.functions.extensions {
+            if extension.url.is_some() == extension.path.is_some() {
+                anyhow::bail!(
+                    "Invalid extension in project.toml, set either url or path: {:?}",
+                    extension.source().unwrap_or_default()
+                );
+            }
+            match extension.file_name() {
+                Some(name) if name.ends_with(".jar") && !file_names.contains(&name) => {
+                    file_names.push(name)
+                }
+                Some(name) if name.ends_with(".jar") => anyhow::bail!(
+                    "Duplicate extension {:?} in project.toml, extension jars need unique file \
+                     names",
+                    name
+                ),
+                _ => anyhow::bail!(
+                    "Invalid extension {:?} in project.toml, expected the location of a .jar file",
+                    extension.source().unwrap_or_default()
+                ),
+            }
+            if let Some(path) = &extension.path {
+                if Path::new(path).is_absolute() || path.split('/').any(|part| part == "..") {
+                    anyhow::bail!(
+                        "Invalid extension path {:?} in project.toml, expected a path inside the \
+                         app directory",
+                        path
+                    );
+                }
+            }
+        }
+
+        // Binding types and keys are file names, and are joined with these separators at launch.
+        let is_binding_name = |name: &str| {
+            !name.is_empty()
+                && !name.starts_with('.')
+                && !name.contains(|c: char| "/\\;:,".contains(c))
+        };
+        for binding in &salesforce.functions.bindings {
+            if let Some(name) = std::iter::once(&binding.r#type)
+                .chain(&binding.keys)
+                .find(|name| !is_binding_name(name))
+            {
+                anyhow::bail!(
+                    "Invalid binding type or key {:?} in project.toml, expected a file name",
+                    name
+                );
+            }
+        }
+
+        Ok(FunctionConfig {
+            is_function,
+            name: function.name.or(salesforce.id).or(project.id),
+            api_version: function.salesforce_api_version,
+            max_heap_size: function.memory.max_heap_size,
+            thread_stack_size: function.memory.thread_stack_size,
+            env: function.env,
+            multiple: salesforce.functions.multiple,
+            runtime_version: salesforce.functions.runtime_version,
+            processes: salesforce.functions.processes,
+            launch_options: salesforce.functions.serve,
+            extensions: salesforce.functions.extensions,
+            required_bindings: salesforce.functions.bindings,
+        })
+    }
+
+    /// JVM flags for the memory hints. An explicit `-Xmx` also disables the heap size derived
+    /// from the container memory limit at launch.
+    pub fn memory_options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        if let Some(max_heap_size) = &self.max_heap_size {
+            options.push(format!("-Xmx{}", max_heap_size));
+        }
+        if let Some(thread_stack_size) = &self.thread_stack_size {
+            options.push(format!("-Xss{}", thread_stack_size));
+        }
+
+        options
+    }
+}
+
+/// The directory detect and the bundler operate on: the subdirectory `project_path`, from
+/// [`PROJECT_PATH_ENV`], or `project-path` in the `project.toml` of the app names, the app
+/// directory itself otherwise.
+pub fn project_dir(
+    app_dir: impl AsRef<Path>,
+    project_path: Option<String>,
+) -> anyhow::Result<PathBuf> {
+    let app_dir = app_dir.as_ref();
+    let project_path = match project_path.filter(|path| !path.is_empty()) {
+        Some(path) => path,
+        None => match ProjectToml::from_app_dir(app_dir)?
+            .functions()
+            .project_path
+            .clone()
+        {
+            Some(path) => path,
+            None => return Ok(app_dir.to_path_buf()),
+        },
+    };
+
+    let is_inside_app = Path::new(&project_path)
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    if !is_inside_app {
+        anyhow::bail!(
+            "Invalid project path {:?}, expected a directory inside the app like \
+             \"services/my-function\"",
+            project_path
+        );
+    }
+    let project_dir = app_dir.join(&project_path);
+    if !project_dir.is_dir() {
+        anyhow::bail!(
+            "Invalid project path {:?}, the app has no such directory",
+            project_path
+        );
+    }
+
+    Ok(project_dir)
+}
+
+/// JVM tuning selected with `SF_FUNCTIONS_JVM_PROFILE`. Functions are mostly short-lived, so
+/// the default trades peak performance for a faster start.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JvmProfile {
+    Coldstart,
+    Throughput,
+}
+
+impl Default for JvmProfile {
+    fn default() -> Self {
+        JvmProfile::Coldstart
+    }
+}
+
+impl FromStr for JvmProfile {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "coldstart" => Ok(JvmProfile::Coldstart),
+            "throughput" => Ok(JvmProfile::Throughput),
+            _ => Err(()),
+        }
+    }
+}
+
+impl JvmProfile {
+    /// Flags of the profile, placed before user flags so those take precedence.
+    pub fn java_tool_options(self) -> Vec<String> {
+        match self {
+            // Compiling with C1 only skips most of the JIT work of a short-lived process. The GC is
+            // selected at launch, where the memory limit is known.
+            JvmProfile::Coldstart => vec![
+                String::from("-XX:TieredStopAtLevel=1"),
+                String::from("-Xss512k"),
+            ],
+            JvmProfile::Throughput => Vec::new(),
+        }
+    }
+}
+
+/// Garbage collector selected with `SF_FUNCTIONS_GC`. Without it, the collector is chosen from
+/// the container memory limit at launch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GarbageCollector {
+    Serial,
+    G1,
+    Z,
+}
+
+impl FromStr for GarbageCollector {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "serial" => Ok(GarbageCollector::Serial),
+            "g1" => Ok(GarbageCollector::G1),
+            "zgc" => Ok(GarbageCollector::Z),
+            _ => Err(()),
+        }
+    }
+}
+
+impl GarbageCollector {
+    /// Up to this limit the single threaded collector has less overhead than G1.
+    const SERIAL_MAX_MEMORY_BYTES: u64 = 1024 * 1024 * 1024;
+
+    /// SerialGC for small containers, G1 for larger ones. `None` without a memory limit, which
+    /// leaves the choice to the JVM.
+    pub fn for_memory_limit(limit_bytes: Option<u64>) -> Option<Self> {
+        limit_bytes.map(|limit_bytes| {
+            if limit_bytes <= Self::SERIAL_MAX_MEMORY_BYTES {
+                GarbageCollector::Serial
+            } else {
+                GarbageCollector::G1
+            }
+        })
+    }
+
+    pub fn java_tool_option(self) -> &'static str {
+        match self {
+            GarbageCollector::Serial => "-XX:+UseSerialGC",
+            GarbageCollector::G1 => "-XX:+UseG1GC",
+            GarbageCollector::Z => "-XX:+UseZGC",
+        }
+    }
+}
+
+/// Sizes as the JVM accepts them for `-Xmx` and `-Xss`, e.g. `512m`, `2G` or `1048576`.
+fn is_memory_size(value: &str) -> bool {
+    let digits = value.trim_end_matches(|c| "kKmMgG".contains(c));
+
+    !digits.is_empty()
+        && value.len() - digits.len() <= 1
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn config(project_toml: &str) -> anyhow::Result<FunctionConfig> {
+        FunctionConfig::from_project_toml(toml::from_str(project_toml)?)
+    }
+
+    #[test]
+    fn from_project_toml_combines_tables() -> anyhow::Result<()> {
+        let config = config(
+            r#"
+[_]
+id = "com.example.function"
+
+[_.metadata.function]
+salesforce-api-version = "53.0"
+
+[_.metadata.function.memory]
+max-heap-size = "512m"
+thread-stack-size = "1m"
+
+[com.salesforce]
+id = "example_function"
+type = "function"
+
+[com.salesforce.functions]
+multiple = true
+
+[com.salesforce.functions.serve]
+workers = 4
+mode = "grpc"
+"#,
+        )?;
+
+        assert!(config.is_function);
+        assert!(config.multiple);
+        assert_eq!(config.name.as_deref(), Some("example_function"));
+        assert_eq!(config.api_version.as_deref(), Some("53.0"));
+        assert_eq!(config.memory_options(), vec!["-Xmx512m", "-Xss1m"]);
+        assert_eq!(
+            config.launch_options.args(),
+            vec!["--workers", "4", "--mode", "grpc"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_project_toml_prefers_function_name() -> anyhow::Result<()> {
+        let config = config(
+            r#"
+[_]
+id = "com.example.function"
+
+[_.metadata.function]
+name = "invoice-sync"
+"#,
+        )?;
+
+        assert!(!config.is_function);
+        assert_eq!(config.name.as_deref(), Some("invoice-sync"));
+        assert!(config.memory_options().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_project_toml_rejects_invalid_values() {
+        assert!(config("[_.metadata.function]\nsalesforce-api-version = \"53\"\n").is_err());
+        assert!(config("[_.metadata.function.memory]\nmax-heap-size = \"lots\"\n").is_err());
+        assert!(config("[_.metadata.function.env]\n\"A=B\" = \"c\"\n").is_err());
+    }
+
+    #[test]
+    fn from_project_toml_reads_required_bindings() -> anyhow::Result<()> {
+        let parsed = config(
+            r#"
+[[com.salesforce.functions.bindings]]
+type = "salesforce-org"
+keys = ["instance-url", "access-token"]
+"#,
+        )?;
+        assert_eq!(
+            parsed.required_bindings,
+            vec![RequiredBinding {
+                r#type: String::from("salesforce-org"),
+                keys: vec![String::from("instance-url"), String::from("access-token")],
+            }]
+        );
+
+        assert!(config("[[com.salesforce.functions.bindings]]\ntype = \"\"\n").is_err());
+        assert!(config(
+            "[[com.salesforce.functions.bindings]]\ntype = \"org\"\nkeys = [\"a,b\"]\n"
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_project_toml_validates_extensions() -> anyhow::Result<()> {
+        let valid = config(
+            r#"
+[[com.salesforce.functions.extensions]]
+url = "https://repo.example.com/sdk/sdk-2.0.jar?token=3"
+
+[[com.salesforce.functions.extensions]]
+path = "lib/log-shim.jar"
+"#,
+        )?;
+        assert_eq!(
+            valid
+                .extensions
+                .iter()
+                .map(|extension| extension.file_name())
+                .collect::<Vec<_>>(),
+            vec![Some("sdk-2.0.jar"), Some("log-shim.jar")]
+        );
+
+        let extension = |fields: &str| {
+            config(&format!(
+                "[[com.salesforce.functions.extensions]]\n{}",
+                fields
+            ))
+        };
+        assert!(extension("").is_err());
+        assert!(extension("url = \"https://example.com/a.jar\"\npath = \"a.jar\"\n").is_err());
+        assert!(extension("url = \"https://example.com/sdk.zip\"\n").is_err());
+        assert!(extension("path = \"/usr/lib/sdk.jar\"\n").is_err());
+        assert!(extension("path = \"../sdk.jar\"\n").is_err());
+        assert!(config(
+            r#"
+[[com.salesforce.functions.extensions]]
+path = "a/sdk.jar"
+
+[[com.salesforce.functions.extensions]]
+path = "b/sdk.jar"
+"#
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn project_dir_resolves_subdirectory() -> anyhow::Result<()> {
+        let app_dir = test_support::app_dir(&[("services/my-function/pom.xml", "")])?;
+        let function_dir = app_dir.path().join("services").join("my-function");
+
+        assert_eq!(project_dir(app_dir.path(), None)?, app_dir.path());
+        assert_eq!(
+            project_dir(app_dir.path(), Some(String::from("services/my-function")))?,
+            function_dir
+        );
+        assert!(project_dir(app_dir.path(), Some(String::from("services/other"))).is_err());
+        assert!(project_dir(app_dir.path(), Some(String::from("../services"))).is_err());
+        assert!(project_dir(app_dir.path(), Some(String::from("/services"))).is_err());
+
+        std::fs::write(
+            app_dir.path().join("project.toml"),
+            "[com.salesforce.functions]\nproject-path = \"services/my-function\"\n",
+        )?;
+        assert_eq!(project_dir(app_dir.path(), None)?, function_dir);
+
+        Ok(())
+    }
+
+    #[test]
+    fn garbage_collector_defaults_by_memory_limit() {
+        assert_eq!("ZGC".parse(), Ok(GarbageCollector::Z));
+        assert_eq!("parallel".parse::<GarbageCollector>(), Err(()));
+        assert_eq!(
+            GarbageCollector::for_memory_limit(Some(512 * 1024 * 1024)),
+            Some(GarbageCollector::Serial)
+        );
+        assert_eq!(
+            GarbageCollector::for_memory_limit(Some(2 * 1024 * 1024 * 1024)),
+            Some(GarbageCollector::G1)
+        );
+        assert_eq!(GarbageCollector::for_memory_limit(None), None);
+    }
+
+    #[test]
+    fn is_memory_size_accepts_jvm_units() {
+        assert!(is_memory_size("512m"));
+        assert!(is_memory_size("2G"));
+        assert!(is_memory_size("1048576"));
+        assert!(!is_memory_size("m"));
+        assert!(!is_memory_size("512mb"));
+        assert!(!is_memory_size("1.5g"));
+    }
+
+    #[test]
+    fn jvm_profile_parses_case_insensitively() {
+        assert_eq!("Throughput".parse(), Ok(JvmProfile::Throughput));
+        assert_eq!("coldstart".parse(), Ok(JvmProfile::Coldstart));
+        assert!("fast".parse::<JvmProfile>().is_err());
+        assert!(JvmProfile::Throughput.java_tool_options().is_empty());
+        assert!(JvmProfile::default()
+            .java_tool_options()
+            .contains(&String::from("-XX:TieredStopAtLevel=1")));
+    }
+}
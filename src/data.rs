@@ -1,5 +1,7 @@
 pub mod buildpack_toml;
 pub mod function_bundle;
+pub mod project_toml;
 pub mod runtime;
+pub mod runtime_descriptor;
 
 pub use runtime::Runtime;
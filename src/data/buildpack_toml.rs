@@ -1,22 +1,201 @@
-use crate::data::Runtime;
+use crate::{
+    data::Runtime,
+    launcher, messages,
+    util::{http::HttpConfig, proxy::ProxyConfig, retry::RetryPolicy},
+};
+use anyhow::Context;
 use serde::Deserialize;
-use std::convert::TryFrom;
+use std::{convert::TryFrom, fs, path::Path};
 use toml::value::Table;
 
 #[derive(Deserialize)]
 pub struct Metadata {
     pub runtime: Runtime,
     pub release: Release,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Overrides the proxy settings from the build platform environment.
+    #[serde(default)]
+    pub proxy: ProxyConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub jvm: Jvm,
+    #[serde(default)]
+    pub bundler: Bundler,
+    #[serde(default)]
+    pub launch: Launch,
+    #[serde(default)]
+    pub opentelemetry: OpenTelemetry,
+    #[serde(default)]
+    pub update_check: UpdateCheck,
+    #[serde(default)]
+    pub sdk: Sdk,
+    /// Version from the `[buildpack]` table, so layers can be invalidated on upgrades.
+    #[serde(skip)]
+    pub buildpack_version: String,
+    /// Ids from the `[[stacks]]` tables, `*` supports any stack.
+    #[serde(skip)]
+    pub stacks: Vec<String>,
+}
+
+impl Metadata {
+    pub fn from_buildpack_dir(buildpack_dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = buildpack_dir.as_ref().join("buildpack.toml");
+        let buildpack_toml: libcnb::data::buildpack::BuildpackToml = toml::from_str(
+            &fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?,
+        )
+        .with_context(|| format!("Could not parse {}", path.display()))?;
+
+        let mut metadata = Metadata::try_from(&buildpack_toml.metadata)
+            .with_context(|| format!("Invalid [metadata] section in {}", path.display()))?;
+        metadata.buildpack_version = buildpack_toml.buildpack.version.to_string();
+        metadata.stacks = buildpack_toml
+            .stacks
+            .iter()
+            .map(|stack| stack.id.as_str().to_string())
+            .collect();
+
+        Ok(metadata)
+    }
+
+    pub fn supports_stack(&self, stack_id: &str) -> bool {
+        self.stacks.is_empty() || self.stacks.iter().any(|id| id == "*" || id == stack_id)
+    }
+
+    /// Why builds on `stack_id` aren't supported, `None` if they are.
+    pub fn unsupported_stack(&self, stack_id: &str) -> Option<String> {
+        if self.supports_stack(stack_id) {
+            return None;
+        }
+
+        Some(messages::render(
+            "detect.unsupported_stack",
+            &[("stack", &stack_id), ("stacks", &self.stacks.join(", "))],
+        ))
+    }
 }
 
 impl TryFrom<&Table> for Metadata {
     type Error = anyhow::Error;
 
     fn try_from(value: &Table) -> Result<Self, Self::Error> {
-        Ok(toml::from_str(&toml::to_string(&value)?)?)
+        let metadata: Metadata = toml::from_str(&toml::to_string(&value)?)?;
+        if let Some(artifact) = metadata.runtime.artifact_without_checksum() {
+            anyhow::bail!("{} requires a checksum or sha256 key", artifact);
+        }
+
+        Ok(metadata)
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct Jvm {
+    /// Flags prepended to `JAVA_TOOL_OPTIONS` at launch, so user provided flags take precedence.
+    #[serde(default)]
+    pub java_tool_options: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Bundler {
+    /// Maximum time the runtime may take to detect and bundle functions.
+    pub timeout_secs: u64,
+    /// JVM flags for the bundler invocation, e.g. a larger `-Xmx` for big projects.
+    pub java_options: Vec<String>,
+    /// Whether the runtime's bundler supports `--incremental`, which updates an existing bundle
+    /// given the module jars that changed since.
+    pub incremental: bool,
+    /// Bundles larger than this many MiB get a warning, as they usually vendor unneeded jars.
+    pub size_warning_mib: u64,
+    /// The Maven and Gradle caches of the bundler are cleared once they grow beyond this many MiB.
+    pub dependency_cache_max_mib: u64,
+    /// Messages for bundler exit codes, so new runtime error codes don't need a code change.
+    pub exit_codes: Vec<ExitCodeMessage>,
+}
+
+impl Default for Bundler {
+    fn default() -> Self {
+        Bundler {
+            timeout_secs: 300,
+            java_options: Vec::new(),
+            incremental: false,
+            size_warning_mib: 200,
+            dependency_cache_max_mib: 1024,
+            exit_codes: Vec::new(),
+        }
+    }
+}
+
+impl Bundler {
+    pub fn exit_code_message(&self, code: i32) -> Option<&ExitCodeMessage> {
+        self.exit_codes
+            .iter()
+            .find(|message| message.codes.contains(&code))
     }
 }
 
+#[derive(Deserialize)]
+pub struct ExitCodeMessage {
+    pub codes: Vec<i32>,
+    pub header: String,
+    /// May reference `{code}` and `{stderr_tail}`, the last lines the bundler wrote to stderr.
+    pub message: String,
+}
+
+impl ExitCodeMessage {
+    pub fn render(&self, code: i32, stderr_tail: &[String]) -> String {
+        self.message
+            .replace("{code}", &code.to_string())
+            .replace("{stderr_tail}", &stderr_tail.join("\n"))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Launch {
+    /// Port the function runtime listens on when `PORT` is not set at launch.
+    pub default_port: u16,
+}
+
+impl Default for Launch {
+    fn default() -> Self {
+        Launch {
+            default_port: launcher::DEFAULT_PORT,
+        }
+    }
+}
+
+/// The OpenTelemetry Java agent attached when `SF_FUNCTIONS_ENABLE_OTEL` is set.
+#[derive(Deserialize, Default)]
+pub struct OpenTelemetry {
+    pub agent_url: Option<String>,
+    pub agent_sha256: Option<String>,
+}
+
+/// Where to look up the latest buildpack release, see [`crate::update_check`]. No check is made
+/// without a URL.
+#[derive(Deserialize, Default)]
+pub struct UpdateCheck {
+    pub url: Option<String>,
+}
+
+/// Known issues of the function SDK, so builds can warn about affected apps without a buildpack
+/// release.
+#[derive(Deserialize, Default)]
+pub struct Sdk {
+    #[serde(default)]
+    pub vulnerable_versions: Vec<VulnerableVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct VulnerableVersion {
+    pub version: String,
+    /// Link to the advisory, shown in the warning.
+    pub advisory: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct Release {
     pub docker: Docker,
@@ -30,6 +209,7 @@ pub struct Docker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::BuildpackTomlBuilder;
     use std::{fs, path::PathBuf};
 
     #[test]
@@ -45,4 +225,164 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn metadata_try_from_reports_missing_keys() {
+        let err = Metadata::try_from(
+            toml::toml! {
+                [runtime]
+                url = "https://foo.com"
+
+                [release.docker]
+                repository = "foo/bar"
+            }
+            .as_table()
+            .unwrap(),
+        )
+        .err()
+        .unwrap();
+
+        assert!(err.to_string().contains("sha256"));
+    }
+
+    #[test]
+    fn metadata_try_from_parses_retry_policy() -> anyhow::Result<()> {
+        let metadata = Metadata::try_from(
+            toml::toml! {
+                [runtime]
+                url = "https://foo.com"
+                sha256 = "ABCDEF"
+
+                [release.docker]
+                repository = "foo/bar"
+
+                [retry]
+                attempts = 5
+                jitter = false
+            }
+            .as_table()
+            .unwrap(),
+        )?;
+
+        assert_eq!(metadata.retry.attempts, 5);
+        assert!(!metadata.retry.jitter);
+        assert_eq!(
+            metadata.retry.initial_backoff_ms,
+            RetryPolicy::default().initial_backoff_ms
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_try_from_defaults_bundler_and_launch_settings() -> anyhow::Result<()> {
+        let metadata = Metadata::try_from(
+            toml::toml! {
+                [runtime]
+                url = "https://foo.com"
+                sha256 = "ABCDEF"
+
+                [release.docker]
+                repository = "foo/bar"
+            }
+            .as_table()
+            .unwrap(),
+        )?;
+
+        assert_eq!(metadata.bundler.timeout_secs, 300);
+        assert!(metadata.bundler.java_options.is_empty());
+        assert_eq!(metadata.launch.default_port, 8080);
+        assert!(metadata.sdk.vulnerable_versions.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_try_from_parses_vulnerable_sdk_versions() -> anyhow::Result<()> {
+        let metadata = Metadata::try_from(
+            toml::toml! {
+                [runtime]
+                url = "https://foo.com"
+                sha256 = "ABCDEF"
+
+                [release.docker]
+                repository = "foo/bar"
+
+                [[sdk.vulnerable_versions]]
+                version = "1.0.0"
+                advisory = "https://example.com/advisory"
+            }
+            .as_table()
+            .unwrap(),
+        )?;
+
+        assert_eq!(
+            metadata.sdk.vulnerable_versions,
+            vec![VulnerableVersion {
+                version: String::from("1.0.0"),
+                advisory: Some(String::from("https://example.com/advisory")),
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn vendored_buildpack_toml_maps_bundler_exit_codes() -> anyhow::Result<()> {
+        let metadata = Metadata::from_buildpack_dir(env!("CARGO_MANIFEST_DIR"))?;
+
+        assert_eq!(
+            metadata
+                .bundler
+                .exit_code_message(1)
+                .map(|m| m.header.as_str()),
+            Some("No functions found")
+        );
+        assert!(metadata.bundler.exit_code_message(42).is_none());
+
+        let message = metadata
+            .bundler
+            .exit_code_message(4)
+            .unwrap()
+            .render(4, &[String::from("java.lang.NullPointerException")]);
+        assert!(message.contains(r#"internal error "4""#));
+        assert!(message.ends_with("java.lang.NullPointerException\n"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_buildpack_dir_reads_buildpack_version() -> anyhow::Result<()> {
+        let metadata = Metadata::from_buildpack_dir(env!("CARGO_MANIFEST_DIR"))?;
+
+        assert_eq!(metadata.buildpack_version, "0.2.4");
+        assert!(metadata.supports_stack("heroku-20"));
+        assert_eq!(
+            metadata.unsupported_stack("heroku-16").as_deref(),
+            Some(
+                "Unsupported stack heroku-16, this buildpack supports heroku-18, heroku-20, \
+                 io.buildpacks.stacks.bionic"
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_buildpack_dir_reads_generated_buildpack_toml() -> anyhow::Result<()> {
+        let buildpack_dir = BuildpackTomlBuilder::default()
+            .version("1.0.0")
+            .stacks(&["heroku-22"])
+            .runtime("max_java", 11)
+            .buildpack_dir()?;
+
+        let metadata = Metadata::from_buildpack_dir(buildpack_dir.path())?;
+
+        assert_eq!(metadata.buildpack_version, "1.0.0");
+        assert_eq!(metadata.runtime.max_java, Some(11));
+        assert!(metadata.supports_stack("heroku-22"));
+        assert!(!metadata.supports_stack("heroku-20"));
+
+        Ok(())
+    }
 }
@@ -1,15 +1,240 @@
+use anyhow::Context;
 use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
 
-#[derive(Deserialize)]
+pub const FILE_NAME: &str = "function-bundle.toml";
+
+/// Newest `function-bundle.toml` schema this buildpack understands.
+pub const SUPPORTED_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize)]
 pub struct Toml {
+    /// Bundles written by runtimes that predate versioning are schema version 1.
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
     pub function: Function,
+    #[serde(default)]
+    pub salesforce: Salesforce,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 pub struct Function {
+    pub id: Option<String>,
     pub class: String,
     pub payload_class: String,
     pub payload_media_type: String,
     pub return_class: String,
     pub return_media_type: String,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Parameter {
+    pub name: String,
+    pub r#type: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Salesforce {
+    pub api_version: Option<String>,
+}
+
+fn default_schema_version() -> u32 {
+    1
+}
+
+impl Toml {
+    pub fn from_bundle_dir(bundle_dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = bundle_dir.as_ref().join(FILE_NAME);
+        let contents =
+            fs::read(&path).with_context(|| format!("Could not read {}", path.display()))?;
+
+        Toml::from_slice(&contents).with_context(|| format!("Invalid {}", path.display()))
+    }
+
+    fn from_slice(contents: &[u8]) -> anyhow::Result<Self> {
+        // Check the version before the full schema, so a newer runtime gets a clear error instead
+        // of a missing field.
+        #[derive(Deserialize)]
+        struct Versioned {
+            #[serde(default = "default_schema_version")]
+            schema_version: u32,
+        }
+
+        let Versioned { schema_version } = toml::from_slice(contents)?;
+        if schema_version > SUPPORTED_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Unsupported schema version {}, this buildpack supports up to version {}. \
+                 Please update the buildpack or pin an older function runtime.",
+                schema_version,
+                SUPPORTED_SCHEMA_VERSION
+            );
+        }
+
+        let toml: Toml = toml::from_slice(contents)?;
+        if let Some(api_version) = &toml.salesforce.api_version {
+            if !is_api_version(api_version) {
+                anyhow::bail!(
+                    "Invalid salesforce.api_version {:?}, expected a version like \"53.0\"",
+                    api_version
+                );
+            }
+        }
+
+        Ok(toml)
+    }
+}
+
+pub fn is_api_version(value: &str) -> bool {
+    let mut parts = value.split('.');
+    matches!(
+        (parts.next(), parts.next(), parts.next()),
+        (Some(major), Some(minor), None)
+            if !major.is_empty()
+                && !minor.is_empty()
+                && major.chars().chain(minor.chars()).all(|c| c.is_ascii_digit())
+    )
+}
+
+/// Bundle directories written by the bundler in multiple functions mode, sorted by name.
+pub fn bundle_dirs(layer_dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(layer_dir)? {
+        let path = entry?.path();
+        if path.join(FILE_NAME).is_file() {
+            dirs.push(path);
+        }
+    }
+    dirs.sort();
+
+    Ok(dirs)
+}
+
+/// Derives a valid CNB process type from a bundle directory name.
+pub fn process_type(bundle_dir: impl AsRef<Path>) -> String {
+    bundle_dir
+        .as_ref()
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_type_replaces_invalid_characters() {
+        assert_eq!(
+            process_type("/layers/function-bundle/com.example.MyFunction"),
+            "com-example-MyFunction"
+        );
+        assert_eq!(
+            process_type("/layers/function-bundle/my_function"),
+            "my_function"
+        );
+    }
+
+    #[test]
+    fn bundle_dirs_only_includes_dirs_with_bundle_toml() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+        for name in &["b", "a", "empty"] {
+            fs::create_dir(layer_dir.path().join(name))?;
+        }
+        fs::write(layer_dir.path().join("a/function-bundle.toml"), "")?;
+        fs::write(layer_dir.path().join("b/function-bundle.toml"), "")?;
+
+        assert_eq!(
+            bundle_dirs(layer_dir.path())?,
+            vec![layer_dir.path().join("a"), layer_dir.path().join("b")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_parses_legacy_bundle_toml() -> anyhow::Result<()> {
+        let toml = Toml::from_slice(
+            br#"
+[function]
+class = "com.example.MyFunction"
+payload_class = "com.example.Payload"
+payload_media_type = "application/json"
+return_class = "com.example.Result"
+return_media_type = "application/json"
+"#,
+        )?;
+
+        assert_eq!(toml.schema_version, 1);
+        assert_eq!(toml.function.class, "com.example.MyFunction");
+        assert!(toml.function.parameters.is_empty());
+        assert_eq!(toml.salesforce.api_version, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_parses_full_metadata() -> anyhow::Result<()> {
+        let toml = Toml::from_slice(
+            br#"
+schema_version = 1
+
+[function]
+id = "my-function"
+class = "com.example.MyFunction"
+payload_class = "com.example.Payload"
+payload_media_type = "application/json"
+return_class = "com.example.Result"
+return_media_type = "application/json"
+parameters = [{ name = "event", type = "com.salesforce.functions.jvm.sdk.InvocationEvent" }]
+
+[function.annotations]
+deprecated = false
+
+[salesforce]
+api_version = "53.0"
+"#,
+        )?;
+
+        assert_eq!(toml.function.id.as_deref(), Some("my-function"));
+        assert_eq!(toml.function.parameters[0].name, "event");
+        assert_eq!(
+            toml.function.annotations.get("deprecated"),
+            Some(&toml::Value::Boolean(false))
+        );
+        assert_eq!(toml.salesforce.api_version.as_deref(), Some("53.0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn from_slice_rejects_newer_schema_version() {
+        let err = Toml::from_slice(b"schema_version = 2").unwrap_err();
+
+        assert!(err.to_string().contains("Unsupported schema version 2"));
+    }
+
+    #[test]
+    fn is_api_version_requires_major_and_minor() {
+        assert!(is_api_version("53.0"));
+        assert!(!is_api_version("53"));
+        assert!(!is_api_version("53.0.1"));
+        assert!(!is_api_version("v53.0"));
+    }
 }
@@ -0,0 +1,254 @@
+use crate::{bindings::RequiredBinding, launcher::LaunchOptions, util::checksum::Checksum};
+use anyhow::Context;
+use serde::Deserialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+#[derive(Deserialize, Default)]
+pub struct ProjectToml {
+    #[serde(rename = "_", default)]
+    pub project: Project,
+    #[serde(default)]
+    pub com: Com,
+}
+
+impl ProjectToml {
+    /// Reads `project.toml` from the app directory, a missing file yields the defaults.
+    pub fn from_app_dir(app_dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = app_dir.as_ref().join("project.toml");
+
+        if !path.exists() {
+            return Ok(ProjectToml::default());
+        }
+
+        toml::from_str(
+            &fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?,
+        )
+        .with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn functions(&self) -> &Functions {
+        &self.com.salesforce.functions
+    }
+
+    pub fn is_function(&self) -> bool {
+        self.com.salesforce.r#type.as_deref() == Some("function")
+    }
+}
+
+/// The `[_]` table of the project descriptor.
+#[derive(Deserialize, Default)]
+pub struct Project {
+    pub id: Option<String>,
+    #[serde(default)]
+    pub metadata: ProjectMetadata,
+}
+
+#[derive(Deserialize, Default)]
+pub struct ProjectMetadata {
+    #[serde(default)]
+    pub function: FunctionMetadata,
+}
+
+/// `[_.metadata.function]`, settings of the function that aren't specific to Salesforce tooling.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct FunctionMetadata {
+    pub name: Option<String>,
+    pub salesforce_api_version: Option<String>,
+    #[serde(default)]
+    pub memory: MemoryHints,
+    /// Defaults for the launch environment, variables set at launch take precedence.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct MemoryHints {
+    /// Maximum heap size in the `-Xmx` format, e.g. `512m`. Replaces the heap size derived from
+    /// the container memory limit.
+    pub max_heap_size: Option<String>,
+    /// Thread stack size in the `-Xss` format, e.g. `1m`.
+    pub thread_stack_size: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct Com {
+    #[serde(default)]
+    pub salesforce: Salesforce,
+}
+
+#[derive(Deserialize, Default)]
+pub struct Salesforce {
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub r#type: Option<String>,
+    #[serde(default)]
+    pub functions: Functions,
+}
+
+#[derive(Deserialize, Default)]
+pub struct Functions {
+    #[serde(default)]
+    pub processes: Vec<Process>,
+    /// Builds every function in the project as its own process type.
+    #[serde(default)]
+    pub multiple: bool,
+    #[serde(rename = "runtime-version")]
+    pub runtime_version: Option<String>,
+    #[serde(default)]
+    pub serve: LaunchOptions,
+    #[serde(default)]
+    pub extensions: Vec<Extension>,
+    #[serde(default)]
+    pub bindings: Vec<RequiredBinding>,
+    /// Subdirectory with the function when the app is a monorepo, see
+    /// [`crate::config::project_dir`].
+    #[serde(rename = "project-path")]
+    pub project_path: Option<String>,
+}
+
+/// A jar appended to the classpath of the function runtime, e.g. a logging shim. Either `url`
+/// (`https://`, `file://` or `s3://`) or `path`, relative to the app directory, is set.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Extension {
+    pub url: Option<String>,
+    pub path: Option<String>,
+    /// `<algorithm>:<hex>`, verified after the jar is downloaded or copied.
+    pub checksum: Option<Checksum>,
+}
+
+impl Extension {
+    /// The `url` or `path` the jar is taken from.
+    pub fn source(&self) -> Option<&str> {
+        self.url.as_deref().or_else(|| self.path.as_deref())
+    }
+
+    /// Name of the jar in the extensions layer, the last segment of its source without any query.
+    pub fn file_name(&self) -> Option<&str> {
+        let source = self.source()?;
+        let source = source.split(|c: char| c == '?' || c == '#').next()?;
+
+        source.rsplit('/').next().filter(|name| !name.is_empty())
+    }
+}
+
+/// An additional launch process serving the function bundle with extra runtime arguments.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Process {
+    #[serde(rename = "type")]
+    pub r#type: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_additional_processes() -> anyhow::Result<()> {
+        let project_toml: ProjectToml = toml::from_str(
+            r#"
+[_]
+schema-version = "0.2"
+
+[[com.salesforce.functions.processes]]
+type = "worker"
+args = ["--workers", "4"]
+
+[[com.salesforce.functions.processes]]
+type = "debug"
+"#,
+        )?;
+        let processes = &project_toml.functions().processes;
+
+        assert_eq!(processes.len(), 2);
+        assert_eq!(processes[0].r#type, "worker");
+        assert_eq!(processes[0].args, vec!["--workers", "4"]);
+        assert_eq!(processes[1].r#type, "debug");
+        assert!(processes[1].args.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_extensions() -> anyhow::Result<()> {
+        let project_toml: ProjectToml = toml::from_str(&format!(
+            r#"
+[[com.salesforce.functions.extensions]]
+url = "https://repo.example.com/log-shim-1.0.jar"
+checksum = "sha256:{}"
+
+[[com.salesforce.functions.extensions]]
+path = "lib/sdk.jar"
+"#,
+            "ab".repeat(32)
+        ))?;
+        let extensions = &project_toml.functions().extensions;
+
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(
+            extensions[0].url.as_deref(),
+            Some("https://repo.example.com/log-shim-1.0.jar")
+        );
+        assert_eq!(
+            extensions[0].checksum,
+            Some(Checksum::sha256("ab".repeat(32)))
+        );
+        assert_eq!(extensions[1].path.as_deref(), Some("lib/sdk.jar"));
+        assert_eq!(extensions[1].checksum, None);
+
+        assert!(toml::from_str::<ProjectToml>(
+            "[[com.salesforce.functions.extensions]]\nurl = \"x.jar\"\nchecksum = \"md5:00\"\n"
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parses_function_metadata() -> anyhow::Result<()> {
+        let project_toml: ProjectToml = toml::from_str(
+            r#"
+[_]
+id = "com.example.function"
+
+[_.metadata.function]
+name = "invoice-sync"
+salesforce-api-version = "53.0"
+
+[_.metadata.function.memory]
+max-heap-size = "512m"
+
+[_.metadata.function.env]
+LOG_LEVEL = "debug"
+"#,
+        )?;
+        let function = &project_toml.project.metadata.function;
+
+        assert_eq!(
+            project_toml.project.id.as_deref(),
+            Some("com.example.function")
+        );
+        assert_eq!(function.name.as_deref(), Some("invoice-sync"));
+        assert_eq!(function.salesforce_api_version.as_deref(), Some("53.0"));
+        assert_eq!(function.memory.max_heap_size.as_deref(), Some("512m"));
+        assert_eq!(function.memory.thread_stack_size, None);
+        assert_eq!(function.env["LOG_LEVEL"], "debug");
+
+        Ok(())
+    }
+
+    #[test]
+    fn defaults_without_salesforce_table() -> anyhow::Result<()> {
+        let project_toml: ProjectToml = toml::from_str("[_]\nid = \"foo\"\n")?;
+
+        assert!(project_toml.functions().processes.is_empty());
+        assert!(project_toml.functions().extensions.is_empty());
+
+        Ok(())
+    }
+}
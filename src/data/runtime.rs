@@ -1,13 +1,169 @@
+use crate::{
+    error::{Error, Result},
+    launcher::ServeMode,
+    util::checksum::{Algorithm, Checksum},
+};
 use serde::Deserialize;
+use std::{collections::BTreeMap, path::PathBuf};
 use toml::value::Table;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct Runtime {
     pub url: String,
+    /// Fallback for `checksum`, which older buildpack releases don't understand.
+    #[serde(default)]
     pub sha256: String,
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+    /// Local runtime jar, relative to the buildpack directory, used instead of downloading `url`.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Alternate download locations, tried in order when `url` is unreachable.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    #[serde(default)]
+    pub verify: VerifyPolicy,
+    /// Additional runtime versions users can pin, keyed by version.
+    #[serde(default)]
+    pub versions: BTreeMap<String, RuntimeArtifact>,
+    /// Architecture specific artifacts that replace `url` and `sha256` on that architecture.
+    #[serde(default, rename = "linux-amd64")]
+    pub linux_amd64: Option<RuntimeArtifact>,
+    #[serde(default, rename = "linux-arm64")]
+    pub linux_arm64: Option<RuntimeArtifact>,
+    /// Stack specific artifacts keyed by stack id, e.g. builds for `heroku-22` that need a newer
+    /// glibc. They replace the architecture specific artifacts on that stack.
+    #[serde(default)]
+    pub stacks: BTreeMap<String, RuntimeArtifact>,
+    /// Range of JDK major versions the runtime supports, inclusive.
+    #[serde(default)]
+    pub min_java: Option<u32>,
+    #[serde(default)]
+    pub max_java: Option<u32>,
+    /// Detached signature verified in addition to the sha256 of downloaded artifacts.
+    #[serde(default)]
+    pub signature: Option<SignatureConfig>,
+    /// Protocols the runtime serves, `None` if the descriptor doesn't say and the runtime has to
+    /// be asked.
+    #[serde(default)]
+    pub protocols: Option<Vec<ServeMode>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SignatureConfig {
+    /// Defaults to the URL the artifact was downloaded from with a `.sig` suffix.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// PEM encoded public key, takes precedence over `public_key_path`.
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Public key file, relative to the buildpack directory.
+    #[serde(default)]
+    pub public_key_path: Option<PathBuf>,
+}
+
+impl SignatureConfig {
+    pub fn url_for(&self, artifact_url: &str) -> String {
+        self.url
+            .clone()
+            .unwrap_or_else(|| format!("{}.sig", artifact_url))
+    }
+}
+
+/// How a runtime artifact whose sha256 doesn't match the expected one is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerifyPolicy {
+    Strict,
+    Warn,
+    Off,
+}
+
+impl Default for VerifyPolicy {
+    fn default() -> Self {
+        VerifyPolicy::Strict
+    }
+}
+
+impl VerifyPolicy {
+    /// Downgrades strict verification to a warning, for pre-release runtimes users opted into.
+    pub fn allow_unverified(self, allow: bool) -> Self {
+        match self {
+            VerifyPolicy::Strict if allow => VerifyPolicy::Warn,
+            policy => policy,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeArtifact {
+    pub url: String,
+    #[serde(default)]
+    pub sha256: String,
+    #[serde(default)]
+    pub checksum: Option<Checksum>,
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    #[serde(default)]
+    pub protocols: Option<Vec<ServeMode>>,
 }
 
 impl Runtime {
+    /// Whether the runtime supports a JDK with the given major version.
+    pub fn supports_java(&self, major_version: u32) -> bool {
+        self.min_java.map_or(true, |min| major_version >= min)
+            && self.max_java.map_or(true, |max| major_version <= max)
+    }
+
+    /// The checksum artifacts are verified against, `checksum` takes precedence over `sha256`.
+    pub fn checksum(&self) -> Checksum {
+        self.checksum
+            .clone()
+            .unwrap_or_else(|| Checksum::sha256(&self.sha256))
+    }
+
+    /// The sha256 of the artifact, if declared, which downloads verify while writing and the
+    /// artifact cache is keyed on.
+    pub fn known_sha256(&self) -> Option<&str> {
+        match &self.checksum {
+            Some(checksum) if checksum.algorithm == Algorithm::Sha256 => Some(&checksum.hex),
+            _ => Some(self.sha256.as_str()).filter(|sha256| !sha256.is_empty()),
+        }
+    }
+
+    /// The table of the first artifact that declares neither `checksum` nor `sha256`.
+    pub fn artifact_without_checksum(&self) -> Option<String> {
+        let has_checksum =
+            |checksum: &Option<Checksum>, sha256: &str| checksum.is_some() || !sha256.is_empty();
+        if !has_checksum(&self.checksum, &self.sha256) {
+            return Some(String::from("[metadata.runtime]"));
+        }
+
+        let arch_artifacts = [
+            ("linux-amd64", &self.linux_amd64),
+            ("linux-arm64", &self.linux_arm64),
+        ];
+        arch_artifacts
+            .iter()
+            .filter_map(|(arch, artifact)| {
+                artifact
+                    .as_ref()
+                    .map(|artifact| (arch.to_string(), artifact))
+            })
+            .chain(
+                self.stacks
+                    .iter()
+                    .map(|(stack, artifact)| (format!("stacks.{:?}", stack), artifact)),
+            )
+            .chain(
+                self.versions
+                    .iter()
+                    .map(|(version, artifact)| (format!("versions.{:?}", version), artifact)),
+            )
+            .find(|(_, artifact)| !has_checksum(&artifact.checksum, &artifact.sha256))
+            .map(|(name, _)| format!("[metadata.runtime.{}]", name))
+    }
+
     pub fn from_runtime_layer(metadata: &Table) -> Self {
         let empty_string = toml::Value::String("".to_string());
         let sha256 = metadata
@@ -24,7 +180,135 @@ impl Runtime {
             .unwrap_or("")
             .to_string();
 
-        Runtime { url, sha256 }
+        let checksum = metadata
+            .get("runtime_jar_checksum")
+            .and_then(toml::Value::as_str)
+            .and_then(|checksum| checksum.parse().ok());
+
+        Runtime {
+            url,
+            sha256,
+            checksum,
+            ..Runtime::default()
+        }
+    }
+
+    /// Selects the artifact for a CNB architecture name such as `amd64` or `arm64`, keeping the
+    /// default artifact when there is none for that architecture.
+    pub fn for_arch(self, arch: &str) -> Runtime {
+        let artifact = match arch {
+            "amd64" => self.linux_amd64.clone(),
+            "arm64" => self.linux_arm64.clone(),
+            _ => None,
+        };
+
+        match artifact {
+            Some(artifact) => Runtime {
+                url: artifact.url,
+                sha256: artifact.sha256,
+                checksum: artifact.checksum,
+                mirrors: artifact.mirrors,
+                protocols: artifact.protocols.or_else(|| self.protocols.clone()),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Selects the artifact for a stack id such as `heroku-22`, keeping the current artifact when
+    /// there is none for that stack.
+    pub fn for_stack(self, stack_id: &str) -> Runtime {
+        match self.stacks.get(stack_id).cloned() {
+            Some(artifact) => Runtime {
+                url: artifact.url,
+                sha256: artifact.sha256,
+                checksum: artifact.checksum,
+                mirrors: artifact.mirrors,
+                protocols: artifact.protocols.or_else(|| self.protocols.clone()),
+                ..self
+            },
+            None => self,
+        }
+    }
+
+    /// Selects the artifact for a pinned version, `None` keeps the default runtime.
+    pub fn resolve(self, version: Option<&str>) -> Result<Runtime> {
+        let version = match version {
+            Some(version) if self.version().as_deref() != Some(version) => version,
+            _ => return Ok(self),
+        };
+
+        match self.versions.get(version) {
+            Some(artifact) => Ok(Runtime {
+                url: artifact.url.clone(),
+                sha256: artifact.sha256.clone(),
+                checksum: artifact.checksum.clone(),
+                mirrors: artifact.mirrors.clone(),
+                verify: self.verify,
+                signature: self.signature.clone(),
+                protocols: artifact.protocols.clone(),
+                ..Runtime::default()
+            }),
+            None => Err(Error::UnknownRuntimeVersion {
+                version: version.to_string(),
+                available: self.available_versions(),
+            }),
+        }
+    }
+
+    pub fn available_versions(&self) -> Vec<String> {
+        self.version()
+            .into_iter()
+            .chain(self.versions.keys().cloned())
+            .collect()
+    }
+
+    /// The version directory of a Maven repository URL, e.g. `0.2.2` in `.../0.2.2/runtime-0.2.2.jar`.
+    pub fn version(&self) -> Option<String> {
+        self.url
+            .rsplit('/')
+            .nth(1)
+            .filter(|segment| segment.starts_with(|c: char| c.is_ascii_digit()))
+            .map(String::from)
+    }
+
+    pub fn urls(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.url.as_str()).chain(self.mirrors.iter().map(String::as_str))
+    }
+
+    pub fn to_runtime_layer(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("runtime_jar_url"),
+            toml::Value::String(self.url.clone()),
+        );
+        metadata.insert(
+            String::from("runtime_jar_sha256"),
+            toml::Value::String(self.sha256.clone()),
+        );
+        if let Some(checksum) = &self.checksum {
+            metadata.insert(
+                String::from("runtime_jar_checksum"),
+                toml::Value::String(checksum.to_string()),
+            );
+        }
+        if let Some(version) = self.version() {
+            metadata.insert(
+                String::from("runtime_version"),
+                toml::Value::String(version),
+            );
+        }
+
+        metadata
+    }
+}
+
+/// The CNB architecture name of the build host, which is also the architecture of the image.
+pub fn host_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        arch => arch,
     }
 }
 
@@ -66,4 +350,244 @@ mod tests {
         assert_eq!(runtime.url, "https://foo.com");
         assert_eq!(runtime.sha256, "");
     }
+
+    #[test]
+    fn to_runtime_layer_round_trips() {
+        let runtime = Runtime {
+            url: String::from("https://foo.com"),
+            sha256: String::from("ABCDEF"),
+            ..Runtime::default()
+        };
+        let parsed = Runtime::from_runtime_layer(&runtime.to_runtime_layer());
+
+        assert_eq!(parsed.url, runtime.url);
+        assert_eq!(parsed.sha256, runtime.sha256);
+    }
+
+    #[test]
+    fn checksum_takes_precedence_over_sha256() -> anyhow::Result<()> {
+        let sha512 = format!("sha512:{}", "ab".repeat(64));
+        let runtime: Runtime = toml::from_str(&format!(
+            "url = \"https://foo.com\"\nsha256 = \"ABCDEF\"\nchecksum = \"{}\"\n",
+            sha512
+        ))?;
+        assert_eq!(runtime.checksum().to_string(), sha512);
+        assert_eq!(runtime.known_sha256(), Some("ABCDEF"));
+        let parsed = Runtime::from_runtime_layer(&runtime.to_runtime_layer());
+        assert_eq!(parsed.checksum, runtime.checksum);
+
+        let runtime: Runtime = toml::from_str(&format!(
+            "url = \"https://foo.com\"\nchecksum = \"{}\"\n",
+            sha512
+        ))?;
+        assert_eq!(runtime.known_sha256(), None);
+        assert_eq!(runtime.artifact_without_checksum(), None);
+
+        let runtime: Runtime = toml::from_str("url = \"https://foo.com\"\nsha256 = \"ABCDEF\"\n")?;
+        assert_eq!(runtime.checksum(), Checksum::sha256("abcdef"));
+
+        let runtime: Runtime = toml::from_str(
+            r#"
+url = "https://foo.com"
+sha256 = "ABCDEF"
+
+[versions."0.9.0"]
+url = "https://foo.com/runtime-0.9.0.jar"
+"#,
+        )?;
+        assert_eq!(
+            runtime.artifact_without_checksum().as_deref(),
+            Some(r#"[metadata.runtime.versions."0.9.0"]"#)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn supports_java_checks_inclusive_range() {
+        let runtime = Runtime {
+            min_java: Some(8),
+            max_java: Some(17),
+            ..Runtime::default()
+        };
+
+        assert!(!runtime.supports_java(7));
+        assert!(runtime.supports_java(8));
+        assert!(runtime.supports_java(17));
+        assert!(!runtime.supports_java(21));
+        assert!(Runtime::default().supports_java(21));
+    }
+
+    #[test]
+    fn version_is_parsed_from_maven_url() {
+        let mut runtime = Runtime::from_runtime_layer(&Table::new());
+        runtime.url = String::from("https://repo1.maven.org/maven2/com/salesforce/functions/sf-fx-runtime-java-runtime/0.2.2/sf-fx-runtime-java-runtime-0.2.2-jar-with-dependencies.jar");
+        assert_eq!(runtime.version(), Some(String::from("0.2.2")));
+
+        runtime.url = String::from("https://foo.com/runtime.jar");
+        assert_eq!(runtime.version(), None);
+    }
+
+    #[test]
+    fn to_runtime_layer_records_version() {
+        let runtime = Runtime {
+            url: String::from("https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"),
+            ..Runtime::default()
+        };
+
+        assert_eq!(
+            runtime.to_runtime_layer().get("runtime_version"),
+            Some(&toml::Value::String(String::from("1.0.0")))
+        );
+    }
+
+    #[test]
+    fn urls_starts_with_primary_url_followed_by_mirrors() {
+        let runtime = Runtime {
+            url: String::from("https://foo.com"),
+            sha256: String::from("ABCDEF"),
+            mirrors: vec![
+                String::from("https://bar.com"),
+                String::from("https://baz.com"),
+            ],
+            ..Runtime::default()
+        };
+
+        assert_eq!(
+            runtime.urls().collect::<Vec<_>>(),
+            vec!["https://foo.com", "https://bar.com", "https://baz.com"]
+        );
+    }
+
+    #[test]
+    fn verify_policy_defaults_to_strict() -> anyhow::Result<()> {
+        let runtime: Runtime = toml::from_str("url = \"https://foo.com\"\nsha256 = \"ABCDEF\"\n")?;
+        assert_eq!(runtime.verify, VerifyPolicy::Strict);
+
+        let runtime: Runtime =
+            toml::from_str("url = \"https://foo.com\"\nsha256 = \"ABCDEF\"\nverify = \"warn\"\n")?;
+        assert_eq!(runtime.verify, VerifyPolicy::Warn);
+
+        Ok(())
+    }
+
+    #[test]
+    fn allow_unverified_only_relaxes_strict_policy() {
+        assert_eq!(
+            VerifyPolicy::Strict.allow_unverified(true),
+            VerifyPolicy::Warn
+        );
+        assert_eq!(
+            VerifyPolicy::Strict.allow_unverified(false),
+            VerifyPolicy::Strict
+        );
+        assert_eq!(VerifyPolicy::Off.allow_unverified(true), VerifyPolicy::Off);
+    }
+
+    #[test]
+    fn resolve_selects_pinned_version() -> anyhow::Result<()> {
+        let runtime: Runtime = toml::from_str(
+            r#"
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"
+sha256 = "ABCDEF"
+
+[versions."0.9.0"]
+url = "https://foo.com/runtime/0.9.0/runtime-0.9.0.jar"
+sha256 = "123456"
+"#,
+        )?;
+
+        assert_eq!(runtime.clone().resolve(None)?.sha256, "ABCDEF");
+        assert_eq!(runtime.clone().resolve(Some("1.0.0"))?.sha256, "ABCDEF");
+        assert_eq!(runtime.clone().resolve(Some("0.9.0"))?.sha256, "123456");
+
+        let err = runtime.resolve(Some("2.0.0")).unwrap_err();
+        assert!(err.to_string().contains("1.0.0, 0.9.0"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn for_arch_selects_architecture_specific_artifact() -> anyhow::Result<()> {
+        let runtime: Runtime = toml::from_str(
+            r#"
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"
+sha256 = "ABCDEF"
+
+[linux-arm64]
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0-arm64.jar"
+sha256 = "123456"
+"#,
+        )?;
+
+        assert_eq!(runtime.clone().for_arch("amd64").sha256, "ABCDEF");
+        assert_eq!(runtime.clone().for_arch("s390x").sha256, "ABCDEF");
+
+        let arm64 = runtime.for_arch("arm64");
+        assert_eq!(arm64.sha256, "123456");
+        assert_eq!(arm64.version(), Some(String::from("1.0.0")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn for_stack_selects_stack_specific_artifact() -> anyhow::Result<()> {
+        let runtime: Runtime = toml::from_str(
+            r#"
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"
+sha256 = "ABCDEF"
+
+[linux-arm64]
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0-arm64.jar"
+sha256 = "123456"
+
+[stacks.heroku-22]
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0-heroku-22.jar"
+"#,
+        )?;
+        assert_eq!(
+            runtime.artifact_without_checksum().as_deref(),
+            Some("[metadata.runtime.stacks.\"heroku-22\"]")
+        );
+
+        assert_eq!(
+            runtime
+                .clone()
+                .for_arch("arm64")
+                .for_stack("heroku-20")
+                .sha256,
+            "123456"
+        );
+        let heroku_22 = runtime.for_arch("arm64").for_stack("heroku-22");
+        assert_eq!(
+            heroku_22.url,
+            "https://foo.com/runtime/1.0.0/runtime-1.0.0-heroku-22.jar"
+        );
+        assert_eq!(heroku_22.version(), Some(String::from("1.0.0")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn resolve_keeps_protocols_of_pinned_version() -> anyhow::Result<()> {
+        let runtime: Runtime = toml::from_str(
+            r#"
+url = "https://foo.com/runtime/1.1.0/runtime-1.1.0.jar"
+sha256 = "ABCDEF"
+protocols = ["http", "grpc"]
+
+[versions."1.0.0"]
+url = "https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"
+sha256 = "123456"
+"#,
+        )?;
+
+        assert_eq!(
+            runtime.clone().resolve(None)?.protocols,
+            Some(vec![ServeMode::Http, ServeMode::Grpc])
+        );
+        assert_eq!(runtime.resolve(Some("1.0.0"))?.protocols, None);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,194 @@
+use crate::launcher::ServeMode;
+use serde::Deserialize;
+
+/// Newest descriptor schema this buildpack understands.
+pub const SUPPORTED_SCHEMA_VERSION: u32 = 1;
+/// Bundler exit code for projects without a function, the one runtimes used before they could
+/// describe their exit codes. `[metadata.bundler]` messages are keyed by these codes.
+pub const LEGACY_NO_FUNCTIONS_EXIT_CODE: i32 = 1;
+/// Reason of the exit code the bundler returns for projects without a function.
+pub const NO_FUNCTIONS_REASON: &str = "no-functions";
+
+/// Capabilities the runtime reports as JSON from `java -jar runtime.jar describe`. Runtimes that
+/// predate the command are described by [`Descriptor::legacy`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Descriptor {
+    pub schema_version: u32,
+    #[serde(default)]
+    pub bundle: Bundle,
+    #[serde(default)]
+    pub serve: Serve,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Bundle {
+    /// Newest `function-bundle.toml` schema the bundler writes.
+    #[serde(default = "default_bundle_schema_version")]
+    pub schema_version: u32,
+    /// Whether the bundler supports `--incremental`, `None` leaves it to `[metadata.bundler]`.
+    #[serde(default)]
+    pub incremental: Option<bool>,
+    #[serde(default)]
+    pub exit_codes: Vec<ExitCode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ExitCode {
+    pub code: i32,
+    /// Machine-readable meaning, e.g. [`NO_FUNCTIONS_REASON`].
+    pub reason: String,
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Serve {
+    /// Flags `serve` accepts, `None` if the runtime doesn't say.
+    #[serde(default)]
+    pub flags: Option<Vec<String>>,
+    #[serde(default)]
+    pub protocols: Option<Vec<ServeMode>>,
+}
+
+fn default_bundle_schema_version() -> u32 {
+    1
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Bundle {
+            schema_version: default_bundle_schema_version(),
+            incremental: None,
+            exit_codes: Vec::new(),
+        }
+    }
+}
+
+impl Descriptor {
+    /// What the buildpack assumes of runtimes without `describe`.
+    pub fn legacy() -> Self {
+        Descriptor {
+            schema_version: 0,
+            bundle: Bundle::default(),
+            serve: Serve::default(),
+        }
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.schema_version == 0
+    }
+
+    /// Parses the output of `describe`, rejecting descriptors newer than this buildpack knows.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let descriptor: Descriptor = serde_json::from_str(json)?;
+        if descriptor.schema_version == 0 || descriptor.schema_version > SUPPORTED_SCHEMA_VERSION {
+            anyhow::bail!(
+                "Unsupported descriptor schema version {}, this buildpack supports up to version {}",
+                descriptor.schema_version,
+                SUPPORTED_SCHEMA_VERSION
+            );
+        }
+
+        Ok(descriptor)
+    }
+
+    /// Flags of `args` that `serve` doesn't accept. Without a list of flags, all are assumed to
+    /// be accepted.
+    pub fn unsupported_serve_flags<'a>(&self, args: &'a [String]) -> Vec<&'a str> {
+        match &self.serve.flags {
+            Some(flags) => args
+                .iter()
+                .filter(|arg| arg.starts_with('-') && !flags.contains(arg))
+                .map(String::as_str)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+impl Bundle {
+    pub fn exit_code(&self, code: i32) -> Option<&ExitCode> {
+        self.exit_codes
+            .iter()
+            .find(|exit_code| exit_code.code == code)
+    }
+
+    /// The exit code for projects without a function.
+    pub fn no_functions_exit_code(&self) -> i32 {
+        self.exit_codes
+            .iter()
+            .find(|exit_code| exit_code.reason == NO_FUNCTIONS_REASON)
+            .map_or(LEGACY_NO_FUNCTIONS_EXIT_CODE, |exit_code| exit_code.code)
+    }
+
+    /// The code `[metadata.bundler]` messages know `code` by, `None` if they don't apply. Only
+    /// runtimes that don't describe their exit codes share the legacy codes.
+    pub fn legacy_exit_code(&self, code: i32) -> Option<i32> {
+        if self.exit_codes.is_empty() {
+            return Some(code);
+        }
+
+        self.exit_code(code)
+            .filter(|exit_code| exit_code.reason == NO_FUNCTIONS_REASON)
+            .map(|_| LEGACY_NO_FUNCTIONS_EXIT_CODE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_json_reads_capabilities() -> anyhow::Result<()> {
+        let descriptor = Descriptor::from_json(
+            r#"{
+                "schema_version": 1,
+                "bundle": {
+                    "schema_version": 2,
+                    "incremental": true,
+                    "exit_codes": [{ "code": 3, "reason": "no-functions" }]
+                },
+                "serve": { "flags": ["-h", "-p", "--mode"], "protocols": ["http", "grpc"] }
+            }"#,
+        )?;
+
+        assert!(!descriptor.is_legacy());
+        assert_eq!(descriptor.bundle.schema_version, 2);
+        assert_eq!(descriptor.bundle.incremental, Some(true));
+        assert_eq!(descriptor.bundle.no_functions_exit_code(), 3);
+        assert_eq!(
+            descriptor.bundle.legacy_exit_code(3),
+            Some(LEGACY_NO_FUNCTIONS_EXIT_CODE)
+        );
+        assert_eq!(descriptor.bundle.legacy_exit_code(1), None);
+        assert_eq!(
+            descriptor.serve.protocols,
+            Some(vec![ServeMode::Http, ServeMode::Grpc])
+        );
+        assert_eq!(
+            descriptor.unsupported_serve_flags(&[
+                String::from("-p"),
+                String::from("8080"),
+                String::from("--workers"),
+                String::from("4"),
+            ]),
+            vec!["--workers"]
+        );
+
+        assert!(Descriptor::from_json(r#"{ "schema_version": 2 }"#).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_keeps_previous_assumptions() {
+        let descriptor = Descriptor::legacy();
+
+        assert!(descriptor.is_legacy());
+        assert_eq!(descriptor.bundle.no_functions_exit_code(), 1);
+        assert_eq!(descriptor.bundle.legacy_exit_code(4), Some(4));
+        assert!(descriptor
+            .unsupported_serve_flags(&[String::from("--workers")])
+            .is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use crate::util::disk_usage;
+use std::{
+    ffi::OsString,
+    io,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml::value::Table;
+
+/// Local Maven repository, relative to the cache directory.
+pub const MAVEN_REPOSITORY_DIR: &str = "m2/repository";
+/// `GRADLE_USER_HOME`, relative to the cache directory.
+pub const GRADLE_USER_HOME_DIR: &str = "gradle";
+
+/// Points Maven and Gradle started by `command` at the caches in `cache_dir`. `maven_opts` is the
+/// `MAVEN_OPTS` of the build, which is kept.
+pub fn configure(command: &mut Command, cache_dir: &Path, maven_opts: Option<OsString>) {
+    let mut opts = maven_opts.unwrap_or_default();
+    if !opts.is_empty() {
+        opts.push(" ");
+    }
+    opts.push(maven_repo_local(cache_dir));
+
+    command
+        .env("MAVEN_OPTS", opts)
+        .env("GRADLE_USER_HOME", cache_dir.join(GRADLE_USER_HOME_DIR));
+}
+
+/// System property for JVMs that resolve Maven dependencies in-process, like the bundler.
+pub fn maven_repo_local(cache_dir: &Path) -> String {
+    format!(
+        "-Dmaven.repo.local={}",
+        cache_dir.join(MAVEN_REPOSITORY_DIR).display()
+    )
+}
+
+/// Size of a dependency cache after [`prune`].
+#[derive(Debug, PartialEq)]
+pub struct Prune {
+    pub size_bytes: u64,
+    /// Bytes removed because the cache grew beyond its limit.
+    pub reclaimed_bytes: u64,
+}
+
+/// Empties `cache_dir` once it grows beyond `max_bytes`. Single artifacts aren't evicted, as that
+/// could leave Gradle's cache metadata pointing at removed files.
+pub fn prune(cache_dir: &Path, max_bytes: u64) -> io::Result<Prune> {
+    let size_bytes = disk_usage::disk_usage(cache_dir, 0)?.total_bytes;
+    if size_bytes <= max_bytes {
+        return Ok(Prune {
+            size_bytes,
+            reclaimed_bytes: 0,
+        });
+    }
+
+    Ok(Prune {
+        size_bytes: 0,
+        reclaimed_bytes: disk_usage::remove_stale(cache_dir, |_| false)?,
+    })
+}
+
+/// Layer metadata recording when the cache was last used and how large it was afterwards.
+pub fn usage_metadata(size_bytes: u64, now: SystemTime) -> Table {
+    let last_used = now
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    let mut metadata = Table::new();
+    metadata.insert(
+        String::from("last_used"),
+        toml::Value::Integer(last_used as i64),
+    );
+    metadata.insert(
+        String::from("size_bytes"),
+        toml::Value::Integer(size_bytes as i64),
+    );
+
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{ffi::OsStr, fs};
+
+    #[test]
+    fn configure_keeps_existing_maven_opts() {
+        let mut command = Command::new("mvn");
+        configure(
+            &mut command,
+            Path::new("/layers/dependency-cache"),
+            Some(OsString::from("-Xmx1g")),
+        );
+
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(
+            OsStr::new("MAVEN_OPTS"),
+            Some(OsStr::new(
+                "-Xmx1g -Dmaven.repo.local=/layers/dependency-cache/m2/repository"
+            ))
+        )));
+        assert!(envs.contains(&(
+            OsStr::new("GRADLE_USER_HOME"),
+            Some(OsStr::new("/layers/dependency-cache/gradle"))
+        )));
+    }
+
+    #[test]
+    fn prune_empties_cache_beyond_limit() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let artifact_dir = dir
+            .path()
+            .join(MAVEN_REPOSITORY_DIR)
+            .join("com/example/1.0");
+        fs::create_dir_all(&artifact_dir)?;
+        fs::write(artifact_dir.join("example-1.0.jar"), vec![0; 100])?;
+
+        assert_eq!(
+            prune(dir.path(), 100)?,
+            Prune {
+                size_bytes: 100,
+                reclaimed_bytes: 0,
+            }
+        );
+        assert_eq!(
+            prune(dir.path(), 99)?,
+            Prune {
+                size_bytes: 0,
+                reclaimed_bytes: 100,
+            }
+        );
+        assert_eq!(fs::read_dir(dir.path())?.count(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{build_system::BuildSystem, config::FunctionConfig, messages};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+pub const BUILD_FILES: &[&str] = &["pom.xml", "build.gradle", "build.gradle.kts"];
+
+/// Artifact id of the Java function SDK, `com.salesforce.functions:sf-fx-sdk-java`.
+pub const SDK_ARTIFACT_ID: &str = "sf-fx-sdk-java";
+/// The interface every Java function implements, as it appears in class files.
+const FUNCTION_INTERFACE: &str = "com/salesforce/functions/jvm/sdk/SalesforceFunction";
+
+/// Checks whether the app is a Java function project. Returns the reasons it isn't, an empty
+/// list means detection passes.
+pub fn check(app_dir: impl AsRef<Path>) -> Vec<String> {
+    let app_dir = app_dir.as_ref();
+    let mut failures = Vec::new();
+
+    if !BUILD_FILES.iter().any(|file| app_dir.join(file).exists()) {
+        failures.push(messages::render(
+            "detect.no_build_file",
+            &[("build_files", &BUILD_FILES.join(", "))],
+        ));
+    }
+
+    // Regular JVM applications and functions are indistinguishable by design, only the function
+    // descriptor tells them apart.
+    if !app_dir.join("function.toml").exists() {
+        if !app_dir.join("project.toml").exists() {
+            failures.push(messages::render("detect.no_descriptor", &[]));
+        } else {
+            match FunctionConfig::from_app_dir(app_dir) {
+                Ok(config) if config.is_function => {}
+                Ok(_) => failures.push(messages::render("detect.not_a_function", &[])),
+                Err(err) => failures.push(format!("{:#}", err)),
+            }
+        }
+    }
+
+    failures
+}
+
+/// Multi-line explanation of why detection failed, one `failures` entry per line, so users can
+/// tell why the buildpack was skipped in a multi-buildpack order.
+pub fn skip_reasons(failures: &[String]) -> String {
+    let reasons = failures
+        .iter()
+        .map(|failure| format!("  - {}", failure))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    messages::render("detect.skipped", &[("reasons", &reasons)])
+}
+
+/// All failed checks on one line, for log processors that expect one line per event.
+pub fn failed_summary(failures: &[String]) -> String {
+    messages::render("detect.failed", &[("failures", &failures.join("; "))])
+}
+
+/// Likely causes when the bundler finds no function in the app, one hint per cause. Empty if
+/// the project looks fine.
+pub fn no_functions_hints(app_dir: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    let app_dir = app_dir.as_ref();
+    let mut hints = Vec::new();
+
+    let (build_file, classes_dir) = match BuildSystem::detect(app_dir) {
+        Some(BuildSystem::Maven) => ("pom.xml", app_dir.join("target").join("classes")),
+        Some(BuildSystem::Gradle) => (
+            if app_dir.join("build.gradle.kts").exists() {
+                "build.gradle.kts"
+            } else {
+                "build.gradle"
+            },
+            app_dir.join("build").join("classes"),
+        ),
+        None => return Ok(hints),
+    };
+
+    if !fs::read_to_string(app_dir.join(build_file))?.contains(SDK_ARTIFACT_ID) {
+        hints.push(messages::render(
+            "hint.missing_sdk",
+            &[("build_file", &build_file), ("artifact", &SDK_ARTIFACT_ID)],
+        ));
+    }
+
+    let class_files = class_files(&classes_dir)?;
+    if class_files.is_empty() {
+        hints.push(messages::render(
+            "hint.no_classes",
+            &[(
+                "classes_dir",
+                &classes_dir
+                    .strip_prefix(app_dir)
+                    .unwrap_or(&classes_dir)
+                    .display(),
+            )],
+        ));
+    } else if !class_files.iter().any(|path| {
+        fs::read(path).map_or(false, |bytes| {
+            bytes
+                .windows(FUNCTION_INTERFACE.len())
+                .any(|window| window == FUNCTION_INTERFACE.as_bytes())
+        })
+    }) {
+        hints.push(messages::render(
+            "hint.no_function_class",
+            &[("interface", &FUNCTION_INTERFACE.replace('/', "."))],
+        ));
+    }
+
+    Ok(hints)
+}
+
+fn class_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+
+    let mut dirs = vec![dir.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .map_or(false, |extension| extension == "class")
+            {
+                files.push(path);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+    use std::fs;
+
+    const FUNCTION_PROJECT_TOML: &str = r#"
+[_]
+schema-version = "0.2"
+
+[com.salesforce]
+type = "function"
+"#;
+
+    #[test]
+    fn check_passes_for_maven_function_project() -> anyhow::Result<()> {
+        let app_dir =
+            test_support::app_dir(&[("pom.xml", ""), ("project.toml", FUNCTION_PROJECT_TOML)])?;
+
+        assert!(check(app_dir.path()).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_matches_fixture_apps() -> anyhow::Result<()> {
+        for fixture in &["function-basic", "function-gradle"] {
+            assert!(check(test_support::fixture(fixture)?.path()).is_empty());
+        }
+        assert_eq!(
+            check(test_support::fixture("not-a-function")?.path()),
+            vec!["No project.toml or function.toml found"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_passes_for_gradle_project_with_function_toml() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        fs::write(app_dir.path().join("build.gradle.kts"), "")?;
+        fs::write(app_dir.path().join("function.toml"), "")?;
+
+        assert!(check(app_dir.path()).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_fails_for_project_toml_without_function_type() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        fs::write(app_dir.path().join("pom.xml"), "")?;
+        fs::write(app_dir.path().join("project.toml"), "[_]\nid = \"foo\"\n")?;
+
+        assert_eq!(
+            check(app_dir.path()),
+            vec![r#"project.toml does not declare com.salesforce.type = "function""#]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_reasons_lists_every_failed_check() {
+        assert_eq!(
+            skip_reasons(&[
+                String::from("No pom.xml found"),
+                String::from("No project.toml or function.toml found"),
+            ]),
+            "Skipping the Java function buildpack, these checks failed:\n  \
+             - No pom.xml found\n  \
+             - No project.toml or function.toml found"
+        );
+    }
+
+    #[test]
+    fn failed_summary_joins_failed_checks() {
+        assert_eq!(
+            failed_summary(&[
+                String::from("No pom.xml found"),
+                String::from("No project.toml or function.toml found"),
+            ]),
+            "Not a Java function project: No pom.xml found; No project.toml or function.toml found"
+        );
+    }
+
+    #[test]
+    fn check_fails_for_empty_app() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+
+        assert_eq!(check(app_dir.path()).len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn no_functions_hints_point_at_missing_sdk_and_classes() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        fs::write(app_dir.path().join("pom.xml"), "<project></project>")?;
+
+        let hints = no_functions_hints(app_dir.path())?;
+        assert_eq!(hints.len(), 2);
+        assert!(hints[0].contains("pom.xml is missing the function SDK dependency"));
+        assert!(hints[1].contains("No compiled classes found in target/classes"));
+
+        let classes_dir = app_dir.path().join("target").join("classes").join("com");
+        fs::create_dir_all(&classes_dir)?;
+        fs::write(classes_dir.join("Helper.class"), "java/lang/Object")?;
+        fs::write(
+            app_dir.path().join("pom.xml"),
+            "<artifactId>sf-fx-sdk-java</artifactId>",
+        )?;
+        let hints = no_functions_hints(app_dir.path())?;
+        assert_eq!(hints.len(), 1);
+        assert!(hints[0].contains("No compiled class implements"));
+
+        fs::write(
+            classes_dir.join("ExampleFunction.class"),
+            "com/salesforce/functions/jvm/sdk/SalesforceFunction",
+        )?;
+        assert!(no_functions_hints(app_dir.path())?.is_empty());
+
+        Ok(())
+    }
+}
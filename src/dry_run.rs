@@ -0,0 +1,165 @@
+use crate::{
+    build::runtime::RUNTIME_JAR_FILE_NAME,
+    builder::Builder,
+    detect,
+    launch::{self, LaunchPaths},
+    layer::{LayerContributor, LayerStore},
+    layers::{
+        artifact_cache::ArtifactCacheLayer,
+        dependency_cache::DependencyCacheLayer,
+        extensions::ExtensionsLayer,
+        function_bundle::{
+            function_bundle_digest, FunctionBundleLayer, FUNCTION_BUNDLE_LAYER_NAME,
+        },
+        observability::ObservabilityLayer,
+        opentelemetry::OpenTelemetryLayer,
+        opt::{OptLayer, LAUNCHER_FILE_NAME, OPT_LAYER_NAME},
+        runtime::{RuntimeLayer, RUNTIME_LAYER_NAME},
+    },
+    observability::{self, ObservabilityConfig},
+    platform::Family,
+    util::{artifact_store::ArtifactStore, logger::Logger},
+};
+use libcnb::platform::Platform;
+use std::path::PathBuf;
+use toml::value::Table;
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Prints the detection result, what would happen to each layer, where artifacts would be
+    /// downloaded from and the launch processes, without downloading or bundling anything.
+    /// Layers that depend on downloaded or bundled contents aren't listed.
+    pub fn print_plan(&self, multiple_functions: bool) -> anyhow::Result<()> {
+        let env = |key: &str| self.ctx.platform.env().var(key).ok();
+
+        self.logger.header("Dry run: detection")?;
+        let failures = detect::check(&self.project_dir);
+        if failures.is_empty() {
+            self.logger.info("The app is a Java function project")?;
+        }
+        for failure in failures {
+            self.logger.info(format!("- {}", failure))?;
+        }
+
+        self.logger.header("Dry run: layers")?;
+        self.plan_layer(&OptLayer {
+            buildpack_dir: &self.ctx.buildpack_dir,
+            default_port: self.settings().default_port()?,
+            env: &self.config.env,
+            required_bindings: &self.config.required_bindings,
+            protocol: self.protocol(),
+        })?;
+        let observability = ObservabilityConfig::from_env(env);
+        if observability.is_enabled() {
+            self.plan_layer(&ObservabilityLayer {
+                builder: self,
+                config: observability.clone(),
+            })?;
+        }
+        let opentelemetry = &self.buildpack_toml_metadata.opentelemetry;
+        let otel_agent_url = opentelemetry
+            .agent_url
+            .as_deref()
+            .filter(|_| observability::otel_agent_enabled(env));
+        if let Some(agent_url) = otel_agent_url {
+            self.plan_layer(&OpenTelemetryLayer::new(
+                self,
+                agent_url,
+                opentelemetry.agent_sha256.as_deref(),
+            )?)?;
+        }
+        self.plan_layer(&ArtifactCacheLayer)?;
+        let artifact_store = ArtifactStore::new(self.layers.layer_dir(ArtifactCacheLayer.name()));
+        self.plan_layer(&RuntimeLayer {
+            builder: self,
+            artifact_store: &artifact_store,
+            previous_download: None,
+        })?;
+        let runtime_jar_path = self
+            .layers
+            .layer_dir(RUNTIME_LAYER_NAME)
+            .join(RUNTIME_JAR_FILE_NAME);
+        if !self.config.extensions.is_empty() {
+            self.plan_layer(&ExtensionsLayer {
+                builder: self,
+                extensions: &self.config.extensions,
+                runtime_jar_path: &runtime_jar_path,
+            })?;
+        }
+        self.plan_layer(&DependencyCacheLayer)?;
+        self.plan_layer(&FunctionBundleLayer {
+            builder: self,
+            runtime_jar_path: &runtime_jar_path,
+            multiple_functions,
+            bundle_digest: function_bundle_digest(
+                &self.project_dir,
+                &self.runtime().checksum().to_string(),
+                multiple_functions,
+            )?,
+            previous_metadata: Table::new(),
+        })?;
+
+        self.logger.header("Dry run: downloads")?;
+        match env("SF_FUNCTIONS_RUNTIME_JAR")
+            .map(PathBuf::from)
+            .or_else(|| {
+                self.runtime()
+                    .path
+                    .as_ref()
+                    .map(|path| self.ctx.buildpack_dir.join(path))
+            }) {
+            Some(path) => self
+                .logger
+                .info(format!("Function runtime: copied from {}", path.display()))?,
+            None => {
+                for url in self.runtime().urls() {
+                    self.logger.info(format!("Function runtime: {}", url))?;
+                }
+            }
+        }
+        if let Some(agent_url) = &observability.agent_url {
+            self.logger.info(format!("Java agent: {}", agent_url))?;
+        }
+        if let Some(agent_url) = otel_agent_url {
+            self.logger
+                .info(format!("OpenTelemetry Java agent: {}", agent_url))?;
+        }
+        for extension in &self.config.extensions {
+            match &extension.url {
+                Some(url) => self.logger.info(format!("Extension: {}", url))?,
+                None => self.logger.info(format!(
+                    "Extension: copied from {}",
+                    extension.source().unwrap_or_default()
+                ))?,
+            }
+        }
+
+        self.logger.header("Dry run: launch")?;
+        let function_bundle_layer_dir = self.layers.layer_dir(FUNCTION_BUNDLE_LAYER_NAME);
+        if multiple_functions && !function_bundle_layer_dir.is_dir() {
+            return self
+                .logger
+                .info("One process per function the bundler finds");
+        }
+        let family = Family::current();
+        let launch = launch::launch(
+            &LaunchPaths {
+                launcher: &self
+                    .layers
+                    .layer_dir(OPT_LAYER_NAME)
+                    .join(family.executable_name(LAUNCHER_FILE_NAME)),
+                runtime_jar: &runtime_jar_path,
+                function_bundle_layer: &function_bundle_layer_dir,
+            },
+            family,
+            multiple_functions,
+            &self.config.processes,
+            &self.config.launch_options,
+        )?;
+        for (process_type, command_line) in launch::process_commands(&launch)? {
+            self.logger
+                .info(format!("{}: {}", process_type, command_line))?;
+        }
+
+        Ok(())
+    }
+}
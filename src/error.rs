@@ -0,0 +1,371 @@
+use crate::{messages, util::checksum::Algorithm};
+use std::{fmt, io, path::PathBuf, time::Duration};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Failures callers can match on. The wording shown to users lives in the [`messages`] catalog,
+/// see [`Error::header`] and [`Error::help`].
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Download of {url} failed: {source}")]
+    Download {
+        url: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("Unsupported URL {url}, expected an http(s)://, file:// or s3:// URL")]
+    UnsupportedUrl { url: String },
+    #[error("Download of function runtime failed from {}", .urls.join(", "))]
+    RuntimeDownload { urls: Vec<String>, attempts: u32 },
+    #[error("Could not read {}: {source}", .path.display())]
+    Copy {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("expected {algorithm} {expected}, got {actual}")]
+    ChecksumMismatch {
+        algorithm: Algorithm,
+        expected: String,
+        actual: String,
+    },
+    #[error("Signature verification of function runtime failed: {reason}")]
+    SignatureVerification { reason: String },
+    #[error("Unknown function runtime version {version}, available versions: {}", .available.join(", "))]
+    UnknownRuntimeVersion {
+        version: String,
+        available: Vec<String>,
+    },
+    #[error("java executable not found")]
+    JdkNotFound,
+    #[error("JDK {version} is not supported by the function runtime")]
+    UnsupportedJdk {
+        version: String,
+        min: Option<u32>,
+        max: Option<u32>,
+    },
+    #[error("Function detection did not finish within {} seconds", .0.as_secs())]
+    BundlerTimeout(Duration),
+    #[error("Function bundler exited with code {code}")]
+    BundlerExit { code: i32, stderr_tail: Vec<String> },
+    #[error("Function bundle is invalid: {}", .problems.join("; "))]
+    InvalidFunctionBundle { problems: Vec<String> },
+    #[error("{reason}")]
+    SmokeTest { reason: String, output: Vec<String> },
+    #[error("Post-bundle hook failed: {reason}")]
+    PostBundleHook { reason: String },
+    #[error("Service binding required by project.toml is incomplete: {reason}")]
+    ServiceBinding { reason: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Probable cause of a failure, so users know whether to retry, fix their project or contact us.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cause {
+    Network,
+    Project,
+    Internal,
+}
+
+impl Cause {
+    pub fn advice(self) -> &'static str {
+        match self {
+            Cause::Network => "Retrying the build usually helps.",
+            Cause::Project => "The project or its build configuration needs a change.",
+            Cause::Internal => "Please retry and contact support should the error persist.",
+        }
+    }
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Cause::Network => "network",
+            Cause::Project => "project",
+            Cause::Internal => "internal",
+        })
+    }
+}
+
+impl Error {
+    pub fn cause(&self) -> Cause {
+        match self {
+            Error::Download { .. } | Error::RuntimeDownload { .. } => Cause::Network,
+            Error::UnsupportedUrl { .. }
+            | Error::ChecksumMismatch { .. }
+            | Error::SignatureVerification { .. }
+            | Error::Io(_) => Cause::Internal,
+            Error::Copy { .. }
+            | Error::UnknownRuntimeVersion { .. }
+            | Error::JdkNotFound
+            | Error::UnsupportedJdk { .. }
+            | Error::BundlerTimeout(_)
+            | Error::BundlerExit { .. }
+            | Error::InvalidFunctionBundle { .. }
+            | Error::SmokeTest { .. }
+            | Error::PostBundleHook { .. }
+            | Error::ServiceBinding { .. } => Cause::Project,
+        }
+    }
+
+    /// Whether another attempt may succeed: connection failures, timeouts, transfers that broke
+    /// off and 5xx responses. Other client errors like a 404 fail the same way every time.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Download { source, .. } => is_transient(source),
+            Error::Io(err) => err
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<reqwest::Error>())
+                .map_or(false, is_transient),
+            _ => false,
+        }
+    }
+
+    /// Key of the error in the [`messages`] catalog.
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            Error::Download { .. } => "error.download",
+            Error::UnsupportedUrl { .. } => "error.unsupported_url",
+            Error::RuntimeDownload { .. } => "error.runtime_download",
+            Error::Copy { .. } => "error.copy",
+            Error::ChecksumMismatch { .. } => "error.checksum_mismatch",
+            Error::SignatureVerification { .. } => "error.signature_verification",
+            Error::UnknownRuntimeVersion { .. } => "error.unknown_runtime_version",
+            Error::JdkNotFound => "error.jdk_not_found",
+            Error::UnsupportedJdk { .. } => "error.unsupported_jdk",
+            Error::BundlerTimeout(_) => "error.bundler_timeout",
+            Error::BundlerExit { .. } => "error.bundler_exit",
+            Error::InvalidFunctionBundle { .. } => "error.invalid_function_bundle",
+            Error::SmokeTest { .. } => "error.smoke_test",
+            Error::PostBundleHook { .. } => "error.post_bundle_hook",
+            Error::ServiceBinding { .. } => "error.service_binding",
+            Error::Io(_) => "error.io",
+        }
+    }
+
+    /// Values of the placeholders in the catalog entry.
+    fn message_args(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Error::Download { url, .. } => vec![("url", url.clone())],
+            Error::UnsupportedUrl { .. } | Error::UnknownRuntimeVersion { .. } | Error::Io(_) => {
+                vec![("error", self.to_string())]
+            }
+            Error::RuntimeDownload { urls, attempts } => {
+                vec![
+                    ("attempts", attempts.to_string()),
+                    ("urls", urls.join("\n")),
+                ]
+            }
+            Error::Copy { path, .. } => vec![("path", path.display().to_string())],
+            Error::ChecksumMismatch {
+                algorithm,
+                expected,
+                actual,
+            } => vec![
+                ("algorithm", algorithm.to_string()),
+                ("expected", expected.clone()),
+                ("actual", actual.clone()),
+            ],
+            Error::SignatureVerification { reason }
+            | Error::PostBundleHook { reason }
+            | Error::ServiceBinding { reason } => vec![("reason", reason.clone())],
+            Error::JdkNotFound => Vec::new(),
+            Error::UnsupportedJdk { version, min, max } => {
+                let range = match (min, max) {
+                    (Some(min), Some(max)) => format!("Java {} to {}", min, max),
+                    (Some(min), None) => format!("Java {} or newer", min),
+                    (None, Some(max)) => format!("Java {} or older", max),
+                    (None, None) => String::from("any Java version"),
+                };
+                vec![("version", version.clone()), ("range", range)]
+            }
+            Error::BundlerTimeout(timeout) => vec![("seconds", timeout.as_secs().to_string())],
+            Error::BundlerExit { code, .. } => vec![("code", code.to_string())],
+            Error::InvalidFunctionBundle { problems } => vec![(
+                "problems",
+                problems
+                    .iter()
+                    .map(|problem| format!("- {}", problem))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            )],
+            Error::SmokeTest { reason, output } => {
+                vec![("reason", reason.clone()), ("output", output.join("\n"))]
+            }
+        }
+    }
+
+    pub fn header(&self) -> &'static str {
+        messages::get(self.message_key()).header
+    }
+
+    /// Explanation of what went wrong and what the user can do about it.
+    pub fn help(&self) -> String {
+        let args = self.message_args();
+        let args: Vec<(&str, &dyn fmt::Display)> = args
+            .iter()
+            .map(|(name, value)| (*name, value as &dyn fmt::Display))
+            .collect();
+
+        messages::render(self.message_key(), &args)
+    }
+}
+
+/// A broken connection or overloaded server, as opposed to a request that can never succeed.
+fn is_transient(err: &reqwest::Error) -> bool {
+    match err.status() {
+        // Download retries start over after a 416, as the partial file is removed.
+        Some(status) => {
+            status.is_server_error() || status == reqwest::StatusCode::RANGE_NOT_SATISFIABLE
+        }
+        None => err.is_connect() || err.is_timeout() || err.is_request() || err.is_body(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn every_variant() -> Vec<Error> {
+        let reason = || String::from("reason");
+        vec![
+            Error::Download {
+                url: String::from("https://example.com/runtime.jar"),
+                source: reqwest::blocking::get("not a url").unwrap_err(),
+            },
+            Error::UnsupportedUrl {
+                url: String::from("ftp://example.com/runtime.jar"),
+            },
+            Error::RuntimeDownload {
+                urls: vec![String::from("https://example.com/runtime.jar")],
+                attempts: 3,
+            },
+            Error::Copy {
+                path: PathBuf::from("runtime.jar"),
+                source: io::Error::new(io::ErrorKind::NotFound, "not found"),
+            },
+            Error::ChecksumMismatch {
+                algorithm: Algorithm::Sha256,
+                expected: String::from("abc"),
+                actual: String::from("def"),
+            },
+            Error::SignatureVerification { reason: reason() },
+            Error::UnknownRuntimeVersion {
+                version: String::from("2.0.0"),
+                available: vec![String::from("1.0.0")],
+            },
+            Error::JdkNotFound,
+            Error::UnsupportedJdk {
+                version: String::from("21"),
+                min: None,
+                max: Some(17),
+            },
+            Error::BundlerTimeout(Duration::from_secs(300)),
+            Error::BundlerExit {
+                code: 42,
+                stderr_tail: Vec::new(),
+            },
+            Error::InvalidFunctionBundle {
+                problems: vec![reason()],
+            },
+            Error::SmokeTest {
+                reason: reason(),
+                output: vec![String::from("Exception")],
+            },
+            Error::PostBundleHook { reason: reason() },
+            Error::ServiceBinding { reason: reason() },
+            Error::Io(io::Error::new(io::ErrorKind::Other, "disk full")),
+        ]
+    }
+
+    #[test]
+    fn every_variant_has_catalog_entry() {
+        for err in every_variant() {
+            let message = messages::CATALOG
+                .iter()
+                .find(|message| message.key == err.message_key());
+
+            assert!(
+                message.is_some(),
+                "{} has no catalog entry",
+                err.message_key()
+            );
+            assert!(!err.header().is_empty());
+            assert_eq!(
+                messages::placeholders(&err.help()),
+                Vec::<&str>::new(),
+                "{} leaves placeholders unfilled",
+                err.message_key()
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_bundler_exit_code_falls_back_to_generic_message() {
+        let err = Error::BundlerExit {
+            code: 42,
+            stderr_tail: Vec::new(),
+        };
+
+        assert_eq!(err.header(), "Detection failed");
+        assert!(err.help().contains("unexpected error code 42"));
+    }
+
+    #[test]
+    fn unknown_runtime_version_lists_available_versions() {
+        let err = Error::UnknownRuntimeVersion {
+            version: String::from("2.0.0"),
+            available: vec![String::from("1.0.0"), String::from("0.9.0")],
+        };
+
+        assert!(err.help().contains("available versions: 1.0.0, 0.9.0"));
+    }
+
+    #[test]
+    fn unsupported_jdk_describes_supported_range() {
+        let err = Error::UnsupportedJdk {
+            version: String::from("21.0.1"),
+            min: Some(8),
+            max: Some(17),
+        };
+
+        assert!(err.help().contains("uses JDK 21.0.1"));
+        assert!(err.help().contains("supports Java 8 to 17"));
+    }
+
+    #[test]
+    fn signature_verification_explains_reason() {
+        let help = Error::SignatureVerification {
+            reason: String::from("the signature does not match the artifact"),
+        }
+        .help();
+
+        assert!(help.contains("function runtime: the signature does not match the artifact."));
+        assert!(help.contains("[metadata.runtime.signature]"));
+    }
+
+    #[test]
+    fn checksum_mismatch_prints_both_digests() {
+        let help = Error::ChecksumMismatch {
+            algorithm: Algorithm::Sha256,
+            expected: String::from("ABCDEF"),
+            actual: String::from("123456"),
+        }
+        .help();
+
+        assert!(help.contains("Expected sha256: ABCDEF"));
+        assert!(help.contains("Actual sha256:   123456"));
+        assert!(help.contains("SF_FUNCTIONS_ALLOW_UNVERIFIED_RUNTIME=1"));
+    }
+
+    #[test]
+    fn post_bundle_hook_names_the_hook() {
+        let help = Error::PostBundleHook {
+            reason: String::from("exited with exit status: 3"),
+        }
+        .help();
+
+        assert!(help.contains(".sf-functions/post-bundle exited with exit status: 3."));
+    }
+}
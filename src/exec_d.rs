@@ -0,0 +1,216 @@
+use crate::{config::GarbageCollector, launcher};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// Name of the executable in the `exec.d` directory of the runtime layer.
+pub const FILE_NAME: &str = "runtime-env";
+/// Launch environment variable with the port to bind when `PORT` is unset or invalid.
+pub const DEFAULT_PORT_ENV: &str = "SF_FX_DEFAULT_PORT";
+/// Build and launch environment variable with the garbage collector, `serial`, `g1` or `zgc`.
+pub const GC_ENV: &str = "SF_FUNCTIONS_GC";
+
+const DEFAULT_TMPDIR: &str = "/tmp";
+// cgroup v1 reports a very large number when there is no limit.
+const UNLIMITED_MEMORY_BYTES: u64 = 1 << 40;
+// Stacks of the assumed thread count, code cache, and direct memory.
+const THREAD_COUNT: u64 = 50;
+const THREAD_STACK_KB: u64 = 512;
+const MIN_HEAP_MB: u64 = 64;
+
+/// The environment the function process starts with, derived from the container it runs in.
+/// Only variables the process should see changed are returned.
+pub fn derived_env(
+    env: impl Fn(&str) -> Option<String>,
+    memory_limit_bytes: Option<u64>,
+) -> BTreeMap<String, String> {
+    let mut derived = BTreeMap::new();
+
+    let port = env("PORT").filter(|port| port.parse::<u16>().is_ok());
+    if port.is_none() {
+        let default_port = env(DEFAULT_PORT_ENV)
+            .filter(|port| port.parse::<u16>().is_ok())
+            .unwrap_or_else(|| launcher::DEFAULT_PORT.to_string());
+        derived.insert(String::from("PORT"), default_port);
+    }
+
+    let tmpdir = env("TMPDIR").filter(|tmpdir| !tmpdir.is_empty());
+    if tmpdir.is_none() {
+        derived.insert(String::from("TMPDIR"), String::from(DEFAULT_TMPDIR));
+    }
+
+    let java_tool_options = env("JAVA_TOOL_OPTIONS").unwrap_or_default();
+    let mut options: Vec<String> = Vec::new();
+    if !java_tool_options.contains("-Xmx") {
+        options.extend(memory_limit_bytes.map(memory_options).unwrap_or_default());
+    }
+    // The JVM refuses to start with more than one collector selected.
+    if !selects_gc(&java_tool_options) {
+        let gc = env(GC_ENV)
+            .and_then(|gc| gc.trim().parse().ok())
+            .or_else(|| GarbageCollector::for_memory_limit(memory_limit_bytes));
+        options.extend(gc.map(|gc| gc.java_tool_option().to_string()));
+    }
+    if !java_tool_options.contains("-Djava.io.tmpdir=") {
+        options.push(format!(
+            "-Djava.io.tmpdir={}",
+            tmpdir.as_deref().unwrap_or(DEFAULT_TMPDIR)
+        ));
+    }
+    if !options.is_empty() {
+        let value = std::iter::once(java_tool_options.as_str())
+            .filter(|options| !options.is_empty())
+            .chain(options.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+        derived.insert(String::from("JAVA_TOOL_OPTIONS"), value);
+    }
+
+    derived
+}
+
+fn selects_gc(java_tool_options: &str) -> bool {
+    java_tool_options
+        .split_whitespace()
+        .any(|option| option.starts_with("-XX:+Use") && option.ends_with("GC"))
+}
+
+/// The memory limit of the container from cgroup v2 or v1, `None` if there is none.
+pub fn memory_limit_bytes(cgroup_dir: impl AsRef<Path>) -> Option<u64> {
+    let cgroup_dir = cgroup_dir.as_ref();
+    let limit = fs::read_to_string(cgroup_dir.join("memory.max"))
+        .or_else(|_| fs::read_to_string(cgroup_dir.join("memory").join("memory.limit_in_bytes")))
+        .ok()?;
+
+    // cgroup v2 reports "max" when there is no limit.
+    limit
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|limit| *limit < UNLIMITED_MEMORY_BYTES)
+}
+
+/// JVM flags that keep heap, metaspace and thread stacks within the container memory limit.
+pub fn memory_options(limit_bytes: u64) -> Vec<String> {
+    let limit_mb = limit_bytes / 1024 / 1024;
+    let metaspace_mb = if limit_mb <= 512 { 96 } else { 128 };
+    let reserved_mb = THREAD_COUNT * THREAD_STACK_KB / 1024 + 64;
+    let heap_mb = limit_mb
+        .saturating_sub(metaspace_mb + reserved_mb)
+        .max(MIN_HEAP_MB);
+
+    vec![
+        format!("-Xmx{}m", heap_mb),
+        format!("-Xss{}k", THREAD_STACK_KB),
+        format!("-XX:MaxMetaspaceSize={}m", metaspace_mb),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| String::from(*value))
+        }
+    }
+
+    #[test]
+    fn derived_env_fills_in_port_tmpdir_and_memory() {
+        let derived = derived_env(
+            env(&[
+                (DEFAULT_PORT_ENV, "5000"),
+                ("JAVA_TOOL_OPTIONS", "-Dfoo=bar"),
+            ]),
+            Some(1024 * 1024 * 1024),
+        );
+
+        assert_eq!(derived["PORT"], "5000");
+        assert_eq!(derived["TMPDIR"], "/tmp");
+        assert_eq!(
+            derived["JAVA_TOOL_OPTIONS"],
+            "-Dfoo=bar -Xmx807m -Xss512k -XX:MaxMetaspaceSize=128m -XX:+UseSerialGC \
+             -Djava.io.tmpdir=/tmp"
+        );
+    }
+
+    #[test]
+    fn derived_env_keeps_user_configuration() {
+        let derived = derived_env(
+            env(&[
+                ("PORT", "9000"),
+                ("TMPDIR", "/workspace/tmp"),
+                ("JAVA_TOOL_OPTIONS", "-Xmx256m"),
+            ]),
+            Some(1024 * 1024 * 1024),
+        );
+
+        assert!(!derived.contains_key("PORT"));
+        assert!(!derived.contains_key("TMPDIR"));
+        assert_eq!(
+            derived["JAVA_TOOL_OPTIONS"],
+            "-Xmx256m -XX:+UseSerialGC -Djava.io.tmpdir=/workspace/tmp"
+        );
+    }
+
+    #[test]
+    fn derived_env_selects_gc_once() {
+        let gc_option = |vars: &[(&str, &str)], limit_mb: u64| {
+            derived_env(env(vars), Some(limit_mb * 1024 * 1024))["JAVA_TOOL_OPTIONS"]
+                .split(' ')
+                .filter(|option| option.ends_with("GC"))
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(gc_option(&[], 512), vec!["-XX:+UseSerialGC"]);
+        assert_eq!(gc_option(&[], 4096), vec!["-XX:+UseG1GC"]);
+        assert_eq!(gc_option(&[(GC_ENV, "zgc")], 512), vec!["-XX:+UseZGC"]);
+        assert_eq!(
+            gc_option(&[("JAVA_TOOL_OPTIONS", "-XX:+UseParallelGC")], 512),
+            vec!["-XX:+UseParallelGC"]
+        );
+    }
+
+    #[test]
+    fn derived_env_replaces_invalid_port() {
+        let derived = derived_env(env(&[("PORT", "")]), None);
+
+        assert_eq!(derived["PORT"], "8080");
+        assert_eq!(derived["JAVA_TOOL_OPTIONS"], "-Djava.io.tmpdir=/tmp");
+    }
+
+    #[test]
+    fn memory_limit_bytes_reads_cgroup_v2_and_v1() -> anyhow::Result<()> {
+        let cgroup_dir = tempfile::tempdir()?;
+        assert_eq!(memory_limit_bytes(cgroup_dir.path()), None);
+
+        fs::create_dir_all(cgroup_dir.path().join("memory"))?;
+        fs::write(
+            cgroup_dir
+                .path()
+                .join("memory")
+                .join("memory.limit_in_bytes"),
+            "9223372036854771712\n",
+        )?;
+        assert_eq!(memory_limit_bytes(cgroup_dir.path()), None);
+
+        fs::write(cgroup_dir.path().join("memory.max"), "max\n")?;
+        assert_eq!(memory_limit_bytes(cgroup_dir.path()), None);
+
+        fs::write(cgroup_dir.path().join("memory.max"), "536870912\n")?;
+        assert_eq!(memory_limit_bytes(cgroup_dir.path()), Some(536870912));
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_options_keeps_minimum_heap() {
+        assert_eq!(
+            memory_options(512 * 1024 * 1024),
+            vec!["-Xmx327m", "-Xss512k", "-XX:MaxMetaspaceSize=96m"]
+        );
+        assert_eq!(memory_options(128 * 1024 * 1024)[0], "-Xmx64m");
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::Error;
+use std::io;
+
+/// Any failure without a more specific code.
+pub const FAILURE: i32 = 1;
+/// The function runtime could not be downloaded from any location.
+pub const DOWNLOAD: i32 = 10;
+/// The function runtime doesn't match the expected checksum.
+pub const CHECKSUM: i32 = 11;
+/// The signature of the function runtime doesn't verify.
+pub const SIGNATURE: i32 = 12;
+/// The pinned function runtime version is unknown.
+pub const RUNTIME_VERSION: i32 = 13;
+/// No JDK was found, or its version is not supported by the runtime.
+pub const JDK: i32 = 20;
+/// Function detection failed or timed out, or wrote an invalid bundle.
+pub const BUNDLER: i32 = 30;
+/// The function failed to load in the verification run.
+pub const VERIFICATION: i32 = 31;
+/// The post-bundle hook of the app failed.
+pub const POST_BUNDLE_HOOK: i32 = 32;
+/// Reading or writing files failed.
+pub const IO: i32 = 40;
+/// A service binding `project.toml` requires is incomplete.
+pub const BINDING: i32 = 50;
+
+/// Exit code of the build binary for `err`, so platform automation can tell failure causes
+/// apart without parsing the build output. Decided by the first typed error in the chain.
+pub fn for_error(err: &anyhow::Error) -> i32 {
+    for cause in err.chain() {
+        if let Some(err) = cause.downcast_ref::<Error>() {
+            return match err {
+                Error::Download { .. }
+                | Error::UnsupportedUrl { .. }
+                | Error::RuntimeDownload { .. } => DOWNLOAD,
+                Error::ChecksumMismatch { .. } => CHECKSUM,
+                Error::SignatureVerification { .. } => SIGNATURE,
+                Error::UnknownRuntimeVersion { .. } => RUNTIME_VERSION,
+                Error::JdkNotFound | Error::UnsupportedJdk { .. } => JDK,
+                Error::BundlerTimeout(_)
+                | Error::BundlerExit { .. }
+                | Error::InvalidFunctionBundle { .. } => BUNDLER,
+                Error::SmokeTest { .. } => VERIFICATION,
+                Error::PostBundleHook { .. } => POST_BUNDLE_HOOK,
+                Error::ServiceBinding { .. } => BINDING,
+                Error::Copy { .. } | Error::Io(_) => IO,
+            };
+        }
+        if cause.is::<io::Error>() {
+            return IO;
+        }
+    }
+
+    FAILURE
+}
+
+/// Short name of `code` that doesn't reveal anything about the app, e.g. for telemetry.
+pub fn category(code: i32) -> &'static str {
+    match code {
+        DOWNLOAD => "download",
+        CHECKSUM => "checksum",
+        SIGNATURE => "signature",
+        RUNTIME_VERSION => "runtime_version",
+        JDK => "jdk",
+        BUNDLER => "bundler",
+        VERIFICATION => "verification",
+        POST_BUNDLE_HOOK => "post_bundle_hook",
+        IO => "io",
+        BINDING => "binding",
+        _ => "failure",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::checksum::Algorithm;
+    use std::time::Duration;
+
+    #[test]
+    fn for_error_finds_typed_error_in_chain() {
+        let err = anyhow::Error::from(Error::BundlerTimeout(Duration::from_secs(1)))
+            .context("Function detection failed");
+        assert_eq!(for_error(&err), BUNDLER);
+
+        let err = anyhow::Error::from(Error::ChecksumMismatch {
+            algorithm: Algorithm::Sha256,
+            expected: String::from("abc"),
+            actual: String::from("def"),
+        });
+        assert_eq!(for_error(&err), CHECKSUM);
+
+        let err = anyhow::Error::from(io::Error::new(io::ErrorKind::Other, "disk full"));
+        assert_eq!(for_error(&err), IO);
+
+        assert_eq!(for_error(&anyhow::anyhow!("Invalid project.toml")), FAILURE);
+    }
+
+    #[test]
+    fn category_names_exit_codes() {
+        assert_eq!(category(BUNDLER), "bundler");
+        assert_eq!(category(FAILURE), "failure");
+        assert_eq!(category(99), "failure");
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{
+    error::{Cause, Error},
+    exit_code,
+    util::{self, logger::Logger},
+};
+use std::io;
+
+/// Prefix of support codes, short for JVM function invoker.
+const SUPPORT_CODE_PREFIX: &str = "JFI";
+
+/// The final "what happened" section of a failed build.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FailureSummary {
+    /// The build phase that failed, `None` before the first one started.
+    pub phase: Option<String>,
+    pub cause: Cause,
+    pub header: String,
+    pub exit_code: i32,
+    /// Identifies the kind of failure without revealing anything about the app, so users can
+    /// give it to support.
+    pub support_code: String,
+}
+
+impl FailureSummary {
+    /// Summarizes `err` by its first typed error. Other errors come from validating the project,
+    /// except for I/O errors.
+    pub fn new(err: &anyhow::Error, phase: Option<&str>) -> Self {
+        let typed = err.chain().find_map(|cause| cause.downcast_ref::<Error>());
+        let (cause, header) = match typed {
+            Some(typed) => (typed.cause(), String::from(typed.header())),
+            None if err.chain().any(|cause| cause.is::<io::Error>()) => {
+                (Cause::Internal, String::from("Unexpected I/O error"))
+            }
+            None => (Cause::Project, err.to_string()),
+        };
+        let exit_code = exit_code::for_error(err);
+        let category = exit_code::category(exit_code);
+        let support_code = format!(
+            "{}-{}-{}",
+            SUPPORT_CODE_PREFIX,
+            exit_code,
+            util::sha256(format!("{}/{}", phase.unwrap_or(""), category).as_bytes())[..6]
+                .to_uppercase()
+        );
+
+        FailureSummary {
+            phase: phase.map(String::from),
+            cause,
+            header,
+            exit_code,
+            support_code,
+        }
+    }
+
+    pub fn log(&self, logger: &impl Logger) -> anyhow::Result<()> {
+        logger.header("What happened")?;
+        logger.info(format!(
+            "Failed phase:  {}",
+            self.phase.as_deref().unwrap_or("Setup")
+        ))?;
+        logger.info(format!("Error:         {}", self.header))?;
+        logger.info(format!(
+            "Likely cause:  {}, {}",
+            self.cause,
+            self.cause.advice()
+        ))?;
+        logger.info(format!("Support code:  {}", self.support_code))?;
+
+        logger.info("Please include the support code when you contact support.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn new_summarizes_typed_errors() {
+        let err = anyhow::Error::from(Error::BundlerTimeout(Duration::from_secs(300)))
+            .context("Could not contribute function-bundle layer");
+
+        let summary = FailureSummary::new(&err, Some("Function detection"));
+
+        assert_eq!(summary.phase.as_deref(), Some("Function detection"));
+        assert_eq!(summary.cause, Cause::Project);
+        assert_eq!(summary.header, "Function detection timed out");
+        assert_eq!(summary.exit_code, exit_code::BUNDLER);
+        assert!(summary.support_code.starts_with("JFI-30-"));
+        assert_eq!(
+            summary.support_code,
+            FailureSummary::new(&err, Some("Function detection")).support_code
+        );
+        assert_ne!(
+            summary.support_code,
+            FailureSummary::new(&err, Some("Extensions")).support_code
+        );
+    }
+
+    #[test]
+    fn new_falls_back_for_untyped_errors() {
+        let summary = FailureSummary::new(&anyhow::anyhow!("Invalid project.toml"), None);
+        assert_eq!(summary.cause, Cause::Project);
+        assert_eq!(summary.header, "Invalid project.toml");
+
+        let err = anyhow::Error::from(io::Error::new(io::ErrorKind::Other, "disk full"));
+        let summary = FailureSummary::new(&err, Some("SBOM"));
+        assert_eq!(summary.cause, Cause::Internal);
+        assert_eq!(summary.exit_code, exit_code::IO);
+    }
+}
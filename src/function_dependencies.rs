@@ -0,0 +1,216 @@
+use crate::util::{
+    self,
+    checksum::{Algorithm, Checksum},
+};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A third-party jar of the function bundle, stored in the dependencies layer and linked from
+/// the bundle, so it only changes in the image when the dependencies do.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyJar {
+    /// Path relative to the function bundle layer.
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+impl DependencyJar {
+    /// `<sha256>/<file name>` in the dependencies layer. Keying by content keeps jars of the same
+    /// name from different functions apart.
+    pub fn stored_path(&self, dependencies_dir: &Path) -> PathBuf {
+        let file_name = self.path.file_name().unwrap_or_default();
+        dependencies_dir.join(&self.sha256).join(file_name)
+    }
+}
+
+/// Every jar below `bundle_layer_dir` that wasn't built from the app, by sha256 in `app_jars`.
+/// Jars already linked into `dependencies_dir` by a previous build are included.
+pub fn dependency_jars(
+    bundle_layer_dir: &Path,
+    dependencies_dir: &Path,
+    app_jars: &HashSet<String>,
+) -> io::Result<Vec<DependencyJar>> {
+    let mut jars = Vec::new();
+    let mut dirs = vec![bundle_layer_dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if path
+                .extension()
+                .map_or(true, |extension| extension != "jar")
+            {
+                continue;
+            }
+
+            let sha256 = if file_type.is_symlink() {
+                let target = fs::read_link(&path)?;
+                match target
+                    .strip_prefix(dependencies_dir)
+                    .ok()
+                    .and_then(|stored| stored.iter().next())
+                {
+                    Some(sha256) => sha256.to_string_lossy().into_owned(),
+                    // Links the bundler or a hook created are left alone.
+                    None => continue,
+                }
+            } else {
+                let sha256 = Checksum::of_file(Algorithm::Sha256, &path)?.hex;
+                if app_jars.contains(&sha256) {
+                    continue;
+                }
+                sha256
+            };
+
+            jars.push(DependencyJar {
+                path: path
+                    .strip_prefix(bundle_layer_dir)
+                    .unwrap_or(&path)
+                    .to_path_buf(),
+                sha256,
+            });
+        }
+    }
+    jars.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(jars)
+}
+
+/// Identifies the set of dependency jars, regardless of where the bundle references them from.
+pub fn digest(jars: &[DependencyJar]) -> String {
+    let mut stored: Vec<String> = jars
+        .iter()
+        .map(|jar| jar.stored_path(Path::new("")).display().to_string())
+        .collect();
+    stored.sort();
+    stored.dedup();
+
+    util::sha256(stored.join("\n").as_bytes())
+}
+
+/// Moves the jars that aren't in `dependencies_dir` yet out of the bundle.
+pub fn store(
+    bundle_layer_dir: &Path,
+    dependencies_dir: &Path,
+    jars: &[DependencyJar],
+) -> io::Result<()> {
+    for jar in jars {
+        let src = bundle_layer_dir.join(&jar.path);
+        let dst = jar.stored_path(dependencies_dir);
+        if dst.exists() || fs::symlink_metadata(&src)?.file_type().is_symlink() {
+            continue;
+        }
+
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&src, &dst)?;
+    }
+
+    Ok(())
+}
+
+/// Replaces every jar in the bundle with a symlink to its copy in `dependencies_dir`.
+pub fn link(
+    bundle_layer_dir: &Path,
+    dependencies_dir: &Path,
+    jars: &[DependencyJar],
+) -> io::Result<()> {
+    for jar in jars {
+        let path = bundle_layer_dir.join(&jar.path);
+        let target = jar.stored_path(dependencies_dir);
+        if fs::read_link(&path).ok().as_ref() == Some(&target) {
+            continue;
+        }
+
+        match fs::remove_file(&path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        symlink(&target, &path)?;
+    }
+
+    Ok(())
+}
+
+/// Whether every symlink below `dir` resolves. Links into a dependencies layer that is gone
+/// leave a bundle that can't be reused.
+pub fn links_resolve(dir: &Path) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return false,
+    };
+
+    entries.flatten().all(|entry| match entry.file_type() {
+        Ok(file_type) if file_type.is_dir() => links_resolve(&entry.path()),
+        Ok(file_type) if file_type.is_symlink() => entry.path().exists(),
+        Ok(_) => true,
+        Err(_) => false,
+    })
+}
+
+#[cfg(target_family = "unix")]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+// Without symlinks, the bundle keeps a full copy.
+#[cfg(not(target_family = "unix"))]
+fn symlink(target: &Path, path: &Path) -> io::Result<()> {
+    fs::copy(target, path).map(|_| ())
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_and_link_move_dependencies_out_of_bundle() -> anyhow::Result<()> {
+        let bundle_dir = tempfile::tempdir()?;
+        let dependencies_dir = tempfile::tempdir()?;
+        let classpath_dir = bundle_dir.path().join("classpath");
+        fs::create_dir(&classpath_dir)?;
+        fs::write(classpath_dir.join("function.jar"), "function")?;
+        fs::write(classpath_dir.join("dependency-1.0.jar"), "dependency")?;
+        fs::write(bundle_dir.path().join("function-bundle.toml"), "")?;
+        let app_jars = vec![util::sha256(b"function")].into_iter().collect();
+
+        let jars = dependency_jars(bundle_dir.path(), dependencies_dir.path(), &app_jars)?;
+        assert_eq!(
+            jars,
+            vec![DependencyJar {
+                path: PathBuf::from("classpath/dependency-1.0.jar"),
+                sha256: util::sha256(b"dependency"),
+            }]
+        );
+
+        store(bundle_dir.path(), dependencies_dir.path(), &jars)?;
+        link(bundle_dir.path(), dependencies_dir.path(), &jars)?;
+        let linked = classpath_dir.join("dependency-1.0.jar");
+        assert!(fs::symlink_metadata(&linked)?.file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&linked)?, "dependency");
+        assert!(!fs::symlink_metadata(classpath_dir.join("function.jar"))?
+            .file_type()
+            .is_symlink());
+
+        // A rebuilt bundle with the same dependencies maps to the same stored jars.
+        let relinked = dependency_jars(bundle_dir.path(), dependencies_dir.path(), &app_jars)?;
+        assert_eq!(relinked, jars);
+        assert_eq!(digest(&relinked), digest(&jars));
+
+        assert!(links_resolve(bundle_dir.path()));
+        fs::remove_dir_all(dependencies_dir.path())?;
+        assert!(!links_resolve(bundle_dir.path()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+use crate::data::function_bundle;
+use libcnb::data::launch::Label;
+use std::path::Path;
+
+/// Namespace of every label this buildpack writes.
+pub const PREFIX: &str = "com.salesforce.function";
+
+/// `com.salesforce.function.<name>`, or `com.salesforce.function.<process type>.<name>` for the
+/// function served by a process in multiple functions mode.
+pub fn function_key(process_type: Option<&str>, name: &str) -> String {
+    match process_type {
+        Some(process_type) => format!("{}.{}.{}", PREFIX, process_type, name),
+        None => format!("{}.{}", PREFIX, name),
+    }
+}
+
+/// `com.salesforce.function.runtime.<name>`.
+pub fn runtime_key(name: &str) -> String {
+    format!("{}.runtime.{}", PREFIX, name)
+}
+
+/// `com.salesforce.function.bundle.<name>`.
+pub fn bundle_key(name: &str) -> String {
+    format!("{}.bundle.{}", PREFIX, name)
+}
+
+/// OCI image labels describing the functions, the runtime and the bundle of the image, so
+/// registries and scanners can index function images without running them.
+pub fn image_labels(
+    function_bundle_layer: &Path,
+    multiple_functions: bool,
+    runtime_version: Option<&str>,
+    bundle_digest: &str,
+) -> anyhow::Result<Vec<Label>> {
+    let bundles = if multiple_functions {
+        function_bundle::bundle_dirs(function_bundle_layer)?
+            .into_iter()
+            .map(|bundle_dir| (Some(function_bundle::process_type(&bundle_dir)), bundle_dir))
+            .collect()
+    } else {
+        vec![(None, function_bundle_layer.to_path_buf())]
+    };
+
+    let mut labels = Vec::new();
+    for (process_type, bundle_dir) in bundles {
+        let function = function_bundle::Toml::from_bundle_dir(bundle_dir)?.function;
+        let key = |name| function_key(process_type.as_deref(), name);
+        labels.push(label(key("class"), function.class));
+        labels.push(label(key("payload-class"), function.payload_class));
+        labels.push(label(key("return-class"), function.return_class));
+    }
+    if let Some(version) = runtime_version {
+        labels.push(label(runtime_key("version"), version));
+    }
+    labels.push(label(bundle_key("sha256"), bundle_digest));
+
+    Ok(labels)
+}
+
+fn label(key: String, value: impl Into<String>) -> Label {
+    Label {
+        key,
+        value: value.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn write_bundle(bundle_dir: &Path, name: &str) -> anyhow::Result<()> {
+        fs::create_dir_all(bundle_dir)?;
+        fs::write(
+            bundle_dir.join(function_bundle::FILE_NAME),
+            format!(
+                r#"
+[function]
+class = "com.example.{name}.Function"
+payload_class = "com.example.{name}.Payload"
+payload_media_type = "application/json"
+return_class = "java.lang.String"
+return_media_type = "application/json"
+"#,
+                name = name
+            ),
+        )?;
+
+        Ok(())
+    }
+
+    fn key_values(labels: Vec<Label>) -> Vec<(String, String)> {
+        labels
+            .into_iter()
+            .map(|label| (label.key, label.value))
+            .collect()
+    }
+
+    #[test]
+    fn image_labels_describe_single_function() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+        write_bundle(layer_dir.path(), "orders")?;
+
+        let labels = key_values(image_labels(
+            layer_dir.path(),
+            false,
+            Some("1.0.0"),
+            "abc123",
+        )?);
+
+        assert_eq!(
+            labels,
+            vec![
+                (
+                    String::from("com.salesforce.function.class"),
+                    String::from("com.example.orders.Function")
+                ),
+                (
+                    String::from("com.salesforce.function.payload-class"),
+                    String::from("com.example.orders.Payload")
+                ),
+                (
+                    String::from("com.salesforce.function.return-class"),
+                    String::from("java.lang.String")
+                ),
+                (
+                    String::from("com.salesforce.function.runtime.version"),
+                    String::from("1.0.0")
+                ),
+                (
+                    String::from("com.salesforce.function.bundle.sha256"),
+                    String::from("abc123")
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_labels_are_keyed_by_process_type() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+        for name in &["invoices", "orders"] {
+            write_bundle(&layer_dir.path().join(name), name)?;
+        }
+
+        let labels = key_values(image_labels(layer_dir.path(), true, None, "abc123")?);
+
+        assert_eq!(
+            labels[1],
+            (
+                String::from("com.salesforce.function.invoices.payload-class"),
+                String::from("com.example.invoices.Payload")
+            )
+        );
+        assert_eq!(
+            labels[5],
+            (
+                String::from("com.salesforce.function.orders.return-class"),
+                String::from("java.lang.String")
+            )
+        );
+        assert_eq!(labels.len(), 7);
+
+        Ok(())
+    }
+}
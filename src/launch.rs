@@ -0,0 +1,340 @@
+use crate::{
+    data::{function_bundle, project_toml},
+    launcher::{
+        LaunchOptions, DEBUG_FLAG, DEBUG_PROCESS_TYPE, HEALTH_PROCESS_TYPE, TEST_FLAG,
+        TEST_PROCESS_TYPE,
+    },
+    platform::Family,
+};
+use libcnb::data::launch::{Launch, Process};
+use std::path::Path;
+
+pub struct LaunchPaths<'a> {
+    pub launcher: &'a Path,
+    pub runtime_jar: &'a Path,
+    pub function_bundle_layer: &'a Path,
+}
+
+/// One process per function in multiple functions mode, otherwise `web`, `debug`, `test` and the
+/// processes from `project.toml`, which replace built-in ones of the same type. Every image gets a
+/// `health` process. The serve processes pass
+/// `launch_options` before any process specific args. On stacks without a POSIX shell, processes
+/// run the launcher directly with all arguments in `args`.
+pub fn launch(
+    paths: &LaunchPaths,
+    family: Family,
+    multiple_functions: bool,
+    project_processes: &[project_toml::Process],
+    launch_options: &LaunchOptions,
+) -> anyhow::Result<Launch> {
+    let mut launch = Launch::new();
+    let serve_args = launch_options.args();
+    let mut process_types = Vec::new();
+    let launch_args = |bundle_dir: &Path| {
+        vec![
+            paths.runtime_jar.display().to_string(),
+            bundle_dir.display().to_string(),
+        ]
+    };
+    let process = |r#type: &str, launcher_args: Vec<String>, args: &[String]| {
+        if family.direct_processes() {
+            let args: Vec<String> = launcher_args.iter().chain(args).cloned().collect();
+            Process::new(r#type, paths.launcher.display().to_string(), &args, true)
+        } else {
+            let command = std::iter::once(paths.launcher.display().to_string())
+                .chain(launcher_args)
+                .collect::<Vec<_>>()
+                .join(" ");
+            Process::new(r#type, command, args, false)
+        }
+    };
+
+    if multiple_functions {
+        for bundle_dir in function_bundle::bundle_dirs(paths.function_bundle_layer)? {
+            let process_type = function_bundle::process_type(&bundle_dir);
+            process_types.push(process_type.clone());
+            launch.processes.push(process(
+                &process_type,
+                launch_args(&bundle_dir),
+                &serve_args,
+            )?);
+        }
+    } else {
+        let web_args = launch_args(paths.function_bundle_layer);
+        if !project_processes.iter().any(|p| p.r#type == "web") {
+            launch
+                .processes
+                .push(process("web", web_args.clone(), &serve_args)?);
+        }
+
+        for project_process in project_processes {
+            process_types.push(project_process.r#type.clone());
+            let args: Vec<String> = serve_args
+                .iter()
+                .chain(&project_process.args)
+                .cloned()
+                .collect();
+            launch
+                .processes
+                .push(process(&project_process.r#type, web_args.clone(), &args)?);
+        }
+
+        // Only in single function mode, where there is exactly one bundle to attach to.
+        if !process_types.iter().any(|t| t == DEBUG_PROCESS_TYPE) {
+            let debug_args = std::iter::once(DEBUG_FLAG.to_string())
+                .chain(web_args.clone())
+                .collect();
+            launch
+                .processes
+                .push(process(DEBUG_PROCESS_TYPE, debug_args, &serve_args)?);
+        }
+        // Invokes the function once, for smoke tests with `docker run --entrypoint test`.
+        if !process_types.iter().any(|t| t == TEST_PROCESS_TYPE) {
+            let test_args = std::iter::once(TEST_FLAG.to_string())
+                .chain(web_args)
+                .collect();
+            launch
+                .processes
+                .push(process(TEST_PROCESS_TYPE, test_args, &[])?);
+        }
+    }
+
+    // A user defined process of the same name takes precedence. The configured port is passed
+    // on, so the check probes the port the runtime listens on when `PORT` is unset.
+    if !process_types.iter().any(|t| t == HEALTH_PROCESS_TYPE) {
+        let health_args = LaunchOptions {
+            port: launch_options.port,
+            ..LaunchOptions::default()
+        }
+        .args();
+        launch.processes.push(process(
+            HEALTH_PROCESS_TYPE,
+            vec![HEALTH_PROCESS_TYPE.to_string()],
+            &health_args,
+        )?);
+    }
+
+    Ok(launch)
+}
+
+/// `(type, command line)` of every process, read back from the serialized launch.toml.
+pub fn process_commands(launch: &Launch) -> anyhow::Result<Vec<(String, String)>> {
+    let launch_toml: toml::Value = toml::from_str(&toml::to_string(launch)?)?;
+
+    Ok(launch_toml
+        .get("processes")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|process| {
+            let str_field = |key: &str| process.get(key).and_then(toml::Value::as_str);
+            let args = process
+                .get("args")
+                .and_then(toml::Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(toml::Value::as_str);
+            let command_line = str_field("command")
+                .into_iter()
+                .chain(args)
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            (
+                str_field("type").unwrap_or_default().to_string(),
+                command_line,
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn paths() -> LaunchPaths<'static> {
+        LaunchPaths {
+            launcher: Path::new("/layers/opt/launch"),
+            runtime_jar: Path::new("/layers/sf-fx-runtime-java/runtime.jar"),
+            function_bundle_layer: Path::new("/layers/function-bundle"),
+        }
+    }
+
+    #[test]
+    fn launch_serves_single_function_as_web_process() -> anyhow::Result<()> {
+        let processes = process_commands(&launch(
+            &paths(),
+            Family::Unix,
+            false,
+            &[],
+            &LaunchOptions::default(),
+        )?)?;
+
+        assert_eq!(
+            processes,
+            vec![
+                (
+                    String::from("web"),
+                    String::from("/layers/opt/launch /layers/sf-fx-runtime-java/runtime.jar /layers/function-bundle")
+                ),
+                (
+                    String::from("debug"),
+                    String::from("/layers/opt/launch --debug /layers/sf-fx-runtime-java/runtime.jar /layers/function-bundle")
+                ),
+                (
+                    String::from("test"),
+                    String::from("/layers/opt/launch --test /layers/sf-fx-runtime-java/runtime.jar /layers/function-bundle")
+                ),
+                (
+                    String::from("health"),
+                    String::from("/layers/opt/launch health")
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn launch_lets_project_processes_replace_builtin_ones() -> anyhow::Result<()> {
+        let project_processes = vec![
+            project_toml::Process {
+                r#type: String::from("web"),
+                args: vec![String::from("--workers"), String::from("4")],
+            },
+            project_toml::Process {
+                r#type: String::from("health"),
+                args: vec![String::from("--workers"), String::from("1")],
+            },
+        ];
+        let commands = process_commands(&launch(
+            &paths(),
+            Family::Unix,
+            false,
+            &project_processes,
+            &LaunchOptions::default(),
+        )?)?;
+        let types: Vec<&str> = commands
+            .iter()
+            .map(|(process_type, _)| process_type.as_str())
+            .collect();
+
+        assert_eq!(types, vec!["web", "health", "debug", "test"]);
+        assert!(commands[0]
+            .1
+            .ends_with("/layers/function-bundle --workers 4"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn process_commands_include_args() -> anyhow::Result<()> {
+        let project_processes = vec![project_toml::Process {
+            r#type: String::from("worker"),
+            args: vec![String::from("--workers"), String::from("1")],
+        }];
+        let commands = process_commands(&launch(
+            &paths(),
+            Family::Unix,
+            false,
+            &project_processes,
+            &LaunchOptions::default(),
+        )?)?;
+
+        assert_eq!(
+            commands[1],
+            (
+                String::from("worker"),
+                String::from("/layers/opt/launch /layers/sf-fx-runtime-java/runtime.jar /layers/function-bundle --workers 1")
+            )
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn process_commands_render_launch_options_first() -> anyhow::Result<()> {
+        let project_processes = vec![project_toml::Process {
+            r#type: String::from("worker"),
+            args: vec![String::from("--verbose")],
+        }];
+        let launch_options = LaunchOptions {
+            port: Some(9000),
+            workers: Some(2),
+            ..LaunchOptions::default()
+        };
+        let commands = process_commands(&launch(
+            &paths(),
+            Family::Unix,
+            false,
+            &project_processes,
+            &launch_options,
+        )?)?;
+
+        assert!(commands[0]
+            .1
+            .ends_with("/layers/function-bundle -p 9000 --workers 2"));
+        assert!(commands[1]
+            .1
+            .ends_with("/layers/function-bundle -p 9000 --workers 2 --verbose"));
+        assert_eq!(
+            commands.last().map(|(_, command)| command.as_str()),
+            Some("/layers/opt/launch health -p 9000")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn launch_has_one_process_per_function_in_multiple_mode() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+        for name in &["com.example.A", "com.example.B"] {
+            fs::create_dir(layer_dir.path().join(name))?;
+            fs::write(
+                layer_dir.path().join(name).join(function_bundle::FILE_NAME),
+                "",
+            )?;
+        }
+        let paths = LaunchPaths {
+            function_bundle_layer: layer_dir.path(),
+            ..paths()
+        };
+        let types: Vec<String> = process_commands(&launch(
+            &paths,
+            Family::Unix,
+            true,
+            &[],
+            &LaunchOptions::default(),
+        )?)?
+        .into_iter()
+        .map(|(process_type, _)| process_type)
+        .collect();
+
+        assert_eq!(types, vec!["com-example-A", "com-example-B", "health"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn launch_starts_launcher_directly_on_windows() -> anyhow::Result<()> {
+        let launch = launch(
+            &paths(),
+            Family::Windows,
+            false,
+            &[],
+            &LaunchOptions::default(),
+        )?;
+        let launch_toml: toml::Value = toml::from_str(&toml::to_string(&launch)?)?;
+        let web = &launch_toml["processes"][0];
+
+        assert_eq!(web["command"].as_str(), Some("/layers/opt/launch"));
+        assert_eq!(web["direct"].as_bool(), Some(true));
+        assert_eq!(
+            process_commands(&launch)?[0].1,
+            "/layers/opt/launch /layers/sf-fx-runtime-java/runtime.jar /layers/function-bundle"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,715 @@
+use crate::{platform::Family, util};
+use serde::Deserialize;
+use std::{
+    ffi::OsString,
+    fmt::{self, Display, Formatter},
+    io::{Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+pub const DEFAULT_PORT: u16 = 8080;
+pub const DEFAULT_DEBUG_PORT: u16 = 5005;
+pub const DEBUG_FLAG: &str = "--debug";
+pub const DEBUG_PROCESS_TYPE: &str = "debug";
+pub const DEFAULT_HEALTH_PATH: &str = "/health";
+pub const HEALTH_PROCESS_TYPE: &str = "health";
+pub const TEST_FLAG: &str = "--test";
+pub const TEST_PROCESS_TYPE: &str = "test";
+/// Launch environment variable with the path of the payload the `test` process invokes the
+/// function with. Without it, the runtime's sample payload is used.
+pub const TEST_PAYLOAD_ENV: &str = "SF_FUNCTIONS_TEST_PAYLOAD";
+/// Launch environment variable with the version of the installed function runtime.
+pub const RUNTIME_VERSION_ENV: &str = "SF_FX_RUNTIME_VERSION";
+/// Launch environment variable with the path of the installed function runtime jar.
+pub const RUNTIME_JAR_ENV: &str = "SF_FX_RUNTIME_JAR";
+/// Launch environment variable with the directory of the function bundle, or of the bundles
+/// when the app contains multiple functions.
+pub const FUNCTION_BUNDLE_DIR_ENV: &str = "SF_FUNCTION_BUNDLE_DIR";
+/// Launch environment variable with the extension jars, joined like a classpath.
+pub const EXTENSIONS_CLASSPATH_ENV: &str = "SF_FUNCTION_EXTENSIONS_CLASSPATH";
+/// Launch environment variable with the main class of the runtime jar, which the runtime is
+/// started with when extension jars are appended to its classpath.
+pub const RUNTIME_MAIN_CLASS_ENV: &str = "SF_FX_RUNTIME_MAIN_CLASS";
+/// Build and launch environment variable with the protocol the runtime serves functions with.
+/// Exported at launch so the platform can route invocations accordingly.
+pub const PROTOCOL_ENV: &str = "SF_FUNCTIONS_PROTOCOL";
+/// Launch environment variable with the class of the payload the function accepts.
+pub const PAYLOAD_CLASS_ENV: &str = "SF_FUNCTION_PAYLOAD_CLASS";
+/// Launch environment variable with the class of the value the function returns.
+pub const RETURN_CLASS_ENV: &str = "SF_FUNCTION_RETURN_CLASS";
+/// Launch environment variable with the path of the native executable of the function, set when
+/// the build compiled one with GraalVM `native-image`.
+pub const NATIVE_BINARY_ENV: &str = "SF_FUNCTION_NATIVE_BINARY";
+
+/// Protocol the runtime serves functions with.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServeMode {
+    Http,
+    Grpc,
+}
+
+impl ServeMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            ServeMode::Http => "http",
+            ServeMode::Grpc => "grpc",
+        }
+    }
+
+    /// Whether a runtime with this `serve --help` output can serve the mode. Runtimes without a
+    /// `--mode` option only serve HTTP.
+    pub fn supported_by_help(self, help: &str) -> bool {
+        match self {
+            ServeMode::Http => true,
+            ServeMode::Grpc => help.contains("--mode") && help.to_lowercase().contains("grpc"),
+        }
+    }
+}
+
+impl Display for ServeMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ServeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "http" => Ok(ServeMode::Http),
+            "grpc" => Ok(ServeMode::Grpc),
+            _ => Err(format!(
+                "unsupported protocol {:?}, expected http or grpc",
+                s
+            )),
+        }
+    }
+}
+
+/// Options of the runtime's `serve` command, `[com.salesforce.functions.serve]` in
+/// `project.toml`. Unset options are left to the runtime, except host and port, which default to
+/// all interfaces and 8080. `PORT` takes precedence over a configured port at launch.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LaunchOptions {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub workers: Option<u32>,
+    pub mode: Option<ServeMode>,
+}
+
+impl LaunchOptions {
+    /// The set options as `serve` command line flags.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(host) = &self.host {
+            args.extend(vec![String::from("-h"), host.clone()]);
+        }
+        if let Some(port) = self.port {
+            args.extend(vec![String::from("-p"), port.to_string()]);
+        }
+        if let Some(workers) = self.workers {
+            args.extend(vec![String::from("--workers"), workers.to_string()]);
+        }
+        if let Some(mode) = self.mode {
+            args.extend(vec![String::from("--mode"), String::from(mode.as_str())]);
+        }
+
+        args
+    }
+
+    /// Reads the flags rendered by [`LaunchOptions::args`] back from a command line and returns
+    /// the options together with all other arguments.
+    pub fn parse(args: &[OsString]) -> anyhow::Result<(Self, Vec<OsString>)> {
+        let mut options = LaunchOptions::default();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let flag = arg.to_str().unwrap_or_default();
+            if !["-h", "--host", "-p", "--port", "--workers", "--mode"].contains(&flag) {
+                rest.push(arg.clone());
+                continue;
+            }
+
+            let value = args
+                .next()
+                .and_then(|value| value.to_str())
+                .ok_or_else(|| anyhow::anyhow!("Missing value for serve option {}", flag))?;
+            let invalid = || anyhow::anyhow!("Invalid value {:?} for serve option {}", value, flag);
+            match flag {
+                "-h" | "--host" => options.host = Some(value.to_string()),
+                "-p" | "--port" => options.port = Some(value.parse().map_err(|_| invalid())?),
+                "--workers" => options.workers = Some(value.parse().map_err(|_| invalid())?),
+                _ => options.mode = Some(value.parse().map_err(|_| invalid())?),
+            }
+        }
+
+        Ok((options, rest))
+    }
+}
+
+/// Arguments for the `java` invocation that serves the function bundle. `serve_args` are passed
+/// through after the rendered `options`. With extension jars in the environment, the runtime
+/// starts from a classpath instead of with `-jar`, which would ignore them.
+pub fn java_args(
+    runtime_jar_path: impl AsRef<Path>,
+    function_bundle_dir: impl AsRef<Path>,
+    options: &LaunchOptions,
+    serve_args: &[OsString],
+    env: impl Fn(&str) -> Option<String>,
+    java_version: impl FnOnce() -> Option<String>,
+) -> anyhow::Result<Vec<OsString>> {
+    let mut args: Vec<OsString> = Vec::new();
+
+    if let Some(debug_port) = debug_port(&env) {
+        let address = match java_version() {
+            Some(version) if version.starts_with("1.8") => debug_port,
+            _ => format!("*:{}", debug_port),
+        };
+        args.push(
+            format!(
+                "-agentlib:jdwp=transport=dt_socket,server=y,suspend=n,address={}",
+                address
+            )
+            .into(),
+        );
+    }
+
+    args.extend(runtime_args(runtime_jar_path, &env));
+    args.extend(native_args(function_bundle_dir, options, serve_args, env)?);
+
+    Ok(args)
+}
+
+/// Arguments for the executable at [`NATIVE_BINARY_ENV`], which embeds the runtime and so takes
+/// the `serve` command directly.
+pub fn native_args(
+    function_bundle_dir: impl AsRef<Path>,
+    options: &LaunchOptions,
+    serve_args: &[OsString],
+    env: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<Vec<OsString>> {
+    // The platform routes traffic to `PORT`, so it wins over a port from project.toml.
+    let platform_port = util::parse_env::<u16>(&env, "PORT", "a port number")?;
+    let options = LaunchOptions {
+        host: options
+            .host
+            .clone()
+            .or_else(|| Some(String::from("0.0.0.0"))),
+        port: platform_port.or(options.port).or(Some(DEFAULT_PORT)),
+        ..options.clone()
+    };
+
+    let mut args: Vec<OsString> = vec![
+        "serve".into(),
+        PathBuf::from(function_bundle_dir.as_ref()).into(),
+    ];
+    args.extend(options.args().into_iter().map(OsString::from));
+    args.extend(serve_args.iter().cloned());
+
+    Ok(args)
+}
+
+/// Arguments for the `java` invocation that runs the function once with `local-invoke`, with the
+/// payload from [`TEST_PAYLOAD_ENV`] if it is set.
+pub fn local_invoke_args(
+    runtime_jar_path: impl AsRef<Path>,
+    function_bundle_dir: impl AsRef<Path>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<OsString> {
+    let mut args = runtime_args(runtime_jar_path, &env);
+    args.push("local-invoke".into());
+    args.push(PathBuf::from(function_bundle_dir.as_ref()).into());
+    if let Some(payload) = env(TEST_PAYLOAD_ENV).filter(|payload| !payload.is_empty()) {
+        args.push("--payload".into());
+        args.push(payload.into());
+    }
+
+    args
+}
+
+/// Starts the runtime, from a classpath with extension jars in the environment, as `-jar` would
+/// ignore them.
+fn runtime_args(
+    runtime_jar_path: impl AsRef<Path>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Vec<OsString> {
+    let extensions = env(EXTENSIONS_CLASSPATH_ENV).filter(|classpath| !classpath.is_empty());
+    match (extensions, env(RUNTIME_MAIN_CLASS_ENV)) {
+        (Some(extensions), Some(main_class)) => {
+            let mut classpath = runtime_jar_path.as_ref().as_os_str().to_os_string();
+            classpath.push(Family::current().classpath_separator());
+            classpath.push(extensions);
+            vec!["-cp".into(), classpath, main_class.into()]
+        }
+        _ => vec![
+            "-jar".into(),
+            PathBuf::from(runtime_jar_path.as_ref()).into(),
+        ],
+    }
+}
+
+/// The JDWP port, if remote debugging is enabled with `DEBUG_PORT` or `SF_FUNCTIONS_DEBUG`.
+pub fn debug_port(env: impl Fn(&str) -> Option<String>) -> Option<String> {
+    if let Some(port) = env("DEBUG_PORT").filter(|port| !port.is_empty()) {
+        return Some(port);
+    }
+
+    match env("SF_FUNCTIONS_DEBUG").as_deref() {
+        Some("1") | Some("true") => Some(DEFAULT_DEBUG_PORT.to_string()),
+        _ => None,
+    }
+}
+
+/// Port and path probed by the `health` process, from `SF_FUNCTIONS_HEALTH_PORT` (falling back to
+/// `PORT`, then the `configured_port` of the serve options) and `SF_FUNCTIONS_HEALTH_PATH`.
+pub fn health_check_target(
+    env: impl Fn(&str) -> Option<String>,
+    configured_port: Option<u16>,
+) -> (String, String) {
+    let non_empty = |key| env(key).filter(|value: &String| !value.is_empty());
+    let port = non_empty("SF_FUNCTIONS_HEALTH_PORT")
+        .or_else(|| non_empty("PORT"))
+        .unwrap_or_else(|| configured_port.unwrap_or(DEFAULT_PORT).to_string());
+    let path =
+        non_empty("SF_FUNCTIONS_HEALTH_PATH").unwrap_or_else(|| String::from(DEFAULT_HEALTH_PATH));
+
+    (port, path)
+}
+
+/// Requests `path` from the function runtime on localhost and succeeds on any 2xx response.
+pub fn health_check(port: &str, path: &str, timeout: Duration) -> anyhow::Result<()> {
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        path
+    );
+    let status = local_http_status(port, &request, timeout)?;
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Function runtime health check returned HTTP {}",
+            status
+        ))
+    }
+}
+
+/// Sends a raw HTTP/1.0 `request` to the given port on localhost and returns the response status.
+pub fn local_http_status(port: &str, request: &str, timeout: Duration) -> anyhow::Result<u16> {
+    let addr: SocketAddr = format!("127.0.0.1:{}", port)
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid port {:?}", port))?;
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    stream.write_all(request.as_bytes())?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    response
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid HTTP response from function runtime"))
+}
+
+/// Extracts the version from `java -version` output, e.g. `1.8.0_282` or `11.0.10`.
+pub fn parse_java_version(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find(|line| line.to_lowercase().contains("version"))
+        .and_then(|line| line.split('"').nth(1))
+        .map(String::from)
+}
+
+/// The major version of a `java -version` version string, e.g. `8` for `1.8.0_282` and `11`
+/// for `11.0.10`.
+pub fn java_major_version(version: &str) -> Option<u32> {
+    let mut components = version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|component| component.parse::<u32>().ok());
+
+    match components.next()?? {
+        1 => components.next()?,
+        major => Some(major),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_to_strings(args: Vec<OsString>) -> Vec<String> {
+        args.into_iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn java_args_serves_bundle_on_default_port() -> anyhow::Result<()> {
+        let args = java_args(
+            "/layers/runtime/runtime.jar",
+            "/layers/function-bundle",
+            &LaunchOptions::default(),
+            &[],
+            |_| None,
+            || panic!("java version should only be checked in debug mode"),
+        )?;
+
+        assert_eq!(
+            args_to_strings(args),
+            vec![
+                "-jar",
+                "/layers/runtime/runtime.jar",
+                "serve",
+                "/layers/function-bundle",
+                "-h",
+                "0.0.0.0",
+                "-p",
+                "8080"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn native_args_serve_bundle_without_runtime_jar() -> anyhow::Result<()> {
+        let args = native_args(
+            "/layers/function-bundle",
+            &LaunchOptions::default(),
+            &[OsString::from("--workers"), OsString::from("4")],
+            |key| match key {
+                "PORT" => Some(String::from("5000")),
+                _ => None,
+            },
+        )?;
+
+        assert_eq!(
+            args_to_strings(args),
+            vec![
+                "serve",
+                "/layers/function-bundle",
+                "-h",
+                "0.0.0.0",
+                "-p",
+                "5000",
+                "--workers",
+                "4"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn java_args_honors_port_debug_port_and_serve_args() -> anyhow::Result<()> {
+        let args = java_args(
+            "runtime.jar",
+            "bundle",
+            &LaunchOptions::default(),
+            &[OsString::from("--workers"), OsString::from("4")],
+            |key| match key {
+                "PORT" => Some(String::from("5000")),
+                "DEBUG_PORT" => Some(String::from("5005")),
+                _ => None,
+            },
+            || Some(String::from("11.0.10")),
+        )?;
+
+        assert_eq!(
+            args_to_strings(args),
+            vec![
+                "-agentlib:jdwp=transport=dt_socket,server=y,suspend=n,address=*:5005",
+                "-jar",
+                "runtime.jar",
+                "serve",
+                "bundle",
+                "-h",
+                "0.0.0.0",
+                "-p",
+                "5000",
+                "--workers",
+                "4"
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn java_args_appends_extensions_to_runtime_classpath() -> anyhow::Result<()> {
+        let args = java_args(
+            "runtime.jar",
+            "bundle",
+            &LaunchOptions::default(),
+            &[],
+            |key| match key {
+                EXTENSIONS_CLASSPATH_ENV => Some(String::from("/layers/extensions/sdk.jar")),
+                RUNTIME_MAIN_CLASS_ENV => Some(String::from("com.example.Runtime")),
+                _ => None,
+            },
+            || None,
+        )?;
+
+        assert_eq!(
+            args_to_strings(args)[..4],
+            [
+                String::from("-cp"),
+                format!(
+                    "runtime.jar{}/layers/extensions/sdk.jar",
+                    Family::current().classpath_separator()
+                ),
+                String::from("com.example.Runtime"),
+                String::from("serve")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn local_invoke_args_pass_test_payload() {
+        assert_eq!(
+            args_to_strings(local_invoke_args("runtime.jar", "bundle", |_| None)),
+            vec!["-jar", "runtime.jar", "local-invoke", "bundle"]
+        );
+        assert_eq!(
+            args_to_strings(local_invoke_args(
+                "runtime.jar",
+                "bundle",
+                |key| match key {
+                    TEST_PAYLOAD_ENV => Some(String::from("/workspace/payload.json")),
+                    _ => None,
+                }
+            ))[2..],
+            [
+                "local-invoke",
+                "bundle",
+                "--payload",
+                "/workspace/payload.json"
+            ]
+        );
+    }
+
+    #[test]
+    fn java_args_uses_plain_debug_address_on_java_8() -> anyhow::Result<()> {
+        let args = java_args(
+            "runtime.jar",
+            "bundle",
+            &LaunchOptions::default(),
+            &[],
+            |key| match key {
+                "DEBUG_PORT" => Some(String::from("5005")),
+                _ => None,
+            },
+            || Some(String::from("1.8.0_282")),
+        )?;
+
+        assert_eq!(
+            args[0],
+            OsString::from("-agentlib:jdwp=transport=dt_socket,server=y,suspend=n,address=5005")
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn java_args_prefers_configured_options_but_platform_port() -> anyhow::Result<()> {
+        let options = LaunchOptions {
+            port: Some(9000),
+            mode: Some(ServeMode::Grpc),
+            ..LaunchOptions::default()
+        };
+        let args = java_args(
+            "runtime.jar",
+            "bundle",
+            &options,
+            &[],
+            |key| match key {
+                "PORT" => Some(String::from("5000")),
+                _ => None,
+            },
+            || None,
+        )?;
+
+        assert_eq!(
+            args_to_strings(args)[4..],
+            ["-h", "0.0.0.0", "-p", "5000", "--mode", "grpc"]
+        );
+
+        let args = java_args("runtime.jar", "bundle", &options, &[], |_| None, || None)?;
+        assert_eq!(args_to_strings(args)[6..8], ["-p", "9000"]);
+
+        let invalid_port = java_args(
+            "runtime.jar",
+            "bundle",
+            &options,
+            &[],
+            |key| match key {
+                "PORT" => Some(String::from("http")),
+                _ => None,
+            },
+            || None,
+        );
+        assert!(invalid_port.is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn launch_options_parse_round_trips_args() -> anyhow::Result<()> {
+        let options = LaunchOptions {
+            host: Some(String::from("127.0.0.1")),
+            port: Some(9000),
+            workers: Some(4),
+            mode: Some(ServeMode::Http),
+        };
+        let mut args: Vec<OsString> = options.args().into_iter().map(OsString::from).collect();
+        args.push(OsString::from("--verbose"));
+
+        let (parsed, rest) = LaunchOptions::parse(&args)?;
+        assert_eq!(parsed, options);
+        assert_eq!(rest, vec![OsString::from("--verbose")]);
+
+        assert!(LaunchOptions::parse(&[OsString::from("--workers")]).is_err());
+        assert!(LaunchOptions::parse(&[OsString::from("--mode"), OsString::from("ws")]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn serve_mode_checks_runtime_help() {
+        assert_eq!("GRPC".parse(), Ok(ServeMode::Grpc));
+        assert!("websocket".parse::<ServeMode>().is_err());
+
+        let help =
+            "Usage: serve [-p=<port>] [--mode=<mode>] <bundle>\n  --mode=<mode>  http or grpc";
+        assert!(ServeMode::Grpc.supported_by_help(help));
+        assert!(!ServeMode::Grpc.supported_by_help("Usage: serve [-p=<port>] <bundle>"));
+        assert!(ServeMode::Http.supported_by_help(""));
+    }
+
+    #[test]
+    fn java_major_version_handles_legacy_scheme() {
+        assert_eq!(java_major_version("1.8.0_282"), Some(8));
+        assert_eq!(java_major_version("11.0.10"), Some(11));
+        assert_eq!(java_major_version("17"), Some(17));
+        assert_eq!(java_major_version("18-ea"), Some(18));
+        assert_eq!(java_major_version("unknown"), None);
+    }
+
+    #[test]
+    fn debug_port_defaults_when_debug_is_enabled() {
+        assert_eq!(debug_port(|_| None), None);
+        assert_eq!(
+            debug_port(|key| match key {
+                "SF_FUNCTIONS_DEBUG" => Some(String::from("1")),
+                _ => None,
+            }),
+            Some(String::from("5005"))
+        );
+        assert_eq!(
+            debug_port(|key| match key {
+                "SF_FUNCTIONS_DEBUG" => Some(String::from("true")),
+                "DEBUG_PORT" => Some(String::from("8000")),
+                _ => None,
+            }),
+            Some(String::from("8000"))
+        );
+        assert_eq!(
+            debug_port(|key| match key {
+                "SF_FUNCTIONS_DEBUG" => Some(String::from("0")),
+                _ => None,
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_java_version_reads_quoted_version() {
+        assert_eq!(
+            parse_java_version(
+                "openjdk version \"11.0.10\" 2021-01-19\nOpenJDK Runtime Environment (build 11.0.10+9)"
+            ),
+            Some(String::from("11.0.10"))
+        );
+        assert_eq!(
+            parse_java_version("java version \"1.8.0_282\""),
+            Some(String::from("1.8.0_282"))
+        );
+        assert_eq!(parse_java_version("command not found"), None);
+    }
+
+    #[test]
+    fn health_check_target_prefers_dedicated_port() {
+        assert_eq!(
+            health_check_target(|_| None, None),
+            (String::from("8080"), String::from("/health"))
+        );
+        assert_eq!(
+            health_check_target(|_| None, Some(9000)).0,
+            String::from("9000")
+        );
+        assert_eq!(
+            health_check_target(
+                |key| match key {
+                    "PORT" => Some(String::from("5000")),
+                    _ => None,
+                },
+                Some(9000)
+            )
+            .0,
+            String::from("5000")
+        );
+        assert_eq!(
+            health_check_target(
+                |key| match key {
+                    "PORT" => Some(String::from("5000")),
+                    "SF_FUNCTIONS_HEALTH_PORT" => Some(String::from("7000")),
+                    "SF_FUNCTIONS_HEALTH_PATH" => Some(String::from("/ready")),
+                    _ => None,
+                },
+                None
+            ),
+            (String::from("7000"), String::from("/ready"))
+        );
+    }
+
+    fn serve_once(response: &'static str) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        port
+    }
+
+    #[test]
+    fn health_check_succeeds_on_2xx() {
+        let port = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n");
+
+        assert!(health_check(&port, "/health", Duration::from_secs(5)).is_ok());
+    }
+
+    #[test]
+    fn health_check_fails_on_error_status() {
+        let port = serve_once("HTTP/1.1 503 Service Unavailable\r\n\r\n");
+        let err = health_check(&port, "/health", Duration::from_secs(5)).unwrap_err();
+
+        assert!(err.to_string().contains("503"));
+    }
+}
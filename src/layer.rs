@@ -0,0 +1,638 @@
+use crate::util::{
+    logger::{DiffLine, Logger},
+    reproducible,
+};
+use libcnb::{build::GenericBuildContext, layer::Layer, platform::Platform};
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+use toml::value::Table;
+
+/// Top-level entries the CNB platform defines for every layer.
+const STANDARD_ENTRIES: &[&str] = &["env", "env.build", "env.launch", "exec.d", "profile.d"];
+
+#[derive(Debug, Clone, Copy)]
+pub struct LayerTypes {
+    pub launch: bool,
+    pub build: bool,
+    pub cache: bool,
+}
+
+/// What happens to a layer, given the metadata of the previous build.
+#[derive(Debug, PartialEq)]
+pub enum Decision {
+    Create,
+    /// Recreates a cached layer, listing the metadata keys that changed.
+    Update(Vec<String>),
+    Skip,
+}
+
+/// A layer the buildpack contributes. [`contribute_layer`] decides whether a cached layer can be
+/// reused and takes care of writing the layer metadata.
+pub trait LayerContributor {
+    fn name(&self) -> &str;
+
+    fn types(&self) -> LayerTypes;
+
+    /// Describes the desired layer contents. A cached layer with the same values is reused.
+    fn metadata(&self) -> Table {
+        Table::new()
+    }
+
+    /// Whether a cached layer with matching metadata is complete enough to be reused.
+    fn is_reusable(&self, _layer_dir: &Path) -> bool {
+        true
+    }
+
+    /// Whether an outdated cached layer keeps its contents for [`LayerContributor::contribute`],
+    /// so it can be updated in place instead of being written from scratch.
+    fn updates_in_place(&self) -> bool {
+        false
+    }
+
+    /// Writes the layer contents into the empty layer directory and returns metadata to record in
+    /// addition to [`LayerContributor::metadata`]. See [`LayerContributor::updates_in_place`]
+    /// for outdated cached layers.
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table>;
+
+    /// Runs on every build after the layer was contributed or reused.
+    fn finish(&self, _layer_dir: &Path) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Top-level entries the layer may contain besides the standard ones like `env.launch`, an
+    /// entry ending in `*` matches by prefix. Anything else in a cached layer is removed before it
+    /// is reused or updated, see [`unexpected_entries`]. `None` skips the check.
+    fn expected_entries(&self) -> Option<Vec<String>> {
+        None
+    }
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Decision::Create => write!(f, "created, not cached"),
+            Decision::Update(changed) if changed.is_empty() => {
+                write!(f, "recreated, cached contents incomplete")
+            }
+            Decision::Update(changed) => write!(f, "updated, changed: {}", changed.join(", ")),
+            Decision::Skip => write!(f, "reused, metadata unchanged"),
+        }
+    }
+}
+
+pub fn decide(types: LayerTypes, cached: &Table, desired: &Table, reusable: bool) -> Decision {
+    if !types.cache || cached.is_empty() {
+        return Decision::Create;
+    }
+
+    let changed: Vec<String> = desired
+        .iter()
+        .filter(|(key, value)| cached.get(*key) != Some(value))
+        .map(|(key, _)| key.clone())
+        .collect();
+
+    if changed.is_empty() && reusable {
+        Decision::Skip
+    } else {
+        Decision::Update(changed)
+    }
+}
+
+/// Lists the old and new value of every changed key, e.g. `stack_id "heroku-18" -> "heroku-20"`.
+fn describe_changes(cached: &Table, desired: &Table, changed: &[String]) -> String {
+    let value = |table: &Table, key: &str| {
+        table
+            .get(key)
+            .map_or_else(|| String::from("(none)"), toml::Value::to_string)
+    };
+
+    changed
+        .iter()
+        .map(|key| format!("{} {} -> {}", key, value(cached, key), value(desired, key)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Field by field diff of the cached and desired metadata, so the reason for a cache miss is
+/// visible in debug output. Keys only the contributed metadata has are left out.
+fn metadata_diff(cached: &Table, desired: &Table) -> Vec<DiffLine> {
+    let mut lines = Vec::new();
+    for (key, desired_value) in desired {
+        match cached.get(key) {
+            Some(cached_value) if cached_value == desired_value => {
+                lines.push(DiffLine::Unchanged(format!("{} = {}", key, desired_value)));
+            }
+            Some(cached_value) => {
+                lines.push(DiffLine::Removed(format!("{} = {}", key, cached_value)));
+                lines.push(DiffLine::Added(format!("{} = {}", key, desired_value)));
+            }
+            None => lines.push(DiffLine::Added(format!("{} = {}", key, desired_value))),
+        }
+    }
+
+    lines
+}
+
+/// Top-level entries of `layer_dir` that neither `expected` nor the platform account for, e.g.
+/// files of an older buildpack version or left by hand in an ephemeral builder.
+pub fn unexpected_entries(layer_dir: &Path, expected: &[String]) -> io::Result<Vec<String>> {
+    let entries = match fs::read_dir(layer_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let is_expected = |name: &str| {
+        STANDARD_ENTRIES.contains(&name)
+            || expected
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => name.starts_with(prefix),
+                    None => name == pattern,
+                })
+    };
+    let mut unexpected = Vec::new();
+    for entry in entries {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if !is_expected(&name) {
+            unexpected.push(name);
+        }
+    }
+    unexpected.sort();
+
+    Ok(unexpected)
+}
+
+/// Removes the [`unexpected_entries`] of a cached layer with a warning, so stale files never mix
+/// with fresh ones.
+fn clean_layer(
+    logger: &impl Logger,
+    name: &str,
+    layer_dir: &Path,
+    expected: &[String],
+) -> anyhow::Result<()> {
+    let unexpected = unexpected_entries(layer_dir, expected)?;
+    if unexpected.is_empty() {
+        return Ok(());
+    }
+
+    for entry in &unexpected {
+        let path = layer_dir.join(entry);
+        if fs::symlink_metadata(&path)?.is_dir() {
+            fs::remove_dir_all(&path)?;
+        } else {
+            fs::remove_file(&path)?;
+        }
+    }
+    logger.warning(
+        "Unexpected files in cached layer",
+        format!(
+            "The cached {} layer contained files this buildpack doesn't write, likely left by an \
+             older buildpack version. They were removed: {}",
+            name,
+            unexpected.join(", ")
+        ),
+    )
+}
+
+/// The layers directory [`contribute_layer`] works in.
+pub trait LayerStore {
+    fn layers_dir(&self) -> &Path;
+
+    /// `SOURCE_DATE_EPOCH` of the platform, which launch layers are normalized to.
+    fn source_date_epoch(&self) -> Option<String>;
+
+    /// Opens the layer, creating its directory.
+    fn layer(&self, name: &str) -> anyhow::Result<Layer> {
+        Ok(Layer::new(name, self.layers_dir())?)
+    }
+
+    /// Path of the layer, unlike [`LayerStore::layer`] without creating it.
+    fn layer_dir(&self, name: &str) -> PathBuf {
+        self.layers_dir().join(name)
+    }
+
+    /// The metadata recorded in `<name>.toml`, empty if the layer wasn't contributed. Like
+    /// [`LayerStore::layer_dir`] it leaves the layers directory untouched.
+    fn metadata(&self, name: &str) -> anyhow::Result<Table> {
+        let path = self.layers_dir().join(format!("{}.toml", name));
+        let layer_toml = match fs::read_to_string(&path) {
+            Ok(layer_toml) => layer_toml,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Table::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut layer_toml: Table = toml::from_str(&layer_toml)?;
+
+        Ok(match layer_toml.remove("metadata") {
+            Some(toml::Value::Table(metadata)) => metadata,
+            _ => Table::new(),
+        })
+    }
+}
+
+/// The layers directory of a build. libcnb 0.1 keeps the one of the build context private, so it
+/// comes from the arguments the lifecycle passes to `bin/build`.
+pub struct BuildLayers {
+    dir: PathBuf,
+    source_date_epoch: Option<String>,
+}
+
+impl BuildLayers {
+    pub fn new(ctx: &GenericBuildContext, dir: impl Into<PathBuf>) -> Self {
+        BuildLayers {
+            dir: dir.into(),
+            source_date_epoch: ctx
+                .platform
+                .env()
+                .var(reproducible::SOURCE_DATE_EPOCH_ENV)
+                .ok(),
+        }
+    }
+}
+
+impl LayerStore for BuildLayers {
+    fn layers_dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn source_date_epoch(&self) -> Option<String> {
+        self.source_date_epoch.clone()
+    }
+}
+
+/// The decision [`contribute_layer`] would take, without changing or creating the layer.
+pub fn plan(
+    store: &impl LayerStore,
+    contributor: &impl LayerContributor,
+) -> anyhow::Result<Decision> {
+    let name = contributor.name();
+
+    Ok(decide(
+        contributor.types(),
+        &store.metadata(name)?,
+        &contributor.metadata(),
+        contributor.is_reusable(&store.layer_dir(name)),
+    ))
+}
+
+/// Contributes the layer and returns it together with the caching decision that was taken.
+pub fn contribute_layer(
+    store: &impl LayerStore,
+    logger: &impl Logger,
+    contributor: &impl LayerContributor,
+) -> anyhow::Result<(Layer, Decision)> {
+    let name = contributor.name();
+    let mut layer = store.layer(name)?;
+    let layer_dir = layer.as_path().to_path_buf();
+    let types = contributor.types();
+    let desired = contributor.metadata();
+    let decision = decide(
+        types,
+        &layer.content_metadata().metadata,
+        &desired,
+        contributor.is_reusable(&layer_dir),
+    );
+
+    match &decision {
+        Decision::Skip => logger.info(format!("Reusing cached {} layer", name))?,
+        Decision::Create => logger.debug(format!("Creating {} layer", name))?,
+        Decision::Update(changed) if changed.is_empty() => logger.debug(format!(
+            "Recreating {} layer, the cached contents are incomplete",
+            name
+        ))?,
+        Decision::Update(changed) => {
+            let cached = &layer.content_metadata().metadata;
+            logger.info(format!(
+                "Updating {} layer, {}",
+                name,
+                describe_changes(cached, &desired, changed)
+            ))?;
+            logger.debug(format!("Metadata of the cached {} layer:", name))?;
+            logger.debug_diff(&metadata_diff(cached, &desired))?;
+        }
+    }
+
+    let update_in_place = contributor.updates_in_place()
+        && matches!(&decision, Decision::Update(changed) if !changed.is_empty());
+    if decision == Decision::Skip || update_in_place {
+        if let Some(expected) = contributor.expected_entries() {
+            clean_layer(logger, name, &layer_dir, &expected)?;
+        }
+    }
+
+    if decision != Decision::Skip {
+        // The metadata is only recorded once the contents are complete, so a failed build never
+        // leaves a layer behind that looks reusable.
+        let content_metadata = layer.mut_content_metadata();
+        content_metadata.launch = types.launch;
+        content_metadata.build = types.build;
+        content_metadata.cache = types.cache;
+        content_metadata.metadata = Table::new();
+        layer.write_content_metadata()?;
+
+        if layer_dir.exists() && !update_in_place {
+            fs::remove_dir_all(&layer_dir)?;
+        }
+        fs::create_dir_all(&layer_dir)?;
+
+        let mut metadata = contributor.contribute(&layer_dir)?;
+        metadata.extend(desired);
+        layer.mut_content_metadata().metadata = metadata;
+        layer.write_content_metadata()?;
+    }
+
+    contributor.finish(&layer_dir)?;
+    // Launch layers end up in the image, where changed timestamps alone would change its digest.
+    if types.launch {
+        reproducible::normalize_tree(
+            &layer_dir,
+            reproducible::mtime(store.source_date_epoch().as_deref()),
+        )?;
+    }
+
+    Ok((layer, decision))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test_support::LayersDir,
+        util::logger::{Entry, Level, MemoryLogger},
+    };
+
+    const CACHED: LayerTypes = LayerTypes {
+        launch: true,
+        build: false,
+        cache: true,
+    };
+
+    fn table(entries: &[(&str, &str)]) -> Table {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), toml::Value::from(*value)))
+            .collect()
+    }
+
+    #[test]
+    fn decide_creates_uncached_and_new_layers() {
+        let metadata = table(&[("sha256", "ABCDEF")]);
+        let uncached = LayerTypes {
+            cache: false,
+            ..CACHED
+        };
+
+        assert_eq!(
+            decide(uncached, &metadata, &metadata, true),
+            Decision::Create
+        );
+        assert_eq!(
+            decide(CACHED, &Table::new(), &metadata, true),
+            Decision::Create
+        );
+    }
+
+    #[test]
+    fn decide_skips_matching_layers_and_ignores_extra_cached_keys() {
+        let cached = table(&[("sha256", "ABCDEF"), ("actual_sha256", "ABCDEF")]);
+
+        assert_eq!(
+            decide(CACHED, &cached, &table(&[("sha256", "ABCDEF")]), true),
+            Decision::Skip
+        );
+    }
+
+    #[test]
+    fn describe_changes_lists_old_and_new_values() {
+        let cached = table(&[("stack_id", "heroku-18")]);
+        let desired = table(&[("stack_id", "heroku-20"), ("buildpack_version", "0.2.4")]);
+
+        assert_eq!(
+            describe_changes(
+                &cached,
+                &desired,
+                &[String::from("stack_id"), String::from("buildpack_version")]
+            ),
+            r#"stack_id "heroku-18" -> "heroku-20", buildpack_version (none) -> "0.2.4""#
+        );
+    }
+
+    #[test]
+    fn metadata_diff_shows_every_desired_field() {
+        let cached = table(&[("stack_id", "heroku-18"), ("url", "https://foo.com")]);
+        let desired = table(&[
+            ("buildpack_version", "0.2.4"),
+            ("stack_id", "heroku-20"),
+            ("url", "https://foo.com"),
+        ]);
+
+        assert_eq!(
+            metadata_diff(&cached, &desired)
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                r#"+ buildpack_version = "0.2.4""#,
+                r#"- stack_id = "heroku-18""#,
+                r#"+ stack_id = "heroku-20""#,
+                r#"  url = "https://foo.com""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn decision_display_names_changed_keys() {
+        assert_eq!(
+            Decision::Update(vec![String::from("sha256"), String::from("url")]).to_string(),
+            "updated, changed: sha256, url"
+        );
+        assert_eq!(
+            Decision::Update(Vec::new()).to_string(),
+            "recreated, cached contents incomplete"
+        );
+    }
+
+    #[test]
+    fn decide_updates_changed_or_incomplete_layers() {
+        let cached = table(&[("sha256", "ABCDEF"), ("url", "https://foo.com")]);
+        let desired = table(&[("sha256", "123456"), ("url", "https://foo.com")]);
+
+        assert_eq!(
+            decide(CACHED, &cached, &desired, true),
+            Decision::Update(vec![String::from("sha256")])
+        );
+        assert_eq!(
+            decide(CACHED, &cached, &cached, false),
+            Decision::Update(Vec::new())
+        );
+    }
+
+    #[test]
+    fn unexpected_entries_ignores_expected_and_standard_entries() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+        for file in &[
+            "runtime.jar",
+            "runtime.tar.gz",
+            "sf-fx-runtime-java-0.1.jar",
+        ] {
+            fs::write(layer_dir.path().join(file), "")?;
+        }
+        fs::create_dir(layer_dir.path().join("env.launch"))?;
+        fs::create_dir(layer_dir.path().join("old"))?;
+
+        assert_eq!(
+            unexpected_entries(layer_dir.path(), &[String::from("runtime.*")])?,
+            vec!["old", "sf-fx-runtime-java-0.1.jar"]
+        );
+        assert!(unexpected_entries(&layer_dir.path().join("missing"), &[])?.is_empty());
+
+        Ok(())
+    }
+
+    struct CachedLayer {
+        version: &'static str,
+    }
+
+    impl LayerContributor for CachedLayer {
+        fn name(&self) -> &str {
+            "cached"
+        }
+
+        fn types(&self) -> LayerTypes {
+            CACHED
+        }
+
+        fn metadata(&self) -> Table {
+            table(&[("version", self.version)])
+        }
+
+        fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+            fs::write(layer_dir.join("contents"), self.version)?;
+            Ok(Table::new())
+        }
+
+        fn expected_entries(&self) -> Option<Vec<String>> {
+            Some(vec![String::from("contents")])
+        }
+    }
+
+    #[test]
+    fn rebuilds_reuse_layers_until_metadata_changes() -> anyhow::Result<()> {
+        let layers_dir = LayersDir::new()?;
+
+        assert_eq!(
+            layers_dir.contribute(&CachedLayer { version: "1.0" })?,
+            Decision::Create
+        );
+        assert_eq!(
+            layers_dir.contribute(&CachedLayer { version: "1.0" })?,
+            Decision::Skip
+        );
+        assert_eq!(
+            layers_dir.contribute(&CachedLayer { version: "1.1" })?,
+            Decision::Update(vec![String::from("version")])
+        );
+        assert_eq!(
+            fs::read_to_string(layers_dir.layer_dir("cached").join("contents"))?,
+            "1.1"
+        );
+        assert_eq!(layers_dir.metadata("cached")?, table(&[("version", "1.1")]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_leaves_layers_dir_untouched() -> anyhow::Result<()> {
+        let layers_dir = LayersDir::new()?;
+
+        assert_eq!(
+            plan(&layers_dir, &CachedLayer { version: "1.0" })?,
+            Decision::Create
+        );
+        assert_eq!(fs::read_dir(layers_dir.path())?.count(), 0);
+
+        layers_dir.contribute(&CachedLayer { version: "1.0" })?;
+        assert_eq!(
+            plan(&layers_dir, &CachedLayer { version: "1.1" })?,
+            Decision::Update(vec![String::from("version")])
+        );
+        assert_eq!(layers_dir.metadata("cached")?, table(&[("version", "1.0")]));
+
+        Ok(())
+    }
+
+    struct BrokenLayer;
+
+    impl LayerContributor for BrokenLayer {
+        fn name(&self) -> &str {
+            "cached"
+        }
+
+        fn types(&self) -> LayerTypes {
+            CACHED
+        }
+
+        fn metadata(&self) -> Table {
+            table(&[("version", "1.1")])
+        }
+
+        fn contribute(&self, _layer_dir: &Path) -> anyhow::Result<Table> {
+            Err(anyhow::anyhow!("Download failed"))
+        }
+    }
+
+    #[test]
+    fn failed_contributions_leave_no_reusable_layer() -> anyhow::Result<()> {
+        let layers_dir = LayersDir::new()?;
+
+        layers_dir.contribute(&CachedLayer { version: "1.0" })?;
+        assert!(layers_dir.contribute(&BrokenLayer).is_err());
+        assert!(layers_dir.metadata("cached")?.is_empty());
+        assert_eq!(
+            layers_dir.contribute(&CachedLayer { version: "1.0" })?,
+            Decision::Create
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn contribute_layer_logs_decisions_and_removed_files() -> anyhow::Result<()> {
+        let layers_dir = LayersDir::new()?;
+        let logger = MemoryLogger::new(true);
+
+        contribute_layer(&layers_dir, &logger, &CachedLayer { version: "1.0" })?;
+        fs::write(layers_dir.layer_dir("cached").join("stale.jar"), "")?;
+        contribute_layer(&layers_dir, &logger, &CachedLayer { version: "1.0" })?;
+        contribute_layer(&layers_dir, &logger, &CachedLayer { version: "1.1" })?;
+
+        assert_eq!(
+            logger.messages(Level::Info),
+            vec![
+                "Reusing cached cached layer",
+                r#"Updating cached layer, version "1.0" -> "1.1""#,
+            ]
+        );
+        assert_eq!(
+            logger
+                .entries()
+                .into_iter()
+                .filter(|entry| entry.level == Level::Warning)
+                .collect::<Vec<_>>(),
+            vec![Entry {
+                level: Level::Warning,
+                header: Some(String::from("Unexpected files in cached layer")),
+                message: String::from(
+                    "The cached cached layer contained files this buildpack doesn't write, \
+                     likely left by an older buildpack version. They were removed: stale.jar"
+                ),
+            }]
+        );
+        assert_eq!(logger.messages(Level::Debug)[0], "Creating cached layer");
+        assert!(!layers_dir.layer_dir("cached").join("stale.jar").exists());
+
+        Ok(())
+    }
+}
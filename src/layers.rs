@@ -0,0 +1,14 @@
+pub mod artifact_cache;
+pub mod cds;
+pub mod dependency_cache;
+pub mod extensions;
+pub mod function_bundle;
+pub mod function_dependencies;
+pub mod metrics;
+pub mod native_image;
+pub mod observability;
+pub mod opentelemetry;
+pub mod opt;
+pub mod report;
+pub mod runtime;
+pub mod truststore;
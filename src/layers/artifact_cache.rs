@@ -0,0 +1,27 @@
+use crate::layer::{LayerContributor, LayerTypes};
+use std::path::Path;
+use toml::value::Table;
+
+pub(crate) const ARTIFACT_CACHE_LAYER_NAME: &str = "artifact-cache";
+
+/// Backs the [`ArtifactStore`](crate::util::artifact_store::ArtifactStore), kept between builds
+/// but never part of the image.
+pub(crate) struct ArtifactCacheLayer;
+
+impl LayerContributor for ArtifactCacheLayer {
+    fn name(&self) -> &str {
+        ARTIFACT_CACHE_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: false,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn contribute(&self, _layer_dir: &Path) -> anyhow::Result<Table> {
+        Ok(Table::new())
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{
+    builder::{Builder, SMOKE_TEST_TIMEOUT},
+    data::function_bundle,
+    launcher::{self, LaunchOptions},
+    layer::{LayerContributor, LayerTypes},
+    smoke,
+    util::{
+        disk_usage::mebibytes,
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml::value::Table;
+
+const CDS_LAYER_NAME: &str = "cds";
+/// Dynamic AppCDS archives (`-XX:ArchiveClassesAtExit`) were added in JDK 13.
+const CDS_MIN_JAVA: u32 = 13;
+
+struct CdsLayer<'r, 'a, 'b, L: Logger> {
+    builder: &'r Builder<'a, 'b, L>,
+    runtime_jar_path: &'r Path,
+    /// Process type, in multiple functions mode, and bundle directory of every function.
+    bundles: Vec<(Option<String>, PathBuf)>,
+    bundle_digest: &'r str,
+    java_version: String,
+}
+
+impl<L: Logger> CdsLayer<'_, '_, '_, L> {
+    fn archive_path(layer_dir: &Path, process_type: Option<&str>) -> PathBuf {
+        layer_dir.join(format!("{}.jsa", process_type.unwrap_or("function")))
+    }
+}
+
+impl<L: Logger> LayerContributor for CdsLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        CDS_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    // Archives only match the exact JDK build and class path they were dumped with.
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("bundle_digest"),
+            toml::Value::String(self.bundle_digest.to_string()),
+        );
+        metadata.insert(
+            String::from("java_version"),
+            toml::Value::String(self.java_version.clone()),
+        );
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        self.bundles.iter().all(|(process_type, _)| {
+            Self::archive_path(layer_dir, process_type.as_deref()).exists()
+        })
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        for (process_type, bundle_dir) in &self.bundles {
+            let archive = Self::archive_path(layer_dir, process_type.as_deref());
+            match self
+                .builder
+                .dump_cds_archive(self.runtime_jar_path, bundle_dir, &archive)
+            {
+                Ok(()) => self.builder.logger.info(format!(
+                    "Created AppCDS archive for {} ({:.1} MiB)",
+                    bundle_dir.display(),
+                    mebibytes(fs::metadata(&archive)?.len())
+                ))?,
+                Err(err) => self.builder.logger.warning(
+                    "Class data sharing skipped",
+                    format!(
+                        "Could not create an AppCDS archive for {}: {}\n\
+                         The function still works, but starts slower.",
+                        bundle_dir.display(),
+                        err
+                    ),
+                )?,
+            }
+        }
+
+        Ok(Table::new())
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        for (process_type, _) in &self.bundles {
+            let archive = Self::archive_path(layer_dir, process_type.as_deref());
+            if !archive.exists() {
+                continue;
+            }
+
+            let option = format!("-XX:SharedArchiveFile={}", archive.display());
+            match process_type {
+                Some(process_type) => layer_env::write_process_launch_env(
+                    layer_dir,
+                    process_type,
+                    "JAVA_TOOL_OPTIONS",
+                    Modification::Prepend,
+                    option,
+                )?,
+                None => layer_env::write_launch_env(
+                    layer_dir,
+                    "JAVA_TOOL_OPTIONS",
+                    Modification::Prepend,
+                    option,
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Warms up every function once with `-XX:ArchiveClassesAtExit` when `SF_FUNCTIONS_ENABLE_CDS`
+    /// is set, and launches it with the resulting AppCDS archive to shorten cold starts. A failed
+    /// warm-up only costs the speedup, so it doesn't fail the build.
+    pub fn contribute_cds_layer(
+        &self,
+        runtime_jar_path: &Path,
+        function_bundle_layer: &Layer,
+        bundle_digest: &str,
+        multiple_functions: bool,
+    ) -> anyhow::Result<Option<Layer>> {
+        let enabled = self.ctx.platform.env().var("SF_FUNCTIONS_ENABLE_CDS");
+        if !matches!(enabled.as_deref(), Ok("1") | Ok("true")) {
+            return Ok(None);
+        }
+
+        let section = Section::new(self.logger, "Creating class data sharing archive")?;
+        let java_version = match self.java_version()? {
+            Some(version)
+                if launcher::java_major_version(&version)
+                    .map_or(false, |major| major >= CDS_MIN_JAVA) =>
+            {
+                version
+            }
+            version => {
+                self.logger.warning(
+                    "Class data sharing skipped",
+                    format!(
+                        "Creating an AppCDS archive requires JDK {} or newer, the build uses {}.",
+                        CDS_MIN_JAVA,
+                        version.as_deref().unwrap_or("an unknown version")
+                    ),
+                )?;
+                return Ok(None);
+            }
+        };
+
+        let bundles = if multiple_functions {
+            function_bundle::bundle_dirs(function_bundle_layer.as_path())?
+                .into_iter()
+                .map(|bundle_dir| (Some(function_bundle::process_type(&bundle_dir)), bundle_dir))
+                .collect()
+        } else {
+            vec![(None, function_bundle_layer.as_path().to_path_buf())]
+        };
+
+        let layer = self.contribute_layer(&CdsLayer {
+            builder: self,
+            runtime_jar_path,
+            bundles,
+            bundle_digest,
+            java_version,
+        })?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+
+    /// Serves the function in `bundle_dir` once and dumps the classes it loaded into `archive`.
+    fn dump_cds_archive(
+        &self,
+        runtime_jar_path: &Path,
+        bundle_dir: &Path,
+        archive: &Path,
+    ) -> anyhow::Result<()> {
+        let port = smoke::free_port()?.to_string();
+        let extensions_env = self.extensions_env(runtime_jar_path)?;
+        let mut command = Command::new("java");
+        command
+            .arg(format!("-XX:ArchiveClassesAtExit={}", archive.display()))
+            .args(launcher::java_args(
+                runtime_jar_path,
+                bundle_dir,
+                &LaunchOptions {
+                    port: None,
+                    ..self.config.launch_options.clone()
+                },
+                &[],
+                |key| match key {
+                    "PORT" => Some(port.clone()),
+                    _ => extensions_env.get(key).cloned(),
+                },
+                || None,
+            )?);
+
+        smoke::warm_up(&mut command, port.parse()?, SMOKE_TEST_TIMEOUT)?;
+        if !archive.exists() {
+            anyhow::bail!("the JVM did not write {}", archive.display());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{
+    builder::Builder,
+    dependency_cache,
+    layer::{LayerContributor, LayerStore, LayerTypes},
+    util::{disk_usage::mebibytes, logger::Logger},
+};
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use toml::value::Table;
+
+const DEPENDENCY_CACHE_LAYER_NAME: &str = "dependency-cache";
+
+/// Maven and Gradle caches of the bundler, see [`dependency_cache`]. The metadata only changes
+/// after use, so the layer is reused unless a build failed before.
+pub(crate) struct DependencyCacheLayer;
+
+impl LayerContributor for DependencyCacheLayer {
+    fn name(&self) -> &str {
+        DEPENDENCY_CACHE_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: false,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn contribute(&self, _layer_dir: &Path) -> anyhow::Result<Table> {
+        Ok(Table::new())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    pub(crate) fn dependency_cache_dir(&self) -> PathBuf {
+        self.layers.layer_dir(DEPENDENCY_CACHE_LAYER_NAME)
+    }
+
+    /// Prunes the dependency cache after the bundler used it, and records its size and the time of
+    /// use in the layer metadata.
+    pub(crate) fn record_dependency_cache_use(&self) -> anyhow::Result<()> {
+        let max_mib = self.settings().dependency_cache_max_mib()?;
+        let mut layer = self.layers.layer(DEPENDENCY_CACHE_LAYER_NAME)?;
+        let prune = dependency_cache::prune(layer.as_path(), max_mib * 1024 * 1024)?;
+        if prune.reclaimed_bytes > 0 {
+            self.logger.info(format!(
+                "Cleared the dependency cache, it grew beyond {} MiB ({:.1} MiB reclaimed)",
+                max_mib,
+                mebibytes(prune.reclaimed_bytes)
+            ))?;
+        }
+        self.diagnostics.record(
+            "Bundler",
+            "dependency cache",
+            format!("{:.1} MiB", mebibytes(prune.size_bytes)),
+        );
+
+        layer
+            .mut_content_metadata()
+            .metadata
+            .extend(dependency_cache::usage_metadata(
+                prune.size_bytes,
+                SystemTime::now(),
+            ));
+        layer.write_content_metadata()?;
+
+        Ok(())
+    }
+}
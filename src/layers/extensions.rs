@@ -0,0 +1,199 @@
+use crate::{
+    builder::Builder,
+    data::project_toml::Extension,
+    launcher,
+    layer::{LayerContributor, LayerStore, LayerTypes},
+    platform::Family,
+    util::{
+        self,
+        checksum::{Algorithm, Checksum},
+        fetch,
+        http::Validators,
+        jar,
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+    },
+};
+use libcnb::layer::Layer;
+use std::{collections::BTreeMap, fs, path::Path};
+use toml::value::Table;
+
+const EXTENSIONS_LAYER_NAME: &str = "extensions";
+
+pub(crate) struct ExtensionsLayer<'r, 'a, 'b, L: Logger> {
+    pub(crate) builder: &'r Builder<'a, 'b, L>,
+    pub(crate) extensions: &'r [Extension],
+    pub(crate) runtime_jar_path: &'r Path,
+}
+
+impl<L: Logger> LayerContributor for ExtensionsLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        EXTENSIONS_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    // Jars copied from the app are tracked by content, as their path doesn't change with them.
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        for extension in self.extensions {
+            let checksum = extension.checksum.clone().or_else(|| {
+                let path = self.builder.project_dir.join(extension.path.as_ref()?);
+                Checksum::of_file(Algorithm::Sha256, path).ok()
+            });
+            let checksum = checksum.map_or_else(String::new, |checksum| checksum.to_string());
+            metadata.insert(
+                extension.file_name().unwrap_or_default().to_string(),
+                toml::Value::String(
+                    format!("{} {}", extension.source().unwrap_or_default(), checksum)
+                        .trim_end()
+                        .to_string(),
+                ),
+            );
+        }
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        self.extensions
+            .iter()
+            .filter_map(Extension::file_name)
+            .all(|file_name| layer_dir.join(file_name).exists())
+    }
+
+    fn expected_entries(&self) -> Option<Vec<String>> {
+        Some(
+            self.extensions
+                .iter()
+                .filter_map(Extension::file_name)
+                .map(String::from)
+                .collect(),
+        )
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        for extension in self.extensions {
+            if let Some(file_name) = extension.file_name() {
+                self.builder
+                    .install_extension(extension, &layer_dir.join(file_name))?;
+            }
+        }
+
+        Ok(Table::new())
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        for (key, value) in self.builder.extensions_env(self.runtime_jar_path)? {
+            self.builder.diagnostics.record("Launch", key, &value);
+            layer_env::write_launch_env(layer_dir, key, Modification::Override, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Installs the extension jars from `project.toml`, which the launcher appends to the
+    /// classpath of the function runtime.
+    pub fn contribute_extensions_layer(
+        &self,
+        runtime_jar_path: impl AsRef<Path>,
+    ) -> anyhow::Result<Option<Layer>> {
+        if self.config.extensions.is_empty() {
+            return Ok(None);
+        }
+
+        let section = Section::new(self.logger, "Installing function extensions")?;
+        let layer = self.contribute_layer(&ExtensionsLayer {
+            builder: self,
+            extensions: &self.config.extensions,
+            runtime_jar_path: runtime_jar_path.as_ref(),
+        })?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+
+    fn install_extension(&self, extension: &Extension, dst: &Path) -> anyhow::Result<()> {
+        let source = extension.source().unwrap_or_default();
+        match &extension.url {
+            Some(url) => {
+                self.logger
+                    .info(format!("Downloading extension from {}", url))?;
+                fetch::fetch(
+                    &self.http_client()?,
+                    url,
+                    dst,
+                    None,
+                    &Validators::default(),
+                    &self.download_retry_policy()?,
+                    |key| self.ambient_env(key),
+                    |_| {},
+                )
+                .map_err(|err| {
+                    anyhow::anyhow!("Could not download extension from {}: {}", url, err)
+                })?;
+            }
+            None => {
+                self.logger.info(format!("Copying extension {}", source))?;
+                util::copy_verified(self.project_dir.join(source), dst, None)
+                    .map_err(|err| anyhow::anyhow!("Could not copy extension: {}", err))?;
+            }
+        }
+
+        if let Some(checksum) = &extension.checksum {
+            let actual = Checksum::of_file(checksum.algorithm, dst)?;
+            if actual != *checksum {
+                fs::remove_file(dst)?;
+                anyhow::bail!(
+                    "Checksum of extension {} does not match\nExpected: {}\nActual:   {}",
+                    source,
+                    checksum,
+                    actual
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Launch environment that makes the launcher append the extension jars to the runtime
+    /// classpath, empty without extensions. Build time invocations of the runtime use it too.
+    pub(crate) fn extensions_env(
+        &self,
+        runtime_jar_path: &Path,
+    ) -> anyhow::Result<BTreeMap<&'static str, String>> {
+        let mut env = BTreeMap::new();
+        if self.config.extensions.is_empty() {
+            return Ok(env);
+        }
+
+        let layer_dir = self.layers.layer_dir(EXTENSIONS_LAYER_NAME);
+        let classpath = self
+            .config
+            .extensions
+            .iter()
+            .filter_map(Extension::file_name)
+            .map(|file_name| layer_dir.join(file_name).display().to_string())
+            .collect::<Vec<_>>()
+            .join(Family::current().classpath_separator());
+        let main_class = jar::main_class(runtime_jar_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "The function runtime jar {} has no Main-Class, extensions can't be added to its \
+                 classpath",
+                runtime_jar_path.display()
+            )
+        })?;
+        env.insert(launcher::EXTENSIONS_CLASSPATH_ENV, classpath);
+        env.insert(launcher::RUNTIME_MAIN_CLASS_ENV, main_class);
+
+        Ok(env)
+    }
+}
@@ -0,0 +1,806 @@
+use crate::{
+    build::{
+        self,
+        bundler::BundlerInvocation,
+        dependency_audit::{self, Dependency},
+    },
+    build_system::{self, BuildSystem},
+    builder::{report, Builder, POST_BUNDLE_HOOK_PATH},
+    data::function_bundle,
+    dependency_cache, detect,
+    error::Error,
+    function_dependencies, launcher,
+    layer::{LayerContributor, LayerStore, LayerTypes},
+    layers::{
+        artifact_cache::ARTIFACT_CACHE_LAYER_NAME,
+        dependency_cache::DependencyCacheLayer,
+        function_dependencies::{app_jar_sha256s, FUNCTION_DEPENDENCIES_LAYER_NAME},
+    },
+    observability,
+    util::{
+        self, digest,
+        disk_usage::{self, mebibytes},
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+        process::{self, OutputLine},
+    },
+};
+use libcnb::layer::Layer;
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use toml::value::Table;
+
+pub(crate) const FUNCTION_BUNDLE_LAYER_NAME: &str = "function-bundle";
+const BUNDLE_BASE_DIGEST_KEY: &str = "bundle_base_digest";
+const MODULE_JARS_KEY: &str = "module_jars";
+/// Number of jars listed in the function bundle size report.
+const LARGEST_JARS_REPORTED: usize = 5;
+
+pub(crate) struct FunctionBundleLayer<'r, 'a, 'b, L: Logger> {
+    pub(crate) builder: &'r Builder<'a, 'b, L>,
+    pub(crate) runtime_jar_path: &'r Path,
+    pub(crate) multiple_functions: bool,
+    pub(crate) bundle_digest: String,
+    /// Metadata of the cached layer, which tells which module jars an existing bundle was built
+    /// from.
+    pub(crate) previous_metadata: Table,
+}
+
+impl<L: Logger> LayerContributor for FunctionBundleLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        FUNCTION_BUNDLE_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("bundle_digest"),
+            toml::Value::String(self.bundle_digest.clone()),
+        );
+
+        metadata
+    }
+
+    // A bundle linking to dependencies that weren't restored has to be bundled again.
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        function_dependencies::links_resolve(layer_dir)
+    }
+
+    fn updates_in_place(&self) -> bool {
+        self.builder.incremental_bundling()
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let incremental = self.builder.incremental_bundling();
+        let module_jars = if incremental {
+            module_jar_digests(&self.builder.project_dir)?
+        } else {
+            BTreeMap::new()
+        };
+        let base_digest = bundle_base_digest(
+            &self.builder.project_dir,
+            &self.builder.runtime().checksum().to_string(),
+            self.multiple_functions,
+        );
+
+        let changed_jars = if incremental
+            && self.previous_metadata.get(BUNDLE_BASE_DIGEST_KEY)
+                == Some(&toml::Value::String(base_digest.clone()))
+        {
+            changed_module_jars(
+                &module_jars_from_metadata(&self.previous_metadata),
+                &module_jars,
+            )
+        } else {
+            None
+        };
+        match &changed_jars {
+            Some(changed_jars) => self.builder.logger.info(format!(
+                "Updating function bundle incrementally, {} module jar(s) changed",
+                changed_jars.len()
+            ))?,
+            // The layer may still hold an outdated bundle, see `updates_in_place`.
+            None => {
+                fs::remove_dir_all(layer_dir)?;
+                fs::create_dir_all(layer_dir)?;
+            }
+        }
+
+        self.builder.bundle_functions(
+            self.runtime_jar_path,
+            layer_dir,
+            self.multiple_functions,
+            changed_jars.as_deref(),
+        )?;
+        self.builder
+            .run_post_bundle_hook(layer_dir, self.multiple_functions)?;
+        self.builder
+            .validate_function_bundles(layer_dir, self.multiple_functions)?;
+
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from(BUNDLE_BASE_DIGEST_KEY),
+            toml::Value::String(base_digest),
+        );
+        metadata.insert(
+            String::from(MODULE_JARS_KEY),
+            toml::Value::Table(
+                module_jars
+                    .into_iter()
+                    .map(|(jar, sha256)| (jar, toml::Value::String(sha256)))
+                    .collect(),
+            ),
+        );
+
+        Ok(metadata)
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        layer_env::write_launch_env(
+            layer_dir,
+            launcher::FUNCTION_BUNDLE_DIR_ENV,
+            Modification::Override,
+            layer_dir.to_string_lossy(),
+        )?;
+
+        self.builder
+            .log_function_bundles(layer_dir, self.multiple_functions)?;
+
+        let runtime_version = self.builder.runtime_version();
+        if self.multiple_functions {
+            for bundle_dir in function_bundle::bundle_dirs(layer_dir)? {
+                write_function_launch_env(
+                    layer_dir,
+                    Some(&function_bundle::process_type(&bundle_dir)),
+                    &function_bundle::Toml::from_bundle_dir(&bundle_dir)?.function,
+                    &runtime_version,
+                )?;
+            }
+        } else {
+            write_function_launch_env(
+                layer_dir,
+                None,
+                &function_bundle::Toml::from_bundle_dir(layer_dir)?.function,
+                &runtime_version,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Whether the bundler can update an existing bundle, as the runtime describes it or
+    /// `[metadata.bundler]` otherwise.
+    fn incremental_bundling(&self) -> bool {
+        self.runtime_descriptor
+            .borrow()
+            .bundle
+            .incremental
+            .unwrap_or(self.buildpack_toml_metadata.bundler.incremental)
+    }
+
+    pub fn contribute_function_bundle_layer(
+        &self,
+        runtime_jar_path: impl AsRef<Path>,
+        multiple_functions: bool,
+        bundle_digest: String,
+    ) -> anyhow::Result<Layer> {
+        let section = Section::new(self.logger, "Detecting function")?;
+
+        self.contribute_layer(&DependencyCacheLayer)?;
+        let previous_metadata = self.layers.metadata(FUNCTION_BUNDLE_LAYER_NAME)?;
+        let function_bundle_layer = self.contribute_layer(&FunctionBundleLayer {
+            builder: self,
+            runtime_jar_path: runtime_jar_path.as_ref(),
+            multiple_functions,
+            bundle_digest,
+            previous_metadata,
+        })?;
+        // Windows stacks can't link into another layer, so their bundles stay in one piece.
+        if cfg!(target_family = "unix") {
+            self.contribute_function_dependencies_layer(function_bundle_layer.as_path())?;
+        }
+        self.log_bundle_size(function_bundle_layer.as_path())?;
+        self.audit_dependencies(function_bundle_layer.as_path())?;
+        section.finish()?;
+
+        Ok(function_bundle_layer)
+    }
+
+    /// Warns about snapshot dependencies, which make rebuilds of the same commit differ, and
+    /// function SDK versions that `buildpack.toml` lists as vulnerable.
+    fn audit_dependencies(&self, bundle_layer_dir: &Path) -> anyhow::Result<()> {
+        let jars = function_dependencies::dependency_jars(
+            bundle_layer_dir,
+            &self.layers.layer_dir(FUNCTION_DEPENDENCIES_LAYER_NAME),
+            &app_jar_sha256s(&self.project_dir)?,
+        )?;
+        let dependencies = jars
+            .iter()
+            .filter_map(|jar| Dependency::from_jar_name(&jar.path.file_name()?.to_string_lossy()))
+            .chain(dependency_audit::gradle_locked_dependencies(
+                &self.project_dir,
+            )?);
+        let findings = dependency_audit::audit(
+            dependencies,
+            &self.buildpack_toml_metadata.sdk.vulnerable_versions,
+        );
+
+        findings.log(self.logger)
+    }
+
+    /// Runs the bundler of the function runtime, which writes the function bundles to `layer_dir`.
+    /// With `changed_jars`, the bundler updates the existing bundles in `layer_dir` incrementally.
+    fn bundle_functions(
+        &self,
+        runtime_jar_path: &Path,
+        layer_dir: &Path,
+        multiple_functions: bool,
+        changed_jars: Option<&[PathBuf]>,
+    ) -> anyhow::Result<()> {
+        let bundle_target = self.bundle_target()?;
+        self.diagnostics
+            .record("Bundler", "target", bundle_target.display());
+        let java_options = self.settings().bundler_java_options();
+        self.diagnostics
+            .record("Bundler", "java options", java_options.join(" "));
+        let dependency_cache_dir = self.dependency_cache_dir();
+        let mut command = Command::new("java");
+        dependency_cache::configure(
+            &mut command,
+            &dependency_cache_dir,
+            self.ambient_env("MAVEN_OPTS").map(OsString::from),
+        );
+        // Before the configured options, so those can point the bundler elsewhere.
+        command.arg(dependency_cache::maven_repo_local(&dependency_cache_dir));
+        let changed_jars = changed_jars.map(|jars| {
+            jars.iter()
+                .map(|jar| self.project_dir.join(jar))
+                .collect::<Vec<_>>()
+        });
+        let invocation = BundlerInvocation {
+            runtime_jar: runtime_jar_path,
+            target: &bundle_target,
+            output_dir: layer_dir,
+            java_options: &java_options,
+            multiple_functions,
+            changed_jars: changed_jars.as_deref(),
+        };
+        let timeout = self.settings().bundler_timeout()?;
+        self.diagnostics
+            .record("Bundler", "timeout", format!("{}s", timeout.as_secs()));
+        let bundler_run = invocation
+            .run(
+                self.command_runner.as_ref(),
+                command,
+                timeout,
+                &mut |line| {
+                    // Bundler output is informational, failing to print it must not fail the build.
+                    let _ = self.logger.info(format!("    {}", line));
+                },
+            )
+            .map_err(|err| {
+                let err = match err.kind() {
+                    io::ErrorKind::NotFound => Error::JdkNotFound,
+                    io::ErrorKind::TimedOut => Error::BundlerTimeout(timeout),
+                    _ => Error::Io(err),
+                };
+                report(self.logger, err)
+            })?;
+        self.record_dependency_cache_use()?;
+
+        match bundler_run.exit_code {
+            Some(0) => self.logger.info("Detection successful"),
+            Some(code) => {
+                let stderr_tail = bundler_run.stderr_tail;
+                let descriptor = self.runtime_descriptor.borrow();
+                let message = descriptor
+                    .bundle
+                    .legacy_exit_code(code)
+                    .and_then(|code| self.buildpack_toml_metadata.bundler.exit_code_message(code));
+                if let Some(message) = message {
+                    let mut body = message.render(code, &stderr_tail);
+                    if code == descriptor.bundle.no_functions_exit_code() {
+                        body.push_str(&self.no_functions_hints());
+                    }
+                    let _ = self.logger.error(&message.header, body);
+                    return Err(Error::BundlerExit { code, stderr_tail }.into());
+                }
+                if let Some(description) = descriptor
+                    .bundle
+                    .exit_code(code)
+                    .and_then(|exit_code| exit_code.description.as_ref())
+                {
+                    self.logger
+                        .info(format!("The bundler exited with {}: {}", code, description))?;
+                }
+
+                Err(report(
+                    self.logger,
+                    Error::BundlerExit { code, stderr_tail },
+                ))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Runs the post-bundle hook of the app, if there is one, once per function bundle. Its output
+    /// goes to the build log, and a failure fails the build.
+    fn run_post_bundle_hook(
+        &self,
+        layer_dir: &Path,
+        multiple_functions: bool,
+    ) -> anyhow::Result<()> {
+        let hook = self.project_dir.join(POST_BUNDLE_HOOK_PATH);
+        if !hook.is_file() {
+            return Ok(());
+        }
+
+        let bundle_dirs = if multiple_functions {
+            function_bundle::bundle_dirs(layer_dir)?
+        } else {
+            vec![layer_dir.to_path_buf()]
+        };
+        let timeout = self.settings().bundler_timeout()?;
+        for bundle_dir in bundle_dirs {
+            self.logger.info(format!(
+                "Running {} for {}",
+                POST_BUNDLE_HOOK_PATH,
+                bundle_dir.display()
+            ))?;
+            let mut command = Command::new(&hook);
+            command.arg(&bundle_dir).current_dir(&self.project_dir);
+
+            let exit_status = process::run_streaming(&mut command, Some(timeout), |line| {
+                let line = match line {
+                    OutputLine::Stdout(line) | OutputLine::Stderr(line) => line,
+                };
+                // Hook output is informational, failing to print it must not fail the build.
+                let _ = self.logger.info(format!("    {}", line));
+            })
+            .map_err(|err| {
+                let reason = match err.kind() {
+                    io::ErrorKind::PermissionDenied => String::from("is not executable"),
+                    io::ErrorKind::TimedOut => {
+                        format!("did not finish within {} seconds", timeout.as_secs())
+                    }
+                    _ => format!("could not be started: {}", err),
+                };
+                report(self.logger, Error::PostBundleHook { reason })
+            })?;
+
+            if !exit_status.success() {
+                return Err(report(
+                    self.logger,
+                    Error::PostBundleHook {
+                        reason: format!("exited with {}", exit_status),
+                    },
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Hints from inspecting the project, rendered for the "No functions found" message.
+    fn no_functions_hints(&self) -> String {
+        match detect::no_functions_hints(&self.project_dir) {
+            Ok(hints) if !hints.is_empty() => std::iter::once(String::from("\nHints:"))
+                .chain(hints.iter().map(|hint| format!("- {}", hint)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Ok(_) => String::new(),
+            Err(err) => {
+                let _ = self
+                    .logger
+                    .debug(format!("Could not inspect the project: {}", err));
+                String::new()
+            }
+        }
+    }
+
+    /// What the bundler inspects: the app for Maven projects, the application jar for Gradle
+    /// projects. Runs the Gradle build if no preceding buildpack did.
+    fn bundle_target(&self) -> anyhow::Result<PathBuf> {
+        let app_dir = &self.project_dir;
+        if BuildSystem::detect(app_dir) != Some(BuildSystem::Gradle) {
+            return Ok(app_dir.clone());
+        }
+
+        if let Some(jar) = build_system::gradle_jar(app_dir)? {
+            return Ok(jar);
+        }
+
+        self.logger
+            .info("No Gradle build output found, building the application jar")?;
+        let mut command = build_system::gradle_command(app_dir);
+        dependency_cache::configure(
+            &mut command,
+            &self.dependency_cache_dir(),
+            self.ambient_env("MAVEN_OPTS").map(OsString::from),
+        );
+        let exit_status = process::run_streaming(&mut command, None, |line| {
+            let line = match line {
+                OutputLine::Stdout(line) | OutputLine::Stderr(line) => line,
+            };
+            let _ = self.logger.info(format!("    {}", line));
+        })
+        .map_err(|err| anyhow::anyhow!("Could not run Gradle: {}", err))?;
+        if !exit_status.success() {
+            anyhow::bail!("The Gradle build failed, see the output above");
+        }
+
+        build_system::gradle_jar(app_dir)?.ok_or_else(|| {
+            anyhow::anyhow!("The Gradle build did not produce an application jar in build/libs")
+        })
+    }
+
+    fn log_function_bundles(
+        &self,
+        layer_dir: &Path,
+        multiple_functions: bool,
+    ) -> anyhow::Result<()> {
+        if multiple_functions {
+            for bundle_dir in function_bundle::bundle_dirs(layer_dir)? {
+                self.log_function_bundle(&bundle_dir)?;
+            }
+        } else {
+            self.log_function_bundle(layer_dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Logs the size of the function bundle layer and its largest jars, and warns when it exceeds
+    /// the configured threshold.
+    fn log_bundle_size(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        let mut usage = disk_usage::disk_usage(layer_dir, LARGEST_JARS_REPORTED)?;
+        // The bundle only links to its dependencies, which count towards its size all the same.
+        let dependencies_dir = self.layers.layer_dir(FUNCTION_DEPENDENCIES_LAYER_NAME);
+        if cfg!(target_family = "unix") && dependencies_dir.is_dir() {
+            let dependencies = disk_usage::disk_usage(&dependencies_dir, LARGEST_JARS_REPORTED)?;
+            usage.total_bytes += dependencies.total_bytes;
+            usage
+                .largest_jars
+                .extend(dependencies.largest_jars.into_iter().map(|(path, size)| {
+                    let file_name = path.file_name().unwrap_or_default();
+                    (
+                        Path::new(FUNCTION_DEPENDENCIES_LAYER_NAME).join(file_name),
+                        size,
+                    )
+                }));
+            usage
+                .largest_jars
+                .sort_by(|(a_path, a_size), (b_path, b_size)| {
+                    b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+                });
+            usage.largest_jars.truncate(LARGEST_JARS_REPORTED);
+        }
+        self.logger.info(format!(
+            "Function bundle size: {:.1} MiB",
+            mebibytes(usage.total_bytes)
+        ))?;
+        for (path, size) in &usage.largest_jars {
+            self.logger.debug(format!(
+                "    {:.1} MiB {}",
+                mebibytes(*size),
+                path.display()
+            ))?;
+        }
+
+        let warning_mib = self.settings().bundle_size_warning_mib()?;
+        if usage.total_bytes > warning_mib * 1024 * 1024 {
+            let largest_jars = usage
+                .largest_jars
+                .iter()
+                .map(|(path, size)| format!("  {:.1} MiB {}", mebibytes(*size), path.display()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            self.logger.warning(
+                "Large function bundle",
+                format!(
+                    "The function bundle is {:.1} MiB, more than the threshold of {} MiB. \
+                     Large bundles increase image size and startup time, and are often caused by \
+                     dependencies that are accidentally bundled, like a second copy of the \
+                     function runtime. The largest jars are:\n{}\n\n\
+                     Set SF_FUNCTIONS_BUNDLE_SIZE_WARNING_MIB to change the threshold.",
+                    mebibytes(usage.total_bytes),
+                    warning_mib,
+                    largest_jars
+                ),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the written function bundles, so a broken bundle fails the build rather than the
+    /// launch.
+    fn validate_function_bundles(
+        &self,
+        layer_dir: &Path,
+        multiple_functions: bool,
+    ) -> anyhow::Result<()> {
+        let bundle_dirs = if multiple_functions {
+            function_bundle::bundle_dirs(layer_dir)?
+        } else {
+            vec![layer_dir.to_path_buf()]
+        };
+        let build_dirs = [
+            self.ctx.buildpack_dir.clone(),
+            self.dependency_cache_dir(),
+            self.layers.layer_dir(ARTIFACT_CACHE_LAYER_NAME),
+            env::temp_dir(),
+        ];
+
+        let mut problems = Vec::new();
+        for bundle_dir in &bundle_dirs {
+            let prefix = match bundle_dir.strip_prefix(layer_dir) {
+                Ok(relative) if multiple_functions => format!("{}: ", relative.display()),
+                _ => String::new(),
+            };
+            problems.extend(
+                build::validate::validate_bundle(bundle_dir, &build_dirs)?
+                    .into_iter()
+                    .map(|problem| format!("{}{}", prefix, problem)),
+            );
+        }
+        if !problems.is_empty() {
+            return Err(report(
+                self.logger,
+                Error::InvalidFunctionBundle { problems },
+            ));
+        }
+
+        self.logger.debug(format!(
+            "Validated {} function bundle(s)",
+            bundle_dirs.len()
+        ))
+    }
+
+    fn log_function_bundle(&self, bundle_dir: &Path) -> anyhow::Result<()> {
+        let function_bundle_toml = function_bundle::Toml::from_bundle_dir(bundle_dir)?;
+
+        self.logger.header(format!(
+            "Detected function: {}",
+            function_bundle_toml.function.class
+        ))?;
+        self.logger.info(format!(
+            "Payload type: {}",
+            function_bundle_toml.function.payload_class
+        ))?;
+        self.logger.info(format!(
+            "Return type: {}",
+            function_bundle_toml.function.return_class
+        ))?;
+        if let Some(api_version) = &function_bundle_toml.salesforce.api_version {
+            self.logger
+                .debug(format!("Salesforce API version: {}", api_version))?;
+        }
+        match (
+            &self.config.api_version,
+            &function_bundle_toml.salesforce.api_version,
+        ) {
+            (Some(configured), Some(detected)) if configured != detected => {
+                self.logger.warning(
+                    "Salesforce API version mismatch",
+                    format!(
+                        "project.toml declares Salesforce API version {}, but the function was \
+                         detected with version {}.",
+                        configured, detected
+                    ),
+                )?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Launch environment describing the payload and return types of `function`.
+fn function_contract_env(function: &function_bundle::Function) -> [(&'static str, &str); 2] {
+    [
+        (launcher::PAYLOAD_CLASS_ENV, &function.payload_class),
+        (launcher::RETURN_CLASS_ENV, &function.return_class),
+    ]
+}
+
+/// Writes the launch environment of `function` into the function bundle layer, scoped to
+/// `process_type` in multiple functions mode. The contract lets tooling and routing layers
+/// inspect a running container, the OpenTelemetry settings are only defaults.
+fn write_function_launch_env(
+    layer_dir: &Path,
+    process_type: Option<&str>,
+    function: &function_bundle::Function,
+    runtime_version: &str,
+) -> io::Result<()> {
+    let write = |name: &str, modification, value: &str| match process_type {
+        Some(process_type) => {
+            layer_env::write_process_launch_env(layer_dir, process_type, name, modification, value)
+        }
+        None => layer_env::write_launch_env(layer_dir, name, modification, value),
+    };
+
+    for (name, value) in function_contract_env(function) {
+        write(name, Modification::Override, value)?;
+    }
+    for (name, value) in observability::otel_env(&function.class, runtime_version) {
+        write(name, Modification::Default, &value)?;
+    }
+
+    Ok(())
+}
+
+/// Digest over the compiled application, the build files, and everything that influences how the
+/// runtime bundles it. Independent of the build context, so it can run while the runtime downloads.
+pub fn function_bundle_digest(
+    app_dir: &Path,
+    runtime_checksum: &str,
+    multiple_functions: bool,
+) -> anyhow::Result<String> {
+    let is_build_output = |path: &Path| {
+        path.extension().map_or(false, |extension| {
+            extension == "class" || extension == "jar"
+        })
+    };
+    let inputs = vec![
+        bundle_base_digest(app_dir, runtime_checksum, multiple_functions),
+        digest::digest_tree(app_dir.join("target"), is_build_output)?,
+        digest::digest_tree(app_dir.join("build"), is_build_output)?,
+    ];
+
+    Ok(util::sha256(inputs.join("\n").as_bytes()))
+}
+
+/// Digest of the bundle inputs other than the compiled classes and jars. An existing bundle can
+/// only be updated incrementally while it's unchanged.
+fn bundle_base_digest(app_dir: &Path, runtime_checksum: &str, multiple_functions: bool) -> String {
+    let mut inputs = vec![runtime_checksum.to_string(), multiple_functions.to_string()];
+    for file in detect::BUILD_FILES
+        .iter()
+        .chain(&["project.toml", POST_BUNDLE_HOOK_PATH])
+    {
+        if let Ok(contents) = fs::read(app_dir.join(file)) {
+            inputs.push(format!("{}:{}", file, util::sha256(&contents)));
+        }
+    }
+
+    util::sha256(inputs.join("\n").as_bytes())
+}
+
+/// sha256 of every Maven module jar, keyed by its path relative to the app.
+fn module_jar_digests(app_dir: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    build_system::maven_module_jars(app_dir)?
+        .into_iter()
+        .map(|jar| {
+            let contents = fs::read(app_dir.join(&jar))?;
+            Ok((jar.to_string_lossy().into_owned(), util::sha256(&contents)))
+        })
+        .collect()
+}
+
+fn module_jars_from_metadata(metadata: &Table) -> BTreeMap<String, String> {
+    metadata
+        .get(MODULE_JARS_KEY)
+        .and_then(toml::Value::as_table)
+        .into_iter()
+        .flatten()
+        .filter_map(|(jar, sha256)| Some((jar.clone(), sha256.as_str()?.to_string())))
+        .collect()
+}
+
+/// The module jars that changed since `previous`, `None` when the bundle has to be built from
+/// scratch because modules were added or removed, or nothing is known about the previous build.
+fn changed_module_jars(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Option<Vec<PathBuf>> {
+    if previous.is_empty() || !previous.keys().eq(current.keys()) {
+        return None;
+    }
+
+    let changed: Vec<PathBuf> = current
+        .iter()
+        .filter(|(jar, sha256)| previous.get(*jar) != Some(sha256))
+        .map(|(jar, _)| PathBuf::from(jar))
+        .collect();
+
+    if changed.is_empty() {
+        None
+    } else {
+        Some(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_module_jars_requires_same_modules() {
+        let jars = |entries: &[(&str, &str)]| {
+            entries
+                .iter()
+                .map(|(jar, sha256)| (jar.to_string(), sha256.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let previous = jars(&[("api/target/api.jar", "a1"), ("fn/target/fn.jar", "f1")]);
+
+        assert_eq!(
+            changed_module_jars(
+                &previous,
+                &jars(&[("api/target/api.jar", "a1"), ("fn/target/fn.jar", "f2")])
+            ),
+            Some(vec![PathBuf::from("fn/target/fn.jar")])
+        );
+        assert_eq!(changed_module_jars(&previous, &previous), None);
+        assert_eq!(
+            changed_module_jars(&previous, &jars(&[("api/target/api.jar", "a2")])),
+            None
+        );
+        assert_eq!(changed_module_jars(&BTreeMap::new(), &previous), None);
+    }
+
+    #[test]
+    fn module_jars_round_trip_through_metadata() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        fs::create_dir_all(app_dir.path().join("target"))?;
+        fs::write(app_dir.path().join("target/app.jar"), "app")?;
+        let module_jars = module_jar_digests(app_dir.path())?;
+        assert_eq!(
+            module_jars.get("target/app.jar"),
+            Some(&util::sha256(b"app"))
+        );
+
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from(MODULE_JARS_KEY),
+            toml::Value::try_from(&module_jars)?,
+        );
+        assert_eq!(module_jars_from_metadata(&metadata), module_jars);
+
+        Ok(())
+    }
+
+    #[test]
+    fn function_bundle_digest_changes_with_classes_and_runtime() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        fs::write(app_dir.path().join("pom.xml"), "<project/>")?;
+        fs::create_dir_all(app_dir.path().join("target/classes"))?;
+        let digest = || function_bundle_digest(app_dir.path(), "ABCDEF", false);
+
+        let initial = digest()?;
+        assert_eq!(digest()?, initial);
+
+        fs::write(app_dir.path().join("target/classes/Function.class"), "v1")?;
+        let compiled = digest()?;
+        assert_ne!(compiled, initial);
+
+        assert_ne!(
+            function_bundle_digest(app_dir.path(), "123456", false)?,
+            compiled
+        );
+        assert_ne!(
+            function_bundle_digest(app_dir.path(), "ABCDEF", true)?,
+            compiled
+        );
+
+        Ok(())
+    }
+}
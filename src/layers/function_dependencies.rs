@@ -0,0 +1,114 @@
+use crate::{
+    build_system,
+    builder::Builder,
+    function_dependencies::{self, DependencyJar},
+    layer::{LayerContributor, LayerStore, LayerTypes},
+    util::{
+        checksum::{Algorithm, Checksum},
+        logger::Logger,
+        reproducible,
+    },
+};
+use libcnb::platform::Platform;
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+use toml::value::Table;
+
+pub(crate) const FUNCTION_DEPENDENCIES_LAYER_NAME: &str = "function-dependencies";
+
+struct FunctionDependenciesLayer<'r> {
+    bundle_layer_dir: &'r Path,
+    jars: &'r [DependencyJar],
+}
+
+impl LayerContributor for FunctionDependenciesLayer<'_> {
+    fn name(&self) -> &str {
+        FUNCTION_DEPENDENCIES_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("dependencies_digest"),
+            toml::Value::String(function_dependencies::digest(self.jars)),
+        );
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        self.jars
+            .iter()
+            .all(|jar| jar.stored_path(layer_dir).exists())
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        function_dependencies::store(self.bundle_layer_dir, layer_dir, self.jars)?;
+
+        Ok(Table::new())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Moves the third-party jars of the function bundle into a layer of their own and links them
+    /// from the bundle. Builds that only change the function's code then leave that layer, and
+    /// its digest in the image, as it was.
+    pub(crate) fn contribute_function_dependencies_layer(
+        &self,
+        bundle_layer_dir: &Path,
+    ) -> anyhow::Result<()> {
+        let dependencies_dir = self.layers.layer_dir(FUNCTION_DEPENDENCIES_LAYER_NAME);
+        let jars = function_dependencies::dependency_jars(
+            bundle_layer_dir,
+            &dependencies_dir,
+            &app_jar_sha256s(&self.project_dir)?,
+        )?;
+        self.contribute_layer(&FunctionDependenciesLayer {
+            bundle_layer_dir,
+            jars: &jars,
+        })?;
+
+        function_dependencies::link(bundle_layer_dir, &dependencies_dir, &jars)?;
+        // Linking touched the directories of the already normalized bundle layer.
+        let source_date_epoch = self
+            .ctx
+            .platform
+            .env()
+            .var(reproducible::SOURCE_DATE_EPOCH_ENV)
+            .ok();
+        reproducible::normalize_tree(
+            bundle_layer_dir,
+            reproducible::mtime(source_date_epoch.as_deref()),
+        )?;
+
+        self.logger.debug(format!(
+            "Linked {} dependency jar(s) from the {} layer",
+            jars.len(),
+            FUNCTION_DEPENDENCIES_LAYER_NAME
+        ))
+    }
+}
+
+/// sha256 of the jars built from the app, which stay in the function bundle layer.
+pub(crate) fn app_jar_sha256s(app_dir: &Path) -> anyhow::Result<HashSet<String>> {
+    let mut jars: Vec<PathBuf> = build_system::maven_module_jars(app_dir)?
+        .into_iter()
+        .map(|jar| app_dir.join(jar))
+        .collect();
+    // Ambiguous Gradle outputs fail the build elsewhere, here they only count as dependencies.
+    jars.extend(build_system::gradle_jar(app_dir).unwrap_or_default());
+
+    jars.iter()
+        .map(|jar| Ok(Checksum::of_file(Algorithm::Sha256, jar)?.hex))
+        .collect()
+}
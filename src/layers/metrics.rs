@@ -0,0 +1,53 @@
+use crate::{
+    builder::Builder,
+    layer::{LayerContributor, LayerTypes},
+    metrics::Metrics,
+    util::logger::Logger,
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::{fs, path::Path};
+use toml::value::Table;
+
+struct MetricsLayer {
+    metrics_toml: String,
+}
+
+impl LayerContributor for MetricsLayer {
+    fn name(&self) -> &str {
+        "build-metrics"
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: false,
+        }
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        fs::write(layer_dir.join("metrics.toml"), &self.metrics_toml)?;
+
+        Ok(Table::new())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Persists the build metrics into a launch layer when `SF_FUNCTIONS_BUILD_METRICS` is set.
+    pub fn contribute_metrics_layer(&self, metrics: &Metrics) -> anyhow::Result<Option<Layer>> {
+        if self
+            .ctx
+            .platform
+            .env()
+            .var("SF_FUNCTIONS_BUILD_METRICS")
+            .is_err()
+        {
+            return Ok(None);
+        }
+
+        self.contribute_layer(&MetricsLayer {
+            metrics_toml: metrics.to_toml()?,
+        })
+        .map(Some)
+    }
+}
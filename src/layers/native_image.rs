@@ -0,0 +1,180 @@
+use crate::{
+    build::native::{self, NativeImageInvocation, NATIVE_LAYER_NAME},
+    builder::{Builder, DESCRIBE_TIMEOUT},
+    launcher,
+    layer::{LayerContributor, LayerTypes},
+    platform::Family,
+    util::{
+        disk_usage::mebibytes,
+        jar,
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use toml::value::Table;
+
+/// Native image compilation commonly takes several minutes for a function with dependencies.
+const NATIVE_IMAGE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+struct NativeImageLayer<'r, 'a, 'b, L: Logger> {
+    builder: &'r Builder<'a, 'b, L>,
+    runtime_jar_path: &'r Path,
+    runtime_main_class: String,
+    bundle_dir: &'r Path,
+    bundle_digest: &'r str,
+    graalvm_version: String,
+}
+
+impl<L: Logger> NativeImageLayer<'_, '_, '_, L> {
+    fn binary_path(layer_dir: &Path) -> PathBuf {
+        layer_dir.join(Family::current().executable_name(native::BINARY_FILE_NAME))
+    }
+}
+
+impl<L: Logger> LayerContributor for NativeImageLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        NATIVE_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("bundle_digest"),
+            toml::Value::String(self.bundle_digest.to_string()),
+        );
+        metadata.insert(
+            String::from("graalvm_version"),
+            toml::Value::String(self.graalvm_version.clone()),
+        );
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        Self::binary_path(layer_dir).is_file()
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let logger = self.builder.logger;
+        let invocation = NativeImageInvocation {
+            runtime_jar: self.runtime_jar_path,
+            runtime_main_class: &self.runtime_main_class,
+            bundle_dir: self.bundle_dir,
+            output: &layer_dir.join(native::BINARY_FILE_NAME),
+        };
+        let result = invocation.run(
+            self.builder.command_runner.as_ref(),
+            NATIVE_IMAGE_TIMEOUT,
+            &mut |line| {
+                let _ = logger.info(format!("    {}", line));
+            },
+        );
+
+        let problem = match result {
+            Ok(exit_status) if exit_status.success() => None,
+            Ok(exit_status) => Some(format!("native-image exited with {}", exit_status)),
+            Err(err) => Some(format!("Could not run native-image: {}", err)),
+        };
+        match problem {
+            None => logger.info(format!(
+                "Created native image ({:.1} MiB)",
+                mebibytes(fs::metadata(Self::binary_path(layer_dir))?.len())
+            ))?,
+            Some(problem) => logger.warning(
+                "Native image skipped",
+                format!("{}\nThe function runs on the JVM instead.", problem),
+            )?,
+        }
+
+        Ok(Table::new())
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        let binary = Self::binary_path(layer_dir);
+        if binary.is_file() {
+            layer_env::write_launch_env(
+                layer_dir,
+                launcher::NATIVE_BINARY_ENV,
+                Modification::Override,
+                binary.display().to_string(),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Experimental: compiles the runtime and function bundle into a native executable with
+    /// GraalVM when `SF_FUNCTIONS_NATIVE` is set, which the launcher starts instead of `java`.
+    /// Without GraalVM, or if compilation fails, the function runs on the JVM as usual.
+    pub fn contribute_native_image_layer(
+        &self,
+        runtime_jar_path: &Path,
+        function_bundle_layer: &Layer,
+        bundle_digest: &str,
+        multiple_functions: bool,
+    ) -> anyhow::Result<Option<Layer>> {
+        let enabled = self.ctx.platform.env().var(native::NATIVE_ENV).ok();
+        if !native::enabled(enabled.as_deref()) {
+            return Ok(None);
+        }
+
+        let section = Section::new(self.logger, "Compiling native image (experimental)")?;
+        if multiple_functions {
+            self.logger.warning(
+                "Native image skipped",
+                "Native images are only supported for apps with a single function.",
+            )?;
+            return Ok(None);
+        }
+        let graalvm_version =
+            match native::graalvm_version(self.command_runner.as_ref(), DESCRIBE_TIMEOUT) {
+                Some(version) => version,
+                None => {
+                    self.logger.warning(
+                        "Native image skipped",
+                        format!(
+                            "{} requires a GraalVM JDK with native-image on the PATH.",
+                            native::NATIVE_ENV
+                        ),
+                    )?;
+                    return Ok(None);
+                }
+            };
+        self.diagnostics
+            .record("Runtime", "native-image version", &graalvm_version);
+        let runtime_main_class = jar::main_class(runtime_jar_path)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "The function runtime jar {} has no Main-Class to compile a native image from",
+                runtime_jar_path.display()
+            )
+        })?;
+
+        let layer = self.contribute_layer(&NativeImageLayer {
+            builder: self,
+            runtime_jar_path,
+            runtime_main_class,
+            bundle_dir: function_bundle_layer.as_path(),
+            bundle_digest,
+            graalvm_version,
+        })?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{
+    builder::Builder,
+    layer::{LayerContributor, LayerTypes},
+    observability::{self, ObservabilityConfig},
+    util::{
+        fetch,
+        http::Validators,
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+        Download,
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::path::Path;
+use toml::value::Table;
+
+const OBSERVABILITY_LAYER_NAME: &str = "observability";
+
+pub(crate) struct ObservabilityLayer<'r, 'a, 'b, L: Logger> {
+    pub(crate) builder: &'r Builder<'a, 'b, L>,
+    pub(crate) config: ObservabilityConfig,
+}
+
+impl<L: Logger> LayerContributor for ObservabilityLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        OBSERVABILITY_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    // JFR only affects the launch environment, which `finish` writes on every build.
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        if let Some(url) = &self.config.agent_url {
+            metadata.insert(String::from("agent_url"), toml::Value::String(url.clone()));
+        }
+        if let Some(sha256) = &self.config.agent_sha256 {
+            metadata.insert(
+                String::from("agent_sha256"),
+                toml::Value::String(sha256.clone()),
+            );
+        }
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        self.config.agent_url.is_none()
+            || layer_dir.join(observability::AGENT_JAR_FILE_NAME).exists()
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let mut metadata = Table::new();
+        if let Some(url) = &self.config.agent_url {
+            let actual_sha256 = self.builder.download_agent(
+                url,
+                self.config.agent_sha256.as_deref(),
+                &layer_dir.join(observability::AGENT_JAR_FILE_NAME),
+            )?;
+            metadata.insert(
+                String::from("agent_actual_sha256"),
+                toml::Value::String(actual_sha256),
+            );
+        }
+
+        Ok(metadata)
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        let java_tool_options = self
+            .config
+            .java_tool_options(&layer_dir.join(observability::AGENT_JAR_FILE_NAME))
+            .join(" ");
+        self.builder.diagnostics.record(
+            "Launch",
+            "observability JAVA_TOOL_OPTIONS",
+            &java_tool_options,
+        );
+        layer_env::write_launch_env(
+            layer_dir,
+            "JAVA_TOOL_OPTIONS",
+            Modification::Prepend,
+            java_tool_options,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Installs the APM agent from `SF_FUNCTIONS_APM_AGENT_URL` and enables a flight recording
+    /// with `SF_FUNCTIONS_ENABLE_JFR`, see [`ObservabilityConfig`].
+    pub fn contribute_observability_layer(&self) -> anyhow::Result<Option<Layer>> {
+        let config = ObservabilityConfig::from_env(|key| self.ctx.platform.env().var(key).ok());
+        if !config.is_enabled() {
+            return Ok(None);
+        }
+
+        let section = Section::new(self.logger, "Installing observability tooling")?;
+        let layer = self.contribute_layer(&ObservabilityLayer {
+            builder: self,
+            config,
+        })?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+
+    pub(crate) fn download_agent(
+        &self,
+        url: &str,
+        expected_sha256: Option<&str>,
+        dst: &Path,
+    ) -> anyhow::Result<String> {
+        self.logger
+            .info(format!("Downloading Java agent from {}", url))?;
+        let sha256 = match fetch::fetch(
+            &self.http_client()?,
+            url,
+            dst,
+            expected_sha256,
+            &Validators::default(),
+            &self.download_retry_policy()?,
+            |key| self.ambient_env(key),
+            |_| {},
+        ) {
+            Ok(Download::Modified { sha256, .. }) => Ok(sha256),
+            // Servers only answer unconditional requests with 304 when they are broken.
+            Ok(Download::NotModified) => Err(String::from("unexpected 304 Not Modified response")),
+            Err(err) => Err(err.to_string()),
+        }
+        .map_err(|reason| {
+            anyhow::anyhow!("Could not download Java agent from {}: {}", url, reason)
+        })?;
+        self.logger.info("Java agent download successful")?;
+
+        Ok(sha256)
+    }
+}
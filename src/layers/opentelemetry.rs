@@ -0,0 +1,132 @@
+use crate::{
+    builder::Builder,
+    layer::{LayerContributor, LayerTypes},
+    observability,
+    util::{
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::path::Path;
+use toml::value::Table;
+
+const OPENTELEMETRY_LAYER_NAME: &str = "opentelemetry-agent";
+
+pub(crate) struct OpenTelemetryLayer<'r, 'a, 'b, L: Logger> {
+    builder: &'r Builder<'a, 'b, L>,
+    agent_url: &'r str,
+    agent_sha256: Option<&'r str>,
+}
+
+impl<'r, 'a, 'b, L: Logger> OpenTelemetryLayer<'r, 'a, 'b, L> {
+    pub(crate) fn new(
+        builder: &'r Builder<'a, 'b, L>,
+        agent_url: &'r str,
+        agent_sha256: Option<&'r str>,
+    ) -> anyhow::Result<Self> {
+        observability::check_otel_agent_pinned(agent_url, agent_sha256)?;
+
+        Ok(OpenTelemetryLayer {
+            builder,
+            agent_url,
+            agent_sha256,
+        })
+    }
+}
+
+impl<L: Logger> LayerContributor for OpenTelemetryLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        OPENTELEMETRY_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("agent_url"),
+            toml::Value::String(self.agent_url.to_string()),
+        );
+        if let Some(sha256) = self.agent_sha256 {
+            metadata.insert(
+                String::from("agent_sha256"),
+                toml::Value::String(sha256.to_string()),
+            );
+        }
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        layer_dir
+            .join(observability::OTEL_AGENT_JAR_FILE_NAME)
+            .exists()
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let actual_sha256 = self.builder.download_agent(
+            self.agent_url,
+            self.agent_sha256,
+            &layer_dir.join(observability::OTEL_AGENT_JAR_FILE_NAME),
+        )?;
+
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("agent_actual_sha256"),
+            toml::Value::String(actual_sha256),
+        );
+
+        Ok(metadata)
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        layer_env::write_launch_env(
+            layer_dir,
+            "JAVA_TOOL_OPTIONS",
+            Modification::Prepend,
+            format!(
+                "-javaagent:{}",
+                layer_dir
+                    .join(observability::OTEL_AGENT_JAR_FILE_NAME)
+                    .display()
+            ),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Installs the OpenTelemetry Java agent from buildpack metadata when
+    /// `SF_FUNCTIONS_ENABLE_OTEL` is set, so invocations emit traces without changes to the image.
+    pub fn contribute_opentelemetry_layer(&self) -> anyhow::Result<Option<Layer>> {
+        if !observability::otel_agent_enabled(|key| self.ctx.platform.env().var(key).ok()) {
+            return Ok(None);
+        }
+
+        let opentelemetry = &self.buildpack_toml_metadata.opentelemetry;
+        let agent_url = opentelemetry.agent_url.as_deref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "SF_FUNCTIONS_ENABLE_OTEL is set, but buildpack.toml does not configure \
+                 [metadata.opentelemetry] agent_url"
+            )
+        })?;
+
+        let section = Section::new(self.logger, "Installing OpenTelemetry Java agent")?;
+        let layer = self.contribute_layer(&OpenTelemetryLayer::new(
+            self,
+            agent_url,
+            opentelemetry.agent_sha256.as_deref(),
+        )?)?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+}
@@ -0,0 +1,149 @@
+use crate::{
+    bindings::{self, RequiredBinding},
+    builder::Builder,
+    exec_d,
+    launcher::{self, ServeMode},
+    layer::{LayerContributor, LayerTypes},
+    platform::{self, Family},
+    util::{
+        layer_env::{self, Modification},
+        logger::Logger,
+    },
+};
+use libcnb::layer::Layer;
+use std::{collections::BTreeMap, fs, path::Path};
+use toml::value::Table;
+
+pub const OPT_LAYER_NAME: &str = "opt";
+pub const LAUNCHER_FILE_NAME: &str = "launch";
+
+/// The launcher and the launch environment every image gets.
+pub struct OptLayer<'p> {
+    pub buildpack_dir: &'p Path,
+    pub default_port: u16,
+    /// Launch environment defaults from `project.toml`.
+    pub env: &'p BTreeMap<String, String>,
+    pub required_bindings: &'p [RequiredBinding],
+    /// Exported so the platform router talks to the function in the protocol it serves.
+    pub protocol: ServeMode,
+}
+
+impl LayerContributor for OptLayer<'_> {
+    fn name(&self) -> &str {
+        OPT_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: true,
+            cache: false,
+        }
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        // The launcher is packaged next to the detect and build binaries of the buildpack.
+        let launcher_file_name = Family::current().executable_name(LAUNCHER_FILE_NAME);
+        let launcher_path = layer_dir.join(&launcher_file_name);
+        fs::copy(
+            self.buildpack_dir.join("bin").join(&launcher_file_name),
+            &launcher_path,
+        )?;
+        platform::set_executable(&launcher_path)?;
+
+        layer_env::write_launch_env(
+            layer_dir,
+            exec_d::DEFAULT_PORT_ENV,
+            Modification::Override,
+            self.default_port.to_string(),
+        )?;
+        layer_env::write_launch_env(
+            layer_dir,
+            launcher::PROTOCOL_ENV,
+            Modification::Override,
+            self.protocol.to_string(),
+        )?;
+        for (key, value) in self.env {
+            layer_env::write_launch_env(layer_dir, key, Modification::Default, value)?;
+        }
+        if !self.required_bindings.is_empty() {
+            layer_env::write_launch_env(
+                layer_dir,
+                bindings::REQUIRED_BINDINGS_ENV,
+                Modification::Override,
+                bindings::format_required(self.required_bindings),
+            )?;
+        }
+
+        Ok(Table::new())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    pub fn contribute_opt_layer(&self) -> anyhow::Result<Layer> {
+        let default_port = self.settings().default_port()?;
+        self.diagnostics
+            .record("Launch", "default port", default_port);
+
+        self.contribute_layer(&OptLayer {
+            buildpack_dir: &self.ctx.buildpack_dir,
+            default_port,
+            env: &self.config.env,
+            required_bindings: &self.config.required_bindings,
+            protocol: self.protocol(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layer::{Decision, LayerStore},
+        test_support::{BuildpackTomlBuilder, LayersDir},
+    };
+
+    #[test]
+    fn contribute_installs_launcher_and_launch_env() -> anyhow::Result<()> {
+        let buildpack_dir = BuildpackTomlBuilder::default().buildpack_dir()?;
+        let layers_dir = LayersDir::new()?;
+        let env = vec![(String::from("LOG_LEVEL"), String::from("debug"))]
+            .into_iter()
+            .collect();
+        let required_bindings = vec![RequiredBinding {
+            r#type: String::from("salesforce-org"),
+            keys: Vec::new(),
+        }];
+
+        let decision = layers_dir.contribute(&OptLayer {
+            buildpack_dir: buildpack_dir.path(),
+            default_port: 8080,
+            env: &env,
+            required_bindings: &required_bindings,
+            protocol: ServeMode::Grpc,
+        })?;
+
+        assert_eq!(decision, Decision::Create);
+        assert_eq!(
+            fs::read_to_string(
+                layers_dir
+                    .layer_dir(OPT_LAYER_NAME)
+                    .join(Family::current().executable_name(LAUNCHER_FILE_NAME))
+            )?,
+            "launcher"
+        );
+        let launch_env = |key| layers_dir.launch_env(OPT_LAYER_NAME, key);
+        assert_eq!(
+            launch_env(exec_d::DEFAULT_PORT_ENV).as_deref(),
+            Some("8080")
+        );
+        assert_eq!(launch_env(launcher::PROTOCOL_ENV).as_deref(), Some("grpc"));
+        assert_eq!(launch_env("LOG_LEVEL").as_deref(), Some("debug"));
+        assert_eq!(
+            launch_env(bindings::REQUIRED_BINDINGS_ENV).as_deref(),
+            Some("salesforce-org")
+        );
+
+        Ok(())
+    }
+}
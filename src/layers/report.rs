@@ -0,0 +1,71 @@
+use crate::{
+    builder::Builder,
+    data::function_bundle,
+    layer::{LayerContributor, LayerTypes},
+    report::{BuildReport, FunctionReport, RuntimeReport},
+    util::logger::Logger,
+};
+use libcnb::layer::Layer;
+use std::path::Path;
+use toml::value::Table;
+
+struct ReportLayer {
+    report: BuildReport,
+}
+
+impl LayerContributor for ReportLayer {
+    fn name(&self) -> &str {
+        "build-report"
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: false,
+        }
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        self.report.write(layer_dir)?;
+
+        Ok(Table::new())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Writes a machine-readable report of the detected functions and the runtime into a launch
+    /// layer, see [`BuildReport`].
+    pub fn contribute_report_layer(
+        &self,
+        function_bundle_layer: &Layer,
+        bundle_digest: &str,
+        multiple_functions: bool,
+    ) -> anyhow::Result<Layer> {
+        let bundle_dirs = if multiple_functions {
+            function_bundle::bundle_dirs(function_bundle_layer.as_path())?
+        } else {
+            vec![function_bundle_layer.as_path().to_path_buf()]
+        };
+        let functions = bundle_dirs
+            .into_iter()
+            .map(|bundle_dir| {
+                function_bundle::Toml::from_bundle_dir(bundle_dir)
+                    .map(|toml| FunctionReport::from(toml.function))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let runtime = &self.buildpack_toml_metadata.runtime;
+        self.contribute_layer(&ReportLayer {
+            report: BuildReport {
+                bundle_digest: bundle_digest.to_string(),
+                runtime: RuntimeReport {
+                    version: runtime.version(),
+                    checksum: runtime.checksum().to_string(),
+                    sha256: runtime.known_sha256().map(String::from),
+                },
+                functions,
+            },
+        })
+    }
+}
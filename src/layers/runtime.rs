@@ -0,0 +1,630 @@
+use crate::{
+    build::{self, runtime::RUNTIME_JAR_FILE_NAME},
+    builder::{report, Builder, EXEC_D_FILE_NAME},
+    data::runtime::VerifyPolicy,
+    error::Error,
+    exec_d, launcher,
+    layer::{self, Decision, LayerContributor, LayerStore, LayerTypes},
+    layers::artifact_cache::{ArtifactCacheLayer, ARTIFACT_CACHE_LAYER_NAME},
+    platform::{self, Family},
+    signature,
+    util::{
+        self,
+        archive::Compression,
+        artifact_store::ArtifactStore,
+        checksum::{Algorithm, Checksum},
+        disk_usage::{self, mebibytes},
+        fetch,
+        http::Validators,
+        layer_env::{self, Modification},
+        logger::{Condensed, Logger, Section},
+        progress::Throttle,
+        Download,
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use toml::value::Table;
+
+pub(crate) const RUNTIME_LAYER_NAME: &str = "sf-fx-runtime-java";
+const ARTIFACT_CACHE_MAX_ENTRIES: usize = 5;
+
+/// Where the runtime jar was downloaded from, recorded in the runtime layer metadata.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DownloadRecord {
+    url: String,
+    sha256: String,
+    validators: Validators,
+}
+
+impl DownloadRecord {
+    fn from_metadata(metadata: &Table) -> Option<Self> {
+        let string = |key: &str| {
+            metadata
+                .get(key)
+                .and_then(toml::Value::as_str)
+                .map(String::from)
+        };
+        let record = DownloadRecord {
+            url: string("download_url")?,
+            sha256: string("runtime_jar_actual_sha256")?,
+            validators: Validators {
+                etag: string("etag"),
+                last_modified: string("last_modified"),
+            },
+        };
+
+        Some(record).filter(|record| !record.validators.is_empty())
+    }
+
+    fn write_metadata(&self, metadata: &mut Table) {
+        metadata.insert(
+            String::from("download_url"),
+            toml::Value::String(self.url.clone()),
+        );
+        for (key, value) in &[
+            ("etag", &self.validators.etag),
+            ("last_modified", &self.validators.last_modified),
+        ] {
+            if let Some(value) = value {
+                metadata.insert(String::from(*key), toml::Value::String(value.clone()));
+            }
+        }
+    }
+}
+
+pub(crate) struct RuntimeLayer<'r, 'a, 'b, L: Logger> {
+    pub(crate) builder: &'r Builder<'a, 'b, L>,
+    pub(crate) artifact_store: &'r ArtifactStore,
+    pub(crate) previous_download: Option<&'r DownloadRecord>,
+}
+
+impl<L: Logger> LayerContributor for RuntimeLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        RUNTIME_LAYER_NAME
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    // A jar cached on another stack or by an older buildpack is not reused, even if it matches.
+    fn metadata(&self) -> Table {
+        let buildpack_toml_metadata = &self.builder.buildpack_toml_metadata;
+        let mut metadata = buildpack_toml_metadata.runtime.to_runtime_layer();
+        metadata.insert(
+            String::from("stack_id"),
+            toml::Value::String(self.builder.ctx.stack_id.to_string()),
+        );
+        metadata.insert(
+            String::from("buildpack_version"),
+            toml::Value::String(buildpack_toml_metadata.buildpack_version.clone()),
+        );
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        layer_dir.join(RUNTIME_JAR_FILE_NAME).exists()
+    }
+
+    // `install_runtime` removes the stale files itself, keeping an artifact that is still current.
+    fn updates_in_place(&self) -> bool {
+        true
+    }
+
+    fn expected_entries(&self) -> Option<Vec<String>> {
+        Some(build::runtime::expected_entries())
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let (actual_sha256, download) =
+            self.builder
+                .install_runtime(layer_dir, self.artifact_store, self.previous_download)?;
+
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("runtime_jar_actual_sha256"),
+            toml::Value::String(actual_sha256),
+        );
+        if let Some(download) = download {
+            download.write_metadata(&mut metadata);
+        }
+
+        Ok(metadata)
+    }
+
+    // The layer is cached, but the options might have changed since.
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        let java_tool_options = self.builder.java_tool_options()?;
+        self.builder
+            .diagnostics
+            .record("Launch", "JAVA_TOOL_OPTIONS", &java_tool_options);
+        layer_env::write_launch_env(
+            layer_dir,
+            "JAVA_TOOL_OPTIONS",
+            Modification::Prepend,
+            java_tool_options,
+        )?;
+        // A default, so the collector can still be changed for a running app.
+        match self.builder.settings().garbage_collector()? {
+            Some(gc) => {
+                self.builder.diagnostics.record("Launch", "GC", &gc);
+                layer_env::write_launch_env(layer_dir, exec_d::GC_ENV, Modification::Default, gc)?;
+            }
+            None => self
+                .builder
+                .diagnostics
+                .record("Launch", "GC", "by container memory limit"),
+        }
+        layer_env::write_launch_env(
+            layer_dir,
+            launcher::RUNTIME_VERSION_ENV,
+            Modification::Override,
+            self.builder.runtime_version(),
+        )?;
+        layer_env::write_launch_env(
+            layer_dir,
+            launcher::RUNTIME_JAR_ENV,
+            Modification::Override,
+            layer_dir.join(RUNTIME_JAR_FILE_NAME).to_string_lossy(),
+        )?;
+
+        // Derives the port, memory flags and temporary directory at container start.
+        let exec_d_dir = layer_dir.join("exec.d");
+        fs::create_dir_all(&exec_d_dir)?;
+        let family = Family::current();
+        let exec_d_path = exec_d_dir.join(family.executable_name(exec_d::FILE_NAME));
+        fs::copy(
+            self.builder
+                .ctx
+                .buildpack_dir
+                .join("bin")
+                .join(family.executable_name(EXEC_D_FILE_NAME)),
+            &exec_d_path,
+        )?;
+        platform::set_executable(&exec_d_path)?;
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Installs the function runtime. A cached runtime only gets a one line summary, the full
+    /// output is kept for installs and debug mode.
+    pub fn contribute_runtime_layer(&self) -> anyhow::Result<Layer> {
+        self.diagnostics.record(
+            "Runtime",
+            "verification",
+            format!("{:?}", self.settings().verify_policy()),
+        );
+        let artifact_store = ArtifactStore::new(self.layers.layer_dir(ARTIFACT_CACHE_LAYER_NAME));
+        // Read before the layer is recreated, so an updated runtime can be fetched conditionally.
+        let previous_download =
+            DownloadRecord::from_metadata(&self.layers.metadata(RUNTIME_LAYER_NAME)?);
+        let runtime_layer = RuntimeLayer {
+            builder: self,
+            artifact_store: &artifact_store,
+            previous_download: previous_download.as_ref(),
+        };
+
+        let cached =
+            !self.logger.is_debug() && layer::plan(&self.layers, &runtime_layer)? == Decision::Skip;
+        let section = if cached {
+            self.logger.info(format!(
+                "Reusing function runtime {} (cached)",
+                self.runtime_version()
+            ))?;
+            None
+        } else {
+            let section = Section::new(self.logger, "Installing Java function runtime")?;
+            self.logger.info(format!(
+                "Function runtime version: {}",
+                self.runtime_version()
+            ))?;
+            Some(section)
+        };
+
+        let logger = Condensed::new(self.logger, cached);
+        self.contribute_layer_with_logger(&logger, &ArtifactCacheLayer)?;
+        let runtime_layer = self.contribute_layer_with_logger(&logger, &runtime_layer)?;
+
+        for sha256 in artifact_store.prune(ARTIFACT_CACHE_MAX_ENTRIES)? {
+            self.logger
+                .debug(format!("Removed artifact {} from the cache", sha256))?;
+        }
+        if let Some(section) = section {
+            section.finish()?;
+        }
+
+        Ok(runtime_layer)
+    }
+
+    /// Copies, restores from the artifact cache, or downloads the runtime artifact into the runtime
+    /// layer and returns its actual sha256, together with the download it came from, if any.
+    /// Compressed tar archives are unpacked, with the jar inside moved to the runtime jar path.
+    fn install_runtime(
+        &self,
+        layer_dir: &Path,
+        artifact_store: &ArtifactStore,
+        previous_download: Option<&DownloadRecord>,
+    ) -> anyhow::Result<(String, Option<DownloadRecord>)> {
+        let runtime = &self.buildpack_toml_metadata.runtime;
+        let local_runtime_jar_path = self
+            .ctx
+            .platform
+            .env()
+            .var("SF_FUNCTIONS_RUNTIME_JAR")
+            .ok()
+            .map(PathBuf::from)
+            .or_else(|| {
+                runtime
+                    .path
+                    .as_ref()
+                    .map(|path| self.ctx.buildpack_dir.join(path))
+            });
+
+        let (artifact_path, compression) = build::runtime::artifact_path(
+            layer_dir,
+            &local_runtime_jar_path.as_ref().map_or_else(
+                || runtime.url.clone(),
+                |path| path.to_string_lossy().into_owned(),
+            ),
+        );
+
+        // An outdated layer keeps its contents. Only a downloaded artifact of the current runtime
+        // is kept, so a buildpack or stack update doesn't fetch it again.
+        let reclaimed = disk_usage::remove_stale(layer_dir, |path| {
+            local_runtime_jar_path.is_none()
+                && path == artifact_path
+                && runtime.checksum().matches_file(path).unwrap_or(false)
+        })?;
+        if reclaimed > 0 {
+            self.logger.info(format!(
+                "Removed files of the previous function runtime, reclaimed {:.1} MiB",
+                mebibytes(reclaimed)
+            ))?;
+        }
+
+        let (actual_sha256, download) = if let Some(local_path) = local_runtime_jar_path {
+            self.logger.info(format!(
+                "Copying function runtime from {}",
+                local_path.display()
+            ))?;
+            let actual_sha256 =
+                util::copy_verified(&local_path, &artifact_path, self.expected_runtime_sha256())
+                    .map_err(|err| report(self.logger, err))?;
+            self.logger.info("Function runtime copy successful")?;
+            (actual_sha256, None)
+        } else if artifact_path.exists() {
+            self.logger
+                .info("Using function runtime from the previous build")?;
+            let sha256 = build::runtime::installed_sha256(runtime, &artifact_path)?;
+            let download = previous_download
+                .filter(|download| download.sha256.eq_ignore_ascii_case(&sha256))
+                .cloned();
+            (sha256, download)
+        } else if self.restore_runtime(artifact_store, &artifact_path)? {
+            self.logger
+                .info("Using function runtime from the artifact cache")?;
+            let sha256 = build::runtime::installed_sha256(runtime, &artifact_path)?;
+            // Keeps the validators around for the next runtime update.
+            let download = previous_download
+                .filter(|download| download.sha256.eq_ignore_ascii_case(&sha256))
+                .cloned();
+            (sha256, download)
+        } else {
+            self.logger.info("Starting download of function runtime")?;
+            let client = self.http_client()?;
+            let download =
+                self.download_runtime(&client, &artifact_path, artifact_store, previous_download)?;
+            self.logger.info("Function runtime download successful")?;
+            self.verify_runtime_signature(&client, &download.url, &artifact_path)?;
+            // The cache only saves future downloads, failing to fill it must not fail the build.
+            if !artifact_store.contains(&download.sha256) {
+                if let Err(err) = artifact_store.store(&artifact_path, &download.sha256) {
+                    self.logger
+                        .debug(format!("Could not cache function runtime: {}", err))?;
+                }
+            }
+            (download.sha256.clone(), Some(download))
+        };
+
+        self.check_runtime_checksum(&artifact_path, &actual_sha256)?;
+        if let Some(compression) = compression {
+            self.unpack_runtime(&artifact_path, compression, layer_dir)?;
+        }
+        self.logger
+            .info("Function runtime installation successful")?;
+
+        Ok((actual_sha256, download))
+    }
+
+    /// Checks the detached signature of a downloaded runtime artifact when buildpack metadata
+    /// configures a signing key. Local and cached artifacts were verified before.
+    fn verify_runtime_signature(
+        &self,
+        client: &reqwest::blocking::Client,
+        artifact_url: &str,
+        artifact_path: &Path,
+    ) -> anyhow::Result<()> {
+        let config = match &self.buildpack_toml_metadata.runtime.signature {
+            Some(config) => config,
+            None => return Ok(()),
+        };
+
+        let pem = match (&config.public_key, &config.public_key_path) {
+            (Some(pem), _) => pem.clone(),
+            (None, Some(path)) => {
+                let path = self.ctx.buildpack_dir.join(path);
+                fs::read_to_string(&path).map_err(|err| {
+                    anyhow::anyhow!("Could not read public key {}: {}", path.display(), err)
+                })?
+            }
+            (None, None) => anyhow::bail!(
+                "[metadata.runtime.signature] requires either public_key or public_key_path"
+            ),
+        };
+        let key = signature::public_key(&pem).map_err(|err| report(self.logger, err))?;
+
+        let signature_url = config.url_for(artifact_url);
+        self.logger.debug(format!(
+            "Verifying function runtime signature from {}",
+            signature_url
+        ))?;
+        // Fetched like the artifact, so signatures can be hosted on file:// or s3:// URLs too.
+        let signature = fetch::fetch_bytes(
+            client,
+            &signature_url,
+            format!("{}.sig", artifact_path.display()),
+            &self.download_retry_policy()?,
+            |key| self.ambient_env(key),
+        );
+        let signature = signature.map_err(|err| {
+            report(
+                self.logger,
+                Error::SignatureVerification {
+                    reason: format!(
+                        "could not download the signature from {}: {}",
+                        signature_url, err
+                    ),
+                },
+            )
+        })?;
+        signature::verify_file(artifact_path, &signature, &key)
+            .map_err(|err| report(self.logger, err))?;
+        self.logger.info("Function runtime signature verified")?;
+
+        Ok(())
+    }
+
+    /// Unpacks a runtime archive into the runtime layer and moves the jar inside to the runtime jar
+    /// path. Other files of the archive stay in the `runtime` directory of the layer.
+    fn unpack_runtime(
+        &self,
+        archive_path: &Path,
+        compression: Compression,
+        layer_dir: &Path,
+    ) -> anyhow::Result<()> {
+        self.logger.info("Unpacking function runtime archive")?;
+        let jar_path = build::runtime::unpack(archive_path, compression, layer_dir)?;
+        self.logger.debug(format!(
+            "Using {} from the function runtime archive",
+            jar_path.display()
+        ))?;
+
+        Ok(())
+    }
+
+    /// Restores the runtime jar with the sha256 from buildpack metadata from the artifact cache.
+    /// Runtimes published with another checksum algorithm are never restored.
+    fn restore_runtime(
+        &self,
+        artifact_store: &ArtifactStore,
+        runtime_jar_path: &Path,
+    ) -> anyhow::Result<bool> {
+        let sha256 = match self.buildpack_toml_metadata.runtime.known_sha256() {
+            Some(sha256) => sha256,
+            None => return Ok(false),
+        };
+        match artifact_store.restore(sha256, runtime_jar_path) {
+            Ok(restored) => Ok(restored),
+            Err(err) => {
+                self.logger.debug(format!(
+                    "Could not restore cached function runtime: {}",
+                    err
+                ))?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// The `JAVA_TOOL_OPTIONS` of the launch, see
+    /// [`Settings::java_tool_options`](crate::settings::Settings::java_tool_options).
+    fn java_tool_options(&self) -> anyhow::Result<String> {
+        let settings = self.settings();
+        let profile = settings.jvm_profile()?;
+        self.diagnostics
+            .record("Launch", "JVM profile", format!("{:?}", profile));
+
+        Ok(settings.java_tool_options(profile, self.config.memory_options()))
+    }
+
+    /// Tries the primary runtime URL first, then each configured mirror in order. A URL the
+    /// previous runtime was downloaded from is asked whether its content changed since, and the
+    /// cached copy is used if it didn't.
+    fn download_runtime(
+        &self,
+        client: &reqwest::blocking::Client,
+        runtime_jar_path: &Path,
+        artifact_store: &ArtifactStore,
+        previous_download: Option<&DownloadRecord>,
+    ) -> anyhow::Result<DownloadRecord> {
+        let runtime = &self.buildpack_toml_metadata.runtime;
+        let retry_policy = self.download_retry_policy()?;
+        let mut last_err = None;
+
+        for (index, url) in runtime.urls().enumerate() {
+            if index > 0 {
+                self.logger
+                    .info(format!("Trying function runtime mirror {}", url))?;
+            }
+
+            let previous_download = previous_download.filter(|download| {
+                download.url == url && artifact_store.contains(&download.sha256)
+            });
+            let validators = previous_download
+                .map(|download| download.validators.clone())
+                .unwrap_or_default();
+            let mut throttle = Throttle::new(10, Duration::from_secs(5));
+            match fetch::fetch(
+                client,
+                url,
+                runtime_jar_path,
+                self.expected_runtime_sha256(),
+                &validators,
+                &retry_policy,
+                |key| self.ambient_env(key),
+                |progress| {
+                    if throttle.should_report(&progress, Instant::now()) {
+                        // Progress output is best effort and must not fail the download.
+                        let _ = self.logger.info(format!("Downloaded {}", progress));
+                    }
+                },
+            ) {
+                Ok(Download::Modified { sha256, validators }) => {
+                    if index > 0 {
+                        self.logger
+                            .info(format!("Downloaded function runtime from mirror {}", url))?;
+                    }
+
+                    return Ok(DownloadRecord {
+                        url: url.to_string(),
+                        sha256,
+                        validators,
+                    });
+                }
+                Ok(Download::NotModified) => {
+                    if let Some(previous_download) = previous_download {
+                        if artifact_store.restore(&previous_download.sha256, runtime_jar_path)? {
+                            self.logger.info(format!(
+                                "Function runtime at {} is unchanged, using the cached copy",
+                                url
+                            ))?;
+                            return Ok(previous_download.clone());
+                        }
+                    }
+                    self.logger.debug(format!(
+                        "{} reported no changes, but there is no cached copy",
+                        url
+                    ))?;
+                }
+                Err(err) => {
+                    self.logger
+                        .debug(format!("Download from {} failed: {}", url, err))?;
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        let err = match last_err {
+            Some(err @ Error::ChecksumMismatch { .. }) => err,
+            _ => Error::RuntimeDownload {
+                urls: runtime.urls().map(String::from).collect(),
+                attempts: retry_policy.attempts,
+            },
+        };
+
+        Err(report(self.logger, err))
+    }
+
+    /// Only strict verification rejects artifacts while they are written, and only by sha256.
+    /// Other checksum algorithms are checked once the artifact is installed.
+    fn expected_runtime_sha256(&self) -> Option<&str> {
+        let runtime = &self.buildpack_toml_metadata.runtime;
+
+        match self.settings().verify_policy() {
+            VerifyPolicy::Strict => runtime.known_sha256(),
+            VerifyPolicy::Warn | VerifyPolicy::Off => None,
+        }
+    }
+
+    fn check_runtime_checksum(
+        &self,
+        artifact_path: &Path,
+        actual_sha256: &str,
+    ) -> anyhow::Result<()> {
+        let expected = self.buildpack_toml_metadata.runtime.checksum();
+        let actual = match expected.algorithm {
+            Algorithm::Sha256 => Checksum::sha256(actual_sha256),
+            algorithm => Checksum::of_file(algorithm, artifact_path)?,
+        };
+        if actual == expected {
+            return Ok(());
+        }
+
+        match self.settings().verify_policy() {
+            VerifyPolicy::Strict => Err(report(
+                self.logger,
+                Error::ChecksumMismatch {
+                    algorithm: expected.algorithm,
+                    expected: expected.hex,
+                    actual: actual.hex,
+                },
+            )),
+            VerifyPolicy::Warn => self.logger.warning(
+                "Function runtime integrity check failed",
+                format!(
+                    r#"
+The installed function runtime does not match the expected checksum.
+Expected {0}: {1}
+Actual {0}:   {2}
+
+Unverified function runtimes are allowed for this build, so the build continues.
+Only use this runtime if you trust where it was downloaded from.
+"#,
+                    expected.algorithm, expected.hex, actual.hex
+                ),
+            ),
+            VerifyPolicy::Off => self.logger.debug(format!(
+                "Skipped function runtime verification, {} is {}",
+                actual.algorithm, actual.hex
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn download_record_round_trips_through_metadata() {
+        let record = DownloadRecord {
+            url: String::from("https://example.com/runtime.jar"),
+            sha256: String::from("abcdef"),
+            validators: Validators {
+                etag: Some(String::from("\"v1\"")),
+                last_modified: None,
+            },
+        };
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("runtime_jar_actual_sha256"),
+            toml::Value::String(record.sha256.clone()),
+        );
+        record.write_metadata(&mut metadata);
+
+        assert_eq!(DownloadRecord::from_metadata(&metadata), Some(record));
+        metadata.remove("etag");
+        assert_eq!(DownloadRecord::from_metadata(&metadata), None);
+    }
+}
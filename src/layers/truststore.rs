@@ -0,0 +1,156 @@
+use crate::{
+    builder::{report, Builder},
+    error::Error,
+    layer::{LayerContributor, LayerTypes},
+    truststore::{self, Certificate},
+    util::{
+        self,
+        layer_env::{self, Modification},
+        logger::{Logger, Section},
+    },
+};
+use libcnb::{layer::Layer, platform::Platform};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use toml::value::Table;
+
+struct TruststoreLayer<'r, 'a, 'b, L: Logger> {
+    builder: &'r Builder<'a, 'b, L>,
+    cacerts: PathBuf,
+    cacerts_sha256: String,
+    keytool: PathBuf,
+    certificates: Vec<Certificate>,
+}
+
+impl<L: Logger> LayerContributor for TruststoreLayer<'_, '_, '_, L> {
+    fn name(&self) -> &str {
+        "truststore"
+    }
+
+    fn types(&self) -> LayerTypes {
+        LayerTypes {
+            launch: true,
+            build: false,
+            cache: true,
+        }
+    }
+
+    // A JDK update ships new public CAs, so the base truststore is part of the metadata.
+    fn metadata(&self) -> Table {
+        let mut metadata = Table::new();
+        metadata.insert(
+            String::from("certificates_digest"),
+            toml::Value::String(truststore::certificates_digest(&self.certificates)),
+        );
+        metadata.insert(
+            String::from("cacerts_sha256"),
+            toml::Value::String(self.cacerts_sha256.clone()),
+        );
+
+        metadata
+    }
+
+    fn is_reusable(&self, layer_dir: &Path) -> bool {
+        layer_dir.join(truststore::FILE_NAME).exists()
+    }
+
+    fn contribute(&self, layer_dir: &Path) -> anyhow::Result<Table> {
+        let truststore_path = layer_dir.join(truststore::FILE_NAME);
+        fs::copy(&self.cacerts, &truststore_path)?;
+
+        let certs_dir = layer_dir.join(truststore::CERTS_DIR);
+        fs::create_dir_all(&certs_dir)?;
+        for certificate in &self.certificates {
+            let certificate_path = certs_dir.join(format!("{}.pem", certificate.alias));
+            fs::write(&certificate_path, &certificate.pem)?;
+            self.builder.import_certificate(
+                &self.keytool,
+                &truststore_path,
+                &certificate_path,
+                &certificate.alias,
+            )?;
+        }
+
+        Ok(Table::new())
+    }
+
+    fn finish(&self, layer_dir: &Path) -> anyhow::Result<()> {
+        layer_env::write_launch_env(
+            layer_dir,
+            "JAVA_TOOL_OPTIONS",
+            Modification::Prepend,
+            truststore::java_tool_options(&layer_dir.join(truststore::FILE_NAME)),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl<L: Logger> Builder<'_, '_, L> {
+    /// Imports the certificates from the `certs` directory of the app and the PEM bundle in
+    /// `SF_FUNCTIONS_CA_CERTS` into a truststore the function uses instead of the JDK default.
+    pub fn contribute_truststore_layer(&self) -> anyhow::Result<Option<Layer>> {
+        let bundle = self
+            .ctx
+            .platform
+            .env()
+            .var("SF_FUNCTIONS_CA_CERTS")
+            .ok()
+            .map(PathBuf::from);
+        let files = truststore::certificate_files(&self.project_dir, bundle)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        let section = Section::new(self.logger, "Installing CA certificates")?;
+        let certificates = truststore::read_certificates(&files)?;
+        // The JDK is installed by a previous buildpack, which exports JAVA_HOME to the build.
+        let java_home = std::env::var_os("JAVA_HOME")
+            .map(PathBuf::from)
+            .ok_or_else(|| report(self.logger, Error::JdkNotFound))?;
+        let cacerts = truststore::jdk_cacerts(&java_home).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Could not find the cacerts truststore of the JDK in {}",
+                java_home.display()
+            )
+        })?;
+
+        let layer = self.contribute_layer(&TruststoreLayer {
+            builder: self,
+            cacerts_sha256: util::sha256(&fs::read(&cacerts)?),
+            cacerts,
+            keytool: java_home.join("bin").join("keytool"),
+            certificates,
+        })?;
+        section.finish()?;
+
+        Ok(Some(layer))
+    }
+
+    fn import_certificate(
+        &self,
+        keytool: &Path,
+        truststore_path: &Path,
+        certificate_path: &Path,
+        alias: &str,
+    ) -> anyhow::Result<()> {
+        truststore::import_certificate(
+            self.command_runner.as_ref(),
+            keytool,
+            truststore_path,
+            certificate_path,
+            alias,
+        )
+        .map_err(|err| match err.downcast_ref::<io::Error>() {
+            Some(io_err) if io_err.kind() == io::ErrorKind::NotFound => {
+                report(self.logger, Error::JdkNotFound)
+            }
+            _ => err,
+        })?;
+
+        self.logger
+            .info(format!("Imported CA certificate {}", alias))
+    }
+}
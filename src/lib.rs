@@ -1,3 +1,35 @@
+pub mod bindings;
+pub mod build;
+pub mod build_system;
 pub mod builder;
+pub mod config;
 pub mod data;
+pub mod dependency_cache;
+pub mod detect;
+pub mod diagnostics;
+pub mod dry_run;
+pub mod error;
+pub mod exec_d;
+pub mod exit_code;
+pub mod failure;
+pub mod function_dependencies;
+pub mod labels;
+pub mod launch;
+pub mod launcher;
+pub mod layer;
+pub mod layers;
+pub mod messages;
+pub mod metrics;
+pub mod observability;
+pub mod platform;
+pub mod report;
+pub mod sbom;
+pub mod settings;
+pub mod signature;
+pub mod smoke;
+pub mod telemetry;
+#[cfg(test)]
+pub(crate) mod test_support;
+pub mod truststore;
+pub mod update_check;
 pub mod util;
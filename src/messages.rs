@@ -0,0 +1,277 @@
+use std::fmt::Display;
+
+/// User facing copy. `{name}` placeholders in the body are filled in by [`render`].
+#[derive(Debug)]
+pub struct Message {
+    pub key: &'static str,
+    pub header: &'static str,
+    pub body: &'static str,
+}
+
+const fn message(key: &'static str, header: &'static str, body: &'static str) -> Message {
+    Message { key, header, body }
+}
+
+pub const CATALOG: &[Message] = &[
+    message(
+        "error.download",
+        "Download of function runtime failed",
+        r#"
+We couldn't download the function runtime from {url}.
+
+This is usually caused by intermittent network issues. Please try again and contact us should the error persist.
+"#,
+    ),
+    message(
+        "error.unsupported_url",
+        "Download of function runtime failed",
+        r#"
+{error}.
+
+Please check the runtime URL and mirrors in [metadata.runtime] of buildpack.toml.
+"#,
+    ),
+    message(
+        "error.runtime_download",
+        "Download of function runtime failed",
+        r#"
+We couldn't download the function runtime from any of these locations after {attempts} attempt(s) each:
+{urls}
+
+This is usually caused by intermittent network issues. Please try again and contact us should the error persist.
+"#,
+    ),
+    message(
+        "error.copy",
+        "Copying of function runtime failed",
+        r#"
+We couldn't copy the function runtime from {path}.
+
+Please make sure the file exists and is readable.
+"#,
+    ),
+    message(
+        "error.checksum_mismatch",
+        "Function runtime integrity check failed",
+        r#"
+We could not verify the integrity of the installed function runtime.
+Expected {algorithm}: {expected}
+Actual {algorithm}:   {actual}
+
+Please try again and contact us should the error persist. To build with a
+pre-release runtime anyway, set SF_FUNCTIONS_ALLOW_UNVERIFIED_RUNTIME=1.
+"#,
+    ),
+    message(
+        "error.signature_verification",
+        "Function runtime signature verification failed",
+        r#"
+We could not verify the signature of the function runtime: {reason}.
+
+The runtime must be signed with the key configured in [metadata.runtime.signature] of
+buildpack.toml. A mismatch means the artifact was modified after signing or the key in the
+buildpack is outdated. Please try again and contact us should the error persist.
+"#,
+    ),
+    message(
+        "error.unknown_runtime_version",
+        "Unsupported function runtime version",
+        r#"
+{error}.
+
+Please pin one of the available versions via runtime-version in the
+[com.salesforce.functions] table of your project.toml or SF_FUNCTIONS_RUNTIME_VERSION.
+"#,
+    ),
+    message(
+        "error.jdk_not_found",
+        "No JDK found",
+        r#"
+The java executable could not be found. This buildpack relies on a JDK installed by a
+buildpack that runs before it, such as heroku/jvm. Please check the buildpack order of
+your builder.
+"#,
+    ),
+    message(
+        "error.unsupported_jdk",
+        "Unsupported JDK version",
+        r#"
+The build uses JDK {version}, but the function runtime supports {range}.
+
+The JDK is installed by a buildpack that runs before this one, such as heroku/jvm. Please
+select a supported version, e.g. with java.runtime.version in system.properties.
+"#,
+    ),
+    message(
+        "error.bundler_timeout",
+        "Function detection timed out",
+        r#"
+Function detection did not finish within {seconds} seconds and was stopped. The limit can be raised
+with the SF_FUNCTIONS_BUNDLER_TIMEOUT environment variable (in seconds).
+"#,
+    ),
+    // Known exit codes are described in buildpack.toml, see `buildpack_toml::Bundler`.
+    message(
+        "error.bundler_exit",
+        "Detection failed",
+        r#"
+Function detection failed with unexpected error code {code}.
+The output above might contain hints what caused this error to happen.
+"#,
+    ),
+    message(
+        "error.invalid_function_bundle",
+        "Invalid function bundle",
+        r#"
+The function bundle written by the function runtime would fail at launch:
+{problems}
+
+Please try again with the latest function runtime and contact us should the error persist.
+"#,
+    ),
+    message(
+        "error.smoke_test",
+        "Function verification failed",
+        r#"
+The function runtime could not load the function: {reason}.
+
+Last lines of the runtime output:
+{output}
+
+Unset SF_FUNCTIONS_VERIFY to skip this check.
+"#,
+    ),
+    message(
+        "error.post_bundle_hook",
+        "Post-bundle hook failed",
+        r#"
+The post-bundle hook in .sf-functions/post-bundle {reason}.
+The output above might contain hints what caused this error to happen.
+
+The hook must be an executable file. It runs with the directory of the function bundle as its
+only argument, and must exit with code 0 for the build to continue.
+"#,
+    ),
+    message(
+        "error.service_binding",
+        "Invalid service binding",
+        r#"
+The service bindings of the build don't satisfy [[com.salesforce.functions.bindings]] in
+project.toml: {reason}.
+
+Bindings are directories with a type file and one file per key. Please add the missing
+keys to the binding, or remove them from project.toml if the function doesn't need them.
+"#,
+    ),
+    message(
+        "error.io",
+        "Unexpected I/O error",
+        r#"
+{error}.
+
+Please try again and contact us should the error persist.
+"#,
+    ),
+    message(
+        "detect.failed",
+        "Not a Java function project",
+        "Not a Java function project: {failures}",
+    ),
+    message(
+        "detect.skipped",
+        "Not a Java function project",
+        "Skipping the Java function buildpack, these checks failed:\n{reasons}",
+    ),
+    message("detect.no_build_file", "", "No {build_files} found"),
+    message(
+        "detect.no_descriptor",
+        "",
+        "No project.toml or function.toml found",
+    ),
+    message(
+        "detect.not_a_function",
+        "",
+        r#"project.toml does not declare com.salesforce.type = "function""#,
+    ),
+    message(
+        "detect.unsupported_stack",
+        "",
+        "Unsupported stack {stack}, this buildpack supports {stacks}",
+    ),
+    message(
+        "hint.missing_sdk",
+        "",
+        "Your {build_file} is missing the function SDK dependency com.salesforce.functions:{artifact}.",
+    ),
+    message(
+        "hint.no_classes",
+        "",
+        "No compiled classes found in {classes_dir}. The app must be compiled by a buildpack that \
+         runs before this one, such as heroku/maven or heroku/gradle.",
+    ),
+    message(
+        "hint.no_function_class",
+        "",
+        "No compiled class implements {interface}. A function is a public class implementing that \
+         interface.",
+    ),
+];
+
+/// The catalog entry for `key`. Keys are fixed in the code, so an unknown one is a bug.
+pub fn get(key: &str) -> &'static Message {
+    CATALOG
+        .iter()
+        .find(|message| message.key == key)
+        .unwrap_or_else(|| panic!("No message {} in the catalog", key))
+}
+
+/// The body of `key` with its placeholders replaced by `args`.
+pub fn render(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    args.iter()
+        .fold(String::from(get(key).body), |body, (name, value)| {
+            body.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+}
+
+/// Names of the `{name}` placeholders in `template`.
+pub fn placeholders(template: &str) -> Vec<&str> {
+    template
+        .split('{')
+        .skip(1)
+        .filter_map(|part| part.split('}').next())
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn catalog_keys_are_unique() {
+        let keys: HashSet<_> = CATALOG.iter().map(|message| message.key).collect();
+
+        assert_eq!(keys.len(), CATALOG.len());
+    }
+
+    #[test]
+    fn render_fills_placeholders() {
+        assert_eq!(
+            render(
+                "detect.unsupported_stack",
+                &[("stack", &"heroku-16"), ("stacks", &"heroku-20, heroku-22")]
+            ),
+            "Unsupported stack heroku-16, this buildpack supports heroku-20, heroku-22"
+        );
+        assert_eq!(
+            placeholders(get("error.checksum_mismatch").body),
+            vec!["algorithm", "expected", "algorithm", "actual"]
+        );
+        // Table headers in the copy aren't placeholders.
+        assert_eq!(
+            placeholders("[[com.salesforce.functions.bindings]] {reason}"),
+            vec!["reason"]
+        );
+    }
+}
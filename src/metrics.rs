@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Phase {
+    pub name: String,
+    pub duration_ms: u64,
+}
+
+/// Collects how long each build phase took.
+#[derive(Default)]
+pub struct Metrics {
+    phases: RefCell<Vec<Phase>>,
+}
+
+#[derive(Serialize)]
+struct MetricsToml<'a> {
+    total_ms: u64,
+    phases: &'a [Phase],
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics::default()
+    }
+
+    pub fn measure<T>(&self, name: impl Into<String>, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(name, start.elapsed());
+
+        result
+    }
+
+    pub fn record(&self, name: impl Into<String>, duration: Duration) {
+        self.phases.borrow_mut().push(Phase {
+            name: name.into(),
+            duration_ms: duration.as_millis() as u64,
+        });
+    }
+
+    pub fn phases(&self) -> Vec<Phase> {
+        self.phases.borrow().clone()
+    }
+
+    /// Name of the phase recorded last, the one that failed when the build fails.
+    pub fn last_phase(&self) -> Option<String> {
+        self.phases.borrow().last().map(|phase| phase.name.clone())
+    }
+
+    pub fn total_ms(&self) -> u64 {
+        self.phases
+            .borrow()
+            .iter()
+            .map(|phase| phase.duration_ms)
+            .sum()
+    }
+
+    /// One aligned line per phase followed by the total.
+    pub fn summary(&self) -> Vec<String> {
+        let phases = self.phases.borrow();
+        let width = phases
+            .iter()
+            .map(|phase| phase.name.len())
+            .chain(std::iter::once("Total".len()))
+            .max()
+            .unwrap_or(0);
+
+        phases
+            .iter()
+            .map(|phase| (phase.name.as_str(), phase.duration_ms))
+            .chain(std::iter::once(("Total", self.total_ms())))
+            .map(|(name, duration_ms)| {
+                format!(
+                    "{:<width$}  {:>8.2}s",
+                    name,
+                    duration_ms as f64 / 1000.0,
+                    width = width
+                )
+            })
+            .collect()
+    }
+
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(&MetricsToml {
+            total_ms: self.total_ms(),
+            phases: &self.phases.borrow(),
+        })?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_aligns_phases_and_adds_total() {
+        let metrics = Metrics::new();
+        metrics.record("Runtime", Duration::from_millis(1500));
+        metrics.record("Function bundle", Duration::from_millis(250));
+
+        assert_eq!(
+            metrics.summary(),
+            vec![
+                "Runtime              1.50s",
+                "Function bundle      0.25s",
+                "Total                1.75s",
+            ]
+        );
+    }
+
+    #[test]
+    fn measure_records_phase_and_returns_result() -> anyhow::Result<()> {
+        let metrics = Metrics::new();
+
+        assert_eq!(metrics.last_phase(), None);
+        assert_eq!(metrics.measure("Answer", || 42), 42);
+        assert_eq!(metrics.phases()[0].name, "Answer");
+        assert_eq!(metrics.last_phase().as_deref(), Some("Answer"));
+
+        let toml = metrics.to_toml()?;
+        assert!(toml.contains("[[phases]]"));
+        assert!(toml.contains("name = \"Answer\""));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{labels, util::fetch::Location};
+use std::path::Path;
+
+/// File name of the APM agent in the observability layer.
+pub const AGENT_JAR_FILE_NAME: &str = "agent.jar";
+/// File name of the OpenTelemetry Java agent in its layer.
+pub const OTEL_AGENT_JAR_FILE_NAME: &str = "opentelemetry-javaagent.jar";
+/// Where the flight recording is dumped when the function exits.
+pub const JFR_RECORDING_PATH: &str = "/tmp/function.jfr";
+
+/// Observability tooling attached to the function JVM, from `SF_FUNCTIONS_ENABLE_JFR`,
+/// `SF_FUNCTIONS_APM_AGENT_URL` and `SF_FUNCTIONS_APM_AGENT_SHA256`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObservabilityConfig {
+    pub jfr: bool,
+    pub agent_url: Option<String>,
+    /// Without a checksum, an agent is reused for as long as its URL doesn't change.
+    pub agent_sha256: Option<String>,
+}
+
+impl ObservabilityConfig {
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let non_empty = |key| var(key).filter(|value: &String| !value.trim().is_empty());
+
+        ObservabilityConfig {
+            jfr: matches!(
+                var("SF_FUNCTIONS_ENABLE_JFR").as_deref(),
+                Some("1") | Some("true")
+            ),
+            agent_url: non_empty("SF_FUNCTIONS_APM_AGENT_URL"),
+            agent_sha256: non_empty("SF_FUNCTIONS_APM_AGENT_SHA256"),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.jfr || self.agent_url.is_some()
+    }
+
+    /// JVM flags attaching the agent installed at `agent_jar` and starting a flight recording.
+    pub fn java_tool_options(&self, agent_jar: &Path) -> Vec<String> {
+        let mut options = Vec::new();
+        if self.agent_url.is_some() {
+            options.push(format!("-javaagent:{}", agent_jar.display()));
+        }
+        if self.jfr {
+            options.push(format!(
+                "-XX:StartFlightRecording=dumponexit=true,filename={}",
+                JFR_RECORDING_PATH
+            ));
+        }
+
+        options
+    }
+}
+
+/// Fails for a remote OpenTelemetry agent without a digest, as the agent is injected into every
+/// JVM of the image.
+pub fn check_otel_agent_pinned(agent_url: &str, agent_sha256: Option<&str>) -> anyhow::Result<()> {
+    if agent_sha256.is_none() && Location::parse(agent_url)?.is_remote() {
+        anyhow::bail!(
+            "The OpenTelemetry Java agent {} has no [metadata.opentelemetry] agent_sha256 in \
+             buildpack.toml, refusing to attach an unverified agent",
+            agent_url
+        );
+    }
+
+    Ok(())
+}
+
+/// Whether `SF_FUNCTIONS_ENABLE_OTEL` asks for the OpenTelemetry Java agent.
+pub fn otel_agent_enabled(var: impl Fn(&str) -> Option<String>) -> bool {
+    matches!(
+        var("SF_FUNCTIONS_ENABLE_OTEL").as_deref(),
+        Some("1") | Some("true")
+    )
+}
+
+/// OpenTelemetry resource defaults for the process serving `function_class`, so traces are
+/// attributed to the function without configuration. Users override them in the launch
+/// environment.
+pub fn otel_env(function_class: &str, runtime_version: &str) -> [(&'static str, String); 2] {
+    [
+        ("OTEL_SERVICE_NAME", function_class.to_string()),
+        (
+            "OTEL_RESOURCE_ATTRIBUTES",
+            format!(
+                "faas.name={},{}={}",
+                function_class,
+                labels::runtime_key("version"),
+                runtime_version
+            ),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_otel_agent_pinned_requires_digest_for_remote_agents() {
+        let url = "https://repo.example.com/opentelemetry-javaagent.jar";
+
+        assert!(check_otel_agent_pinned(url, Some(&"ab".repeat(32))).is_ok());
+        assert!(check_otel_agent_pinned("file:///opt/opentelemetry-javaagent.jar", None).is_ok());
+        assert_eq!(
+            check_otel_agent_pinned(url, None).unwrap_err().to_string(),
+            "The OpenTelemetry Java agent https://repo.example.com/opentelemetry-javaagent.jar has \
+             no [metadata.opentelemetry] agent_sha256 in buildpack.toml, refusing to attach an \
+             unverified agent"
+        );
+        assert!(check_otel_agent_pinned("s3://bucket/opentelemetry-javaagent.jar", None).is_err());
+    }
+
+    #[test]
+    fn from_env_enables_jfr_and_agent() {
+        let config = ObservabilityConfig::from_env(|key| match key {
+            "SF_FUNCTIONS_ENABLE_JFR" => Some(String::from("1")),
+            "SF_FUNCTIONS_APM_AGENT_URL" => Some(String::from("https://example.com/agent.jar")),
+            "SF_FUNCTIONS_APM_AGENT_SHA256" => Some(String::from(" ")),
+            _ => None,
+        });
+
+        assert!(config.is_enabled());
+        assert_eq!(
+            config.java_tool_options(Path::new("/layers/observability/agent.jar")),
+            vec![
+                "-javaagent:/layers/observability/agent.jar",
+                "-XX:StartFlightRecording=dumponexit=true,filename=/tmp/function.jfr",
+            ]
+        );
+        assert_eq!(config.agent_sha256, None);
+        assert!(!ObservabilityConfig::from_env(|_| None).is_enabled());
+    }
+
+    #[test]
+    fn otel_env_names_service_after_function_class() {
+        let env = otel_env("com.example.OrderFunction", "1.0.0");
+
+        assert_eq!(
+            env[0],
+            (
+                "OTEL_SERVICE_NAME",
+                String::from("com.example.OrderFunction")
+            )
+        );
+        assert_eq!(
+            env[1].1,
+            "faas.name=com.example.OrderFunction,com.salesforce.function.runtime.version=1.0.0"
+        );
+    }
+}
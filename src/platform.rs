@@ -0,0 +1,153 @@
+use std::{
+    fs, io,
+    path::Path,
+    process::{Child, Command},
+};
+
+/// Operating system family of the stack. Buildpacks run on the stack's build image, so it's the
+/// family the buildpack binaries were compiled for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Family {
+    Unix,
+    Windows,
+}
+
+impl Family {
+    pub fn current() -> Self {
+        if cfg!(target_family = "windows") {
+            Family::Windows
+        } else {
+            Family::Unix
+        }
+    }
+
+    /// File name of an executable, Windows only runs files with a known extension.
+    pub fn executable_name(self, name: &str) -> String {
+        match self {
+            Family::Unix => name.to_string(),
+            Family::Windows => format!("{}.exe", name),
+        }
+    }
+
+    /// Whether processes are started directly instead of through a shell. Windows stacks don't
+    /// have a POSIX shell to interpret the command line.
+    pub fn direct_processes(self) -> bool {
+        self == Family::Windows
+    }
+
+    /// Separator of the entries of a Java classpath.
+    pub fn classpath_separator(self) -> &'static str {
+        match self {
+            Family::Unix => ":",
+            Family::Windows => ";",
+        }
+    }
+}
+
+/// Makes `path` executable by everyone. Windows decides by file extension, so this is a no-op
+/// there.
+pub fn set_executable(path: impl AsRef<Path>) -> io::Result<()> {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut permissions = fs::metadata(path.as_ref())?.permissions();
+        permissions.set_mode(permissions.mode() | 0o755);
+        fs::set_permissions(path.as_ref(), permissions)?;
+    }
+    #[cfg(not(target_family = "unix"))]
+    let _ = fs::metadata(path.as_ref())?;
+
+    Ok(())
+}
+
+/// Replaces the current process with `command`, so signals sent to the container reach it
+/// directly. Without `exec`, waits for the command and exits with its status instead.
+pub fn exec(mut command: Command) -> io::Error {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::process::CommandExt;
+
+        command.exec()
+    }
+    #[cfg(not(target_family = "unix"))]
+    match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(err) => err,
+    }
+}
+
+/// Asks `child` to shut down, with `SIGTERM` on Unix. Windows has no equivalent for console
+/// processes, so it is killed there.
+pub fn terminate(child: &mut Child) -> io::Result<()> {
+    #[cfg(target_family = "unix")]
+    {
+        let pid = child.id() as libc::pid_t;
+        if unsafe { libc::kill(pid, libc::SIGTERM) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+    #[cfg(not(target_family = "unix"))]
+    child.kill()
+}
+
+/// Where an exec.d executable writes its environment: file descriptor 3 on Unix, the handle in
+/// `CNB_EXEC_D_HANDLE` on Windows.
+pub fn exec_d_output() -> io::Result<fs::File> {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::io::FromRawFd;
+
+        Ok(unsafe { fs::File::from_raw_fd(3) })
+    }
+    #[cfg(target_family = "windows")]
+    {
+        use std::os::windows::io::{FromRawHandle, RawHandle};
+
+        let handle = std::env::var("CNB_EXEC_D_HANDLE")
+            .ok()
+            .and_then(|value| u64::from_str_radix(value.trim_start_matches("0x"), 16).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "CNB_EXEC_D_HANDLE is not set to a hexadecimal handle",
+                )
+            })?;
+
+        Ok(unsafe { fs::File::from_raw_handle(handle as usize as RawHandle) })
+    }
+    #[cfg(not(any(target_family = "unix", target_family = "windows")))]
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "exec.d is not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executable_name_has_extension_on_windows() {
+        assert_eq!(Family::Unix.executable_name("launch"), "launch");
+        assert_eq!(Family::Windows.executable_name("launch"), "launch.exe");
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn set_executable_adds_execute_bits() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("launch");
+        fs::write(&path, "")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+        set_executable(&path)?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o755);
+
+        Ok(())
+    }
+}
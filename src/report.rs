@@ -0,0 +1,110 @@
+use crate::data::function_bundle;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+pub const TOML_FILE_NAME: &str = "report.toml";
+pub const JSON_FILE_NAME: &str = "report.json";
+
+/// Build results for tooling that would otherwise have to scrape the build output. Written as
+/// TOML and JSON with the same structure.
+#[derive(Debug, Serialize)]
+pub struct BuildReport {
+    /// Digest of the inputs the function bundle layer was built from.
+    pub bundle_digest: String,
+    pub runtime: RuntimeReport,
+    pub functions: Vec<FunctionReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RuntimeReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// In the `<algorithm>:<hex>` format, see [`crate::util::checksum::Checksum`].
+    pub checksum: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionReport {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub class: String,
+    pub payload_class: String,
+    pub payload_media_type: String,
+    pub return_class: String,
+    pub return_media_type: String,
+}
+
+impl From<function_bundle::Function> for FunctionReport {
+    fn from(function: function_bundle::Function) -> Self {
+        FunctionReport {
+            id: function.id,
+            class: function.class,
+            payload_class: function.payload_class,
+            payload_media_type: function.payload_media_type,
+            return_class: function.return_class,
+            return_media_type: function.return_media_type,
+        }
+    }
+}
+
+impl BuildReport {
+    pub fn to_toml(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Writes `report.toml` and `report.json` into `dir`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> anyhow::Result<()> {
+        fs::write(dir.as_ref().join(TOML_FILE_NAME), self.to_toml()?)?;
+        fs::write(dir.as_ref().join(JSON_FILE_NAME), self.to_json()?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> BuildReport {
+        BuildReport {
+            bundle_digest: String::from("abc123"),
+            runtime: RuntimeReport {
+                version: Some(String::from("0.2.2")),
+                checksum: format!("sha256:{}", "de".repeat(32)),
+                sha256: Some("de".repeat(32)),
+            },
+            functions: vec![FunctionReport {
+                id: None,
+                class: String::from("com.example.ExampleFunction"),
+                payload_class: String::from("com.example.Payload"),
+                payload_media_type: String::from("application/json"),
+                return_class: String::from("com.example.Result"),
+                return_media_type: String::from("application/json"),
+            }],
+        }
+    }
+
+    #[test]
+    fn toml_and_json_have_the_same_structure() -> anyhow::Result<()> {
+        let report = report();
+        let from_toml: serde_json::Value =
+            serde_json::to_value(toml::from_str::<toml::Value>(&report.to_toml()?)?)?;
+        let from_json: serde_json::Value = serde_json::from_str(&report.to_json()?)?;
+
+        assert_eq!(from_toml, from_json);
+        assert_eq!(from_json["runtime"]["version"], "0.2.2");
+        assert_eq!(
+            from_json["functions"][0]["class"],
+            "com.example.ExampleFunction"
+        );
+        assert!(from_json["functions"][0].get("id").is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{data::Runtime, util};
+use libcnb::data::bom::Entry;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use toml::value::{Table, Value};
+
+/// The function runtime as an entry of the bill of materials in `launch.toml`, which the lifecycle
+/// records in the image metadata.
+pub fn runtime_entry(runtime: &Runtime) -> Entry {
+    let mut metadata = Table::new();
+    if let Some(version) = runtime.version() {
+        metadata.insert(String::from("version"), Value::from(version));
+    }
+    if let Some(sha256) = runtime.known_sha256() {
+        metadata.insert(String::from("sha256"), Value::from(sha256));
+    }
+    metadata.insert(
+        String::from("checksum"),
+        Value::from(runtime.checksum().to_string()),
+    );
+    metadata.insert(String::from("url"), Value::from(runtime.url.as_str()));
+
+    Entry {
+        name: String::from("sf-fx-runtime-java"),
+        metadata,
+    }
+}
+
+/// Lists the function itself and every jar shipped in the function bundle.
+pub fn function_bundle_entry(
+    function_class: impl Into<String>,
+    bundle_dir: impl AsRef<Path>,
+) -> anyhow::Result<Entry> {
+    let mut jars = Vec::new();
+    collect_jars(bundle_dir.as_ref(), &mut jars)?;
+    jars.sort();
+
+    let mut dependencies = Vec::new();
+    for jar in jars {
+        let mut dependency = Table::new();
+        dependency.insert(
+            String::from("name"),
+            Value::from(
+                jar.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            ),
+        );
+        dependency.insert(
+            String::from("sha256"),
+            Value::from(util::sha256(&fs::read(&jar)?)),
+        );
+        dependencies.push(Value::Table(dependency));
+    }
+
+    let mut metadata = Table::new();
+    metadata.insert(String::from("function"), Value::from(function_class.into()));
+    metadata.insert(String::from("dependencies"), Value::Array(dependencies));
+
+    Ok(Entry {
+        name: String::from("function-bundle"),
+        metadata,
+    })
+}
+
+fn collect_jars(dir: &Path, jars: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_jars(&path, jars)?;
+        } else if path
+            .extension()
+            .map_or(false, |extension| extension == "jar")
+        {
+            jars.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn function_bundle_entry_lists_function_and_jars() -> anyhow::Result<()> {
+        let bundle_dir = tempfile::tempdir()?;
+        fs::create_dir(bundle_dir.path().join("lib"))?;
+        fs::write(bundle_dir.path().join("lib/dependency-1.0.jar"), "jar")?;
+        fs::write(bundle_dir.path().join("function-bundle.toml"), "")?;
+
+        let entry = function_bundle_entry("com.example.MyFunction", bundle_dir.path())?;
+
+        assert_eq!(entry.name, "function-bundle");
+        assert_eq!(
+            entry.metadata["function"].as_str(),
+            Some("com.example.MyFunction")
+        );
+        let dependencies = entry.metadata["dependencies"].as_array().unwrap();
+        assert_eq!(dependencies.len(), 1);
+        assert_eq!(dependencies[0]["name"].as_str(), Some("dependency-1.0"));
+        assert_eq!(
+            dependencies[0]["sha256"].as_str(),
+            Some(util::sha256(b"jar").as_str())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn runtime_entry_records_version_and_checksum() {
+        let runtime = Runtime {
+            url: String::from("https://foo.com/runtime/1.0.0/runtime-1.0.0.jar"),
+            sha256: String::from("ABCDEF"),
+            ..Runtime::default()
+        };
+
+        let entry = runtime_entry(&runtime);
+
+        assert_eq!(entry.name, "sf-fx-runtime-java");
+        assert_eq!(entry.metadata["version"].as_str(), Some("1.0.0"));
+        assert_eq!(entry.metadata["sha256"].as_str(), Some("ABCDEF"));
+        assert_eq!(
+            entry.metadata["url"].as_str(),
+            Some("https://foo.com/runtime/1.0.0/runtime-1.0.0.jar")
+        );
+        assert!(toml::to_string(&libcnb::data::launch::Launch {
+            bom: vec![entry],
+            ..libcnb::data::launch::Launch::new()
+        })
+        .is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    config::{GarbageCollector, JvmProfile},
+    data::{buildpack_toml, runtime::VerifyPolicy},
+    exec_d, util,
+};
+use std::time::Duration;
+
+/// Build settings from the platform environment, falling back to the defaults in
+/// `buildpack.toml`.
+pub struct Settings<'m, E: Fn(&str) -> Option<String>> {
+    env: E,
+    metadata: &'m buildpack_toml::Metadata,
+}
+
+impl<'m, E: Fn(&str) -> Option<String>> Settings<'m, E> {
+    pub fn new(env: E, metadata: &'m buildpack_toml::Metadata) -> Self {
+        Settings { env, metadata }
+    }
+
+    /// Whether `SF_FUNCTIONS_DRY_RUN` asks to only print the plan of the build.
+    pub fn is_dry_run(&self) -> bool {
+        matches!(
+            (self.env)("SF_FUNCTIONS_DRY_RUN").as_deref(),
+            Some("1") | Some("true")
+        )
+    }
+
+    /// `SF_FUNCTIONS_JVM_PROFILE`, coldstart unless set.
+    pub fn jvm_profile(&self) -> anyhow::Result<JvmProfile> {
+        Ok(util::parse_env::<JvmProfile>(
+            &self.env,
+            "SF_FUNCTIONS_JVM_PROFILE",
+            "coldstart or throughput",
+        )?
+        .unwrap_or_default())
+    }
+
+    /// Buildpack defaults, the flags of `profile`, `memory_options`, then the flags from
+    /// `SF_FUNCTIONS_JAVA_TOOL_OPTIONS` at build time.
+    pub fn java_tool_options(&self, profile: JvmProfile, memory_options: Vec<String>) -> String {
+        let mut options = self.metadata.jvm.java_tool_options.clone();
+        options.extend(profile.java_tool_options());
+        options.extend(memory_options);
+        if let Some(user_options) = (self.env)("SF_FUNCTIONS_JAVA_TOOL_OPTIONS") {
+            options.push(user_options);
+        }
+
+        options.join(" ")
+    }
+
+    /// `SF_FUNCTIONS_GC` as set at build time, passed on to the launch environment. Validated
+    /// here, as the launch only falls back to the default for invalid values.
+    pub fn garbage_collector(&self) -> anyhow::Result<Option<String>> {
+        let gc =
+            util::parse_env::<GarbageCollector>(&self.env, exec_d::GC_ENV, "serial, g1 or zgc")?;
+
+        Ok(gc
+            .and((self.env)(exec_d::GC_ENV))
+            .map(|gc| gc.trim().to_ascii_lowercase()))
+    }
+
+    /// `SF_FUNCTIONS_DEFAULT_PORT`, falling back to the buildpack default.
+    pub fn default_port(&self) -> anyhow::Result<u16> {
+        match util::parse_env::<u16>(&self.env, "SF_FUNCTIONS_DEFAULT_PORT", "a port number")? {
+            Some(0) => {
+                anyhow::bail!("Invalid SF_FUNCTIONS_DEFAULT_PORT \"0\", expected a port number")
+            }
+            Some(port) => Ok(port),
+            None => Ok(self.metadata.launch.default_port),
+        }
+    }
+
+    /// `SF_FUNCTIONS_BUNDLER_TIMEOUT` in seconds, falling back to the buildpack default.
+    pub fn bundler_timeout(&self) -> anyhow::Result<Duration> {
+        let secs = util::parse_env(
+            &self.env,
+            "SF_FUNCTIONS_BUNDLER_TIMEOUT",
+            "a number of seconds",
+        )?
+        .unwrap_or(self.metadata.bundler.timeout_secs);
+
+        Ok(Duration::from_secs(secs))
+    }
+
+    /// `SF_FUNCTIONS_BUNDLE_SIZE_WARNING_MIB`, falling back to the buildpack default.
+    pub fn bundle_size_warning_mib(&self) -> anyhow::Result<u64> {
+        Ok(util::parse_env(
+            &self.env,
+            "SF_FUNCTIONS_BUNDLE_SIZE_WARNING_MIB",
+            "a number of MiB",
+        )?
+        .unwrap_or(self.metadata.bundler.size_warning_mib))
+    }
+
+    /// `SF_FUNCTIONS_DEPENDENCY_CACHE_MAX_MIB`, falling back to the buildpack default.
+    pub fn dependency_cache_max_mib(&self) -> anyhow::Result<u64> {
+        Ok(util::parse_env(
+            &self.env,
+            "SF_FUNCTIONS_DEPENDENCY_CACHE_MAX_MIB",
+            "a number of MiB",
+        )?
+        .unwrap_or(self.metadata.bundler.dependency_cache_max_mib))
+    }
+
+    /// Buildpack defaults, then the flags from `SF_FUNCTIONS_BUNDLE_JAVA_OPTS`, so the JVM uses
+    /// the user provided value of repeated flags.
+    pub fn bundler_java_options(&self) -> Vec<String> {
+        let mut options = self.metadata.bundler.java_options.clone();
+        if let Some(user_options) = (self.env)("SF_FUNCTIONS_BUNDLE_JAVA_OPTS") {
+            options.extend(user_options.split_whitespace().map(String::from));
+        }
+
+        options
+    }
+
+    /// The policy from buildpack metadata, relaxed to a warning when the user opted into
+    /// unverified runtimes with `SF_FUNCTIONS_ALLOW_UNVERIFIED_RUNTIME`.
+    pub fn verify_policy(&self) -> VerifyPolicy {
+        let allow_unverified = (self.env)("SF_FUNCTIONS_ALLOW_UNVERIFIED_RUNTIME")
+            .map_or(false, |value| {
+                value == "1" || value.eq_ignore_ascii_case("true")
+            });
+
+        self.metadata
+            .runtime
+            .verify
+            .allow_unverified(allow_unverified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::BuildpackTomlBuilder;
+    use std::collections::HashMap;
+    use toml::value::{Table, Value};
+
+    fn metadata(buildpack_toml: BuildpackTomlBuilder) -> anyhow::Result<buildpack_toml::Metadata> {
+        let buildpack_dir = buildpack_toml.buildpack_dir()?;
+        buildpack_toml::Metadata::from_buildpack_dir(buildpack_dir.path())
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn default_port_falls_back_to_buildpack_default() -> anyhow::Result<()> {
+        let launch = vec![(String::from("default_port"), Value::from(9090))]
+            .into_iter()
+            .collect::<Table>();
+        let metadata = metadata(BuildpackTomlBuilder::default().metadata("launch", launch))?;
+
+        assert_eq!(Settings::new(env(&[]), &metadata).default_port()?, 9090);
+        assert_eq!(
+            Settings::new(env(&[("SF_FUNCTIONS_DEFAULT_PORT", " 8081 ")]), &metadata)
+                .default_port()?,
+            8081
+        );
+        assert!(
+            Settings::new(env(&[("SF_FUNCTIONS_DEFAULT_PORT", "0")]), &metadata)
+                .default_port()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn bundler_timeout_rejects_invalid_values() -> anyhow::Result<()> {
+        let metadata = metadata(BuildpackTomlBuilder::default())?;
+
+        assert_eq!(
+            Settings::new(env(&[("SF_FUNCTIONS_BUNDLER_TIMEOUT", "90")]), &metadata)
+                .bundler_timeout()?,
+            Duration::from_secs(90)
+        );
+        assert!(
+            Settings::new(env(&[("SF_FUNCTIONS_BUNDLER_TIMEOUT", "soon")]), &metadata)
+                .bundler_timeout()
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_options_come_after_buildpack_defaults() -> anyhow::Result<()> {
+        let metadata = metadata(BuildpackTomlBuilder::default())?;
+        let settings = Settings::new(
+            env(&[
+                ("SF_FUNCTIONS_JAVA_TOOL_OPTIONS", "-Dfoo=bar"),
+                ("SF_FUNCTIONS_BUNDLE_JAVA_OPTS", "-Xmx1g  -Xss1m"),
+            ]),
+            &metadata,
+        );
+
+        assert!(settings
+            .java_tool_options(JvmProfile::Throughput, vec![String::from("-Xmx512m")])
+            .ends_with("-Xmx512m -Dfoo=bar"));
+        assert!(settings
+            .bundler_java_options()
+            .ends_with(&[String::from("-Xmx1g"), String::from("-Xss1m")]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn dry_run_and_unverified_runtimes_are_opt_in() -> anyhow::Result<()> {
+        let metadata = metadata(BuildpackTomlBuilder::default())?;
+        let settings = Settings::new(
+            env(&[
+                ("SF_FUNCTIONS_DRY_RUN", "true"),
+                ("SF_FUNCTIONS_ALLOW_UNVERIFIED_RUNTIME", "TRUE"),
+            ]),
+            &metadata,
+        );
+
+        assert!(settings.is_dry_run());
+        assert_eq!(settings.verify_policy(), VerifyPolicy::Warn);
+        assert!(!Settings::new(env(&[]), &metadata).is_dry_run());
+        assert_eq!(
+            Settings::new(env(&[]), &metadata).verify_policy(),
+            VerifyPolicy::Strict
+        );
+
+        Ok(())
+    }
+}
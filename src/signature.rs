@@ -0,0 +1,99 @@
+use crate::error::{Error, Result};
+use p256::{
+    ecdsa::{signature::DigestVerifier, Signature, VerifyingKey},
+    pkcs8::FromPublicKey,
+    PublicKey,
+};
+use sha2::{Digest, Sha256};
+use std::{fs, io, path::Path};
+
+const PGP_PUBLIC_KEY_BEGIN: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+
+/// Parses a PEM encoded ECDSA P-256 public key, the default key type of `cosign generate-key-pair`.
+pub fn public_key(pem: &str) -> Result<VerifyingKey> {
+    if pem.trim_start().starts_with(PGP_PUBLIC_KEY_BEGIN) {
+        return Err(invalid_key(
+            "OpenPGP keys are not supported, please use a cosign (ECDSA P-256) public key",
+        ));
+    }
+
+    PublicKey::from_public_key_pem(pem.trim())
+        .map(VerifyingKey::from)
+        .map_err(|_| invalid_key("expected a PEM encoded ECDSA P-256 public key"))
+}
+
+/// Verifies a detached signature as written by `cosign sign-blob`, a base64 encoded DER
+/// ECDSA signature over the sha256 of the file.
+pub fn verify_file(path: impl AsRef<Path>, signature: &[u8], key: &VerifyingKey) -> Result<()> {
+    let signature = base64::decode(
+        signature
+            .iter()
+            .copied()
+            .filter(|byte| !byte.is_ascii_whitespace())
+            .collect::<Vec<_>>(),
+    )
+    .ok()
+    .and_then(|der| Signature::from_der(&der).ok())
+    .ok_or_else(|| Error::SignatureVerification {
+        reason: String::from("the signature is not a base64 encoded ECDSA signature"),
+    })?;
+
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path.as_ref())?, &mut hasher)?;
+
+    key.verify_digest(hasher, &signature)
+        .map_err(|_| Error::SignatureVerification {
+            reason: String::from("the signature does not match the artifact"),
+        })
+}
+
+fn invalid_key(reason: &str) -> Error {
+    Error::SignatureVerification {
+        reason: format!("invalid public key, {}", reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use p256::ecdsa::{signature::DigestSigner, SigningKey};
+
+    // Arbitrary P-256 scalar, for tests only.
+    const SECRET_KEY: [u8; 32] = [
+        0x51, 0x9b, 0x42, 0x3d, 0x71, 0x5f, 0x8b, 0x58, 0x1f, 0x4f, 0xa8, 0xee, 0x59, 0xf4, 0x77,
+        0x1a, 0x5b, 0x44, 0xc8, 0x13, 0x0b, 0x4e, 0x3e, 0xac, 0xca, 0x54, 0xa6, 0x7d, 0xa7, 0xb4,
+        0x9a, 0x0d,
+    ];
+
+    fn sign(data: &[u8]) -> Vec<u8> {
+        let signing_key = SigningKey::from_bytes(&SECRET_KEY).unwrap();
+        let signature: Signature = signing_key.sign_digest(Sha256::new().chain(data));
+        base64::encode(signature.to_der().as_bytes()).into_bytes()
+    }
+
+    #[test]
+    fn verify_file_checks_signature() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("runtime.jar");
+        fs::write(&path, "runtime")?;
+        let key = SigningKey::from_bytes(&SECRET_KEY)?.verifying_key();
+
+        verify_file(&path, &sign(b"runtime"), &key)?;
+
+        let err = verify_file(&path, &sign(b"tampered"), &key).unwrap_err();
+        assert_eq!(
+            err.header(),
+            "Function runtime signature verification failed"
+        );
+        assert!(verify_file(&path, b"not base64!", &key).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn public_key_rejects_pgp_keys() {
+        let err = public_key("-----BEGIN PGP PUBLIC KEY BLOCK-----\n...").unwrap_err();
+
+        assert!(err.to_string().contains("OpenPGP keys are not supported"));
+    }
+}
@@ -0,0 +1,259 @@
+use crate::{
+    error::{Error, Result},
+    launcher, platform,
+};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Read},
+    net::TcpListener,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+const OUTPUT_TAIL_LINES: usize = 20;
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A CloudEvent with an empty payload, the least the runtime needs to route it to the function.
+const INVOKE_REQUEST: &str = "POST / HTTP/1.0\r\n\
+    Host: localhost\r\n\
+    Content-Type: application/json\r\n\
+    Content-Length: 0\r\n\
+    ce-specversion: 1.0\r\n\
+    ce-id: buildpack-smoke-test\r\n\
+    ce-source: urn:buildpack:smoke-test\r\n\
+    ce-type: com.salesforce.function.invoke.sync\r\n\
+    Connection: close\r\n\r\n";
+
+/// A port on localhost that was free a moment ago.
+pub fn free_port() -> io::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+/// Starts `command`, which serves the function on `port`, and invokes the function once. Any HTTP
+/// response means the function loaded, errors of the function itself don't fail the check.
+/// Returns the HTTP status of the invocation.
+pub fn invoke(command: &mut Command, port: u16, timeout: Duration) -> Result<u16> {
+    run(command, port, timeout, |child| {
+        let _ = child.kill();
+    })
+}
+
+/// Like [`invoke`], but asks the runtime to shut down afterwards instead of killing it, so JVM
+/// exit actions like `-XX:ArchiveClassesAtExit` run. Killed if it doesn't exit within `timeout`.
+pub fn warm_up(command: &mut Command, port: u16, timeout: Duration) -> Result<u16> {
+    run(command, port, timeout, |child| {
+        stop_gracefully(child, timeout)
+    })
+}
+
+fn stop_gracefully(child: &mut Child, timeout: Duration) {
+    if platform::terminate(child).is_ok() {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            match child.try_wait() {
+                Ok(None) => thread::sleep(POLL_INTERVAL),
+                _ => return,
+            }
+        }
+    }
+    let _ = child.kill();
+}
+
+fn run(
+    command: &mut Command,
+    port: u16,
+    timeout: Duration,
+    stop: impl FnOnce(&mut Child),
+) -> Result<u16> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => Error::JdkNotFound,
+            _ => Error::Io(err),
+        })?;
+
+    let output = Arc::new(Mutex::new(VecDeque::with_capacity(OUTPUT_TAIL_LINES)));
+    let streams: Vec<Box<dyn Read + Send>> = vec![
+        Box::new(child.stdout.take().expect("stdout is piped")),
+        Box::new(child.stderr.take().expect("stderr is piped")),
+    ];
+    let readers: Vec<_> = streams
+        .into_iter()
+        .map(|stream| collect_tail(stream, Arc::clone(&output)))
+        .collect();
+
+    let result = wait_for_response(&mut child, port, timeout);
+    stop(&mut child);
+    let _ = child.wait();
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    result.map_err(|reason| Error::SmokeTest {
+        reason,
+        output: output
+            .lock()
+            .map(|output| output.iter().cloned().collect())
+            .unwrap_or_default(),
+    })
+}
+
+fn wait_for_response(
+    child: &mut Child,
+    port: u16,
+    timeout: Duration,
+) -> std::result::Result<u16, String> {
+    let deadline = Instant::now() + timeout;
+    let port = port.to_string();
+
+    loop {
+        if let Some(status) = child.try_wait().map_err(|err| err.to_string())? {
+            return Err(format!("the runtime exited early with {}", status));
+        }
+
+        // The function gets the remaining time to answer once the runtime accepts connections.
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match launcher::local_http_status(&port, INVOKE_REQUEST, remaining.max(POLL_INTERVAL)) {
+            Ok(status) => return Ok(status),
+            Err(_) if Instant::now() + POLL_INTERVAL < deadline => thread::sleep(POLL_INTERVAL),
+            Err(err) => {
+                return Err(format!(
+                    "no response within {} seconds ({})",
+                    timeout.as_secs(),
+                    err
+                ))
+            }
+        }
+    }
+}
+
+fn collect_tail(
+    stream: Box<dyn Read + Send>,
+    output: Arc<Mutex<VecDeque<String>>>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+            if let Ok(mut output) = output.lock() {
+                if output.len() == OUTPUT_TAIL_LINES {
+                    output.pop_front();
+                }
+                output.push_back(line);
+            }
+        }
+    })
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn invoke_accepts_any_http_response() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            stream
+                .write_all(b"HTTP/1.1 500 Internal Server Error\r\n\r\n")
+                .unwrap();
+        });
+
+        let status = invoke(
+            Command::new("sleep").arg("30"),
+            port,
+            Duration::from_secs(10),
+        )?;
+        assert_eq!(status, 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_reports_output_of_crashed_runtime() -> anyhow::Result<()> {
+        let err = invoke(
+            Command::new("sh").args(&[
+                "-c",
+                "echo starting; echo 'java.lang.ClassNotFoundException: com.example.Fn' >&2; exit 1",
+            ]),
+            free_port()?,
+            Duration::from_secs(10),
+        )
+        .unwrap_err();
+
+        match err {
+            Error::SmokeTest { reason, output } => {
+                assert!(reason.contains("exited early"));
+                assert!(output.contains(&String::from(
+                    "java.lang.ClassNotFoundException: com.example.Fn"
+                )));
+            }
+            err => panic!("unexpected error {:?}", err),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_times_out_without_response() -> anyhow::Result<()> {
+        let err = invoke(
+            Command::new("sleep").arg("30"),
+            free_port()?,
+            Duration::from_secs(1),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().contains("no response within 1 seconds"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn warm_up_lets_runtime_exit_gracefully() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let ready = dir.path().join("ready");
+        let exited = dir.path().join("exited");
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let server_ready = ready.clone();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            // Only answer once the trap is installed, warm_up sends SIGTERM right after.
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while !server_ready.exists() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(10));
+            }
+            stream.write_all(b"HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        });
+
+        let status = warm_up(
+            Command::new("sh").args(&[
+                "-c",
+                &format!(
+                    "trap 'touch {}; exit 0' TERM; touch {}; while true; do sleep 0.1; done",
+                    exited.display(),
+                    ready.display()
+                ),
+            ]),
+            port,
+            Duration::from_secs(10),
+        )?;
+        assert_eq!(status, 200);
+        assert!(exited.exists());
+
+        Ok(())
+    }
+}
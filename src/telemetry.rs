@@ -0,0 +1,250 @@
+use crate::{
+    layer::{Decision, LayerTypes},
+    metrics::Phase,
+};
+use serde::Serialize;
+use std::{
+    cell::Cell,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Opts in to build telemetry, nothing is recorded unless it is `1` or `true`.
+pub const TELEMETRY_ENV: &str = "SF_FUNCTIONS_TELEMETRY";
+/// URL the build events are posted to as JSON.
+pub const ENDPOINT_ENV: &str = "SF_FUNCTIONS_TELEMETRY_ENDPOINT";
+/// File the build events are appended to, one JSON document per line.
+pub const FILE_ENV: &str = "SF_FUNCTIONS_TELEMETRY_FILE";
+const SCHEMA_VERSION: u32 = 1;
+
+/// Where build events go.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sink {
+    Endpoint(String),
+    File(PathBuf),
+    /// The build log, when neither an endpoint nor a file is configured.
+    Log,
+}
+
+impl Sink {
+    /// `None` unless [`TELEMETRY_ENV`] opts in. An endpoint wins over a file.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let opted_in = var(TELEMETRY_ENV).map_or(false, |value| {
+            value == "1" || value.eq_ignore_ascii_case("true")
+        });
+        if !opted_in {
+            return None;
+        }
+
+        let non_empty = |key| var(key).filter(|value| !value.is_empty());
+        Some(match (non_empty(ENDPOINT_ENV), non_empty(FILE_ENV)) {
+            (Some(endpoint), _) => Sink::Endpoint(endpoint),
+            (None, Some(path)) => Sink::File(PathBuf::from(path)),
+            (None, None) => Sink::Log,
+        })
+    }
+}
+
+/// Counts how often cached layers were reused. Layers that aren't cached are always created, so
+/// they don't count.
+#[derive(Default)]
+pub struct CacheStats {
+    reused: Cell<u32>,
+    rebuilt: Cell<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CacheSummary {
+    pub reused: u32,
+    pub rebuilt: u32,
+    /// Share of reused layers, `None` if no cached layer was contributed.
+    pub hit_rate: Option<f64>,
+}
+
+impl CacheStats {
+    pub fn record(&self, types: LayerTypes, decision: &Decision) {
+        if !types.cache {
+            return;
+        }
+
+        let counter = match decision {
+            Decision::Skip => &self.reused,
+            Decision::Create | Decision::Update(_) => &self.rebuilt,
+        };
+        counter.set(counter.get() + 1);
+    }
+
+    pub fn summary(&self) -> CacheSummary {
+        let reused = self.reused.get();
+        let rebuilt = self.rebuilt.get();
+        let total = reused + rebuilt;
+
+        CacheSummary {
+            reused,
+            rebuilt,
+            hit_rate: if total == 0 {
+                None
+            } else {
+                Some(f64::from(reused) / f64::from(total))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+/// A build, without anything that identifies the app: no paths, names, URLs or error messages.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildEvent {
+    pub schema_version: u32,
+    pub buildpack_version: String,
+    pub runtime_version: String,
+    pub arch: String,
+    pub outcome: Outcome,
+    /// See [`crate::exit_code::category`].
+    pub error_category: Option<&'static str>,
+    pub duration_ms: u64,
+    pub phases: Vec<Phase>,
+    pub cache: CacheSummary,
+}
+
+impl BuildEvent {
+    pub fn new(
+        buildpack_version: impl Into<String>,
+        runtime_version: impl Into<String>,
+        arch: impl Into<String>,
+        error_category: Option<&'static str>,
+        phases: Vec<Phase>,
+        cache: CacheSummary,
+    ) -> Self {
+        BuildEvent {
+            schema_version: SCHEMA_VERSION,
+            buildpack_version: buildpack_version.into(),
+            runtime_version: runtime_version.into(),
+            arch: arch.into(),
+            outcome: if error_category.is_some() {
+                Outcome::Failure
+            } else {
+                Outcome::Success
+            },
+            error_category,
+            duration_ms: phases.iter().map(|phase| phase.duration_ms).sum(),
+            phases,
+            cache,
+        }
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Appends the event to `path` as a single line.
+    pub fn append_to(&self, path: &Path) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        writeln!(file, "{}", self.to_json()?)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const CACHED: LayerTypes = LayerTypes {
+        launch: true,
+        build: false,
+        cache: true,
+    };
+
+    #[test]
+    fn sink_requires_opt_in() {
+        let sink = |pairs: &'static [(&'static str, &'static str)]| {
+            Sink::from_env(move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| String::from(*value))
+            })
+        };
+
+        assert_eq!(sink(&[(ENDPOINT_ENV, "https://example.com/events")]), None);
+        assert_eq!(sink(&[(TELEMETRY_ENV, "0")]), None);
+        assert_eq!(sink(&[(TELEMETRY_ENV, "true")]), Some(Sink::Log));
+        assert_eq!(
+            sink(&[(TELEMETRY_ENV, "1"), (FILE_ENV, "/tmp/events.jsonl")]),
+            Some(Sink::File(PathBuf::from("/tmp/events.jsonl")))
+        );
+        assert_eq!(
+            sink(&[
+                (TELEMETRY_ENV, "1"),
+                (FILE_ENV, "/tmp/events.jsonl"),
+                (ENDPOINT_ENV, "https://example.com/events"),
+            ]),
+            Some(Sink::Endpoint(String::from("https://example.com/events")))
+        );
+    }
+
+    #[test]
+    fn cache_stats_only_count_cached_layers() {
+        let stats = CacheStats::default();
+        assert_eq!(stats.summary().hit_rate, None);
+
+        stats.record(CACHED, &Decision::Skip);
+        stats.record(CACHED, &Decision::Skip);
+        stats.record(CACHED, &Decision::Skip);
+        stats.record(CACHED, &Decision::Update(vec![String::from("sha256")]));
+        stats.record(
+            LayerTypes {
+                cache: false,
+                ..CACHED
+            },
+            &Decision::Create,
+        );
+
+        assert_eq!(
+            stats.summary(),
+            CacheSummary {
+                reused: 3,
+                rebuilt: 1,
+                hit_rate: Some(0.75),
+            }
+        );
+    }
+
+    #[test]
+    fn append_to_writes_one_line_per_event() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("events.jsonl");
+        let event = BuildEvent::new(
+            "0.2.4",
+            "1.1.4",
+            "amd64",
+            Some("bundler"),
+            vec![Phase {
+                name: String::from("JDK"),
+                duration_ms: 120,
+            }],
+            CacheStats::default().summary(),
+        );
+
+        event.append_to(&path)?;
+        event.append_to(&path)?;
+
+        let contents = fs::read_to_string(&path)?;
+        assert_eq!(contents.lines().count(), 2);
+        let json: serde_json::Value = serde_json::from_str(contents.lines().next().unwrap())?;
+        assert_eq!(json["outcome"], "failure");
+        assert_eq!(json["error_category"], "bundler");
+        assert_eq!(json["duration_ms"], 120);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod app_dir;
+pub mod buildpack_toml;
+pub mod layers_dir;
+
+pub use app_dir::{app_dir, fixture};
+pub use buildpack_toml::BuildpackTomlBuilder;
+pub use layers_dir::LayersDir;
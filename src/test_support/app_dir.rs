@@ -0,0 +1,45 @@
+use std::{fs, io, path::Path};
+use tempfile::TempDir;
+
+/// An app directory with `files`, given as paths relative to it and their contents.
+pub fn app_dir(files: &[(&str, &str)]) -> io::Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    for (path, contents) in files {
+        let path = dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+    }
+
+    Ok(dir)
+}
+
+/// A copy of an app from `tests/fixtures`, so tests can change it.
+pub fn fixture(name: &str) -> io::Result<TempDir> {
+    let dir = tempfile::tempdir()?;
+    copy_dir(
+        &Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name),
+        dir.path(),
+    )?;
+
+    Ok(dir)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+
+    Ok(())
+}
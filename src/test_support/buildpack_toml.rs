@@ -0,0 +1,113 @@
+use crate::{layers::opt::LAUNCHER_FILE_NAME, platform::Family};
+use std::{fs, io, path::Path};
+use tempfile::TempDir;
+use toml::value::{Table, Value};
+
+/// Writes a `buildpack.toml` with a runtime, a release repository and the given stacks and
+/// metadata, so tests don't depend on the vendored one.
+pub struct BuildpackTomlBuilder {
+    version: String,
+    stacks: Vec<String>,
+    metadata: Table,
+}
+
+impl Default for BuildpackTomlBuilder {
+    fn default() -> Self {
+        let runtime = vec![
+            (
+                String::from("url"),
+                Value::from("https://repo.example.com/sf-fx-runtime-java-1.0.0.jar"),
+            ),
+            (String::from("sha256"), Value::from("ab".repeat(32))),
+        ]
+        .into_iter()
+        .collect::<Table>();
+
+        let docker = vec![(
+            String::from("repository"),
+            Value::from("public.ecr.aws/example/jvm-function-invoker-buildpack"),
+        )]
+        .into_iter()
+        .collect::<Table>();
+        let release = vec![(String::from("docker"), Value::Table(docker))]
+            .into_iter()
+            .collect::<Table>();
+
+        BuildpackTomlBuilder {
+            version: String::from("0.2.4"),
+            stacks: vec![String::from("heroku-20")],
+            metadata: vec![
+                (String::from("runtime"), Value::Table(runtime)),
+                (String::from("release"), Value::Table(release)),
+            ]
+            .into_iter()
+            .collect(),
+        }
+    }
+}
+
+impl BuildpackTomlBuilder {
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = String::from(version);
+        self
+    }
+
+    /// Replaces the stacks, the default is `heroku-20`.
+    pub fn stacks(mut self, stacks: &[&str]) -> Self {
+        self.stacks = stacks.iter().map(|stack| stack.to_string()).collect();
+        self
+    }
+
+    /// Sets `key` of `[metadata.runtime]`.
+    pub fn runtime(mut self, key: &str, value: impl Into<Value>) -> Self {
+        if let Some(Value::Table(runtime)) = self.metadata.get_mut("runtime") {
+            runtime.insert(String::from(key), value.into());
+        }
+        self
+    }
+
+    /// Replaces the `[metadata.<section>]` table.
+    pub fn metadata(mut self, section: &str, table: Table) -> Self {
+        self.metadata
+            .insert(String::from(section), Value::Table(table));
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let mut buildpack_toml = format!(
+            "api = \"0.5\"\n\n[buildpack]\nid = \"heroku/jvm-function-invoker\"\nversion = \"{}\"\n\
+             name = \"JVM Function Invoker\"\n",
+            self.version
+        );
+        for stack in &self.stacks {
+            buildpack_toml.push_str(&format!("\n[[stacks]]\nid = \"{}\"\n", stack));
+        }
+        let mut root = Table::new();
+        root.insert(
+            String::from("metadata"),
+            Value::Table(self.metadata.clone()),
+        );
+        buildpack_toml.push('\n');
+        buildpack_toml.push_str(&toml::to_string(&root).expect("metadata is valid TOML"));
+
+        buildpack_toml
+    }
+
+    pub fn write_to(&self, buildpack_dir: &Path) -> io::Result<()> {
+        fs::write(buildpack_dir.join("buildpack.toml"), self.build())
+    }
+
+    /// A buildpack directory with the `buildpack.toml` and a placeholder launcher in `bin`.
+    pub fn buildpack_dir(&self) -> io::Result<TempDir> {
+        let dir = tempfile::tempdir()?;
+        self.write_to(dir.path())?;
+        let bin_dir = dir.path().join("bin");
+        fs::create_dir(&bin_dir)?;
+        fs::write(
+            bin_dir.join(Family::current().executable_name(LAUNCHER_FILE_NAME)),
+            "launcher",
+        )?;
+
+        Ok(dir)
+    }
+}
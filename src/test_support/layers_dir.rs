@@ -0,0 +1,58 @@
+use crate::{
+    layer::{self, Decision, LayerContributor, LayerStore},
+    util::logger::MemoryLogger,
+};
+use std::{fs, io, path::Path};
+use tempfile::TempDir;
+
+/// A layers directory to contribute layers to without a build context. Layer metadata is kept in
+/// `<name>.toml` as the CNB lifecycle does, so contributing again takes the caching decision of a
+/// rebuild.
+pub struct LayersDir {
+    dir: TempDir,
+}
+
+impl LayersDir {
+    pub fn new() -> io::Result<Self> {
+        Ok(LayersDir {
+            dir: tempfile::tempdir()?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Contributes the layer with [`layer::contribute_layer`], discarding its log output.
+    pub fn contribute(&self, contributor: &impl LayerContributor) -> anyhow::Result<Decision> {
+        let (_, decision) = layer::contribute_layer(self, &MemoryLogger::new(false), contributor)?;
+
+        Ok(decision)
+    }
+
+    /// The value the layer sets `key` to in the launch environment, with any modification.
+    pub fn launch_env(&self, name: &str, key: &str) -> Option<String> {
+        let env_dir = self.layer_dir(name).join("env.launch");
+        let prefix = format!("{}.", key);
+
+        fs::read_dir(env_dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .find(|entry| {
+                let file_name = entry.file_name();
+                let file_name = file_name.to_string_lossy();
+                file_name == key || file_name.starts_with(&prefix)
+            })
+            .and_then(|entry| fs::read_to_string(entry.path()).ok())
+    }
+}
+
+impl LayerStore for LayersDir {
+    fn layers_dir(&self) -> &Path {
+        self.path()
+    }
+
+    fn source_date_epoch(&self) -> Option<String> {
+        None
+    }
+}
@@ -0,0 +1,274 @@
+use crate::util::{self, http, process::CommandRunner};
+use anyhow::Context;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Directory in the app with additional CA certificates in PEM format.
+pub const CERTS_DIR: &str = "certs";
+pub const FILE_NAME: &str = "truststore";
+/// The well-known password of JDK truststores. It only protects integrity, not secrecy.
+pub const STORE_PASSWORD: &str = "changeit";
+
+const CERTIFICATE_EXTENSIONS: &[&str] = &["pem", "crt", "cer"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Certificate {
+    pub alias: String,
+    pub pem: String,
+}
+
+/// Certificate files from the `certs` directory of the app in name order, followed by `bundle`.
+/// A relative `bundle` path is resolved against the app.
+pub fn certificate_files(app_dir: &Path, bundle: Option<PathBuf>) -> io::Result<Vec<PathBuf>> {
+    let certs_dir = app_dir.join(CERTS_DIR);
+    let mut files = Vec::new();
+
+    if certs_dir.is_dir() {
+        for entry in fs::read_dir(&certs_dir)? {
+            let path = entry?.path();
+            let is_certificate = path.extension().map_or(false, |extension| {
+                CERTIFICATE_EXTENSIONS
+                    .iter()
+                    .any(|candidate| extension == *candidate)
+            });
+            if path.is_file() && is_certificate {
+                files.push(path);
+            }
+        }
+        files.sort();
+    }
+
+    files.extend(bundle.map(|bundle| app_dir.join(bundle)));
+
+    Ok(files)
+}
+
+/// Every certificate in `files`, aliased after the file it comes from. Bundles are split, as
+/// keytool only imports the first certificate of a file.
+pub fn read_certificates(files: &[PathBuf]) -> anyhow::Result<Vec<Certificate>> {
+    let mut certificates = Vec::new();
+
+    for file in files {
+        let pem = fs::read_to_string(file)
+            .with_context(|| format!("Could not read CA certificates {}", file.display()))?;
+        let pems = http::pem_certificates(&pem);
+        if pems.is_empty() {
+            anyhow::bail!("No PEM certificates found in {}", file.display());
+        }
+
+        let stem = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        for (index, pem) in pems.into_iter().enumerate() {
+            certificates.push(Certificate {
+                alias: format!("{}-{}", stem, index),
+                pem: pem.to_string(),
+            });
+        }
+    }
+
+    Ok(certificates)
+}
+
+/// Changes whenever a certificate is added, removed, renamed or replaced.
+pub fn certificates_digest(certificates: &[Certificate]) -> String {
+    let mut data = Vec::new();
+    for certificate in certificates {
+        data.extend_from_slice(certificate.alias.as_bytes());
+        data.push(0);
+        data.extend_from_slice(certificate.pem.as_bytes());
+        data.push(0);
+    }
+
+    util::sha256(&data)
+}
+
+/// The `cacerts` of the JDK. It is the base of the truststore, so the public CAs stay trusted.
+pub fn jdk_cacerts(java_home: &Path) -> Option<PathBuf> {
+    // JDK 9 and later, then JDK 8.
+    [
+        java_home.join("lib").join("security").join("cacerts"),
+        java_home
+            .join("jre")
+            .join("lib")
+            .join("security")
+            .join("cacerts"),
+    ]
+    .iter()
+    .find(|path| path.is_file())
+    .cloned()
+}
+
+/// Imports `certificate_path` into the truststore with keytool. Failing to start keytool is
+/// returned as the [`io::Error`], so a missing JDK can be told apart.
+pub fn import_certificate(
+    runner: &dyn CommandRunner,
+    keytool: &Path,
+    truststore_path: &Path,
+    certificate_path: &Path,
+    alias: &str,
+) -> anyhow::Result<()> {
+    let (exit_status, stdout) = runner.stdout(
+        Command::new(keytool)
+            .arg("-importcert")
+            .arg("-noprompt")
+            .args(&["-alias", alias])
+            .arg("-file")
+            .arg(certificate_path)
+            .arg("-keystore")
+            .arg(truststore_path)
+            .args(&["-storepass", STORE_PASSWORD]),
+        None,
+    )?;
+
+    if !exit_status.success() {
+        anyhow::bail!(
+            "Could not import CA certificate {} from {}: {}",
+            alias,
+            certificate_path.display(),
+            stdout.trim()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn java_tool_options(truststore_path: &Path) -> String {
+    format!(
+        "-Djavax.net.ssl.trustStore={} -Djavax.net.ssl.trustStorePassword={}",
+        truststore_path.display(),
+        STORE_PASSWORD
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEM: &str = "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----";
+
+    #[test]
+    fn certificate_files_lists_certs_dir_and_bundle() -> anyhow::Result<()> {
+        let app_dir = tempfile::tempdir()?;
+        let certs_dir = app_dir.path().join(CERTS_DIR);
+        fs::create_dir(&certs_dir)?;
+        for name in &["b.crt", "a.pem", "README.md", "c.cer"] {
+            fs::write(certs_dir.join(name), "")?;
+        }
+
+        assert_eq!(
+            certificate_files(app_dir.path(), Some(PathBuf::from("corp.pem")))?,
+            vec![
+                certs_dir.join("a.pem"),
+                certs_dir.join("b.crt"),
+                certs_dir.join("c.cer"),
+                app_dir.path().join("corp.pem"),
+            ]
+        );
+        assert_eq!(
+            certificate_files(
+                tempfile::tempdir()?.path(),
+                Some(PathBuf::from("/etc/ssl/corp.pem"))
+            )?,
+            vec![PathBuf::from("/etc/ssl/corp.pem")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_certificates_splits_bundles() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let bundle = dir.path().join("Corp.pem");
+        fs::write(&bundle, format!("{}\n{}\n", PEM, PEM))?;
+        let empty = dir.path().join("empty.pem");
+        fs::write(&empty, "")?;
+
+        let certificates = read_certificates(&[bundle])?;
+        let aliases: Vec<&str> = certificates
+            .iter()
+            .map(|certificate| certificate.alias.as_str())
+            .collect();
+
+        assert_eq!(aliases, vec!["corp-0", "corp-1"]);
+        assert_eq!(certificates[0].pem, PEM);
+        assert!(read_certificates(&[empty]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn certificates_digest_depends_on_aliases() {
+        let certificate = Certificate {
+            alias: String::from("corp-0"),
+            pem: String::from(PEM),
+        };
+        let renamed = Certificate {
+            alias: String::from("other-0"),
+            ..certificate.clone()
+        };
+
+        assert_eq!(
+            certificates_digest(&[certificate.clone()]),
+            certificates_digest(&[certificate.clone()])
+        );
+        assert_ne!(
+            certificates_digest(&[certificate]),
+            certificates_digest(&[renamed])
+        );
+    }
+
+    #[cfg(target_family = "unix")]
+    #[test]
+    fn import_certificate_reports_keytool_output_on_failure() {
+        use crate::util::process::{OutputLine, ScriptedRunner};
+
+        let runner = ScriptedRunner::new(
+            1,
+            vec![OutputLine::Stdout(String::from(
+                "keytool error: java.lang.Exception: Input not an X.509 certificate",
+            ))],
+        );
+
+        let err = import_certificate(
+            &runner,
+            Path::new("/jdk/bin/keytool"),
+            Path::new("/layers/truststore/truststore"),
+            Path::new("/layers/truststore/certs/corp-0.pem"),
+            "corp-0",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Could not import CA certificate corp-0 from /layers/truststore/certs/corp-0.pem: \
+             keytool error: java.lang.Exception: Input not an X.509 certificate"
+        );
+        assert!(runner
+            .args
+            .borrow()
+            .windows(2)
+            .any(|pair| pair[0] == "-alias" && pair[1] == "corp-0"));
+    }
+
+    #[test]
+    fn jdk_cacerts_supports_jdk_8_layout() -> anyhow::Result<()> {
+        let java_home = tempfile::tempdir()?;
+        assert_eq!(jdk_cacerts(java_home.path()), None);
+
+        let security_dir = java_home.path().join("jre").join("lib").join("security");
+        fs::create_dir_all(&security_dir)?;
+        fs::write(security_dir.join("cacerts"), "")?;
+
+        assert_eq!(
+            jdk_cacerts(java_home.path()),
+            Some(security_dir.join("cacerts"))
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+/// Document served by the latest-version endpoint configured in `[metadata.update_check]`.
+#[derive(Debug, Deserialize)]
+pub struct LatestRelease {
+    pub version: String,
+    /// Releases that no longer work with current function runtimes or platforms.
+    #[serde(default)]
+    pub incompatibilities: Vec<Incompatibility>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Incompatibility {
+    pub versions: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Status<'a> {
+    UpToDate,
+    Outdated { latest: &'a str },
+    Incompatible { latest: &'a str, message: &'a str },
+}
+
+impl LatestRelease {
+    pub fn from_toml(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// How `current`, the version of the running buildpack, relates to the latest release.
+    pub fn status(&self, current: &str) -> Status<'_> {
+        if let Some(incompatibility) = self
+            .incompatibilities
+            .iter()
+            .find(|incompatibility| incompatibility.versions.iter().any(|v| v == current))
+        {
+            return Status::Incompatible {
+                latest: &self.version,
+                message: &incompatibility.message,
+            };
+        }
+
+        match compare_versions(current, &self.version) {
+            Ordering::Less => Status::Outdated {
+                latest: &self.version,
+            },
+            _ => Status::UpToDate,
+        }
+    }
+}
+
+/// Compares dotted versions numerically, e.g. `0.10.0` is newer than `0.9.1`. Pre-release
+/// suffixes are ignored.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let components = |version: &str| -> Vec<u64> {
+        version
+            .split(|c| c == '-' || c == '+')
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|component| component.parse().unwrap_or(0))
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_versions_is_numeric() {
+        assert_eq!(compare_versions("0.9.1", "0.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.2.0-rc.1", "1.1.9"), Ordering::Greater);
+    }
+
+    #[test]
+    fn status_reports_known_incompatibilities() -> anyhow::Result<()> {
+        let release = LatestRelease::from_toml(
+            r#"
+version = "0.3.0"
+
+[[incompatibilities]]
+versions = ["0.2.0", "0.2.1"]
+message = "Functions built with 0.2.0 and 0.2.1 fail to start on runtime 1.x."
+"#,
+        )?;
+
+        assert_eq!(release.status("0.3.0"), Status::UpToDate);
+        assert_eq!(
+            release.status("0.2.4"),
+            Status::Outdated { latest: "0.3.0" }
+        );
+        assert!(matches!(
+            release.status("0.2.1"),
+            Status::Incompatible {
+                latest: "0.3.0",
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+}
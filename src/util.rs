@@ -1,17 +1,466 @@
+pub mod archive;
+pub mod artifact_store;
+pub mod checksum;
+pub mod digest;
+pub mod disk_usage;
+pub mod fetch;
+pub mod http;
+pub mod jar;
+pub mod layer_env;
 pub mod logger;
+pub mod process;
+pub mod progress;
+pub mod proxy;
+pub mod reproducible;
+pub mod retry;
 
+use crate::error::{Error, Result};
+use http::Validators;
+use progress::{Progress, ProgressReader};
+use reqwest::{
+    header::{HeaderMap, IF_RANGE, RANGE},
+    StatusCode,
+};
+use retry::RetryPolicy;
 use sha2::Digest;
-use std::{fs, io};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-pub fn download(uri: impl AsRef<str>, dst: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
-    let response = reqwest::blocking::get(uri.as_ref())?;
-    let mut content = io::Cursor::new(response.bytes()?);
-    let mut file = fs::File::create(dst.as_ref())?;
-    io::copy(&mut content, &mut file)?;
+/// Downloads `uri` to `dst`, hashing the body while it is written, and returns its sha256. When
+/// `expected_sha256` is given, the file only appears at `dst` once its sha256 matches.
+/// `on_progress` is called after every chunk. A retry after an interrupted transfer resumes with a
+/// range request where the server supports it.
+pub fn download_verified(
+    client: &reqwest::blocking::Client,
+    uri: impl AsRef<str>,
+    dst: impl AsRef<Path>,
+    expected_sha256: Option<&str>,
+    retry_policy: &RetryPolicy,
+    on_progress: impl FnMut(Progress),
+) -> Result<String> {
+    match download_conditional(
+        client,
+        uri,
+        dst,
+        expected_sha256,
+        &Validators::default(),
+        retry_policy,
+        on_progress,
+    )? {
+        Download::Modified { sha256, .. } => Ok(sha256),
+        // Servers only answer unconditional requests with 304 when they are broken.
+        Download::NotModified => Err(Error::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected 304 Not Modified response",
+        ))),
+    }
+}
+
+/// Outcome of [`download_conditional`].
+#[derive(Debug, PartialEq)]
+pub enum Download {
+    /// The body was written to `dst`.
+    Modified {
+        sha256: String,
+        validators: Validators,
+    },
+    /// The content didn't change since the download `validators` came from, `dst` is untouched.
+    NotModified,
+}
+
+/// Like [`download_verified`], but sends `validators` of an earlier download along.
+pub fn download_conditional(
+    client: &reqwest::blocking::Client,
+    uri: impl AsRef<str>,
+    dst: impl AsRef<Path>,
+    expected_sha256: Option<&str>,
+    validators: &Validators,
+    retry_policy: &RetryPolicy,
+    on_progress: impl FnMut(Progress),
+) -> Result<Download> {
+    download_with_headers(
+        client,
+        uri,
+        &HeaderMap::new(),
+        dst,
+        expected_sha256,
+        validators,
+        retry_policy,
+        on_progress,
+    )
+}
+
+/// Like [`download_conditional`], but sends `headers` with every request, e.g. authorization.
+#[allow(clippy::too_many_arguments)]
+fn download_with_headers(
+    client: &reqwest::blocking::Client,
+    uri: impl AsRef<str>,
+    headers: &HeaderMap,
+    dst: impl AsRef<Path>,
+    expected_sha256: Option<&str>,
+    validators: &Validators,
+    retry_policy: &RetryPolicy,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Download> {
+    let dst = dst.as_ref();
+    let partial_path = partial_path(dst);
+    // Leftovers of another download, e.g. from a different mirror, can't be resumed.
+    remove_file_if_exists(&partial_path)?;
+    // Validators of the response the partial file came from.
+    let mut partial_validators = Validators::default();
+
+    let result = retry_policy.run(Error::is_retryable, |_| {
+        let offset = fs::metadata(&partial_path).map_or(0, |metadata| metadata.len());
+        let request = client.get(uri.as_ref()).headers(headers.clone());
+        let request = if offset > 0 {
+            let request = request.header(RANGE, format!("bytes={}-", offset));
+            match partial_validators.if_range() {
+                Some(if_range) => request.header(IF_RANGE, if_range),
+                None => request,
+            }
+        } else {
+            validators.apply(request)
+        };
+
+        let response = request.send().map_err(|source| Error::Download {
+            url: uri.as_ref().to_string(),
+            source,
+        })?;
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+            // Starts over on the next attempt.
+            remove_file_if_exists(&partial_path)?;
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|source| Error::Download {
+                url: uri.as_ref().to_string(),
+                source,
+            })?;
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(Download::NotModified);
+        }
+
+        // Servers that ignore the range send the whole body again.
+        let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+        let offset = if resumed { offset } else { 0 };
+        partial_validators = Validators::from_headers(response.headers());
+        let total = response.content_length().map(|length| length + offset);
+        let actual_sha256 = write_partial(
+            ProgressReader::new(response, total, &mut on_progress).starting_at(offset),
+            dst,
+            resumed,
+        )?;
+        let sha256 = finish_partial(dst, actual_sha256, expected_sha256)?;
+
+        Ok(Download::Modified {
+            sha256,
+            validators: partial_validators.clone(),
+        })
+    });
+
+    if result.is_err() {
+        remove_file_if_exists(&partial_path)?;
+    }
+
+    result
+}
+
+/// Copies `src` to `dst` with the same verification guarantees as [`download_verified`].
+pub fn copy_verified(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    expected_sha256: Option<&str>,
+) -> Result<String> {
+    let src = fs::File::open(src.as_ref()).map_err(|source| Error::Copy {
+        path: src.as_ref().to_path_buf(),
+        source,
+    })?;
+
+    write_verified(src, dst.as_ref(), expected_sha256)
+}
 
-    Ok(())
+fn write_verified(reader: impl Read, dst: &Path, expected_sha256: Option<&str>) -> Result<String> {
+    let actual_sha256 = write_partial(reader, dst, false).map_err(|err| {
+        let _ = fs::remove_file(partial_path(dst));
+        err
+    })?;
+
+    finish_partial(dst, actual_sha256, expected_sha256)
+}
+
+fn partial_path(dst: &Path) -> PathBuf {
+    dst.with_extension("partial")
+}
+
+/// Writes `reader` to the partial file of `dst`, after its current contents when `append` is set,
+/// and returns the sha256 of the whole partial file. Failed writes leave the partial file behind.
+fn write_partial(mut reader: impl Read, dst: &Path, append: bool) -> io::Result<String> {
+    let partial_path = partial_path(dst);
+    let mut writer = if append {
+        let mut hasher = sha2::Sha256::new();
+        io::copy(&mut fs::File::open(&partial_path)?, &mut hasher)?;
+        Sha256Writer::with_hasher(
+            fs::OpenOptions::new().append(true).open(&partial_path)?,
+            hasher,
+        )
+    } else {
+        Sha256Writer::new(fs::File::create(&partial_path)?)
+    };
+
+    io::copy(&mut reader, &mut writer)?;
+    writer.finish()
+}
+
+/// Moves the partial file of `dst` into place, unless it doesn't match `expected_sha256`.
+fn finish_partial(
+    dst: &Path,
+    actual_sha256: String,
+    expected_sha256: Option<&str>,
+) -> Result<String> {
+    let partial_path = partial_path(dst);
+    if let Some(expected_sha256) = expected_sha256 {
+        if actual_sha256 != expected_sha256 {
+            fs::remove_file(&partial_path)?;
+            return Err(Error::ChecksumMismatch {
+                algorithm: checksum::Algorithm::Sha256,
+                expected: expected_sha256.to_string(),
+                actual: actual_sha256,
+            });
+        }
+    }
+
+    fs::rename(&partial_path, dst)?;
+
+    Ok(actual_sha256)
+}
+
+fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Parses a user provided environment variable, `None` when it is unset or empty. `expected`
+/// describes the format in the error message, e.g. "a number of seconds".
+pub fn parse_env<T: FromStr>(
+    var: &impl Fn(&str) -> Option<String>,
+    key: &str,
+    expected: &str,
+) -> anyhow::Result<Option<T>> {
+    match var(key).filter(|value| !value.trim().is_empty()) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| anyhow::anyhow!("Invalid {} {:?}, expected {}", key, value, expected)),
+        None => Ok(None),
+    }
 }
 
 pub fn sha256(data: &[u8]) -> String {
     format!("{:x}", sha2::Sha256::digest(data))
 }
+
+struct Sha256Writer<W: Write> {
+    inner: W,
+    hasher: sha2::Sha256,
+}
+
+impl<W: Write> Sha256Writer<W> {
+    fn new(inner: W) -> Self {
+        Sha256Writer::with_hasher(inner, sha2::Sha256::new())
+    }
+
+    fn with_hasher(inner: W, hasher: sha2::Sha256) -> Self {
+        Sha256Writer { inner, hasher }
+    }
+
+    fn finish(mut self) -> io::Result<String> {
+        self.inner.flush()?;
+        Ok(format!("{:x}", self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for Sha256Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    #[test]
+    fn parse_env_names_invalid_variable() -> anyhow::Result<()> {
+        let var = |key: &str| match key {
+            "RETRIES" => Some(String::from(" 5 ")),
+            "TIMEOUT" => Some(String::from("soon")),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        };
+
+        assert_eq!(parse_env::<u32>(&var, "RETRIES", "a number")?, Some(5));
+        assert_eq!(parse_env::<u32>(&var, "EMPTY", "a number")?, None);
+        assert_eq!(parse_env::<u32>(&var, "MISSING", "a number")?, None);
+        assert_eq!(
+            parse_env::<u64>(&var, "TIMEOUT", "a number of seconds")
+                .unwrap_err()
+                .to_string(),
+            "Invalid TIMEOUT \"soon\", expected a number of seconds"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_verified_moves_file_into_place_on_match() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dst = dir.path().join("runtime.jar");
+
+        let actual = write_verified(&b"hello"[..], &dst, Some(&sha256(b"hello")))?;
+
+        assert_eq!(actual, sha256(b"hello"));
+        assert_eq!(fs::read(&dst)?, b"hello");
+        assert!(!dst.with_extension("partial").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_verified_leaves_nothing_behind_on_mismatch() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dst = dir.path().join("runtime.jar");
+
+        let err = write_verified(&b"hello"[..], &dst, Some("ABCDEF")).unwrap_err();
+
+        match err {
+            Error::ChecksumMismatch { actual, .. } => assert_eq!(actual, sha256(b"hello")),
+            err => panic!("unexpected error {}", err),
+        }
+        assert!(!dst.exists());
+        assert!(!dst.with_extension("partial").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_verified_without_expectation_returns_digest() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dst = dir.path().join("runtime.jar");
+
+        assert_eq!(write_verified(&b"hello"[..], &dst, None)?, sha256(b"hello"));
+        assert!(dst.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn download_resumes_interrupted_transfer() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}/runtime.jar", listener.local_addr()?);
+        let server = thread::spawn(move || -> io::Result<Vec<String>> {
+            let mut requests = Vec::new();
+            // The first response promises the whole body but ends halfway.
+            for response in &[
+                "HTTP/1.1 200 OK\r\nContent-Length: 11\r\nETag: \"v1\"\r\n\
+                 Connection: close\r\n\r\nhello",
+                "HTTP/1.1 206 Partial Content\r\nContent-Length: 6\r\n\
+                 Content-Range: bytes 5-10/11\r\nETag: \"v1\"\r\nConnection: close\r\n\r\n world",
+            ] {
+                let (mut stream, _) = listener.accept()?;
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request)?;
+                requests.push(String::from_utf8_lossy(&request[..read]).to_lowercase());
+                stream.write_all(response.as_bytes())?;
+            }
+            Ok(requests)
+        });
+        let dir = tempfile::tempdir()?;
+        let dst = dir.path().join("runtime.jar");
+        let retry_policy = RetryPolicy {
+            attempts: 2,
+            initial_backoff_ms: 0,
+            ..RetryPolicy::default()
+        };
+
+        let actual = download_verified(
+            &reqwest::blocking::Client::new(),
+            &url,
+            &dst,
+            Some(&sha256(b"hello world")),
+            &retry_policy,
+            |_| {},
+        )?;
+
+        assert_eq!(actual, sha256(b"hello world"));
+        assert_eq!(fs::read(&dst)?, b"hello world");
+        assert!(!dst.with_extension("partial").exists());
+        let requests = server.join().unwrap()?;
+        assert!(!requests[0].contains("range:"));
+        assert!(requests[1].contains("range: bytes=5-"));
+        assert!(requests[1].contains("if-range: \"v1\""));
+
+        Ok(())
+    }
+
+    #[test]
+    fn download_does_not_retry_client_errors() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = listener.local_addr()?;
+        let server = thread::spawn(move || -> io::Result<usize> {
+            let mut requests = 0;
+            loop {
+                let (mut stream, _) = listener.accept()?;
+                let mut request = [0; 4096];
+                let read = stream.read(&mut request)?;
+                if request[..read].starts_with(b"GET /stop ") {
+                    return Ok(requests);
+                }
+                requests += 1;
+                stream.write_all(
+                    b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                )?;
+            }
+        });
+        let dir = tempfile::tempdir()?;
+        let retry_policy = RetryPolicy {
+            attempts: 3,
+            initial_backoff_ms: 0,
+            ..RetryPolicy::default()
+        };
+
+        let result = download_verified(
+            &reqwest::blocking::Client::new(),
+            format!("http://{}/runtime.jar", address),
+            dir.path().join("runtime.jar"),
+            None,
+            &retry_policy,
+            |_| {},
+        );
+        TcpStream::connect(address)?.write_all(b"GET /stop HTTP/1.1\r\n\r\n")?;
+
+        assert!(matches!(
+            result,
+            Err(Error::Download { ref source, .. }) if source.status() == Some(StatusCode::NOT_FOUND)
+        ));
+        assert_eq!(server.join().unwrap()?, 1);
+
+        Ok(())
+    }
+}
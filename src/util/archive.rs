@@ -0,0 +1,183 @@
+use anyhow::Context;
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+/// Compression of a tar archive, detected from the file name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// `None` for anything that isn't a compressed tar archive, like a plain jar.
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        // Ignores query strings and fragments of URLs.
+        let file_name = file_name
+            .split(|c| c == '?' || c == '#')
+            .next()
+            .unwrap_or(file_name)
+            .to_ascii_lowercase();
+
+        if file_name.ends_with(".tar.gz") || file_name.ends_with(".tgz") {
+            Some(Compression::Gzip)
+        } else if file_name.ends_with(".zst") || file_name.ends_with(".tzst") {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Extension used for the archive on disk, so later builds detect the same format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Compression::Gzip => "tar.gz",
+            Compression::Zstd => "tar.zst",
+        }
+    }
+
+    fn decoder<'a>(self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(flate2::read::GzDecoder::new(reader)),
+            Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        })
+    }
+}
+
+/// Unpacks the compressed tar archive at `path` into `dst`. Entries outside of `dst` are skipped.
+pub fn unpack(
+    path: impl AsRef<Path>,
+    compression: Compression,
+    dst: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("Could not open {}", path.display()))?;
+
+    fs::create_dir_all(dst.as_ref())?;
+    tar::Archive::new(compression.decoder(BufReader::new(file))?)
+        .unpack(dst.as_ref())
+        .with_context(|| format!("Could not unpack {}", path.display()))
+}
+
+/// The jar to run from an unpacked archive: the only jar, or the only
+/// `*-jar-with-dependencies.jar` if the archive ships additional jars.
+pub fn find_jar(dir: impl AsRef<Path>) -> anyhow::Result<PathBuf> {
+    let mut jars = Vec::new();
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else if path
+                .extension()
+                .map_or(false, |extension| extension == "jar")
+            {
+                jars.push(path);
+            }
+        }
+    }
+    jars.sort();
+
+    let candidates = if jars.len() > 1 {
+        jars.iter()
+            .filter(|path| {
+                path.to_string_lossy()
+                    .ends_with("-jar-with-dependencies.jar")
+            })
+            .collect::<Vec<_>>()
+    } else {
+        jars.iter().collect()
+    };
+
+    match candidates.as_slice() {
+        [jar] => Ok(jar.to_path_buf()),
+        [] if jars.is_empty() => anyhow::bail!("The archive does not contain a jar"),
+        _ => anyhow::bail!(
+            "The archive contains multiple jars, expected a single one or a single \
+             *-jar-with-dependencies.jar: {}",
+            jars.iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compression_is_detected_from_file_name() {
+        assert_eq!(
+            Compression::from_file_name("https://foo.com/runtime-1.0.0.tar.gz"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_file_name("runtime.TGZ"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_file_name("https://foo.com/runtime.tar.zst?token=1"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_file_name("runtime.jar"), None);
+        assert_eq!(Compression::from_file_name("runtime.jar?format=.tgz"), None);
+    }
+
+    #[test]
+    fn find_jar_prefers_jar_with_dependencies() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("lib"))?;
+        fs::write(
+            dir.path().join("runtime-1.0.0-jar-with-dependencies.jar"),
+            "",
+        )?;
+        fs::write(dir.path().join("lib").join("agent.jar"), "")?;
+
+        assert_eq!(
+            find_jar(dir.path())?,
+            dir.path().join("runtime-1.0.0-jar-with-dependencies.jar")
+        );
+
+        fs::write(
+            dir.path()
+                .join("lib")
+                .join("other-jar-with-dependencies.jar"),
+            "",
+        )?;
+        assert!(find_jar(dir.path()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unpack_extracts_gzip_archive() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let archive_path = dir.path().join("runtime.tar.gz");
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            File::create(&archive_path)?,
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(7);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, "runtime/runtime.jar", &b"runtime"[..])?;
+        builder.into_inner()?.finish()?;
+
+        let dst = dir.path().join("unpacked");
+        unpack(&archive_path, Compression::Gzip, &dst)?;
+
+        let jar = find_jar(&dst)?;
+        assert_eq!(jar, dst.join("runtime").join("runtime.jar"));
+        assert_eq!(fs::read_to_string(jar)?, "runtime");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{
+    error::{Error, Result},
+    util,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Downloaded artifacts keyed by their sha256, so the same content is never fetched twice while
+/// it stays in the store. Backed by a cache layer shared by everything the build downloads.
+pub struct ArtifactStore {
+    dir: PathBuf,
+}
+
+impl ArtifactStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        ArtifactStore { dir: dir.into() }
+    }
+
+    fn path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(sha256.to_ascii_lowercase())
+    }
+
+    pub fn contains(&self, sha256: &str) -> bool {
+        self.path(sha256).is_file()
+    }
+
+    /// Copies the artifact with the given sha256 to `dst`. Returns `false` if the store doesn't
+    /// have it, including when the stored copy is corrupt, which is removed.
+    pub fn restore(&self, sha256: &str, dst: impl AsRef<Path>) -> Result<bool> {
+        let path = self.path(sha256);
+        if !path.is_file() {
+            return Ok(false);
+        }
+
+        match util::copy_verified(&path, dst, Some(&sha256.to_ascii_lowercase())) {
+            Ok(_) => {
+                // Marks the artifact as recently used for `prune`.
+                fs::OpenOptions::new()
+                    .write(true)
+                    .open(&path)?
+                    .set_modified(SystemTime::now())?;
+                Ok(true)
+            }
+            Err(Error::ChecksumMismatch { .. }) => {
+                fs::remove_file(&path)?;
+                Ok(false)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Adds `src`, which must match `sha256`, to the store.
+    pub fn store(&self, src: impl AsRef<Path>, sha256: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        util::copy_verified(src, self.path(sha256), Some(&sha256.to_ascii_lowercase()))?;
+
+        Ok(())
+    }
+
+    /// Removes all but the `keep` most recently used artifacts and returns the removed digests.
+    pub fn prune(&self, keep: usize) -> io::Result<Vec<String>> {
+        if !self.dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        let mut artifacts = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                artifacts.push((entry.metadata()?.modified()?, entry.path()));
+            }
+        }
+        artifacts.sort_by(|a, b| b.cmp(a));
+
+        let mut removed = Vec::new();
+        for (_, path) in artifacts.into_iter().skip(keep) {
+            fs::remove_file(&path)?;
+            removed.extend(
+                path.file_name()
+                    .map(|file_name| file_name.to_string_lossy().into_owned()),
+            );
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn restore_returns_stored_artifact() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ArtifactStore::new(dir.path().join("store"));
+        let src = dir.path().join("runtime.jar");
+        fs::write(&src, "runtime")?;
+        let sha256 = util::sha256(b"runtime");
+        let dst = dir.path().join("restored.jar");
+
+        assert!(!store.restore(&sha256, &dst)?);
+
+        store.store(&src, &sha256)?;
+        assert!(store.restore(&sha256.to_ascii_uppercase(), &dst)?);
+        assert_eq!(fs::read_to_string(&dst)?, "runtime");
+
+        Ok(())
+    }
+
+    #[test]
+    fn restore_removes_corrupt_artifact() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ArtifactStore::new(dir.path());
+        let sha256 = util::sha256(b"runtime");
+        fs::write(dir.path().join(&sha256), "tampered")?;
+
+        assert!(!store.restore(&sha256, dir.path().join("restored.jar"))?);
+        assert!(!dir.path().join(&sha256).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn prune_keeps_most_recently_used() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let store = ArtifactStore::new(dir.path());
+        let now = SystemTime::now();
+        for (name, age_secs) in &[("a", 30), ("b", 10), ("c", 20)] {
+            fs::write(dir.path().join(name), name)?;
+            fs::OpenOptions::new()
+                .write(true)
+                .open(dir.path().join(name))?
+                .set_modified(now - Duration::from_secs(*age_secs))?;
+        }
+
+        assert_eq!(store.prune(2)?, vec![String::from("a")]);
+        assert!(dir.path().join("b").exists());
+        assert!(dir.path().join("c").exists());
+
+        Ok(())
+    }
+}
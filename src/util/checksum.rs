@@ -0,0 +1,222 @@
+use sha2::Digest;
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// Hash algorithms artifacts can be published with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha256,
+    Sha512,
+    Blake3,
+}
+
+impl Algorithm {
+    fn hex_len(self) -> usize {
+        match self {
+            Algorithm::Sha256 | Algorithm::Blake3 => 64,
+            Algorithm::Sha512 => 128,
+        }
+    }
+
+    /// Hashes everything written to the returned writer, see [`Hasher::finish`].
+    fn hasher(self) -> Hasher {
+        match self {
+            Algorithm::Sha256 => Hasher::Sha256(sha2::Sha256::new()),
+            Algorithm::Sha512 => Hasher::Sha512(sha2::Sha512::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+            Algorithm::Blake3 => "blake3",
+        })
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "sha256" => Ok(Algorithm::Sha256),
+            "sha512" => Ok(Algorithm::Sha512),
+            "blake3" => Ok(Algorithm::Blake3),
+            _ => Err(format!(
+                "unsupported checksum algorithm {:?}, expected sha256, sha512 or blake3",
+                s
+            )),
+        }
+    }
+}
+
+/// A digest in the `<algorithm>:<hex>` format of buildpack metadata, e.g. `sha512:0a1b…`.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// Lowercase hex digest.
+    pub hex: String,
+}
+
+impl Checksum {
+    pub fn sha256(hex: impl AsRef<str>) -> Self {
+        Checksum {
+            algorithm: Algorithm::Sha256,
+            hex: hex.as_ref().to_ascii_lowercase(),
+        }
+    }
+
+    pub fn of_file(algorithm: Algorithm, path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut hasher = algorithm.hasher();
+        io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+
+        Ok(Checksum {
+            algorithm,
+            hex: hasher.finish(),
+        })
+    }
+
+    pub fn matches_file(&self, path: impl AsRef<Path>) -> io::Result<bool> {
+        Ok(Checksum::of_file(self.algorithm, path)? == *self)
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hex)
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (algorithm, hex) = s
+            .split_once(':')
+            .ok_or_else(|| format!("invalid checksum {:?}, expected <algorithm>:<hex>", s))?;
+        let algorithm = algorithm.parse::<Algorithm>()?;
+        if hex.len() != algorithm.hex_len() || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid {} checksum {:?}, expected {} hex digits",
+                algorithm,
+                hex,
+                algorithm.hex_len()
+            ));
+        }
+
+        Ok(Checksum {
+            algorithm,
+            hex: hex.to_ascii_lowercase(),
+        })
+    }
+}
+
+impl TryFrom<String> for Checksum {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+enum Hasher {
+    Sha256(sha2::Sha256),
+    Sha512(sha2::Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    fn finish(self) -> String {
+        match self {
+            Hasher::Sha256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Sha512(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+        }
+    }
+}
+
+impl Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(buf),
+            Hasher::Sha512(hasher) => hasher.update(buf),
+            Hasher::Blake3(hasher) => {
+                hasher.update(buf);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_str_parses_algorithm_prefix() {
+        let sha512 = format!("SHA512:{}", "AB".repeat(64));
+        assert_eq!(
+            sha512.parse::<Checksum>(),
+            Ok(Checksum {
+                algorithm: Algorithm::Sha512,
+                hex: "ab".repeat(64),
+            })
+        );
+        assert_eq!(
+            format!("sha256:{}", "ab".repeat(32)).parse::<Checksum>(),
+            Ok(Checksum {
+                algorithm: Algorithm::Sha256,
+                hex: "ab".repeat(32),
+            })
+        );
+        assert!(format!("sha256:{}", "ab".repeat(64))
+            .parse::<Checksum>()
+            .is_err());
+
+        assert!("ab".repeat(32).parse::<Checksum>().is_err());
+        assert!(format!("md5:{}", "ab".repeat(16))
+            .parse::<Checksum>()
+            .is_err());
+        assert!(format!("sha512:{}", "ab".repeat(32))
+            .parse::<Checksum>()
+            .is_err());
+    }
+
+    #[test]
+    fn of_file_hashes_with_each_algorithm() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("artifact");
+        fs::write(&path, "hello")?;
+
+        assert_eq!(
+            Checksum::of_file(Algorithm::Sha256, &path)?,
+            Checksum::sha256("2CF24DBA5FB0A30E26E83B2AC5B9E29E1B161E5C1FA7425E73043362938B9824")
+        );
+        assert_eq!(
+            Checksum::of_file(Algorithm::Sha512, &path)?.hex,
+            "9b71d224bd62f3785d96d46ad3ea3d73319bfbc2890caadae2dff72519673ca7\
+             2323c3d99ba5c11d7c7acc6e14b8c5da0c4663475c2e5c3adef46f73bcdec043"
+        );
+        assert_eq!(
+            Checksum::of_file(Algorithm::Blake3, &path)?.hex,
+            "ea8f163db38682925e4491c5e58d4bb3506ef8c14eb78a86e908c5624a67200f"
+        );
+
+        Ok(())
+    }
+}
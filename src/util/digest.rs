@@ -0,0 +1,85 @@
+use sha2::{Digest, Sha256};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Computes a sha256 over the relative paths and contents of all files below `root` accepted by
+/// `filter`. Files are visited in sorted order so the digest is stable across file systems.
+pub fn digest_tree(root: impl AsRef<Path>, filter: impl Fn(&Path) -> bool) -> io::Result<String> {
+    let root = root.as_ref();
+    let mut files = Vec::new();
+    if root.is_dir() {
+        collect_files(root, &filter, &mut files)?;
+    }
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(
+            file.strip_prefix(root)
+                .unwrap_or(&file)
+                .to_string_lossy()
+                .as_bytes(),
+        );
+        hasher.update(&[0]);
+        hasher.update(&fs::read(&file)?);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn collect_files(
+    dir: &Path,
+    filter: &impl Fn(&Path) -> bool,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, filter, files)?;
+        } else if filter(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_class(path: &Path) -> bool {
+        path.extension()
+            .map_or(false, |extension| extension == "class")
+    }
+
+    #[test]
+    fn digest_tree_changes_with_content_and_ignores_filtered_files() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("classes"))?;
+        fs::write(dir.path().join("classes/Function.class"), "v1")?;
+
+        let first = digest_tree(dir.path(), is_class)?;
+        fs::write(dir.path().join("report.txt"), "ignored")?;
+        assert_eq!(digest_tree(dir.path(), is_class)?, first);
+
+        fs::write(dir.path().join("classes/Function.class"), "v2")?;
+        assert_ne!(digest_tree(dir.path(), is_class)?, first);
+
+        Ok(())
+    }
+
+    #[test]
+    fn digest_tree_of_missing_dir_is_digest_of_nothing() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+
+        assert_eq!(
+            digest_tree(dir.path().join("missing"), is_class)?,
+            digest_tree(dir.path(), is_class)?
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, PartialEq)]
+pub struct DiskUsage {
+    pub total_bytes: u64,
+    /// Largest jar files below the root, largest first, with paths relative to the root.
+    pub largest_jars: Vec<(PathBuf, u64)>,
+}
+
+/// Sums the sizes of all files below `root` and collects the `top` largest jars. Symlinks are
+/// not followed.
+pub fn disk_usage(root: impl AsRef<Path>, top: usize) -> io::Result<DiskUsage> {
+    let root = root.as_ref();
+    let mut usage = DiskUsage::default();
+    let mut jars = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(entry.path());
+            } else if file_type.is_file() {
+                let size = entry.metadata()?.len();
+                usage.total_bytes += size;
+
+                let path = entry.path();
+                if path
+                    .extension()
+                    .map_or(false, |extension| extension == "jar")
+                {
+                    jars.push((path.strip_prefix(root).unwrap_or(&path).to_path_buf(), size));
+                }
+            }
+        }
+    }
+
+    jars.sort_by(|(a_path, a_size), (b_path, b_size)| {
+        b_size.cmp(a_size).then_with(|| a_path.cmp(b_path))
+    });
+    jars.truncate(top);
+    usage.largest_jars = jars;
+
+    Ok(usage)
+}
+
+/// Removes every entry directly in `dir` that `keep` rejects, directories with their contents,
+/// and returns the number of bytes reclaimed.
+pub fn remove_stale(dir: impl AsRef<Path>, keep: impl Fn(&Path) -> bool) -> io::Result<u64> {
+    let mut reclaimed = 0;
+    for entry in fs::read_dir(dir.as_ref())? {
+        let entry = entry?;
+        let path = entry.path();
+        if keep(&path) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            reclaimed += disk_usage(&path, 0)?.total_bytes;
+            fs::remove_dir_all(&path)?;
+        } else {
+            reclaimed += entry.metadata()?.len();
+            fs::remove_file(&path)?;
+        }
+    }
+
+    Ok(reclaimed)
+}
+
+pub fn mebibytes(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disk_usage_sums_files_and_sorts_jars() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("lib"))?;
+        fs::write(dir.path().join("function-bundle.toml"), vec![0; 10])?;
+        fs::write(dir.path().join("lib").join("small.jar"), vec![0; 100])?;
+        fs::write(dir.path().join("lib").join("large.jar"), vec![0; 1000])?;
+        fs::write(dir.path().join("lib").join("medium.jar"), vec![0; 500])?;
+
+        let usage = disk_usage(dir.path(), 2)?;
+        assert_eq!(usage.total_bytes, 1610);
+        assert_eq!(
+            usage.largest_jars,
+            vec![
+                (PathBuf::from("lib/large.jar"), 1000),
+                (PathBuf::from("lib/medium.jar"), 500)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn remove_stale_keeps_selected_entries() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("runtime").join("lib"))?;
+        fs::write(
+            dir.path().join("runtime").join("lib").join("old.jar"),
+            vec![0; 300],
+        )?;
+        fs::write(dir.path().join("runtime.tar.gz"), vec![0; 200])?;
+        fs::write(dir.path().join("runtime.jar"), vec![0; 100])?;
+
+        let reclaimed = remove_stale(dir.path(), |path| path.ends_with("runtime.jar"))?;
+
+        assert_eq!(reclaimed, 500);
+        assert!(dir.path().join("runtime.jar").exists());
+        assert!(!dir.path().join("runtime").exists());
+        assert!(!dir.path().join("runtime.tar.gz").exists());
+
+        Ok(())
+    }
+}
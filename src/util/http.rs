@@ -0,0 +1,211 @@
+use crate::util::{self, proxy::ProxyConfig};
+use anyhow::Context;
+use reqwest::{
+    blocking::{Client, RequestBuilder},
+    header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Certificate, Proxy,
+};
+use serde::Deserialize;
+use std::{fs, path::PathBuf, time::Duration};
+
+const PEM_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_END: &str = "-----END CERTIFICATE-----";
+
+/// Settings shared by every HTTP request the buildpack makes.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: u64,
+    /// Limit for a whole request including its body, generous enough for large runtime jars.
+    pub timeout_secs: u64,
+    /// PEM bundle with additional trusted CA certificates.
+    pub ca_file: Option<PathBuf>,
+    /// Only trust `ca_file`, e.g. behind TLS intercepting proxies.
+    pub disable_builtin_roots: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout_secs: 10,
+            timeout_secs: 600,
+            ca_file: None,
+            disable_builtin_roots: false,
+        }
+    }
+}
+
+/// Response validators of an earlier download. Sending them back lets the server answer
+/// `304 Not Modified` instead of the same content again.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+
+        Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+
+    /// Value for an `If-Range` header, so a resumed download only continues the same content.
+    /// Weak ETags can't be used for ranges.
+    pub fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or_else(|| self.last_modified.as_deref())
+    }
+
+    /// Makes `request` conditional on the content having changed.
+    pub fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        request
+    }
+}
+
+impl HttpConfig {
+    /// `SSL_CERT_FILE` and the download timeouts from the build environment take precedence over
+    /// the configured values.
+    pub fn merge_env(mut self, var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        if let Some(ca_file) = var("SSL_CERT_FILE").filter(|value| !value.is_empty()) {
+            self.ca_file = Some(PathBuf::from(ca_file));
+        }
+        if let Some(secs) = util::parse_env(
+            &var,
+            "SF_FUNCTIONS_DOWNLOAD_CONNECT_TIMEOUT",
+            "a number of seconds",
+        )? {
+            self.connect_timeout_secs = secs;
+        }
+        if let Some(secs) =
+            util::parse_env(&var, "SF_FUNCTIONS_DOWNLOAD_TIMEOUT", "a number of seconds")?
+        {
+            self.timeout_secs = secs;
+        }
+
+        Ok(self)
+    }
+
+    /// A client with connection pooling and TCP keep-alive, so retries and subsequent requests to
+    /// the same host reuse connections.
+    pub fn client(&self, proxy: &ProxyConfig) -> anyhow::Result<Client> {
+        let proxy = proxy.clone();
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .tcp_keepalive(Duration::from_secs(60))
+            .no_proxy()
+            .proxy(Proxy::custom(move |url| proxy.proxy_for(url)))
+            .tls_built_in_root_certs(!self.disable_builtin_roots);
+
+        if let Some(ca_file) = &self.ca_file {
+            let pem = fs::read_to_string(ca_file)
+                .with_context(|| format!("Could not read CA certificates {}", ca_file.display()))?;
+            let certificates = pem_certificates(&pem);
+            if certificates.is_empty() {
+                anyhow::bail!("No PEM certificates found in {}", ca_file.display());
+            }
+
+            for certificate in certificates {
+                builder =
+                    builder.add_root_certificate(Certificate::from_pem(certificate.as_bytes())?);
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+}
+
+/// Splits a PEM bundle into its certificates, skipping anything between them.
+pub fn pem_certificates(pem: &str) -> Vec<&str> {
+    let mut certificates = Vec::new();
+    let mut rest = pem;
+
+    while let Some(start) = rest.find(PEM_BEGIN) {
+        match rest[start..].find(PEM_END) {
+            Some(end) => {
+                let end = start + end + PEM_END.len();
+                certificates.push(&rest[start..end]);
+                rest = &rest[end..];
+            }
+            None => break,
+        }
+    }
+
+    certificates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pem_certificates_splits_bundle() {
+        let pem = format!(
+            "# Root CA\n{}\nAAAA\n{}\n\n{}\nBBBB\n{}\n{}\ntruncated",
+            PEM_BEGIN, PEM_END, PEM_BEGIN, PEM_END, PEM_BEGIN
+        );
+
+        assert_eq!(
+            pem_certificates(&pem),
+            vec![
+                format!("{}\nAAAA\n{}", PEM_BEGIN, PEM_END),
+                format!("{}\nBBBB\n{}", PEM_BEGIN, PEM_END)
+            ]
+        );
+        assert!(pem_certificates("not a certificate").is_empty());
+    }
+
+    #[test]
+    fn merge_env_prefers_build_environment() -> anyhow::Result<()> {
+        let config = HttpConfig {
+            ca_file: Some(PathBuf::from("/buildpack/ca.pem")),
+            ..HttpConfig::default()
+        };
+
+        assert_eq!(config.clone().merge_env(|_| None)?, config);
+
+        let merged = config.merge_env(|key| match key {
+            "SSL_CERT_FILE" => Some(String::from("/etc/ssl/custom.pem")),
+            "SF_FUNCTIONS_DOWNLOAD_CONNECT_TIMEOUT" => Some(String::from("30")),
+            "SF_FUNCTIONS_DOWNLOAD_TIMEOUT" => Some(String::from("1200")),
+            _ => None,
+        })?;
+        assert_eq!(merged.ca_file, Some(PathBuf::from("/etc/ssl/custom.pem")));
+        assert_eq!(merged.connect_timeout_secs, 30);
+        assert_eq!(merged.timeout_secs, 1200);
+
+        Ok(())
+    }
+
+    #[test]
+    fn merge_env_rejects_invalid_timeouts() {
+        let merged = HttpConfig::default().merge_env(|key| match key {
+            "SF_FUNCTIONS_DOWNLOAD_TIMEOUT" => Some(String::from("10m")),
+            _ => None,
+        });
+
+        assert!(merged.is_err());
+    }
+}
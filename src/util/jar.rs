@@ -0,0 +1,246 @@
+use flate2::read::DeflateDecoder;
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+const MANIFEST_PATH: &str = "META-INF/MANIFEST.MF";
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x0201_4b50;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+/// The end of central directory record plus the longest possible archive comment.
+const MAX_END_OF_CENTRAL_DIRECTORY_LEN: u64 = 22 + 0xffff;
+
+/// The `Main-Class` attribute of the manifest of the jar at `path`, if it has one.
+pub fn main_class(path: impl AsRef<Path>) -> io::Result<Option<String>> {
+    Ok(manifest(path)?.and_then(|manifest| manifest_attribute(&manifest, "Main-Class")))
+}
+
+/// Names of all entries of the jar at `path`, e.g. `com/example/Function.class`.
+pub fn entry_names(path: impl AsRef<Path>) -> io::Result<Vec<String>> {
+    Ok(central_directory(&mut File::open(path)?)?
+        .into_iter()
+        .map(|entry| String::from_utf8_lossy(&entry.name).into_owned())
+        .collect())
+}
+
+/// Reads `META-INF/MANIFEST.MF` from the jar at `path`. Only stored and deflated entries are
+/// supported, which covers every jar the JDK tools write.
+fn manifest(path: impl AsRef<Path>) -> io::Result<Option<String>> {
+    let mut file = File::open(path)?;
+    match central_directory(&mut file)?
+        .into_iter()
+        .find(|entry| entry.name == MANIFEST_PATH.as_bytes())
+    {
+        Some(entry) => read_entry(
+            &mut file,
+            entry.local_header_offset,
+            entry.method,
+            entry.compressed_len,
+        )
+        .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// An entry of the central directory, which lists every file of the jar.
+struct CentralEntry {
+    name: Vec<u8>,
+    method: u16,
+    compressed_len: u64,
+    local_header_offset: u64,
+}
+
+fn central_directory(file: &mut File) -> io::Result<Vec<CentralEntry>> {
+    let len = file.seek(SeekFrom::End(0))?;
+    let tail_len = len.min(MAX_END_OF_CENTRAL_DIRECTORY_LEN);
+    let mut tail = vec![0; tail_len as usize];
+    file.seek(SeekFrom::Start(len - tail_len))?;
+    file.read_exact(&mut tail)?;
+
+    let eocd = (0..tail.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(&tail, i) == END_OF_CENTRAL_DIRECTORY_SIGNATURE)
+        .ok_or_else(|| invalid("missing end of central directory record"))?;
+    let entries = u16_at(&tail, eocd + 10);
+    let mut central_directory = vec![0; u32_at(&tail, eocd + 12) as usize];
+    file.seek(SeekFrom::Start(u64::from(u32_at(&tail, eocd + 16))))?;
+    file.read_exact(&mut central_directory)?;
+
+    let mut offset = 0;
+    let mut central_entries = Vec::with_capacity(usize::from(entries));
+    for _ in 0..entries {
+        let header = central_directory
+            .get(offset..offset + 46)
+            .filter(|header| u32_at(header, 0) == CENTRAL_DIRECTORY_SIGNATURE)
+            .ok_or_else(|| invalid("corrupt central directory"))?;
+        let name_len = usize::from(u16_at(header, 28));
+        let entry_len =
+            46 + name_len + usize::from(u16_at(header, 30)) + usize::from(u16_at(header, 32));
+        let name = central_directory
+            .get(offset + 46..offset + 46 + name_len)
+            .ok_or_else(|| invalid("corrupt central directory"))?;
+
+        central_entries.push(CentralEntry {
+            name: name.to_vec(),
+            method: u16_at(header, 10),
+            compressed_len: u64::from(u32_at(header, 20)),
+            local_header_offset: u64::from(u32_at(header, 42)),
+        });
+        offset += entry_len;
+    }
+
+    Ok(central_entries)
+}
+
+fn read_entry(
+    file: &mut File,
+    local_header_offset: u64,
+    method: u16,
+    compressed_len: u64,
+) -> io::Result<String> {
+    let mut header = [0; 30];
+    file.seek(SeekFrom::Start(local_header_offset))?;
+    file.read_exact(&mut header)?;
+    if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+        return Err(invalid("corrupt local file header"));
+    }
+    let extra_len = i64::from(u16_at(&header, 26)) + i64::from(u16_at(&header, 28));
+    file.seek(SeekFrom::Current(extra_len))?;
+
+    let mut compressed = file.take(compressed_len);
+    let mut contents = String::new();
+    match method {
+        0 => compressed.read_to_string(&mut contents)?,
+        8 => DeflateDecoder::new(compressed).read_to_string(&mut contents)?,
+        _ => return Err(invalid("unsupported compression method of manifest")),
+    };
+
+    Ok(contents)
+}
+
+/// Looks up a main section attribute, joining continuation lines, which start with a space.
+fn manifest_attribute(manifest: &str, name: &str) -> Option<String> {
+    let mut attributes: Vec<String> = Vec::new();
+    for line in manifest.lines() {
+        // The main section ends at the first blank line.
+        if line.is_empty() {
+            break;
+        }
+        match (line.strip_prefix(' '), attributes.last_mut()) {
+            (Some(continuation), Some(attribute)) => attribute.push_str(continuation),
+            _ => attributes.push(line.to_string()),
+        }
+    }
+
+    attributes.into_iter().find_map(|attribute| {
+        let (key, value) = attribute.split_once(':')?;
+        if key.eq_ignore_ascii_case(name) {
+            Some(value.trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(bytes[offset..offset + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn invalid(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid jar: {}", reason),
+    )
+}
+
+/// A jar with a single stored entry, laid out as `zip -0` writes it.
+#[cfg(test)]
+pub fn stored_jar(name: &str, contents: &str) -> Vec<u8> {
+    let name = name.as_bytes();
+    let contents = contents.as_bytes();
+    let crc = 0u32;
+    let mut jar = Vec::new();
+
+    jar.extend(&LOCAL_HEADER_SIGNATURE.to_le_bytes());
+    jar.extend(&[20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    jar.extend(&crc.to_le_bytes());
+    jar.extend(&(contents.len() as u32).to_le_bytes());
+    jar.extend(&(contents.len() as u32).to_le_bytes());
+    jar.extend(&(name.len() as u16).to_le_bytes());
+    jar.extend(&[0, 0]);
+    jar.extend(name);
+    jar.extend(contents);
+
+    let central_directory_offset = jar.len() as u32;
+    jar.extend(&CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    jar.extend(&[20, 0, 20, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    jar.extend(&crc.to_le_bytes());
+    jar.extend(&(contents.len() as u32).to_le_bytes());
+    jar.extend(&(contents.len() as u32).to_le_bytes());
+    jar.extend(&(name.len() as u16).to_le_bytes());
+    jar.extend(&[0; 12]);
+    jar.extend(&0u32.to_le_bytes());
+    jar.extend(name);
+    let central_directory_len = jar.len() as u32 - central_directory_offset;
+
+    jar.extend(&END_OF_CENTRAL_DIRECTORY_SIGNATURE.to_le_bytes());
+    jar.extend(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    jar.extend(&central_directory_len.to_le_bytes());
+    jar.extend(&central_directory_offset.to_le_bytes());
+    jar.extend(&[0, 0]);
+
+    jar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn main_class_reads_manifest() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("runtime.jar");
+        fs::write(
+            &path,
+            stored_jar(
+                MANIFEST_PATH,
+                "Manifest-Version: 1.0\r\nMain-Class: com.salesforce.functions.jvm.runtime.Java\r\n \
+                 FunctionRuntime\r\n\r\nName: com/example/\r\nMain-Class: Other\r\n",
+            ),
+        )?;
+        assert_eq!(
+            main_class(&path)?.as_deref(),
+            Some("com.salesforce.functions.jvm.runtime.JavaFunctionRuntime")
+        );
+
+        fs::write(&path, stored_jar(MANIFEST_PATH, "Manifest-Version: 1.0\n"))?;
+        assert_eq!(main_class(&path)?, None);
+
+        fs::write(&path, "not a jar")?;
+        assert_eq!(
+            main_class(&path).map_err(|err| err.kind()),
+            Err(io::ErrorKind::InvalidData)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn entry_names_lists_jar_entries() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("function.jar");
+        fs::write(&path, stored_jar("com/example/MyFunction.class", ""))?;
+
+        assert_eq!(entry_names(&path)?, vec!["com/example/MyFunction.class"]);
+
+        Ok(())
+    }
+}
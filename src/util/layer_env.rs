@@ -0,0 +1,130 @@
+use std::{fs, io, path::Path};
+
+/// How the lifecycle applies a layer environment file, see the CNB buildpack spec.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Modification {
+    Override,
+    Default,
+    Append,
+    Prepend,
+}
+
+impl Modification {
+    fn suffix(self) -> &'static str {
+        match self {
+            Modification::Override => "override",
+            Modification::Default => "default",
+            Modification::Append => "append",
+            Modification::Prepend => "prepend",
+        }
+    }
+}
+
+/// Writes `env.launch/<name>.<modification>` into the layer. Appending and prepending
+/// use a single space as delimiter.
+pub fn write_launch_env(
+    layer_dir: impl AsRef<Path>,
+    name: &str,
+    modification: Modification,
+    value: impl AsRef<str>,
+) -> io::Result<()> {
+    write_env(
+        &layer_dir.as_ref().join("env.launch"),
+        name,
+        modification,
+        value.as_ref(),
+    )
+}
+
+/// Like [`write_launch_env`], but only applies to processes of type `process_type`.
+pub fn write_process_launch_env(
+    layer_dir: impl AsRef<Path>,
+    process_type: &str,
+    name: &str,
+    modification: Modification,
+    value: impl AsRef<str>,
+) -> io::Result<()> {
+    write_env(
+        &layer_dir.as_ref().join("env.launch").join(process_type),
+        name,
+        modification,
+        value.as_ref(),
+    )
+}
+
+fn write_env(
+    env_dir: &Path,
+    name: &str,
+    modification: Modification,
+    value: &str,
+) -> io::Result<()> {
+    fs::create_dir_all(env_dir)?;
+    fs::write(
+        env_dir.join(format!("{}.{}", name, modification.suffix())),
+        value,
+    )?;
+
+    if let Modification::Append | Modification::Prepend = modification {
+        fs::write(env_dir.join(format!("{}.delim", name)), " ")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_launch_env_writes_value_and_delimiter() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+
+        write_launch_env(
+            layer_dir.path(),
+            "JAVA_TOOL_OPTIONS",
+            Modification::Prepend,
+            "-Xss512k",
+        )?;
+        write_launch_env(layer_dir.path(), "PORT", Modification::Default, "8080")?;
+
+        let env_dir = layer_dir.path().join("env.launch");
+        assert_eq!(
+            fs::read_to_string(env_dir.join("JAVA_TOOL_OPTIONS.prepend"))?,
+            "-Xss512k"
+        );
+        assert_eq!(
+            fs::read_to_string(env_dir.join("JAVA_TOOL_OPTIONS.delim"))?,
+            " "
+        );
+        assert_eq!(fs::read_to_string(env_dir.join("PORT.default"))?, "8080");
+        assert!(!env_dir.join("PORT.delim").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_process_launch_env_writes_into_process_dir() -> anyhow::Result<()> {
+        let layer_dir = tempfile::tempdir()?;
+
+        write_process_launch_env(
+            layer_dir.path(),
+            "orders",
+            "PORT",
+            Modification::Override,
+            "8081",
+        )?;
+
+        assert_eq!(
+            fs::read_to_string(
+                layer_dir
+                    .path()
+                    .join("env.launch")
+                    .join("orders")
+                    .join("PORT.override")
+            )?,
+            "8081"
+        );
+
+        Ok(())
+    }
+}
@@ -1,39 +1,521 @@
 use anyhow::anyhow;
-use std::{fmt::Display, io::Write};
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    io::{self, IsTerminal, Write},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 
-pub struct Logger {
-    debug: bool,
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LogFormat {
+    Human,
+    Json,
 }
 
-impl Logger {
+impl LogFormat {
+    /// Parses the value of `BPLOG_FORMAT`, anything but `json` selects the human format.
+    pub fn from_env_value(value: Option<&str>) -> Self {
+        match value {
+            Some(value) if value.eq_ignore_ascii_case("json") => LogFormat::Json,
+            _ => LogFormat::Human,
+        }
+    }
+}
+
+/// Least severe output that is shown. Errors are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl LogLevel {
+    /// Parses the value of `BPLOG_LEVEL`, `None` for unknown levels.
+    pub fn from_env_value(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoggerConfig {
+    pub level: LogLevel,
+    pub format: LogFormat,
+    pub color: ColorChoice,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        LoggerConfig {
+            level: LogLevel::Info,
+            format: LogFormat::Human,
+            color: color_choice(|_| None, io::stdout().is_terminal()),
+        }
+    }
+}
+
+impl LoggerConfig {
+    /// Resolves the logger settings from `BPLOG_LEVEL`, `HEROKU_BUILDPACK_DEBUG`, `BPLOG_FORMAT`,
+    /// `NO_COLOR` and `CLICOLOR_FORCE`. `BPLOG_LEVEL` wins over `HEROKU_BUILDPACK_DEBUG`.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let level = var("BPLOG_LEVEL")
+            .and_then(|value| LogLevel::from_env_value(&value))
+            .unwrap_or(if var("HEROKU_BUILDPACK_DEBUG").is_some() {
+                LogLevel::Debug
+            } else {
+                LogLevel::Info
+            });
+
+        LoggerConfig {
+            level,
+            format: LogFormat::from_env_value(var("BPLOG_FORMAT").as_deref()),
+            color: color_choice(&var, io::stdout().is_terminal()),
+        }
+    }
+}
+
+fn debug_level(debug: bool) -> LogLevel {
+    if debug {
+        LogLevel::Debug
+    } else {
+        LogLevel::Info
+    }
+}
+
+/// `CLICOLOR_FORCE` wins over `NO_COLOR`, which wins over terminal detection.
+fn color_choice(var: impl Fn(&str) -> Option<String>, is_terminal: bool) -> ColorChoice {
+    let is_set = |key: &str| var(key).map_or(false, |value| !value.is_empty() && value != "0");
+
+    if is_set("CLICOLOR_FORCE") {
+        ColorChoice::Always
+    } else if var("NO_COLOR").map_or(false, |value| !value.is_empty()) || !is_terminal {
+        ColorChoice::Never
+    } else {
+        ColorChoice::Auto
+    }
+}
+
+/// Destination for all user facing build output. `error` always returns an `Err` carrying the
+/// header, so call sites can bail with `?`.
+pub trait Logger {
+    fn header(&self, msg: impl Display) -> anyhow::Result<()>;
+    fn info(&self, msg: impl Display) -> anyhow::Result<()>;
+    fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()>;
+    fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()>;
+    fn debug(&self, msg: impl Display) -> anyhow::Result<()>;
+
+    /// Logs `lines` at debug level, prefixed like a unified diff.
+    fn debug_diff(&self, lines: &[DiffLine]) -> anyhow::Result<()> {
+        for line in lines {
+            self.debug(line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Indents info lines until the matching [`Logger::dedent`], see [`Section`].
+    fn indent(&self) {}
+
+    fn dedent(&self) {}
+
+    /// Whether debug output is shown, which keeps the detailed output of condensed steps.
+    fn is_debug(&self) -> bool {
+        false
+    }
+}
+
+/// A line of [`Logger::debug_diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine {
+    Unchanged(String),
+    Removed(String),
+    Added(String),
+}
+
+impl Display for DiffLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiffLine::Unchanged(line) => write!(f, "  {}", line),
+            DiffLine::Removed(line) => write!(f, "- {}", line),
+            DiffLine::Added(line) => write!(f, "+ {}", line),
+        }
+    }
+}
+
+/// Logs info lines at debug level while `condensed`, e.g. for steps that were cached.
+pub struct Condensed<'l, L: Logger> {
+    logger: &'l L,
+    condensed: bool,
+}
+
+impl<'l, L: Logger> Condensed<'l, L> {
+    pub fn new(logger: &'l L, condensed: bool) -> Self {
+        Condensed { logger, condensed }
+    }
+}
+
+impl<L: Logger> Logger for Condensed<'_, L> {
+    fn header(&self, msg: impl Display) -> anyhow::Result<()> {
+        self.logger.header(msg)
+    }
+
+    fn info(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.condensed {
+            self.logger.debug(msg)
+        } else {
+            self.logger.info(msg)
+        }
+    }
+
+    fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        self.logger.error(header, msg)
+    }
+
+    fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        self.logger.warning(header, msg)
+    }
+
+    fn debug(&self, msg: impl Display) -> anyhow::Result<()> {
+        self.logger.debug(msg)
+    }
+
+    fn debug_diff(&self, lines: &[DiffLine]) -> anyhow::Result<()> {
+        self.logger.debug_diff(lines)
+    }
+
+    fn indent(&self) {
+        self.logger.indent()
+    }
+
+    fn dedent(&self) {
+        self.logger.dedent()
+    }
+
+    fn is_debug(&self) -> bool {
+        self.logger.is_debug()
+    }
+}
+
+/// A build step: prints a header and indents the info lines logged while it is alive. Only a
+/// [`Section::finish`]ed step reports how long it took, one that fails just ends the indentation.
+pub struct Section<'l, L: Logger> {
+    logger: &'l L,
+    start: Instant,
+}
+
+impl<'l, L: Logger> Section<'l, L> {
+    pub fn new(logger: &'l L, header: impl Display) -> anyhow::Result<Self> {
+        logger.header(header)?;
+        logger.indent();
+
+        Ok(Section {
+            logger,
+            start: Instant::now(),
+        })
+    }
+
+    /// Reports the step as done, with how long it took.
+    pub fn finish(self) -> anyhow::Result<()> {
+        self.logger.info(format!(
+            "... done ({:.1}s)",
+            self.start.elapsed().as_secs_f64()
+        ))
+    }
+}
+
+impl<L: Logger> Drop for Section<'_, L> {
+    fn drop(&mut self) {
+        self.logger.dedent();
+    }
+}
+
+const INDENT: &str = "  ";
+
+/// Colored, human readable output on stdout and stderr.
+pub struct StandardLogger {
+    level: LogLevel,
+    color: ColorChoice,
+    depth: Cell<usize>,
+}
+
+impl StandardLogger {
     pub fn new(debug: bool) -> Self {
-        Logger { debug }
+        StandardLogger::from_config(LoggerConfig {
+            level: debug_level(debug),
+            ..LoggerConfig::default()
+        })
+    }
+
+    pub fn from_config(config: LoggerConfig) -> Self {
+        StandardLogger {
+            level: config.level,
+            color: config.color,
+            depth: Cell::new(0),
+        }
+    }
+}
+
+impl Logger for StandardLogger {
+    fn header(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.level < LogLevel::Info {
+            return Ok(());
+        }
+        write_header(self.color, msg)
+    }
+
+    fn info(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.level < LogLevel::Info {
+            return Ok(());
+        }
+        write_info(
+            self.color,
+            format!("{}{}", INDENT.repeat(self.depth.get()), msg),
+        )
     }
 
-    pub fn header(&self, msg: impl Display) -> anyhow::Result<()> {
-        Ok(header(msg)?)
+    fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        write_error(self.color, header, msg)
     }
 
-    pub fn info(&self, msg: impl Display) -> anyhow::Result<()> {
-        Ok(info(msg)?)
+    fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        if self.level < LogLevel::Warn {
+            return Ok(());
+        }
+        write_warning(self.color, header, msg)
     }
 
-    pub fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
-        Ok(error(header, msg)?)
+    fn debug(&self, msg: impl Display) -> anyhow::Result<()> {
+        write_debug(self.color, msg, self.level >= LogLevel::Debug)
     }
 
-    pub fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
-        Ok(warning(header, msg)?)
+    fn is_debug(&self) -> bool {
+        self.level >= LogLevel::Debug
     }
 
-    pub fn debug(&self, msg: impl Display) -> anyhow::Result<()> {
-        Ok(debug(msg, self.debug)?)
+    fn debug_diff(&self, lines: &[DiffLine]) -> anyhow::Result<()> {
+        if self.level < LogLevel::Debug {
+            return Ok(());
+        }
+        write_diff(self.color, lines)
     }
+
+    fn indent(&self) {
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    fn dedent(&self) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+}
+
+/// One JSON object per line, tagged with the most recent header as section.
+pub struct JsonLogger {
+    level: LogLevel,
+    section: RefCell<Option<String>>,
 }
 
-pub fn header(msg: impl Display) -> anyhow::Result<()> {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+impl JsonLogger {
+    pub fn new(debug: bool) -> Self {
+        JsonLogger::from_config(LoggerConfig {
+            level: debug_level(debug),
+            ..LoggerConfig::default()
+        })
+    }
+
+    pub fn from_config(config: LoggerConfig) -> Self {
+        JsonLogger {
+            level: config.level,
+            section: RefCell::new(None),
+        }
+    }
+
+    fn json(&self, mut out: impl Write, level: &str, msg: impl Display) -> anyhow::Result<()> {
+        let event = JsonEvent {
+            level,
+            section: self.section.borrow().clone(),
+            message: msg.to_string(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_millis() as u64)
+                .unwrap_or(0),
+        };
+        serde_json::to_writer(&mut out, &event)?;
+        writeln!(&mut out)?;
+
+        Ok(())
+    }
+}
+
+impl Logger for JsonLogger {
+    // The section is tracked even when headers are filtered, so warnings keep their context.
+    fn header(&self, msg: impl Display) -> anyhow::Result<()> {
+        self.section.replace(Some(msg.to_string()));
+        if self.level < LogLevel::Info {
+            return Ok(());
+        }
+        self.json(io::stdout(), "header", msg)
+    }
+
+    fn info(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.level < LogLevel::Info {
+            return Ok(());
+        }
+        self.json(io::stdout(), "info", msg)
+    }
+
+    fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        self.json(
+            io::stderr(),
+            "error",
+            format!("{}: {}", header, msg.to_string().trim()),
+        )?;
+        Err(anyhow!(format!("{}", header)))
+    }
+
+    fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        if self.level < LogLevel::Warn {
+            return Ok(());
+        }
+        self.json(
+            io::stdout(),
+            "warning",
+            format!("{}: {}", header, msg.to_string().trim()),
+        )
+    }
+
+    fn debug(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.level >= LogLevel::Debug {
+            self.json(io::stdout(), "debug", msg)?;
+        }
+
+        Ok(())
+    }
+
+    fn is_debug(&self) -> bool {
+        self.level >= LogLevel::Debug
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Header,
+    Info,
+    Error,
+    Warning,
+    Debug,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub level: Level,
+    pub header: Option<String>,
+    pub message: String,
+}
+
+/// Records all output in memory so tests can assert on it.
+#[derive(Default)]
+pub struct MemoryLogger {
+    pub debug: bool,
+    entries: RefCell<Vec<Entry>>,
+    depth: Cell<usize>,
+}
+
+impl MemoryLogger {
+    pub fn new(debug: bool) -> Self {
+        MemoryLogger {
+            debug,
+            ..MemoryLogger::default()
+        }
+    }
+
+    pub fn entries(&self) -> Vec<Entry> {
+        self.entries.borrow().clone()
+    }
+
+    pub fn messages(&self, level: Level) -> Vec<String> {
+        self.entries
+            .borrow()
+            .iter()
+            .filter(|entry| entry.level == level)
+            .map(|entry| entry.message.clone())
+            .collect()
+    }
+
+    fn push(&self, level: Level, header: Option<String>, message: impl Display) {
+        self.entries.borrow_mut().push(Entry {
+            level,
+            header,
+            message: message.to_string(),
+        });
+    }
+}
+
+impl Logger for MemoryLogger {
+    fn header(&self, msg: impl Display) -> anyhow::Result<()> {
+        self.push(Level::Header, None, msg);
+        Ok(())
+    }
+
+    fn info(&self, msg: impl Display) -> anyhow::Result<()> {
+        self.push(
+            Level::Info,
+            None,
+            format!("{}{}", INDENT.repeat(self.depth.get()), msg),
+        );
+        Ok(())
+    }
+
+    fn error(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        self.push(Level::Error, Some(header.to_string()), msg);
+        Err(anyhow!(format!("{}", header)))
+    }
+
+    fn warning(&self, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+        self.push(Level::Warning, Some(header.to_string()), msg);
+        Ok(())
+    }
+
+    fn debug(&self, msg: impl Display) -> anyhow::Result<()> {
+        if self.debug {
+            self.push(Level::Debug, None, msg);
+        }
+
+        Ok(())
+    }
+
+    fn indent(&self) {
+        self.depth.set(self.depth.get() + 1);
+    }
+
+    fn dedent(&self) {
+        self.depth.set(self.depth.get().saturating_sub(1));
+    }
+
+    fn is_debug(&self) -> bool {
+        self.debug
+    }
+}
+
+/// A single log line in JSON mode, `timestamp` is in milliseconds since the unix epoch.
+#[derive(Serialize)]
+struct JsonEvent<'a> {
+    level: &'a str,
+    section: Option<String>,
+    message: String,
+    timestamp: u64,
+}
+
+fn write_header(color: ColorChoice, msg: impl Display) -> anyhow::Result<()> {
+    let mut stdout = StandardStream::stdout(color);
     stdout.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)).set_bold(true))?;
     writeln!(&mut stdout, "\n[{}]", msg)?;
     stdout.reset()?;
@@ -41,16 +523,16 @@ pub fn header(msg: impl Display) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn info(msg: impl Display) -> anyhow::Result<()> {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+fn write_info(color: ColorChoice, msg: impl Display) -> anyhow::Result<()> {
+    let mut stdout = StandardStream::stdout(color);
     stdout.reset()?;
     writeln!(&mut stdout, "[INFO] {}", msg)?;
 
     Ok(())
 }
 
-pub fn error(header: impl Display, msg: impl Display) -> anyhow::Result<()> {
-    let mut stderr = StandardStream::stderr(ColorChoice::Always);
+fn write_error(color: ColorChoice, header: impl Display, msg: impl Display) -> anyhow::Result<()> {
+    let mut stderr = StandardStream::stderr(color);
     stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true))?;
     writeln!(&mut stderr, "\n[ERROR: {}]", header)?;
     stderr.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?;
@@ -60,9 +542,9 @@ pub fn error(header: impl Display, msg: impl Display) -> anyhow::Result<()> {
     Err(anyhow!(format!("{}", header)))
 }
 
-pub fn debug(msg: impl Display, debug: bool) -> anyhow::Result<()> {
+fn write_debug(color: ColorChoice, msg: impl Display, debug: bool) -> anyhow::Result<()> {
     if debug {
-        let mut stdout = StandardStream::stdout(ColorChoice::Always);
+        let mut stdout = StandardStream::stdout(color);
         stdout.reset()?;
         writeln!(&mut stdout, "[DEBUG] {}", msg)?;
     }
@@ -70,8 +552,27 @@ pub fn debug(msg: impl Display, debug: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-pub fn warning(header: impl Display, msg: impl Display) -> anyhow::Result<()> {
-    let mut stdout = StandardStream::stdout(ColorChoice::Always);
+fn write_diff(color: ColorChoice, lines: &[DiffLine]) -> anyhow::Result<()> {
+    let mut stdout = StandardStream::stdout(color);
+    for line in lines {
+        match line {
+            DiffLine::Unchanged(_) => stdout.reset()?,
+            DiffLine::Removed(_) => stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)))?,
+            DiffLine::Added(_) => stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?,
+        }
+        writeln!(&mut stdout, "[DEBUG] {}", line)?;
+    }
+    stdout.reset()?;
+
+    Ok(())
+}
+
+fn write_warning(
+    color: ColorChoice,
+    header: impl Display,
+    msg: impl Display,
+) -> anyhow::Result<()> {
+    let mut stdout = StandardStream::stdout(color);
     stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)).set_bold(true))?;
     writeln!(&mut stdout, "\n[WARNING: {}]", header)?;
     stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow)))?;
@@ -80,3 +581,174 @@ pub fn warning(header: impl Display, msg: impl Display) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_format_from_env_value() {
+        assert_eq!(LogFormat::from_env_value(Some("json")), LogFormat::Json);
+        assert_eq!(LogFormat::from_env_value(Some("JSON")), LogFormat::Json);
+        assert_eq!(LogFormat::from_env_value(Some("text")), LogFormat::Human);
+        assert_eq!(LogFormat::from_env_value(None), LogFormat::Human);
+    }
+
+    #[test]
+    fn logger_config_resolves_level() {
+        let config = |pairs: &'static [(&'static str, &'static str)]| {
+            LoggerConfig::from_env(move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| String::from(*value))
+            })
+        };
+
+        assert_eq!(config(&[]).level, LogLevel::Info);
+        assert_eq!(
+            config(&[("HEROKU_BUILDPACK_DEBUG", "1")]).level,
+            LogLevel::Debug
+        );
+        assert_eq!(config(&[("BPLOG_LEVEL", "WARN")]).level, LogLevel::Warn);
+        assert_eq!(
+            config(&[("BPLOG_LEVEL", "error"), ("HEROKU_BUILDPACK_DEBUG", "1")]).level,
+            LogLevel::Error
+        );
+        assert_eq!(config(&[("BPLOG_LEVEL", "loud")]).level, LogLevel::Info);
+    }
+
+    #[test]
+    fn color_choice_honors_env_and_terminal() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == key)
+                    .map(|(_, value)| String::from(*value))
+            }
+        };
+
+        assert_eq!(color_choice(env(&[]), true), ColorChoice::Auto);
+        assert_eq!(color_choice(env(&[]), false), ColorChoice::Never);
+        assert_eq!(
+            color_choice(env(&[("NO_COLOR", "1")]), true),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(env(&[("NO_COLOR", "")]), true),
+            ColorChoice::Auto
+        );
+        assert_eq!(
+            color_choice(env(&[("CLICOLOR_FORCE", "1")]), false),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            color_choice(env(&[("CLICOLOR_FORCE", "0")]), false),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            color_choice(env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]), true),
+            ColorChoice::Always
+        );
+    }
+
+    #[test]
+    fn json_writes_one_event_per_line() -> anyhow::Result<()> {
+        let logger = JsonLogger::new(false);
+        logger
+            .section
+            .replace(Some(String::from("Installing Java function runtime")));
+        let mut out = Vec::new();
+
+        logger.json(&mut out, "info", "Starting download of function runtime")?;
+
+        let line = String::from_utf8(out)?;
+        assert!(line.ends_with('\n'));
+        let event: serde_json::Value = serde_json::from_str(&line)?;
+        assert_eq!(event["level"], "info");
+        assert_eq!(event["section"], "Installing Java function runtime");
+        assert_eq!(event["message"], "Starting download of function runtime");
+        assert!(event["timestamp"].is_u64());
+
+        Ok(())
+    }
+
+    #[test]
+    fn memory_logger_records_entries() {
+        let logger = MemoryLogger::new(false);
+
+        logger.header("Installing Java function runtime").unwrap();
+        logger.info("Starting download").unwrap();
+        logger.debug("Hidden without debug").unwrap();
+        let err = logger.error("Download failed", "Network down").unwrap_err();
+
+        assert_eq!(err.to_string(), "Download failed");
+        assert_eq!(logger.messages(Level::Info), vec!["Starting download"]);
+        assert!(logger.messages(Level::Debug).is_empty());
+        assert_eq!(
+            logger.entries().last(),
+            Some(&Entry {
+                level: Level::Error,
+                header: Some(String::from("Download failed")),
+                message: String::from("Network down"),
+            })
+        );
+    }
+
+    #[test]
+    fn section_indents_info_lines_and_reports_duration() -> anyhow::Result<()> {
+        let logger = MemoryLogger::new(false);
+
+        let section = Section::new(&logger, "Installing Java function runtime")?;
+        logger.info("Starting download")?;
+        section.finish()?;
+        logger.info("After the section")?;
+
+        assert_eq!(
+            logger.messages(Level::Header),
+            vec!["Installing Java function runtime"]
+        );
+        let info = logger.messages(Level::Info);
+        assert_eq!(info[0], "  Starting download");
+        assert!(info[1].starts_with("  ... done ("));
+        assert!(info[1].ends_with("s)"));
+        assert_eq!(info[2], "After the section");
+
+        Ok(())
+    }
+
+    #[test]
+    fn section_dropped_without_finish_is_not_done() -> anyhow::Result<()> {
+        let logger = MemoryLogger::new(false);
+
+        let failed = || -> anyhow::Result<()> {
+            let section = Section::new(&logger, "Detecting function")?;
+            logger.info("Reading function.toml")?;
+            "not a number".parse::<u16>()?;
+            section.finish()
+        };
+        assert!(failed().is_err());
+        logger.info("After the section")?;
+
+        assert_eq!(
+            logger.messages(Level::Info),
+            vec!["  Reading function.toml", "After the section"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn condensed_logs_info_lines_at_debug_level() -> anyhow::Result<()> {
+        let logger = MemoryLogger::new(true);
+
+        Condensed::new(&logger, true).info("Reusing cached layer")?;
+        Condensed::new(&logger, false).info("Downloading runtime")?;
+
+        assert_eq!(logger.messages(Level::Debug), vec!["Reusing cached layer"]);
+        assert_eq!(logger.messages(Level::Info), vec!["Downloading runtime"]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    io::{self, BufRead, BufReader, Read},
+    process::{Command, ExitStatus, Stdio},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
+
+/// Runs `command` with captured stdout and stderr, calling `on_line` for every line in the order
+/// they arrive. `on_line` runs on the calling thread, so it may use non thread safe loggers.
+///
+/// If `timeout` elapses before the process closes its output, the process is killed and an error
+/// of kind `io::ErrorKind::TimedOut` is returned.
+pub fn run_streaming(
+    command: &mut Command,
+    timeout: Option<Duration>,
+    mut on_line: impl FnMut(OutputLine),
+) -> io::Result<ExitStatus> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let (sender, receiver) = mpsc::channel();
+    let stdout_reader = child
+        .stdout
+        .take()
+        .map(|stdout| forward_lines(stdout, sender.clone(), OutputLine::Stdout));
+    let stderr_reader = child
+        .stderr
+        .take()
+        .map(|stderr| forward_lines(stderr, sender, OutputLine::Stderr));
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    // Ends once both reader threads have dropped their senders.
+    loop {
+        let received = match deadline {
+            Some(deadline) => {
+                receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            }
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(line) => on_line(line),
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {
+                child.kill()?;
+                child.wait()?;
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "process did not finish within {:?}",
+                        timeout.unwrap_or_default()
+                    ),
+                ));
+            }
+        }
+    }
+
+    for reader in stdout_reader.into_iter().chain(stderr_reader) {
+        let _ = reader.join();
+    }
+
+    child.wait()
+}
+
+/// Runs the `java` invocations of the build, so tests can simulate the JVM.
+pub trait CommandRunner {
+    /// See [`run_streaming`].
+    fn run(
+        &self,
+        command: &mut Command,
+        timeout: Option<Duration>,
+        on_line: &mut dyn FnMut(OutputLine),
+    ) -> io::Result<ExitStatus>;
+
+    /// Runs `command` and collects its stdout, dropping stderr.
+    fn stdout(
+        &self,
+        command: &mut Command,
+        timeout: Option<Duration>,
+    ) -> io::Result<(ExitStatus, String)> {
+        let mut stdout = String::new();
+        let exit_status = self.run(command, timeout, &mut |line| {
+            if let OutputLine::Stdout(line) = line {
+                stdout.push_str(&line);
+                stdout.push('\n');
+            }
+        })?;
+
+        Ok((exit_status, stdout))
+    }
+}
+
+/// Starts real processes with [`run_streaming`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(
+        &self,
+        command: &mut Command,
+        timeout: Option<Duration>,
+        on_line: &mut dyn FnMut(OutputLine),
+    ) -> io::Result<ExitStatus> {
+        run_streaming(command, timeout, on_line)
+    }
+}
+
+/// Prints `lines` and exits with `exit_code` instead of running the command, recording its
+/// arguments.
+#[cfg(all(test, target_family = "unix"))]
+pub struct ScriptedRunner {
+    pub exit_code: i32,
+    pub lines: Vec<OutputLine>,
+    pub args: std::cell::RefCell<Vec<std::ffi::OsString>>,
+}
+
+#[cfg(all(test, target_family = "unix"))]
+impl ScriptedRunner {
+    pub fn new(exit_code: i32, lines: Vec<OutputLine>) -> Self {
+        ScriptedRunner {
+            exit_code,
+            lines,
+            args: Default::default(),
+        }
+    }
+}
+
+#[cfg(all(test, target_family = "unix"))]
+impl CommandRunner for ScriptedRunner {
+    fn run(
+        &self,
+        command: &mut Command,
+        _timeout: Option<Duration>,
+        on_line: &mut dyn FnMut(OutputLine),
+    ) -> io::Result<ExitStatus> {
+        use std::os::unix::process::ExitStatusExt;
+
+        self.args
+            .replace(command.get_args().map(Into::into).collect());
+        self.lines.iter().cloned().for_each(on_line);
+
+        Ok(ExitStatus::from_raw(self.exit_code << 8))
+    }
+}
+
+fn forward_lines(
+    stream: impl Read + Send + 'static,
+    sender: mpsc::Sender<OutputLine>,
+    wrap: fn(String) -> OutputLine,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let sent = line.map(|line| sender.send(wrap(line)).is_ok());
+            if !matches!(sent, Ok(true)) {
+                break;
+            }
+        }
+    })
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn run_streaming_captures_both_streams() -> io::Result<()> {
+        let mut lines = Vec::new();
+        let status = run_streaming(
+            Command::new("sh")
+                .arg("-c")
+                .arg("echo out; echo err >&2; exit 3"),
+            None,
+            |line| lines.push(line),
+        )?;
+
+        assert_eq!(status.code(), Some(3));
+        assert!(lines.contains(&OutputLine::Stdout(String::from("out"))));
+        assert!(lines.contains(&OutputLine::Stderr(String::from("err"))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_streaming_kills_process_after_timeout() {
+        let started = Instant::now();
+        let result = run_streaming(
+            Command::new("sh")
+                .arg("-c")
+                .arg("echo started; exec sleep 30"),
+            Some(Duration::from_millis(200)),
+            |_| {},
+        );
+
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn stdout_drops_stderr() -> io::Result<()> {
+        let (status, stdout) = SystemRunner.stdout(
+            Command::new("sh")
+                .arg("-c")
+                .arg("echo '{'; echo warning >&2; echo '}'"),
+            None,
+        )?;
+
+        assert!(status.success());
+        assert_eq!(stdout, "{\n}\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    fmt,
+    io::{self, Read},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub bytes: u64,
+    pub total: Option<u64>,
+}
+
+impl Progress {
+    pub fn percent(&self) -> Option<u64> {
+        self.total
+            .filter(|total| *total > 0)
+            .map(|total| self.bytes * 100 / total)
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mebibytes = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+
+        match (self.percent(), self.total) {
+            (Some(percent), Some(total)) => write!(
+                f,
+                "{}% ({:.1} of {:.1} MiB)",
+                percent,
+                mebibytes(self.bytes),
+                mebibytes(total)
+            ),
+            _ => write!(f, "{:.1} MiB", mebibytes(self.bytes)),
+        }
+    }
+}
+
+/// Calls `on_progress` with the running byte count while reading from `inner`.
+pub struct ProgressReader<R, F> {
+    inner: R,
+    bytes: u64,
+    total: Option<u64>,
+    on_progress: F,
+}
+
+impl<R: Read, F: FnMut(Progress)> ProgressReader<R, F> {
+    pub fn new(inner: R, total: Option<u64>, on_progress: F) -> Self {
+        ProgressReader {
+            inner,
+            bytes: 0,
+            total,
+            on_progress,
+        }
+    }
+
+    /// Counts `bytes` that were read before, e.g. by an interrupted download that is resumed.
+    pub fn starting_at(mut self, bytes: u64) -> Self {
+        self.bytes = bytes;
+        self
+    }
+}
+
+impl<R: Read, F: FnMut(Progress)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes += read as u64;
+        (self.on_progress)(Progress {
+            bytes: self.bytes,
+            total: self.total,
+        });
+
+        Ok(read)
+    }
+}
+
+/// Limits progress reports to every `step_percent` when the total size is known, or to once per
+/// `interval` otherwise, so CI logs stay readable.
+pub struct Throttle {
+    step_percent: u64,
+    interval: Duration,
+    last_percent: Option<u64>,
+    last_report: Option<Instant>,
+}
+
+impl Throttle {
+    pub fn new(step_percent: u64, interval: Duration) -> Self {
+        Throttle {
+            step_percent: step_percent.max(1),
+            interval,
+            last_percent: None,
+            last_report: None,
+        }
+    }
+
+    pub fn should_report(&mut self, progress: &Progress, now: Instant) -> bool {
+        let report = match progress.percent() {
+            Some(percent) => {
+                let step = percent / self.step_percent;
+                self.last_percent
+                    .map_or(step > 0, |last| step > last / self.step_percent)
+            }
+            None => self
+                .last_report
+                .map_or(true, |last| now.duration_since(last) >= self.interval),
+        };
+
+        if report {
+            self.last_percent = progress.percent();
+            self.last_report = Some(now);
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn progress_reader_reports_running_total() -> io::Result<()> {
+        let mut reports = Vec::new();
+        let mut reader = ProgressReader::new(&b"hello world"[..], Some(11), |progress| {
+            reports.push(progress)
+        });
+        let mut buf = [0; 5];
+
+        reader.read_exact(&mut buf)?;
+        reader.read_exact(&mut buf)?;
+        drop(reader);
+
+        assert_eq!(reports.last().unwrap().bytes, 10);
+        assert_eq!(reports.last().unwrap().percent(), Some(90));
+
+        Ok(())
+    }
+
+    #[test]
+    fn throttle_reports_each_step_once() {
+        let mut throttle = Throttle::new(25, Duration::from_secs(5));
+        let now = Instant::now();
+        let progress = |bytes| Progress {
+            bytes,
+            total: Some(100),
+        };
+
+        let reported: Vec<u64> = (0..=100)
+            .filter(|bytes| throttle.should_report(&progress(*bytes), now))
+            .collect();
+
+        assert_eq!(reported, vec![25, 50, 75, 100]);
+    }
+
+    #[test]
+    fn throttle_reports_by_interval_without_total() {
+        let mut throttle = Throttle::new(10, Duration::from_secs(5));
+        let start = Instant::now();
+        let progress = Progress {
+            bytes: 1024,
+            total: None,
+        };
+
+        assert!(throttle.should_report(&progress, start));
+        assert!(!throttle.should_report(&progress, start + Duration::from_secs(1)));
+        assert!(throttle.should_report(&progress, start + Duration::from_secs(6)));
+    }
+
+    #[test]
+    fn progress_display() {
+        assert_eq!(
+            Progress {
+                bytes: 1024 * 1024,
+                total: Some(4 * 1024 * 1024)
+            }
+            .to_string(),
+            "25% (1.0 of 4.0 MiB)"
+        );
+        assert_eq!(
+            Progress {
+                bytes: 512 * 1024,
+                total: None
+            }
+            .to_string(),
+            "0.5 MiB"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use reqwest::Url;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub http_proxy: Option<String>,
+    pub https_proxy: Option<String>,
+    /// Comma separated list of hosts or domain suffixes that bypass the proxy, `*` bypasses all.
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    /// Reads the conventional proxy variables, preferring the upper case spelling.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let lookup = |key: &str| {
+            var(&key.to_uppercase())
+                .or_else(|| var(key))
+                .filter(|value| !value.is_empty())
+        };
+
+        ProxyConfig {
+            http_proxy: lookup("http_proxy"),
+            https_proxy: lookup("https_proxy"),
+            no_proxy: lookup("no_proxy"),
+        }
+    }
+
+    /// Values set in `other` take precedence over the ones in `self`.
+    pub fn merge(self, other: &ProxyConfig) -> Self {
+        ProxyConfig {
+            http_proxy: other.http_proxy.clone().or(self.http_proxy),
+            https_proxy: other.https_proxy.clone().or(self.https_proxy),
+            no_proxy: other.no_proxy.clone().or(self.no_proxy),
+        }
+    }
+
+    pub fn proxy_for(&self, url: &Url) -> Option<String> {
+        if let (Some(no_proxy), Some(host)) = (&self.no_proxy, url.host_str()) {
+            if is_excluded(no_proxy, host) {
+                return None;
+            }
+        }
+
+        match url.scheme() {
+            "http" => self.http_proxy.clone(),
+            "https" => self.https_proxy.clone(),
+            _ => None,
+        }
+    }
+}
+
+fn is_excluded(no_proxy: &str, host: &str) -> bool {
+    no_proxy
+        .split(',')
+        .map(|entry| entry.trim().trim_start_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ProxyConfig {
+        ProxyConfig {
+            http_proxy: Some(String::from("http://proxy:3128")),
+            https_proxy: Some(String::from("http://secure-proxy:3128")),
+            no_proxy: Some(String::from("localhost, .internal.example.com")),
+        }
+    }
+
+    #[test]
+    fn proxy_for_selects_proxy_by_scheme() {
+        let config = config();
+
+        assert_eq!(
+            config.proxy_for(&Url::parse("http://repo1.maven.org/foo.jar").unwrap()),
+            Some(String::from("http://proxy:3128"))
+        );
+        assert_eq!(
+            config.proxy_for(&Url::parse("https://repo1.maven.org/foo.jar").unwrap()),
+            Some(String::from("http://secure-proxy:3128"))
+        );
+    }
+
+    #[test]
+    fn proxy_for_honors_no_proxy() {
+        let config = config();
+
+        assert_eq!(
+            config.proxy_for(&Url::parse("https://localhost/foo.jar").unwrap()),
+            None
+        );
+        assert_eq!(
+            config.proxy_for(&Url::parse("https://repo.internal.example.com/foo.jar").unwrap()),
+            None
+        );
+        assert_eq!(
+            config.proxy_for(&Url::parse("https://example.com/foo.jar").unwrap()),
+            Some(String::from("http://secure-proxy:3128"))
+        );
+    }
+
+    #[test]
+    fn from_env_prefers_upper_case_and_merge_prefers_override() {
+        let env = ProxyConfig::from_env(|key| match key {
+            "HTTPS_PROXY" => Some(String::from("http://upper:3128")),
+            "https_proxy" => Some(String::from("http://lower:3128")),
+            "http_proxy" => Some(String::from("http://lower:3128")),
+            _ => None,
+        });
+
+        assert_eq!(env.https_proxy, Some(String::from("http://upper:3128")));
+        assert_eq!(env.http_proxy, Some(String::from("http://lower:3128")));
+
+        let merged = env.merge(&ProxyConfig {
+            http_proxy: Some(String::from("http://override:3128")),
+            ..ProxyConfig::default()
+        });
+
+        assert_eq!(
+            merged.http_proxy,
+            Some(String::from("http://override:3128"))
+        );
+        assert_eq!(merged.https_proxy, Some(String::from("http://upper:3128")));
+    }
+}
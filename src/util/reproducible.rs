@@ -0,0 +1,161 @@
+use std::{
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// 1980-01-01T00:00:01Z, the timestamp the lifecycle gives files in reproducible images.
+pub const NORMALIZED_MTIME_SECS: u64 = 315_532_801;
+/// Build environment variable with the timestamp, in seconds since the unix epoch, that
+/// reproducible build pipelines expect generated files to carry.
+pub const SOURCE_DATE_EPOCH_ENV: &str = "SOURCE_DATE_EPOCH";
+
+/// The modification time of normalized files: `SOURCE_DATE_EPOCH` if it is set to a valid
+/// timestamp, the lifecycle's otherwise.
+pub fn mtime(source_date_epoch: Option<&str>) -> SystemTime {
+    let secs = source_date_epoch
+        .and_then(|secs| secs.trim().parse().ok())
+        .unwrap_or(NORMALIZED_MTIME_SECS);
+
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+/// Gives `root` and everything below it the modification time `mtime` and fixed permissions, so
+/// rebuilding identical contents yields an identical layer. Files become `0644`, or `0755` if
+/// anyone could execute them, directories `0755`. Symlinks are left alone.
+pub fn normalize_tree(root: impl AsRef<Path>, mtime: SystemTime) -> io::Result<()> {
+    let root = root.as_ref();
+    if root.is_dir() {
+        normalize_dir(root, mtime)?;
+    }
+
+    Ok(())
+}
+
+// Children first, as changing them would update the modification time of the directory again.
+fn normalize_dir(dir: &Path, mtime: SystemTime) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            normalize_dir(&entry.path(), mtime)?;
+        } else if file_type.is_file() {
+            normalize_entry(&entry.path(), false, mtime)?;
+        }
+    }
+
+    normalize_entry(dir, true, mtime)
+}
+
+fn normalize_entry(path: &Path, is_dir: bool, mtime: SystemTime) -> io::Result<()> {
+    #[cfg(target_family = "unix")]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = fs::metadata(path)?.permissions().mode();
+        let normalized_mode = if is_dir || mode & 0o111 != 0 {
+            0o755
+        } else {
+            0o644
+        };
+        fs::set_permissions(path, fs::Permissions::from_mode(normalized_mode))?;
+    }
+    #[cfg(not(target_family = "unix"))]
+    let _ = is_dir;
+
+    fs::File::open(path)?.set_modified(mtime)?;
+
+    Ok(())
+}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use super::*;
+    use std::{os::unix::fs::PermissionsExt, path::PathBuf, thread};
+
+    /// Relative path, mode, modification time and contents of every entry below `root`.
+    fn tree_listing(root: &Path) -> io::Result<Vec<(PathBuf, u32, SystemTime, Vec<u8>)>> {
+        let mut listing = Vec::new();
+        let mut dirs = vec![root.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                let metadata = fs::metadata(&path)?;
+                let contents = if metadata.is_dir() {
+                    dirs.push(path.clone());
+                    Vec::new()
+                } else {
+                    fs::read(&path)?
+                };
+                listing.push((
+                    path.strip_prefix(root).unwrap().to_path_buf(),
+                    metadata.permissions().mode() & 0o777,
+                    metadata.modified()?,
+                    contents,
+                ));
+            }
+        }
+        listing.sort();
+
+        Ok(listing)
+    }
+
+    fn write_layer(layer_dir: &Path, launcher_mode: u32) -> io::Result<()> {
+        fs::create_dir_all(layer_dir.join("env.launch"))?;
+        fs::write(layer_dir.join("env.launch").join("PORT.default"), "8080")?;
+        fs::write(layer_dir.join("launch"), "#!/bin/sh")?;
+        fs::set_permissions(
+            layer_dir.join("launch"),
+            fs::Permissions::from_mode(launcher_mode),
+        )?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn normalize_tree_makes_rebuilt_layers_identical() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+
+        write_layer(&first, 0o775)?;
+        thread::sleep(Duration::from_millis(20));
+        write_layer(&second, 0o700)?;
+        assert_ne!(tree_listing(&first)?, tree_listing(&second)?);
+
+        normalize_tree(&first, mtime(None))?;
+        normalize_tree(&second, mtime(None))?;
+
+        let listing = tree_listing(&first)?;
+        assert_eq!(listing, tree_listing(&second)?);
+        assert!(listing.iter().all(|(_, _, modified, _)| *modified
+            == UNIX_EPOCH + Duration::from_secs(NORMALIZED_MTIME_SECS)));
+        let mode = |name: &str| {
+            listing
+                .iter()
+                .find(|(path, ..)| path == Path::new(name))
+                .map(|(_, mode, ..)| *mode)
+        };
+        assert_eq!(mode("launch"), Some(0o755));
+        assert_eq!(mode("env.launch"), Some(0o755));
+        assert_eq!(mode("env.launch/PORT.default"), Some(0o644));
+
+        Ok(())
+    }
+
+    #[test]
+    fn mtime_honors_source_date_epoch() {
+        assert_eq!(
+            mtime(Some("1700000000")),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        assert_eq!(
+            mtime(Some("yesterday")),
+            UNIX_EPOCH + Duration::from_secs(NORMALIZED_MTIME_SECS)
+        );
+        assert_eq!(
+            mtime(None),
+            UNIX_EPOCH + Duration::from_secs(NORMALIZED_MTIME_SECS)
+        );
+    }
+}
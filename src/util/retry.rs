@@ -0,0 +1,202 @@
+use crate::util;
+use serde::Deserialize;
+use std::{
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub attempts: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    pub multiplier: f64,
+    /// Randomize each backoff between half and the full computed delay.
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 10_000,
+            multiplier: 2.0,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn none() -> Self {
+        RetryPolicy {
+            attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Applies `SF_FUNCTIONS_DOWNLOAD_RETRIES`, the number of retries after the first attempt.
+    pub fn merge_env(mut self, var: impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        if let Some(retries) =
+            util::parse_env::<u32>(&var, "SF_FUNCTIONS_DOWNLOAD_RETRIES", "a number of retries")?
+        {
+            self.attempts = retries.saturating_add(1);
+        }
+
+        Ok(self)
+    }
+
+    /// Backoff before the given retry, where `retry` starts at 1 for the second attempt.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1) as i32;
+        let millis = (self.initial_backoff_ms as f64 * self.multiplier.powi(exponent))
+            .min(self.max_backoff_ms as f64) as u64;
+
+        let millis = if self.jitter && millis > 0 {
+            millis / 2 + jitter_source() % (millis / 2 + 1)
+        } else {
+            millis
+        };
+
+        Duration::from_millis(millis)
+    }
+
+    /// Calls `f` with the number of the attempt until it succeeds, fails with an error that isn't
+    /// `is_retryable` or runs out of attempts.
+    pub fn run<T, E>(
+        &self,
+        is_retryable: impl Fn(&E) -> bool,
+        mut f: impl FnMut(u32) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let attempts = self.attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            match f(attempt) {
+                Ok(value) => return Ok(value),
+                Err(err) if attempt >= attempts || !is_retryable(&err) => return Err(err),
+                Err(_) => {
+                    thread::sleep(self.backoff(attempt));
+                    attempt += 1;
+                }
+            }
+        }
+    }
+}
+
+// Jitter only needs to spread out retries of concurrent builds, not be unpredictable.
+fn jitter_source() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.subsec_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_max() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(10), Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn backoff_with_jitter_stays_within_bounds() {
+        let policy = RetryPolicy::default();
+
+        for retry in 1..5 {
+            let unjittered = RetryPolicy {
+                jitter: false,
+                ..policy.clone()
+            }
+            .backoff(retry);
+            let backoff = policy.backoff(retry);
+
+            assert!(backoff <= unjittered);
+            assert!(backoff >= unjittered / 2);
+        }
+    }
+
+    #[test]
+    fn merge_env_counts_retries_after_first_attempt() -> anyhow::Result<()> {
+        let policy = RetryPolicy::default().merge_env(|key| match key {
+            "SF_FUNCTIONS_DOWNLOAD_RETRIES" => Some(String::from("0")),
+            _ => None,
+        })?;
+
+        assert_eq!(policy.attempts, 1);
+        assert_eq!(
+            RetryPolicy::default().merge_env(|_| None)?,
+            RetryPolicy::default()
+        );
+        assert!(RetryPolicy::default()
+            .merge_env(|_| Some(String::from("-1")))
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn run_retries_until_success() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 0,
+            ..RetryPolicy::default()
+        };
+        let result: Result<u32, ()> = policy.run(
+            |_| true,
+            |attempt| if attempt < 3 { Err(()) } else { Ok(attempt) },
+        );
+
+        assert_eq!(result, Ok(3));
+    }
+
+    #[test]
+    fn run_gives_up_after_configured_attempts() {
+        let policy = RetryPolicy {
+            attempts: 2,
+            initial_backoff_ms: 0,
+            ..RetryPolicy::default()
+        };
+        let mut calls = 0;
+        let result: Result<(), u32> = policy.run(
+            |_| true,
+            |attempt| {
+                calls += 1;
+                Err(attempt)
+            },
+        );
+
+        assert_eq!(result, Err(2));
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn run_stops_at_errors_that_are_not_retryable() {
+        let policy = RetryPolicy {
+            initial_backoff_ms: 0,
+            ..RetryPolicy::default()
+        };
+        let mut calls = 0;
+        let result: Result<(), u16> = policy.run(
+            |status| *status >= 500,
+            |attempt| {
+                calls += 1;
+                Err(if attempt == 1 { 503 } else { 404 })
+            },
+        );
+
+        assert_eq!(result, Err(404));
+        assert_eq!(calls, 2);
+    }
+}
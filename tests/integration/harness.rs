@@ -0,0 +1,241 @@
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tempfile::TempDir;
+
+/// Buildpacks that provide the JDK and compile the app before this buildpack runs.
+const PRECEDING_BUILDPACKS: &[&str] = &["heroku/jvm", "heroku/maven"];
+
+pub struct PackBuild {
+    fixture: String,
+    env: Vec<(String, String)>,
+    preceding_buildpacks: Vec<String>,
+}
+
+impl PackBuild {
+    pub fn new(fixture: &str) -> Self {
+        PackBuild {
+            fixture: String::from(fixture),
+            env: Vec::new(),
+            preceding_buildpacks: PRECEDING_BUILDPACKS
+                .iter()
+                .map(|buildpack| String::from(*buildpack))
+                .collect(),
+        }
+    }
+
+    /// Replaces the buildpacks that run before this buildpack, e.g. for Gradle projects.
+    pub fn preceding_buildpacks(mut self, buildpacks: &[&str]) -> Self {
+        self.preceding_buildpacks = buildpacks
+            .iter()
+            .map(|buildpack| String::from(*buildpack))
+            .collect();
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((String::from(key), String::from(value)));
+        self
+    }
+
+    pub fn run(self) -> BuildOutput {
+        let image = format!(
+            "jvm-function-invoker-test-{}-{}",
+            self.fixture,
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_nanos())
+                .unwrap_or_default()
+        );
+
+        let mut command = Command::new("pack");
+        command
+            .arg("build")
+            .arg(&image)
+            .arg("--builder")
+            .arg(builder())
+            .args(&["--pull-policy", "if-not-present"])
+            .arg("--path")
+            .arg(manifest_dir().join("tests/fixtures").join(&self.fixture));
+        for buildpack in &self.preceding_buildpacks {
+            command.arg("--buildpack").arg(buildpack);
+        }
+        command.arg("--buildpack").arg(buildpack_dir());
+        for (key, value) in &self.env {
+            command.arg("--env").arg(format!("{}={}", key, value));
+        }
+
+        let output = command
+            .output()
+            .expect("pack must be installed to run integration tests");
+
+        BuildOutput {
+            image,
+            success: output.status.success(),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+    }
+}
+
+pub struct BuildOutput {
+    pub image: String,
+    pub success: bool,
+    /// Combined stdout and stderr of `pack build`.
+    pub output: String,
+}
+
+impl BuildOutput {
+    pub fn assert_success(&self) {
+        assert!(self.success, "pack build failed:\n{}", self.output);
+    }
+
+    /// Names of the layers the given buildpack contributed to the image.
+    pub fn layers(&self, buildpack_id: &str) -> Vec<String> {
+        self.label("io.buildpacks.lifecycle.metadata")["buildpacks"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|buildpack| buildpack["key"] == buildpack_id)
+            .filter_map(|buildpack| buildpack["layers"].as_object())
+            .flat_map(|layers| layers.keys().cloned())
+            .collect()
+    }
+
+    pub fn process_types(&self) -> Vec<String> {
+        self.label("io.buildpacks.build.metadata")["processes"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|process| process["type"].as_str().map(String::from))
+            .collect()
+    }
+
+    fn label(&self, name: &str) -> serde_json::Value {
+        let output = Command::new("docker")
+            .args(&["inspect", "--format"])
+            .arg(format!("{{{{ index .Config.Labels \"{}\" }}}}", name))
+            .arg(&self.image)
+            .output()
+            .expect("docker must be installed to run integration tests");
+        assert!(output.status.success(), "could not inspect {}", self.image);
+
+        serde_json::from_slice(&output.stdout).expect("image label is not valid JSON")
+    }
+}
+
+impl Drop for BuildOutput {
+    fn drop(&mut self) {
+        if self.success {
+            let _ = Command::new("docker")
+                .args(&["rmi", "--force"])
+                .arg(&self.image)
+                .output();
+        }
+    }
+}
+
+/// Runs `bin/build` on a fixture without pack or Docker, with this repository as the buildpack
+/// directory. Only suitable for builds that don't need a JDK, like dry runs.
+pub struct LocalBuild {
+    fixture: String,
+    platform_env: Vec<(String, String)>,
+}
+
+impl LocalBuild {
+    pub fn new(fixture: &str) -> Self {
+        LocalBuild {
+            fixture: String::from(fixture),
+            platform_env: Vec::new(),
+        }
+    }
+
+    /// Sets `key` in the platform directory, as `pack build --env` does.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.platform_env
+            .push((String::from(key), String::from(value)));
+        self
+    }
+
+    pub fn run(self) -> LocalBuildOutput {
+        let dir = tempfile::tempdir().expect("could not create build directory");
+        let layers_dir = dir.path().join("layers");
+        let platform_env_dir = dir.path().join("platform").join("env");
+        let plan_path = dir.path().join("plan.toml");
+        fs::create_dir(&layers_dir).expect("could not create layers directory");
+        fs::create_dir_all(&platform_env_dir).expect("could not create platform directory");
+        for (key, value) in &self.platform_env {
+            fs::write(platform_env_dir.join(key), value).expect("could not write platform env");
+        }
+        fs::write(&plan_path, "entries = []").expect("could not write buildpack plan");
+
+        let output = Command::new(env!("CARGO_BIN_EXE_bp_build"))
+            .arg(&layers_dir)
+            .arg(dir.path().join("platform"))
+            .arg(&plan_path)
+            .current_dir(manifest_dir().join("tests/fixtures").join(&self.fixture))
+            .env("CNB_BUILDPACK_DIR", manifest_dir())
+            .env("CNB_STACK_ID", "heroku-20")
+            .output()
+            .expect("could not run bin/build");
+
+        LocalBuildOutput {
+            dir,
+            success: output.status.success(),
+            output: format!(
+                "{}{}",
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        }
+    }
+}
+
+pub struct LocalBuildOutput {
+    dir: TempDir,
+    pub success: bool,
+    pub output: String,
+}
+
+impl LocalBuildOutput {
+    pub fn assert_success(&self) {
+        assert!(self.success, "bin/build failed:\n{}", self.output);
+    }
+
+    /// Everything the build left in the layers directory, layer directories and `<layer>.toml`
+    /// files alike.
+    pub fn layers_dir_entries(&self) -> Vec<String> {
+        let mut entries: Vec<String> = fs::read_dir(self.dir.path().join("layers"))
+            .expect("could not read layers directory")
+            .map(|entry| {
+                entry
+                    .expect("could not read layers directory")
+                    .file_name()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        entries.sort();
+        entries
+    }
+}
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn builder() -> String {
+    env::var("INTEGRATION_TEST_BUILDER").unwrap_or_else(|_| String::from("heroku/buildpacks:20"))
+}
+
+fn buildpack_dir() -> PathBuf {
+    env::var_os("INTEGRATION_TEST_BUILDPACK_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| manifest_dir().join("target/buildpack"))
+}
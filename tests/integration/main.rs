@@ -0,0 +1,118 @@
+//! End to end tests that build the apps in `tests/fixtures` with `pack`. They need `pack`, a
+//! running Docker daemon and the buildpack packaged by `cargo make development`, so they are
+//! ignored by default:
+//!
+//! ```sh
+//! cargo make development
+//! cargo test --test integration -- --ignored
+//! ```
+//!
+//! Tests that run `bin/build` directly with [`LocalBuild`] need neither and always run.
+mod harness;
+
+use harness::{LocalBuild, PackBuild};
+
+const BUILDPACK_ID: &str = "heroku/jvm-function-invoker";
+
+#[test]
+#[ignore]
+fn builds_single_function() {
+    let build = PackBuild::new("function-basic").run();
+
+    build.assert_success();
+    assert!(build
+        .output
+        .contains("Detected function: com.example.ExampleFunction"));
+
+    let layers = build.layers(BUILDPACK_ID);
+    for layer in &[
+        "opt",
+        "sf-fx-runtime-java",
+        "function-bundle",
+        "function-dependencies",
+        "build-report",
+    ] {
+        assert!(layers.iter().any(|l| l == layer), "missing layer {}", layer);
+    }
+
+    assert_eq!(
+        build.process_types(),
+        vec!["web", "debug", "test", "health"]
+    );
+}
+
+#[test]
+#[ignore]
+fn builds_gradle_function() {
+    let build = PackBuild::new("function-gradle")
+        .preceding_buildpacks(&["heroku/jvm", "heroku/gradle"])
+        .run();
+
+    build.assert_success();
+    assert!(build
+        .output
+        .contains("Detected function: com.example.ExampleFunction"));
+    assert_eq!(
+        build.process_types(),
+        vec!["web", "debug", "test", "health"]
+    );
+}
+
+#[test]
+#[ignore]
+fn rejects_app_without_function() {
+    let build = PackBuild::new("not-a-function").run();
+
+    assert!(!build.success);
+    assert!(build
+        .output
+        .contains("Skipping the Java function buildpack, these checks failed:"));
+    assert!(build
+        .output
+        .contains("  - No project.toml or function.toml found"));
+}
+
+#[test]
+#[ignore]
+fn rejects_unknown_runtime_version() {
+    let build = PackBuild::new("function-basic")
+        .env("SF_FUNCTIONS_RUNTIME_VERSION", "0.0.0")
+        .run();
+
+    assert!(!build.success);
+    assert!(build
+        .output
+        .contains("Unsupported function runtime version"));
+}
+
+#[test]
+#[ignore]
+fn dry_run_prints_plan_without_bundling() {
+    let build = PackBuild::new("function-basic")
+        .env("SF_FUNCTIONS_DRY_RUN", "1")
+        .run();
+
+    build.assert_success();
+    assert!(build
+        .output
+        .contains("function-bundle: would be created, not cached"));
+    assert!(build
+        .output
+        .contains("Function runtime: https://repo1.maven.org/"));
+    assert!(build.output.contains("web: "));
+    assert!(!build.output.contains("Detected function"));
+    assert!(build.layers(BUILDPACK_ID).is_empty());
+}
+
+#[test]
+fn dry_run_creates_no_layers() {
+    let build = LocalBuild::new("function-basic")
+        .env("SF_FUNCTIONS_DRY_RUN", "1")
+        .run();
+
+    build.assert_success();
+    assert!(build
+        .output
+        .contains("function-bundle: would be created, not cached"));
+    assert_eq!(build.layers_dir_entries(), Vec::<String>::new());
+}